@@ -1,32 +1,128 @@
+mod action;
+mod atomicwrite;
+mod backup;
+mod batch;
+mod calc;
+mod capabilities;
+mod cli;
+mod clipboard;
 mod command;
+mod commandbar;
+mod config;
+mod diff;
+mod digraph;
+mod doctor;
 mod documentstatus;
+mod dryrun;
+mod encoding;
+mod escape;
 mod fileinfo;
+mod filetype;
+mod keybindings;
+mod linemarks;
+mod linerange;
 mod messagebar;
+mod open;
+mod overlay;
+mod paths;
+mod quit;
+mod replace;
+mod roundtrip;
+mod stash;
+mod startup;
 mod statusbar;
+mod statuspublisher;
+mod tasks;
 mod terminal;
+mod throttle;
+mod trash;
+mod truncate;
 mod uicomponent;
 mod view;
 use self::{
+    action::Run,
+    cli::Arguments,
     command::{
         Command::{self, Edit, Move, System},
-        System::{Quit, Resize, Save}, 
+        Edit::Insert,
+        Edit::InsertNewline,
+        System::{
+            Calculate, Digraph, Dismiss, FindNext, FindPrevious, Palette, Quit, Replace, Resize, Save,
+            Search, ToggleLineMark, ToggleLineNumbers, CopyLine, ToggleSelection, SwapPosition,
+            GotoLine, ToggleSoftWrap, SaveAs, OpenFile, NextBuffer,
+            Reload, ToggleReadOnly, WordCount,
+        },
     },
+    commandbar::CommandBar,
+    config::Config,
+    diff::DiffLine,
+    dryrun::DryRun,
+    keybindings::KeyMap,
+    linerange::RangeOperation,
     messagebar::MessageBar,
-    terminal::Size,
+    overlay::Overlay,
+    replace::ReplaceState,
+    terminal::{Position, Size},
+    truncate::{EllipsisSide, truncate_to_width},
+};
+use crossterm::event::{
+    Event, KeyEvent, KeyEventKind, MouseButton, MouseEvent, MouseEventKind,
 };
-use crossterm::event::{Event, KeyEvent, KeyEventKind, read};
 use statusbar::Statusbar;
+use startup::StartupTimer;
+use statuspublisher::StatusPublisher;
+use tasks::Tasks;
 use std::{
     env,
-    io::Error,
+    io::{Error, IsTerminal, Read, stdin},
     panic::{set_hook, take_hook},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
 };
 use terminal::Terminal;
+use throttle::RenderThrottle;
 use uicomponent::UIComponent;
-use view::View;
+use view::{ScrollDirection, View, swapfile::CrashSwaps};
 pub const NAME: &str = env!("CARGO_PKG_NAME");
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
-const QUIT_TIMES: u8 = 3;
+const MAX_TITLE_WIDTH: usize = 80; // 终端标题最多显示的列数。
+const MAX_RENDER_FAILURES: usize = 5; // 连续这么多帧绘制失败时，暂存修改并退出。
+
+/// 当前正在等待用户回应的提示。
+#[derive(Default, Eq, PartialEq, Clone, Copy)]
+enum PromptType {
+    ConfirmSave,                           // 保存前发现可疑缩进，等待确认。
+    Palette,                               // 命令面板，在命令栏中输入动作名称。
+    Search,                                // 输入要查找的文本。
+    ReplaceSearch,                         // 全部替换：输入要查找的文本。
+    ReplaceWith,                           // 全部替换：输入替换为的文本。
+    ReplacePreview,                        // 全部替换：预览并选择要替换的位置。
+    ReplaceEachSearch,                     // 逐个替换：输入要查找的文本。
+    ReplaceEachWith,                       // 逐个替换：输入替换为的文本。
+    ConfirmReplace,                        // 逐个替换：询问是否替换高亮的匹配。
+    ConfirmChanges,                        // 破坏性动作试运行后等待确认。
+    ChangePreview,                         // 预览并选择破坏性动作要修改的行。
+    Digraph(Option<char>),                 // 二合字母模式，附带已输入的第一个字符。
+    DigraphSearch,                         // 输入查询以列出二合字母。
+    GotoLine,                              // 输入要跳转到的行号（可带列号）。
+    SaveAs,                                // 输入保存的文件名。
+    OpenFile,                              // 输入要打开的文件名。
+    SetEncoding,                           // 输入保存时使用的编码。
+    ConfirmCreate,                         // 要打开的文件不存在，询问是否新建。
+    ConfirmReload,                         // 有未保存的修改，询问是否放弃修改重新读取文件。
+    ConfirmRecoverSwap,                    // 文件旁边留有之前的交换文件，询问是否恢复其中的修改。
+    ConfirmOverwrite,                      // 磁盘上的文件被其他程序修改过，询问是否仍然保存。
+    ConfirmLossySave,                      // 有字符无法用文件的编码表示，询问是否仍然保存。
+    DigraphList,                           // 浏览匹配的二合字母。
+    LineMarkList,                          // 浏览带有持久高亮的行。
+    TrashList,                             // 浏览回收站中的删除。
+    WaitForTasks,                          // 退出前等待后台任务结束。
+    ConfirmForceQuit,                      // 后台任务超时未结束，询问是否强制退出。
+    ConfirmQuit,                           // 有未保存的修改，询问是否退出。
+    UnsavedChanges,                        // 退出前查看与磁盘上的文件相比未保存的修改。
+    #[default]
+    None,
+}
 
 /// `Editor` 结构体是编辑器的核心，
 #[derive(Default)]
@@ -35,84 +131,372 @@ pub struct Editor {
     view: View,              // 编辑器的视图，用于渲染内容。
     status_bar: Statusbar,   // 状态栏，用于显示状态信息。
     message_bar: MessageBar, // 消息栏，用于显示消息。
+    command_bar: CommandBar, // 命令栏，用于读取用户输入。
     terminal_size: Size,     // 终端的尺寸。
     title: String,           // 编辑器的标题。
-    quit_times: u8,          // 退出确认次数
+    config: Config,          // 编辑器配置。
+    prompt_type: PromptType, // 当前的提示类型。
+    throttle: RenderThrottle, // 根据终端速度调整重绘频率。
+    overlay: Overlay,        // 覆盖在视图之上的列表，例如替换预览。
+    replace_state: ReplaceState, // 全部替换的查找内容和匹配。
+    status_publisher: StatusPublisher, // 将状态发布给外部程序。
+    dry_run: DryRun,         // 等待确认的破坏性动作。
+    digraph_matches: Vec<(String, char)>, // 列表中显示的二合字母。
+    startup: Option<StartupTimer>, // 启动各阶段的耗时，首帧绘制后显示。
+    tasks: Tasks,            // 正在后台运行的任务，如保存。
+    quit_deadline: Option<Instant>, // 退出时等待后台任务的截止时间。
+    readonly: bool,          // 只读模式下拒绝所有修改缓冲区的操作。
+    pending_event: Option<Event>, // 判定单独的 Esc 时提前读到、尚未处理的事件。
+    render_failures: usize,  // 连续绘制失败的帧数。
+    resume_prompt: Option<(PromptType, String)>, // 从临时打开的覆盖层返回时恢复的提示及其消息。
+    unsaved_diff: Vec<DiffLine>, // 列表中显示的未保存的修改。
+    pending_open: Option<PathBuf>, // 等待确认是否新建的文件。
+    key_map: KeyMap,         // 按键绑定文件中的自定义绑定。
+    exit_message: Option<String>, // 恢复终端之后打印的说明，如因终端失效而退出。
+    crash_swaps: CrashSwaps, // 与 panic 钩子共享的未保存内容，崩溃时写入交换文件。
 }
 
 impl Editor {
     /// 构造方法，用于创建一个新的 `Editor` 实例。
+    ///
+    /// 这里只做显示首帧所必需的初始化，其余步骤推迟到 `finish_startup`。
+    ///
+    /// # Errors
+    ///
+    /// 无法初始化终端时返回错误。
     pub fn new() -> Result<Self, Error> {
-        // 设置 panic 钩子，在程序崩溃时恢复终端状态。
-        let current_hook = take_hook();
-        set_hook(Box::new(move |panic_info| {
-            let _ = Terminal::terminate();
-            current_hook(panic_info);
-        }));
+        let mut startup = StartupTimer::start();
+
+        // 设置 panic 钩子，在程序崩溃时保存未保存的修改并恢复终端状态。
+        let crash_swaps = CrashSwaps::default();
+        Self::set_panic_hook(crash_swaps.clone());
+
+        // 没有指定文件而标准输入是管道时，在进入原始模式之前读完它；
+        // 之后 crossterm 从 /dev/tty 读取按键，键盘仍然可用。
+        let arguments = Arguments::parse(env::args().skip(1));
+        let wants_file = arguments
+            .as_ref()
+            .is_ok_and(|arguments| arguments.file_name.is_some());
+        let piped = (!wants_file && !stdin().is_terminal()).then(|| {
+            let mut bytes = Vec::new();
+            stdin().lock().read_to_end(&mut bytes).map(|_| bytes)
+        });
 
         // 初始化终端并进入原始模式。
         Terminal::initialize()?;
+        startup.phase("terminal");
 
         // 创建默认视图并加载文件（如果提供了文件名）。
+        let (config, config_warning) = Config::load();
         let mut editor = Self::default();
+        editor.crash_swaps = crash_swaps;
+        editor.config = config;
+        editor.view.apply_config(&editor.config);
+        editor.throttle =
+            RenderThrottle::new(editor.config.render_mode, editor.config.slow_flush_threshold);
+        editor.apply_render_mode();
         let size = Terminal::size().unwrap_or_default();
         editor.resize(size);
         editor
             .message_bar
-            .update_message("HELP: Ctrl-S = save | Ctrl-D = quit");
+            .update_message("HELP: Ctrl-S = save | Ctrl-P = palette | Ctrl-D = quit");
+        if let Some(warning) = config_warning {
+            editor.message_bar.update_message(&format!("Config: {warning}"));
+        }
+        let (key_map, problems) = KeyMap::load();
+        editor.key_map = key_map;
+        if !problems.is_empty() {
+            editor.message_bar.update_message(&format!(
+                "Key bindings: {} problem(s): {}",
+                problems.len(),
+                problems.join("; ")
+            ));
+        }
+        startup.phase("ui");
 
-        let args: Vec<String> = env::args().collect();
-        if let Some(file_name) = args.get(1) {
-            if editor.view.load(file_name).is_err() {
-                editor
-                    .message_bar
-                    .update_message(&format!("ERR: Could not open file: {file_name}"));
+        match piped {
+            Some(Ok(bytes)) => {
+                if let Err(err) = editor.view.load_reader(bytes.as_slice(), "[stdin]") {
+                    editor
+                        .message_bar
+                        .update_message(&format!("ERR: Could not read standard input: {err}"));
+                }
             }
+            Some(Err(err)) => editor
+                .message_bar
+                .update_message(&format!("ERR: Could not read standard input: {err}")),
+            None => {}
+        }
+
+        match arguments {
+            Ok(arguments) => {
+                editor.config.apply_cwd_argument(arguments.cwd);
+                if let Some(timeout) = arguments.escape_timeout {
+                    editor.config.escape_timeout = timeout;
+                }
+                if let Some(file_name) = arguments.file_name {
+                    editor.open_start_file(&file_name);
+                }
+                if let Some(line) = arguments.line {
+                    editor.goto_start_location(line, arguments.column.unwrap_or(1));
+                }
+                if arguments.readonly {
+                    editor.readonly = true;
+                    editor
+                        .message_bar
+                        .update_message("Opened read-only (Alt+W to edit)");
+                }
+                editor.check_swap_file();
+                startup.phase("load");
+                if arguments.startup_timings {
+                    editor.startup = Some(startup);
+                }
+            }
+            Err(err) => editor.message_bar.update_message(&format!("ERR: {err}")),
         }
         editor.refresh_status();
         Ok(editor)
     }
 
+    /// 打开命令行中指定的文件，在消息栏中说明打开失败的原因或需要注意的格式。
+    fn open_start_file(&mut self, file_name: &str) {
+        let path = self.config.resolve_path(file_name);
+        if let Err(err) = self.view.load(&path) {
+            self.message_bar.update_message(&format!(
+                "ERR: Could not open file {file_name}: {}",
+                open::describe_error(&err)
+            ));
+        } else if self.view.is_read_only() {
+            self.message_bar
+                .update_message("File is not writable, opened read-only (Alt+W to edit anyway)");
+        } else if self.view.text_format().mixed {
+            self.message_bar.update_message(&format!(
+                "Mixed line endings, will be saved as {}",
+                self.view.text_format().line_ending.name()
+            ));
+        }
+    }
+
+    /// 光标移到命令行中指定的行和列（都从 1 开始）；超出文件末尾的行号落在最后一行，
+    /// 并在消息栏中说明，而不是报错。
+    fn goto_start_location(&mut self, line: usize, column: usize) {
+        let line_count = self.view.line_count();
+        if line > line_count && line_count > 0 {
+            self.view.goto_location(line_count.saturating_sub(1), 0);
+            self.message_bar.update_message(&format!(
+                "Line {line} is past the end of the file, moved to the last line ({line_count})"
+            ));
+        } else {
+            self.view
+                .goto_location(line.saturating_sub(1), column.saturating_sub(1));
+        }
+    }
+
+    /// 首帧绘制完成后执行的初始化：这些步骤不影响编辑，推迟执行以尽快显示界面。
+    fn finish_startup(&mut self) {
+        if let Some(startup) = &mut self.startup {
+            startup.first_frame();
+        }
+        if self.config.publish_status {
+            let status_file = paths::state_dir().map(|dir| dir.join("status"));
+            self.status_publisher = StatusPublisher::new(status_file);
+            self.refresh_status();
+        }
+        if self.config.trash.persist {
+            let _ = self.view.load_trash();
+        }
+        if paths::state_dir().is_none() {
+            self.message_bar.update_message(
+                "No writable state directory: stashes are kept in memory only",
+            );
+        }
+        if let Some(mut startup) = self.startup.take() {
+            startup.phase("deferred");
+            let (segmented, total) = self.view.segmented_lines();
+            self.message_bar.update_message(&format!(
+                "{} | {segmented} of {total} lines segmented",
+                startup.report()
+            ));
+        }
+    }
+
+    /// 命令行中指定了 `--doctor` 时，不进入编辑界面而打印终端环境的诊断报告，返回进程的退出码。
+    #[must_use]
+    pub fn run_doctor() -> Option<i32> {
+        let arguments = Arguments::parse(env::args().skip(1)).ok()?;
+        if !arguments.doctor {
+            return None;
+        }
+        match doctor::run() {
+            Ok(()) => Some(0),
+            Err(err) => {
+                eprintln!("{NAME}: {err}");
+                Some(1)
+            }
+        }
+    }
+
+    /// 命令行中指定了 `--check-roundtrip` 时，检查文件能否原样加载并保存，返回进程的退出码。
+    #[must_use]
+    pub fn run_check_roundtrip() -> Option<i32> {
+        let arguments = Arguments::parse(env::args().skip(1)).ok()?;
+        let file_name = arguments.check_roundtrip?;
+        let config = Config {
+            cwd: arguments.cwd,
+            ..Config::default()
+        };
+        match roundtrip::check(&config.resolve_path(&file_name), config.fallback_encoding) {
+            Ok(true) => Some(0),
+            Ok(false) => Some(1),
+            Err(err) => {
+                eprintln!("{NAME}: {err}");
+                Some(2)
+            }
+        }
+    }
+
+    /// 命令行中指定了 `--batch` 时，不初始化终端而直接执行脚本，返回进程的退出码。
+    #[must_use]
+    pub fn run_batch() -> Option<i32> {
+        let arguments = Arguments::parse(env::args().skip(1)).ok()?;
+        let script = arguments.batch?;
+        let config = Config {
+            cwd: arguments.cwd,
+            ..Config::default()
+        };
+        let file = arguments
+            .file_name
+            .map(|file_name| config.resolve_path(&file_name));
+        match batch::run(&config.resolve_path(&script), file.as_deref(), &config) {
+            Ok(()) => Some(0),
+            Err(err) => {
+                eprintln!("{NAME}: {err}");
+                Some(1)
+            }
+        }
+    }
+
+    /// 以默认配置执行批处理脚本，与 `--batch` 相同，但返回错误信息而不是退出码；
+    /// 供集成测试直接调用。
+    ///
+    /// # Errors
+    ///
+    /// 脚本无法读取、文件无法打开或某条命令执行失败时，返回带有脚本行号的错误信息。
+    pub fn batch(script: &Path, file: Option<&Path>) -> Result<(), String> {
+        batch::run(script, file, &Config::default())
+    }
+
     pub fn resize(&mut self, size: Size) {
         self.terminal_size = size;
-        self.view.resize(Size {
-            height: size.height.saturating_sub(2),
-            width: size.width,
-        });
+        self.view.resize(self.view_size());
+        self.overlay.resize(self.view_size());
 
         self.message_bar.resize(Size {
             height: 1,
             width: size.width,
         });
+        self.command_bar.resize(Size {
+            height: 1,
+            width: size.width,
+        });
         self.status_bar.resize(Size {
             height: 1,
             width: size.width,
         });
     }
 
+    /// 视图区域的尺寸（除去状态栏和消息栏）。
+    const fn view_size(&self) -> Size {
+        Size {
+            height: self.terminal_size.height.saturating_sub(2),
+            width: self.terminal_size.width,
+        }
+    }
+
+    /// 设置 panic 钩子：先把 `crash_swaps` 中未保存的内容写入交换文件，再恢复终端状态。
+    ///
+    /// 交换文件在钩子里写入，而不是依赖缓冲区在栈展开时的 `Drop`，
+    /// 这样 `panic = "abort"` 时崩溃也不会丢失修改。
+    fn set_panic_hook(crash_swaps: CrashSwaps) {
+        let current_hook = take_hook();
+        set_hook(Box::new(move |panic_info| {
+            crash_swaps.write_all();
+            let _ = Terminal::terminate();
+            statuspublisher::cleanup();
+            current_hook(panic_info);
+        }));
+    }
+
     pub fn refresh_status(&mut self) {
-        let status = self.view.get_status();
-        let title = format!("{} - {NAME}", status.file_name);
+        let mut status = self.view.get_status();
+        status.is_read_only |= self.readonly;
+        let mut title = format!("{} - {NAME}", status.file_name);
+        if let Some(template) = &self.config.title_status_template {
+            title.push(' ');
+            title.push_str(&status.render_template(template));
+        }
+        let title = truncate_to_width(&title, MAX_TITLE_WIDTH, EllipsisSide::Start);
+        self.status_publisher.publish(&status);
         self.status_bar.update_status(status);
 
-        if title != self.title && matches!(Terminal::set_title(&title), Ok(_)) {
+        if title != self.title && Terminal::set_title(&title).is_ok() {
             self.title = title;
         }
     }
 
     /// 主运行循环，处理用户输入并刷新屏幕。
+    ///
+    /// # Panics
+    ///
+    /// 无法从终端读取事件时崩溃，崩溃前 panic 钩子会恢复终端状态。
     pub fn run(&mut self) {
+        let mut started = false;
         loop {
             // 刷新屏幕内容。
             self.refresh_screen();
+            if !started {
+                started = true;
+                self.finish_startup();
+                continue;
+            }
 
             // 如果标志为退出，则跳出循环。
             if self.should_quit {
                 break;
             }
 
+            // 有后台任务时定期检查它们是否结束。
+            if let Some(interval) = self.tasks.poll_interval() {
+                self.check_tasks();
+                if !matches!(Terminal::poll(interval), Ok(true)) {
+                    continue;
+                }
+            }
+
+            // 没有输入时，利用空闲时间完成可见区域之外的语法高亮。
+            if self.view.has_pending_highlight() && !matches!(Terminal::poll(Duration::ZERO), Ok(true)) {
+                self.view.highlight_idle();
+                continue;
+            }
+
+            // 有尚未发布的状态时，最多等到防抖期结束，期间没有输入就发布它。
+            if let Some(deadline) = self.status_publisher.pending_deadline()
+                && !matches!(Terminal::poll(deadline.saturating_duration_since(Instant::now())), Ok(true))
+            {
+                self.status_publisher.flush_pending();
+                continue;
+            }
+
+            // 有尚未写入交换文件的修改时，最多等到写入的时间点，期间没有输入就写入。
+            if let Some(deadline) = self.view.swap_deadline()
+                && !matches!(Terminal::poll(deadline.saturating_duration_since(Instant::now())), Ok(true))
+            {
+                self.view.sync_swap();
+                continue;
+            }
+
             // 读取用户输入事件并处理。
-            match read() {
+            match self.read_event() {
                 Ok(event) => self.evaluate_event(event),
                 Err(err) => {
                     #[cfg(debug_assertions)]
@@ -121,11 +505,52 @@ impl Editor {
                     }
                 }
             }
-            let status = self.view.get_status();
-            self.status_bar.update_status(status);
+            self.coalesce_events();
+            self.refresh_status();
+            self.view.sync_swap();
+            self.view.update_crash_swaps(&self.crash_swaps);
+        }
+    }
+
+    /// 在下一帧之前处理所有已到达的事件，使输入法一次上屏或粘贴产生的多个按键只重绘一次。
+    /// 降级模式下还会一直等到下一帧的时间点。
+    fn coalesce_events(&mut self) {
+        let deadline = if self.throttle.is_degraded() {
+            self.throttle.next_frame_at()
+        } else {
+            Instant::now()
+        };
+        while !self.should_quit {
+            if let Some(event) = self.pending_event.take() {
+                self.evaluate_event(event);
+                continue;
+            }
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            match Terminal::poll(timeout) {
+                Ok(true) => match self.read_event() {
+                    Ok(event) => self.evaluate_event(event),
+                    Err(_) => break,
+                },
+                _ => break,
+            }
         }
     }
 
+    /// 读取一个事件，被拆开的 Alt 组合键在这里合并（见 `escape::resolve`）。
+    fn read_event(&mut self) -> Result<Event, Error> {
+        let (event, pending) = escape::resolve(Terminal::read()?, self.config.escape_timeout)?;
+        self.pending_event = pending;
+        Ok(event)
+    }
+
+    /// 根据当前是否降级，开关装饰性的渲染并更新状态栏标志。
+    fn apply_render_mode(&mut self) {
+        let degraded = self.throttle.is_degraded();
+        self.view
+            .set_show_scroll_markers(self.config.show_scroll_markers && !degraded);
+        self.status_bar.set_degraded(degraded);
+    }
+
     /// 处理用户输入事件。
     #[allow(clippy::needless_pass_by_value)]
     fn evaluate_event(&mut self, event: Event) {
@@ -135,54 +560,473 @@ impl Editor {
             _ => false,
         };
 
-        if should_process {
-            if let Ok(command) = Command::try_from(event) {
-                self.process_command(command);
+        if matches!(&event, Event::Key(KeyEvent { kind: KeyEventKind::Press, .. }) | Event::Paste(_)) {
+            self.view.clear_flash();
+        }
+        if let Event::Paste(text) = &event {
+            self.paste_text(text);
+            return;
+        }
+        if let Event::Mouse(mouse_event) = &event {
+            self.handle_mouse(*mouse_event);
+            return;
+        }
+        if !should_process {
+            return;
+        }
+        let custom = match &event {
+            Event::Key(key_event) => self.key_map.get(*key_event),
+            _ => None,
+        };
+        if let Some(command) = custom.or_else(|| Command::try_from(event).ok()) {
+            self.process_command(command);
+        }
+    }
+
+    /// 处理鼠标事件：没有提示时左键按下把光标移到点击的位置，滚轮滚动视图，其他鼠标事件被忽略。
+    fn handle_mouse(&mut self, event: MouseEvent) {
+        if self.prompt_type != PromptType::None {
+            return;
+        }
+        match event.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                self.view.clear_flash();
+                self.view.click(Position {
+                    col: usize::from(event.column),
+                    row: usize::from(event.row),
+                });
+            }
+            MouseEventKind::ScrollUp => {
+                self.view.scroll_screen(ScrollDirection::Up, self.config.scroll_lines);
+            }
+            MouseEventKind::ScrollDown => {
+                self.view.scroll_screen(ScrollDirection::Down, self.config.scroll_lines);
+            }
+            _ => {}
+        }
+    }
+
+    /// 处理终端的括号粘贴：一次收到整段文本，而不是逐个按键。
+    ///
+    /// 在命令栏中只插入第一行；没有提示时插入到光标处；其余提示中忽略。
+    fn paste_text(&mut self, text: &str) {
+        if self.in_text_prompt() {
+            let line = text.lines().next().unwrap_or_default();
+            for character in line.chars().filter(|ch| !ch.is_control()) {
+                self.command_bar.handle_edit_command(Insert(character));
             }
+        } else if self.prompt_type == PromptType::None && self.check_writable() {
+            self.view.insert_pasted(text);
         }
     }
 
     fn process_command(&mut self, command: Command) {
+        if let System(Resize(size)) = command {
+            self.resize(size);
+            return;
+        }
+        match self.prompt_type {
+            PromptType::ConfirmSave => self.process_command_during_confirm_save(command),
+            PromptType::Palette
+            | PromptType::Search
+            | PromptType::ReplaceSearch
+            | PromptType::ReplaceWith
+            | PromptType::ReplaceEachSearch
+            | PromptType::ReplaceEachWith
+            | PromptType::DigraphSearch
+            | PromptType::GotoLine
+            | PromptType::SaveAs
+            | PromptType::OpenFile
+            | PromptType::SetEncoding => {
+                self.process_command_during_text_prompt(command);
+            }
+            PromptType::ReplacePreview => self.process_command_during_replace_preview(command),
+            PromptType::ConfirmReplace => self.process_command_during_confirm_replace(command),
+            PromptType::ConfirmChanges => self.process_command_during_confirm_changes(command),
+            PromptType::ChangePreview => self.process_command_during_change_preview(command),
+            PromptType::Digraph(first) => self.process_command_during_digraph(command, first),
+            PromptType::DigraphList => self.process_command_during_digraph_list(command),
+            PromptType::LineMarkList => self.process_command_during_line_mark_list(command),
+            PromptType::TrashList => self.process_command_during_trash_list(command),
+            PromptType::WaitForTasks => self.process_command_during_wait_for_tasks(command),
+            PromptType::ConfirmForceQuit => {
+                self.process_command_during_confirm_force_quit(command);
+            }
+            PromptType::ConfirmQuit => self.process_command_during_confirm_quit(command),
+            PromptType::ConfirmCreate => self.process_command_during_confirm_create(command),
+            PromptType::ConfirmReload => self.process_command_during_confirm_reload(command),
+            PromptType::ConfirmRecoverSwap => {
+                self.process_command_during_confirm_recover_swap(command);
+            }
+            PromptType::ConfirmOverwrite => self.process_command_during_confirm_overwrite(command),
+            PromptType::ConfirmLossySave => {
+                self.process_command_during_confirm_lossy_save(command);
+            }
+            PromptType::UnsavedChanges => self.process_command_during_unsaved_changes(command),
+            PromptType::None => self.process_command_no_prompt(command),
+        }
+    }
+
+    /// 当前是否在命令栏中输入文本。
+    const fn in_text_prompt(&self) -> bool {
+        matches!(
+            self.prompt_type,
+            PromptType::Palette
+                | PromptType::Search
+                | PromptType::ReplaceSearch
+                | PromptType::ReplaceWith
+                | PromptType::ReplaceEachSearch
+                | PromptType::ReplaceEachWith
+                | PromptType::DigraphSearch
+                | PromptType::GotoLine
+                | PromptType::SaveAs
+                | PromptType::OpenFile
+                | PromptType::SetEncoding
+        )
+    }
+
+    /// 当前是否显示覆盖层。
+    const fn in_overlay(&self) -> bool {
+        matches!(
+            self.prompt_type,
+            PromptType::ReplacePreview
+                | PromptType::ChangePreview
+                | PromptType::DigraphList
+                | PromptType::LineMarkList
+                | PromptType::TrashList
+                | PromptType::UnsavedChanges
+        )
+    }
+
+    /// 在命令栏中显示输入提示。
+    fn enter_text_prompt(&mut self, prompt_type: PromptType, prompt: &str) {
+        self.prompt_type = prompt_type;
+        self.command_bar.set_prompt(prompt);
+        self.command_bar.clear_value();
+    }
+
+    /// 暂时离开当前待决的提示（例如打开覆盖层查看详情），之后由 `resume_prompt` 回到同一个提示。
+    fn suspend_prompt(&mut self, message: &str) {
+        self.resume_prompt = Some((self.prompt_type, message.to_string()));
+    }
+
+    /// 关闭临时打开的覆盖层，回到之前待决的提示并重新显示它的消息。
+    fn resume_prompt(&mut self) {
+        if let Some((prompt_type, message)) = self.resume_prompt.take() {
+            self.prompt_type = prompt_type;
+            self.message_bar.update_message(&message);
+        } else {
+            self.prompt_type = PromptType::None;
+        }
+        self.view.set_needs_redraw(true);
+    }
+
+    /// 离开命令栏，恢复显示消息栏。
+    fn leave_text_prompt(&mut self) {
+        self.prompt_type = PromptType::None;
+        self.message_bar.set_needs_redraw(true);
+    }
+
+    /// 处理命令栏中的按键，回车时提交输入的内容。
+    fn process_command_during_text_prompt(&mut self, command: Command) {
         match command {
-            System(Quit) => self.handle_quit(),
-            System(Resize(size)) => self.resize(size),
-            _ => self.reset_quit_times(), // Reset quit times for all other commands
+            System(
+                Quit | Resize(_) | Save | Palette | Digraph | Calculate | ToggleLineMark | Search
+                | FindNext | FindPrevious | Replace | ToggleLineNumbers | CopyLine | ToggleSelection
+                | SwapPosition | GotoLine | ToggleSoftWrap | SaveAs | OpenFile | NextBuffer
+                | Reload | ToggleReadOnly | WordCount,
+            )
+            | Move(..) => {}
+            System(Dismiss) => {
+                let message = match self.prompt_type {
+                    PromptType::Palette => "Palette closed.",
+                    PromptType::Search => "Search cancelled.",
+                    PromptType::GotoLine => "Go to line cancelled.",
+                    PromptType::SaveAs => "Save cancelled.",
+                    PromptType::OpenFile => "Open cancelled.",
+                    PromptType::SetEncoding => "Encoding unchanged.",
+                    _ => "Replace cancelled.",
+                };
+                self.leave_text_prompt();
+                self.message_bar.update_message(message);
+            }
+            Edit(InsertNewline) => {
+                let prompt_type = self.prompt_type;
+                let value = self.command_bar.value();
+                self.leave_text_prompt();
+                self.submit_text_prompt(prompt_type, &value);
+            }
+            Edit(edit_command) => self.command_bar.handle_edit_command(edit_command),
+        }
+    }
+
+    /// 根据提示类型处理命令栏中提交的内容。
+    fn submit_text_prompt(&mut self, prompt_type: PromptType, value: &str) {
+        match prompt_type {
+            PromptType::Palette => self.run_palette_query(value),
+            PromptType::Search => self.submit_search(value),
+            PromptType::ReplaceSearch => self.submit_replace_query(value),
+            PromptType::ReplaceWith => self.submit_replacement(value),
+            PromptType::ReplaceEachSearch => self.submit_replace_each_query(value),
+            PromptType::ReplaceEachWith => self.submit_replace_each_replacement(value),
+            PromptType::DigraphSearch => self.submit_digraph_search(value),
+            PromptType::GotoLine => self.submit_goto_line(value),
+            PromptType::SaveAs => self.submit_save_as(value),
+            PromptType::OpenFile => self.submit_open_file(value),
+            PromptType::SetEncoding => self.submit_set_encoding(value),
+            _ => {}
+        }
+    }
+
+    /// 执行命令面板中输入的动作或行范围命令。
+    fn run_palette_query(&mut self, query: &str) {
+        if query.trim_start().starts_with(':') {
+            self.run_range_command(query);
+        } else if let Some(action) = action::find(query) {
+            match action.run {
+                Run::Immediate(run) => run(self),
+                Run::Destructive(plan) => self.run_destructive(action.name, plan),
+            }
+        } else {
+            self.message_bar
+                .update_message(&format!("Actions: {}", action::names()));
+        }
+    }
+
+    fn process_command_no_prompt(&mut self, command: Command) {
+        if matches!(command, System(Quit)) {
+            self.handle_quit();
+            return;
         }
         match command {
-            System(Quit | Resize(_)) => {} // already handled above 1Has a conversation. Original line has a conversation.
+            System(Quit | Resize(_)) => {} // Quit and Resize already handled above
+            System(Dismiss) => self.view.clear_selection(),
             System(Save) => self.handle_save(),
-            Edit(edit_command) => self.view.handle_edit_command(edit_command),
-            Move(move_command) => self.view.handle_move_command(move_command),
+            System(SaveAs) => self.start_save_as(),
+            System(OpenFile) => self.start_open_file(),
+            System(NextBuffer) => self.next_buffer(),
+            System(Reload) => self.reload_file(),
+            System(Palette) => self.enter_text_prompt(PromptType::Palette, "Action: "),
+            System(Digraph) => self.start_digraph(),
+            System(Calculate) => self.calculate(),
+            System(ToggleLineMark) => self.toggle_line_mark(),
+            System(Search) => self.enter_text_prompt(PromptType::Search, "Search: "),
+            System(Replace) => self.start_replace_each(),
+            System(GotoLine) => self.enter_text_prompt(PromptType::GotoLine, "Go to line: "),
+            System(ToggleLineNumbers) => self.toggle_line_numbers(),
+            System(ToggleSoftWrap) => self.toggle_soft_wrap(),
+            System(WordCount) => self.word_count(),
+            System(ToggleReadOnly) => self.toggle_readonly(),
+            System(SwapPosition) => {
+                if !self.view.swap_position() {
+                    self.message_bar
+                        .update_message("Position stored (Ctrl-T again to swap).");
+                }
+            }
+            System(ToggleSelection) => {
+                let message = if self.view.toggle_selection_mode() {
+                    "Selection started (move to extend, Esc or Ctrl-Space to cancel)."
+                } else {
+                    "Selection cancelled."
+                };
+                self.message_bar.update_message(message);
+            }
+            System(CopyLine) => match self
+                .view
+                .copy_selection()
+                .map(|text| (text, "Selection copied."))
+                .map_or_else(|| self.view.copy_line().map(|line| (line, "Line copied.")), Ok)
+            {
+                Ok((text, message)) => {
+                    if Terminal::copy_to_clipboard(&text).is_ok() {
+                        self.message_bar.update_message(message);
+                    } else {
+                        self.message_bar
+                            .update_message("ERR: Could not copy to clipboard");
+                    }
+                }
+                Err(message) => self.message_bar.update_message(&message),
+            },
+            System(FindNext) => {
+                let result = self.view.search_forward();
+                self.report_search(result);
+            }
+            System(FindPrevious) => {
+                let result = self.view.search_backward();
+                self.report_search(result);
+            }
+            Edit(edit_command) => self.handle_edit(edit_command),
+            Move(move_command, select) => self.view.handle_move_command(move_command, select),
+        }
+    }
+
+    /// 查找输入的文本，光标移动到第一个匹配处。
+    fn submit_search(&mut self, query: &str) {
+        if query.is_empty() {
+            self.message_bar.update_message("Search cancelled.");
+            return;
+        }
+        let result = self.view.search(query);
+        self.report_search(result);
+    }
+
+    /// 跳转到输入的 `行` 或 `行:列`（都从 1 开始），输入无效时在消息栏中显示原因。
+    fn submit_goto_line(&mut self, value: &str) {
+        let value = value.trim();
+        if value.is_empty() {
+            self.message_bar.update_message("Go to line cancelled.");
+            return;
+        }
+        match parse_goto_target(value, self.view.line_count()) {
+            Ok((line_index, grapheme_index)) => {
+                self.view.goto_location(line_index, grapheme_index);
+            }
+            Err(err) => self.message_bar.update_message(&err),
+        }
+    }
+
+    /// 查找失败时在消息栏中显示原因。
+    fn report_search(&mut self, result: Result<(), String>) {
+        if let Err(err) = result {
+            self.message_bar.update_message(&err);
+        }
+    }
+
+    /// 处理可疑缩进确认提示中的按键。
+    fn process_command_during_confirm_save(&mut self, command: Command) {
+        match command {
+            Edit(Insert('y' | 'Y')) => {
+                self.prompt_type = PromptType::None;
+                self.save();
+            }
+            Edit(Insert('f' | 'F')) => {
+                self.prompt_type = PromptType::None;
+                self.view.fix_indentation();
+                self.save();
+            }
+            Edit(Insert('n' | 'N')) | System(Dismiss) => {
+                self.prompt_type = PromptType::None;
+                self.message_bar.update_message("Save aborted.");
+            }
+            _ => {}
+        }
+    }
+
+    /// 执行 `:10,20d` 形式的行范围命令，并报告影响的行数。
+    fn run_range_command(&mut self, input: &str) {
+        let current_line = self.view.text_location().line_index;
+        let (range, operation) = match linerange::parse(input, current_line, self.view.line_count())
+        {
+            Ok(parsed) => parsed,
+            Err(err) => {
+                self.message_bar.update_message(&format!("ERR: {err}"));
+                return;
+            }
+        };
+        if operation != RangeOperation::Yank && !self.check_writable() {
+            return;
+        }
+        let message = match operation {
+            RangeOperation::Delete => format!("{} lines deleted", self.view.delete_lines(range)),
+            RangeOperation::Indent => format!("{} lines indented", self.view.indent_lines(range)),
+            RangeOperation::Dedent => format!("{} lines dedented", self.view.dedent_lines(range)),
+            RangeOperation::Yank => {
+                let text = self.view.lines_text(range);
+                self.view.set_register(&text);
+                if Terminal::copy_to_clipboard(&text).is_ok() {
+                    format!("{} lines yanked", range.len())
+                } else {
+                    String::from("ERR: Could not copy to clipboard")
+                }
+            }
+        };
+        self.message_bar.update_message(&message);
+    }
+
+    /// 只读模式下或文件没有写权限时报告并返回 `false`。
+    fn check_writable(&mut self) -> bool {
+        if self.readonly {
+            self.message_bar
+                .update_message("ERR: Buffer is read-only — press Alt+W to edit it");
+        } else if self.view.is_read_only() {
+            self.message_bar.update_message(
+                "ERR: File is not writable — press Alt+W to edit it anyway (save as another file)",
+            );
+        }
+        !self.readonly && !self.view.is_read_only()
+    }
+
+    /// 执行编辑命令；修改整个缓冲区的命令和命令面板中的同名动作一样先试运行再确认。
+    fn handle_edit(&mut self, edit_command: command::Edit) {
+        match edit_command {
+            command::Edit::ConvertIndentation => {
+                self.run_destructive("convert indentation", View::indentation_changes);
+            }
+            command::Edit::TrimTrailingWhitespace => {
+                self.run_destructive("trim trailing whitespace", View::trailing_whitespace_changes);
+            }
+            _ => self.apply_edit(edit_command),
+        }
+    }
+
+    fn apply_edit(&mut self, edit_command: command::Edit) {
+        if !self.check_writable() {
+            return;
+        }
+        if let Err(message) = self.view.handle_edit_command(edit_command) {
+            self.message_bar.update_message(&message);
         }
     }
 
     fn handle_save(&mut self) {
-        if self.view.save().is_ok() {
-            self.message_bar.update_message("File saved successfully.");
-        } else {
-            self.message_bar.update_message("Error writing file!");
+        if !self.check_writable() {
+            return;
+        }
+        if self.view.file_path().is_none() {
+            self.start_save_as();
+            return;
+        }
+        if self.config.lint_indentation_on_save {
+            let count = self.view.suspicious_indentation_count();
+            if count > 0 {
+                self.prompt_type = PromptType::ConfirmSave;
+                self.message_bar.update_message(&format!(
+                    "{count} lines with suspicious indentation — save anyway? (y)es / (f)ix / (n)o"
+                ));
+                return;
+            }
         }
+        self.save();
     }
 
+    /// 询问保存的文件名。
+    fn start_save_as(&mut self) {
+        if self.check_writable() {
+            self.enter_text_prompt(PromptType::SaveAs, "Save as: ");
+        }
+    }
 
-    #[allow(clippy::arithmetic_side_effects)]
-     fn handle_quit(&mut self) {
-         if !self.view.get_status().is_modified || self.quit_times + 1 == QUIT_TIMES { 
-             self.should_quit = true;
-         } else if self.view.get_status().is_modified { 
-             self.message_bar.update_message(&format!(
-                 "WARNING! File has unsaved changes. Press Ctrl-D {} more times to quit.",
-                 QUIT_TIMES - self.quit_times - 1
-             ));
- 
-             self.quit_times += 1;
-         }
-     }
+    /// 以输入的文件名保存，之后的保存也写入该文件；相对路径相对于配置的工作目录。
+    fn submit_save_as(&mut self, file_name: &str) {
+        let file_name = file_name.trim();
+        if file_name.is_empty() {
+            self.message_bar.update_message("Save cancelled.");
+            return;
+        }
+        self.view.set_path(&self.config.resolve_path(file_name));
+        self.handle_save();
+    }
 
-     fn reset_quit_times(&mut self) {
-        if self.quit_times > 0 {
-            self.quit_times = 0;
-            self.message_bar.update_message(""); 
+    /// 退出编辑器；有后台任务时先等待它们结束，有未保存的修改时先询问。
+    fn handle_quit(&mut self) {
+        if !self.tasks.is_empty() {
+            self.wait_for_tasks();
+            return;
+        }
+        if self.view.focus_modified_buffer() {
+            self.confirm_quit();
+        } else {
+            self.should_quit = true;
         }
     }
 
@@ -195,24 +1039,111 @@ impl Editor {
         // 隐藏光标。
         let _ = Terminal::hide_caret();
 
-        self.message_bar
-            .render(self.terminal_size.height.saturating_sub(1));
+        let bottom_bar_row = self.terminal_size.height.saturating_sub(1);
+        let mut result = if self.in_text_prompt() {
+            self.command_bar.render(bottom_bar_row)
+        } else {
+            self.message_bar.render(bottom_bar_row)
+        };
 
         if self.terminal_size.height > 1 {
-            self.status_bar
-                .render(self.terminal_size.height.saturating_sub(2));
+            result = result.and(
+                self.status_bar
+                    .render(self.terminal_size.height.saturating_sub(2)),
+            );
         }
 
         if self.terminal_size.height > 2 {
-            self.view.render(0);
+            let rendered = match self.prompt_type {
+                PromptType::ReplacePreview => {
+                    self.fill_replace_preview();
+                    self.overlay.render(0)
+                }
+                PromptType::ChangePreview => {
+                    self.fill_change_preview();
+                    self.overlay.render(0)
+                }
+                PromptType::DigraphList => {
+                    self.fill_digraph_list();
+                    self.overlay.render(0)
+                }
+                PromptType::LineMarkList => {
+                    self.fill_line_mark_list();
+                    self.overlay.render(0)
+                }
+                PromptType::TrashList => {
+                    self.fill_trash_list();
+                    self.overlay.render(0)
+                }
+                PromptType::UnsavedChanges => {
+                    self.fill_unsaved_changes();
+                    self.overlay.render(0)
+                }
+                _ => self.view.render(0),
+            };
+            result = result.and(rendered);
+        }
+
+        // 将光标移动到当前的位置；视图被滚轮滚走、光标不在视图中时不显示光标。
+        let caret_position = if self.in_overlay() {
+            Some(self.overlay.caret_position())
+        } else if self.in_text_prompt() {
+            Some(Position {
+                row: bottom_bar_row,
+                col: self.command_bar.caret_position_col(),
+            })
+        } else {
+            self.view.caret_visible().then(|| self.view.caret_position())
+        };
+        if let Some(caret_position) = caret_position {
+            result = result.and(Terminal::move_caret_to(caret_position));
+            // 显示光标。
+            result = result.and(Terminal::show_caret());
         }
 
-        // 将光标移动到当前的位置。
-        let _ = Terminal::move_caret_to(self.view.caret_position());
+        // 刷新终端，同时记录刷新耗时以检测过慢的终端。
+        let flush_start = Instant::now();
+        result = result.and(Terminal::execute());
+        self.throttle.record_flush(flush_start.elapsed());
+        self.apply_render_mode();
+
+        if result.is_ok() {
+            self.render_failures = 0;
+        } else {
+            self.handle_render_failure();
+        }
+    }
 
-        // 显示光标并刷新终端。
-        let _ = Terminal::show_caret();
-        let _ = Terminal::execute();
+    /// 一帧没有完整绘制到终端上：已经写出的部分可能只到达了一半，下一帧重绘所有组件。
+    ///
+    /// 连续失败 `MAX_RENDER_FAILURES` 帧时认为终端已经不可用，暂存未保存的修改后退出，
+    /// 而不是不停地重试。
+    fn handle_render_failure(&mut self) {
+        self.render_failures = self.render_failures.saturating_add(1);
+        self.view.set_needs_redraw(true);
+        self.status_bar.set_needs_redraw(true);
+        self.message_bar.set_needs_redraw(true);
+        self.command_bar.set_needs_redraw(true);
+        self.overlay.set_needs_redraw(true);
+        if self.render_failures < MAX_RENDER_FAILURES {
+            return;
+        }
+        let stashed = match self.view.stash_all_unsaved() {
+            Ok(paths) if paths.is_empty() => String::new(),
+            Ok(paths) => format!(
+                ", unsaved changes stashed to {}",
+                paths
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Err(err) => format!(", could not stash unsaved changes: {err}"),
+        };
+        self.exit_message = Some(format!(
+            "{NAME}: terminal stopped accepting output after {MAX_RENDER_FAILURES} failed frames{stashed}"
+        ));
+        self.should_quit = true;
     }
 }
 
@@ -222,9 +1153,427 @@ impl Drop for Editor {
         // 恢复终端状态。
         let _ = Terminal::terminate();
 
-        // 如果退出标志为真，打印退出消息。
-        if self.should_quit {
+        // 因终端失效而退出时说明原因，否则打印退出消息。
+        if let Some(message) = &self.exit_message {
+            eprintln!("{message}");
+        } else if self.should_quit {
             let _ = Terminal::print("Goodbye.\r\n");
         }
     }
 }
+
+/// 解析跳转提示中输入的 `行` 或 `行:列`，返回从 0 开始的行索引和字素索引。
+fn parse_goto_target(value: &str, line_count: usize) -> Result<(usize, usize), String> {
+    let (line, column) = value
+        .split_once(':')
+        .map_or((value, None), |(line, column)| (line, Some(column)));
+    let parse = |text: &str, what: &str| match text.trim().parse::<usize>() {
+        Ok(0) => Err(format!("{what} numbers start at 1")),
+        Ok(number) => Ok(number.saturating_sub(1)),
+        Err(_) => Err(format!("Not a {}: {}", what.to_lowercase(), text.trim())),
+    };
+    let line_index = parse(line, "Line")?;
+    let last = line_count.max(1);
+    if line_index >= last {
+        return Err(format!("Line {} is out of range (1-{last})", line_index.saturating_add(1)));
+    }
+    let grapheme_index = column.map_or(Ok(0), |column| parse(column, "Column"))?;
+    Ok((line_index, grapheme_index))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 打开了 `text` 的编辑器，不初始化终端。
+    fn editor_with(text: &str) -> Editor {
+        let mut editor = Editor::default();
+        editor.view.load_reader(text.as_bytes(), "[test]").unwrap();
+        editor
+    }
+
+    fn lines(editor: &Editor) -> Vec<String> {
+        (0..editor.view.line_count()).map(|index| editor.view.line_text(index)).collect()
+    }
+
+    #[test]
+    fn declining_a_bulk_edit_leaves_the_buffer_unchanged() {
+        let mut editor = editor_with("a \nb\t\nc\n");
+        editor.handle_edit(command::Edit::TrimTrailingWhitespace);
+        assert_eq!(
+            editor.message_bar.text(),
+            "This will modify 2 lines — proceed? (y/n / p to preview)"
+        );
+
+        editor.process_command(Edit(Insert('n')));
+
+        assert_eq!(editor.message_bar.text(), "Aborted.");
+        assert_eq!(lines(&editor), vec!["a ", "b\t", "c"]);
+        assert!(editor.prompt_type == PromptType::None);
+    }
+
+    #[test]
+    fn accepting_a_bulk_edit_applies_it_as_one_undo_step() {
+        let mut editor = editor_with("a \nb\t\nc\n");
+        editor.handle_edit(command::Edit::TrimTrailingWhitespace);
+
+        editor.process_command(Edit(Insert('y')));
+
+        assert_eq!(editor.message_bar.text(), "trim trailing whitespace: 2 lines changed.");
+        assert_eq!(lines(&editor), vec!["a", "b", "c"]);
+        editor.process_command(Edit(command::Edit::Undo));
+        assert_eq!(lines(&editor), vec!["a ", "b\t", "c"]);
+    }
+
+    #[test]
+    fn bulk_edits_apply_without_asking_when_confirmation_is_off() {
+        let mut editor = editor_with("a \nb\t\nc\n");
+        editor.config.confirm_destructive_actions = false;
+
+        editor.handle_edit(command::Edit::TrimTrailingWhitespace);
+
+        assert_eq!(lines(&editor), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn both_bulk_edits_can_be_previewed_line_by_line() {
+        for (edit_command, text) in [
+            (command::Edit::TrimTrailingWhitespace, "a \nb\t\nc\n"),
+            (command::Edit::ConvertIndentation, "x\n    a\n    b\n\tc\n"),
+        ] {
+            let mut editor = editor_with(text);
+            editor.handle_edit(edit_command);
+            assert!(editor.prompt_type == PromptType::ConfirmChanges);
+
+            editor.process_command(Edit(Insert('p')));
+            assert!(editor.prompt_type == PromptType::ChangePreview);
+            editor.process_command(System(Dismiss));
+
+            assert_eq!(editor.message_bar.text(), "Aborted.");
+            assert_eq!(lines(&editor).join("\n"), text.trim_end_matches('\n'));
+        }
+    }
+
+    #[test]
+    fn range_commands_change_the_lines_and_undo_in_one_step() {
+        let text = "a\n\tb\n\tc\nd\n";
+        for (input, message, expected) in [
+            (":2,3d", "2 lines deleted", vec!["a", "d"]),
+            (":1,$>", "4 lines indented", vec!["\ta", "\t\tb", "\t\tc", "\td"]),
+            (":.,3<", "2 lines dedented", vec!["a", "b", "c", "d"]),
+        ] {
+            let mut editor = editor_with(text);
+
+            editor.run_range_command(input);
+
+            assert_eq!(editor.message_bar.text(), message, "{input}");
+            assert_eq!(lines(&editor), expected, "{input}");
+            editor.process_command(Edit(command::Edit::Undo));
+            assert_eq!(lines(&editor), vec!["a", "\tb", "\tc", "d"], "{input}");
+        }
+    }
+
+    #[test]
+    fn reloading_over_unsaved_changes_stashes_them_for_recovery() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        std::fs::write(&path, "first\n").unwrap();
+        let mut editor = Editor::default();
+        editor.view.load(&path).unwrap();
+        for character in "mine ".chars() {
+            editor.process_command(Edit(Insert(character)));
+        }
+        // 另一个程序同时修改了磁盘上的文件。
+        std::fs::write(&path, "theirs\n").unwrap();
+
+        editor.reload_file();
+        assert!(editor.prompt_type == PromptType::ConfirmReload);
+        editor.process_command(Edit(Insert('y')));
+
+        assert!(editor.message_bar.text().ends_with("your unsaved version was stashed"));
+        assert_eq!(lines(&editor), vec!["theirs"]);
+        let (stashed, contents) = stash::latest().unwrap();
+        assert_eq!(contents, "mine first\n");
+
+        editor.recover_stash();
+        let _ = std::fs::remove_file(&stashed);
+        assert_eq!(lines(&editor), vec!["mine first"]);
+        assert!(editor.view.file_path().is_none());
+    }
+
+    #[test]
+    fn a_thousand_line_file_draws_its_first_frame_within_budget() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("large.txt");
+        let text = (1..=1000)
+            .map(|number| format!("line {number} of the file"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        std::fs::write(&path, text).unwrap();
+        terminal::headless::set_size(Size { height: 30, width: 100 });
+
+        // 与 `new` 和 `run` 相同的顺序：初始化界面、加载文件、绘制首帧、完成推迟的初始化。
+        let mut startup = StartupTimer::start();
+        let mut editor = Editor::default();
+        editor.resize(Terminal::size().unwrap());
+        startup.phase("ui");
+        editor.view.load(&path).unwrap();
+        startup.phase("load");
+        editor.startup = Some(startup);
+        editor.refresh_screen();
+        editor.finish_startup();
+
+        let output = terminal::headless::take_output();
+        assert!(output.contains("line 1 of the file"));
+        assert!(!output.contains("line 100 of the file"));
+        let report = editor.message_bar.text();
+        assert!(report.contains("within budget"), "{report}");
+    }
+
+    #[test]
+    fn without_a_state_dir_the_editor_still_starts_edits_and_saves() {
+        paths::without_state_dir(|| {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("notes.txt");
+            std::fs::write(&path, "first\n").unwrap();
+            let mut editor = Editor::default();
+            editor.view.load(&path).unwrap();
+            editor.finish_startup();
+            assert!(editor.message_bar.text().starts_with("No writable state directory"));
+
+            for character in "mine ".chars() {
+                editor.process_command(Edit(Insert(character)));
+            }
+            // 重新加载时未保存的内容只能暂存在内存中，但重新加载不受影响。
+            std::fs::write(&path, "theirs\n").unwrap();
+            editor.reload_file();
+            editor.process_command(Edit(Insert('y')));
+            assert!(editor.message_bar.text().ends_with("your unsaved version was stashed"));
+            assert_eq!(lines(&editor), vec!["theirs"]);
+
+            editor.process_command(Edit(Insert('!')));
+            assert!(editor.view.save().unwrap().is_empty());
+            let saved = std::fs::read_to_string(&path).unwrap();
+            assert!(saved.contains('!'));
+            assert_eq!(saved, format!("{}\n", lines(&editor).join("\n")));
+        });
+    }
+
+    /// 逐个字符按下的按键事件。
+    fn key_events(text: &str) -> Vec<Event> {
+        use crossterm::event::{KeyCode, KeyModifiers};
+        text.chars()
+            .map(|character| Event::Key(KeyEvent::new(KeyCode::Char(character), KeyModifiers::NONE)))
+            .collect()
+    }
+
+    /// 主循环中的一轮：等待并处理一批输入，然后绘制一帧，返回这一帧的输出。
+    fn input_then_frame(editor: &mut Editor) -> String {
+        let event = editor.read_event().unwrap();
+        editor.evaluate_event(event);
+        editor.coalesce_events();
+        editor.refresh_status();
+        editor.refresh_screen();
+        terminal::headless::take_output()
+    }
+
+    #[test]
+    fn an_input_method_burst_is_drawn_in_one_frame_with_the_caret_after_it() {
+        // 行号栏占两列，文本区只有十列，宽字符的输入会引起水平滚动。
+        terminal::headless::set_size(Size { height: 5, width: 12 });
+        let mut editor = Editor::default();
+        editor.view.apply_config(&editor.config);
+        editor.apply_render_mode();
+        editor.resize(Terminal::size().unwrap());
+        editor.refresh_screen();
+        terminal::headless::take_output();
+
+        terminal::headless::send_events(key_events("你好世界啊呀"));
+        terminal::headless::send_events(key_events("!"));
+        let frame = input_then_frame(&mut editor);
+
+        // 第一批输入全部处理完才绘制，之后的一批留到下一帧。
+        assert_eq!(frame.matches("\x1b[?25l").count(), 1);
+        assert_eq!(editor.view.line_text(0), "你好世界啊呀");
+        // 共 12 列宽的文本滚过了 4 列，左侧的延续标记盖住了半个「世」；光标紧跟在最后一个字
+        // 之后，不在右侧的延续标记上：行号栏 2 列加上可见的 8 列，即第 10 列
+        // （终端坐标从 1 开始为第 11 列）。
+        assert!(frame.contains("‹\x1b[0m⋯界啊呀"), "{frame:?}");
+        assert!(frame.ends_with("\x1b[1;11H\x1b[?25h"), "{frame:?}");
+
+        let frame = input_then_frame(&mut editor);
+        assert_eq!(frame.matches("\x1b[?25l").count(), 1);
+        assert_eq!(editor.view.line_text(0), "你好世界啊呀!");
+        assert!(frame.ends_with("\x1b[1;11H\x1b[?25h"), "{frame:?}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn control_characters_in_the_file_name_do_not_reach_the_terminal() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bad\nname \x1b.txt");
+        std::fs::write(&path, "text\n").unwrap();
+        terminal::headless::set_size(Size { height: 5, width: 60 });
+        let mut editor = Editor::default();
+        editor.resize(Terminal::size().unwrap());
+        terminal::headless::take_output();
+
+        editor.view.load(&path).unwrap();
+        editor.refresh_status();
+        editor.refresh_screen();
+
+        let output = terminal::headless::take_output();
+        assert!(output.contains("\x1b]0;bad?name ?.txt - snows_edit\x07"), "{output:?}");
+        assert!(output.contains("bad?name ?.txt"));
+        assert!(!output.contains('\n'));
+        assert!(!output.contains("\x1b."));
+    }
+
+    #[test]
+    fn a_key_read_while_resolving_an_escape_is_still_handled() {
+        use crossterm::event::{KeyCode, KeyModifiers};
+        let mut editor = editor_with("one\ntwo\n");
+        editor.view.goto_location(1, 0);
+        assert_eq!(editor.view.get_status().current_line_index, 1);
+        let escape = Event::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        let up = Event::Key(KeyEvent::new(KeyCode::Up, KeyModifiers::NONE));
+        terminal::headless::send_events([escape, up]);
+
+        input_then_frame(&mut editor);
+
+        assert!(editor.pending_event.is_none());
+        assert_eq!(editor.view.get_status().current_line_index, 0);
+    }
+
+    #[test]
+    fn the_frame_after_a_failed_one_repaints_everything() {
+        let size = Size { height: 6, width: 40 };
+        terminal::headless::set_size(size);
+        // 依次让一帧中的每一次写入失败，直到这一帧的写入次数都试过为止。
+        for k in 1.. {
+            let mut editor = editor_with("alpha\nbeta\ngamma\n");
+            editor.message_bar.update_message("hello");
+            editor.refresh_status();
+            editor.resize(size);
+
+            terminal::headless::fail_on_write(k);
+            editor.refresh_screen();
+            terminal::headless::stop_failing();
+            terminal::headless::take_output();
+            if editor.render_failures == 0 {
+                assert!(k > 10, "a full frame takes more than {k} writes");
+                break;
+            }
+
+            editor.refresh_screen();
+
+            let text = terminal::headless::take_text();
+            for expected in ["alpha", "beta", "gamma", "[test]", "hello"] {
+                assert!(text.contains(expected), "failing write {k}: {expected} missing from {text:?}");
+            }
+            assert_eq!(editor.render_failures, 0);
+        }
+    }
+
+    #[test]
+    fn a_terminal_that_keeps_failing_stashes_the_changes_and_quits() {
+        paths::without_state_dir(|| {
+            terminal::headless::set_size(Size { height: 6, width: 40 });
+            let mut editor = editor_with("alpha\n");
+            editor.resize(Terminal::size().unwrap());
+            editor.process_command(Edit(Insert('!')));
+
+            terminal::headless::fail_on_write(1);
+            for _ in 1..MAX_RENDER_FAILURES {
+                editor.refresh_screen();
+                assert!(!editor.should_quit);
+            }
+            editor.refresh_screen();
+            terminal::headless::stop_failing();
+
+            assert!(editor.should_quit);
+            let message = editor.exit_message.clone().unwrap();
+            assert!(
+                message.contains("after 5 failed frames, unsaved changes stashed to <memory>"),
+                "{message}"
+            );
+            let (_, contents) = stash::latest().unwrap();
+            assert_eq!(contents, "!alpha\n");
+        });
+    }
+
+    #[test]
+    fn the_panic_hook_writes_unsaved_changes_to_the_swap_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        std::fs::write(&path, "first\n").unwrap();
+        let mut editor = Editor::default();
+        editor.view.load(&path).unwrap();
+        for character in "mine ".chars() {
+            editor.process_command(Edit(Insert(character)));
+        }
+        editor.view.update_crash_swaps(&editor.crash_swaps);
+
+        // 编辑器仍然存在，交换文件只可能是钩子写入的，而不是缓冲区的 `Drop`。
+        Editor::set_panic_hook(editor.crash_swaps.clone());
+        let result = std::panic::catch_unwind(|| panic!("crash while editing"));
+        drop(take_hook());
+        assert!(result.is_err());
+
+        let swap = view::swapfile::path_for(&path).unwrap();
+        assert_eq!(std::fs::read_to_string(swap).unwrap(), "mine first\n");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "first\n");
+    }
+
+    #[test]
+    fn quitting_can_look_at_the_unsaved_changes_then_save_and_quit() {
+        use crossterm::event::{KeyCode, KeyModifiers};
+        terminal::headless::set_size(Size { height: 10, width: 60 });
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        std::fs::write(&path, "alpha\nbeta\n").unwrap();
+        let mut editor = Editor::default();
+        editor.resize(Terminal::size().unwrap());
+        editor.view.load(&path).unwrap();
+        editor.refresh_screen();
+        terminal::headless::take_output();
+
+        terminal::headless::send_events(key_events("new "));
+        input_then_frame(&mut editor);
+        terminal::headless::send_events([Event::Key(KeyEvent::new(KeyCode::Char('d'), KeyModifiers::CONTROL))]);
+        input_then_frame(&mut editor);
+        assert!(editor.prompt_type == PromptType::ConfirmQuit);
+
+        // `d` 打开未保存修改的列表，列出被替换的行和新的行。
+        terminal::headless::send_events(key_events("d"));
+        let event = editor.read_event().unwrap();
+        editor.evaluate_event(event);
+        editor.refresh_screen();
+        let frame = terminal::headless::take_text();
+        assert!(editor.prompt_type == PromptType::UnsavedChanges);
+        assert!(frame.contains("-      1  alpha"), "{frame}");
+        assert!(frame.contains("+      1  new alpha"), "{frame}");
+
+        // Esc 回到同一个退出询问，什么都没有丢失。
+        terminal::headless::send_events([Event::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))]);
+        input_then_frame(&mut editor);
+        assert!(editor.prompt_type == PromptType::ConfirmQuit);
+        assert!(editor.message_bar.text().contains("Quit?"), "{}", editor.message_bar.text());
+        assert!(!editor.should_quit);
+
+        terminal::headless::send_events(key_events("s"));
+        input_then_frame(&mut editor);
+        // 保存在后台进行，退出等它完成。
+        for _ in 0..5000 {
+            if editor.should_quit {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+            editor.check_tasks();
+        }
+        assert!(editor.should_quit, "{}", editor.message_bar.text());
+        assert!(editor.tasks.is_empty());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new alpha\nbeta\n");
+        assert_eq!(editor.view.modified_buffer_count(), 0);
+    }
+}
@@ -0,0 +1,489 @@
+use super::{
+    Editor, PromptType, command::Edit, dryrun::Plan, encoding::FileEncoding, stash, terminal::Terminal, throttle::RenderMode,
+    view::{View, textformat::LineEnding},
+};
+use std::{
+    env,
+    path::{Path, PathBuf, absolute},
+    process::{Command, Stdio},
+    thread,
+};
+
+/// 动作的执行方式。
+pub enum Run {
+    Immediate(fn(&mut Editor)), // 直接执行。
+    Destructive(Plan),          // 破坏性动作：先试运行计算修改，确认后再应用。
+}
+
+/// 可以从命令面板执行的动作。
+pub struct Action {
+    pub name: &'static str, // 在命令面板中输入的名称。
+    pub run: Run,           // 执行该动作的方式。
+}
+
+/// 命令面板中可用的所有动作。
+pub const ACTIONS: &[Action] = &[
+    Action {
+        name: "copy file path",
+        run: Run::Immediate(Editor::copy_file_path),
+    },
+    Action {
+        name: "copy relative path",
+        run: Run::Immediate(Editor::copy_relative_path),
+    },
+    Action {
+        name: "copy directory",
+        run: Run::Immediate(Editor::copy_directory),
+    },
+    Action {
+        name: "copy position reference",
+        run: Run::Immediate(Editor::copy_position_reference),
+    },
+    Action {
+        name: "replace all",
+        run: Run::Immediate(Editor::start_replace_all),
+    },
+    Action {
+        name: "convert indentation",
+        run: Run::Destructive(View::indentation_changes),
+    },
+    Action {
+        name: "trim trailing whitespace",
+        run: Run::Destructive(View::trailing_whitespace_changes),
+    },
+    Action {
+        name: "digraphs",
+        run: Run::Immediate(Editor::start_digraph_search),
+    },
+    Action {
+        name: "recover stashed version",
+        run: Run::Immediate(Editor::recover_stash),
+    },
+    Action {
+        name: "paste aligned",
+        run: Run::Immediate(|editor| editor.apply_edit(Edit::PasteAligned)),
+    },
+    Action {
+        name: "calculate",
+        run: Run::Immediate(Editor::calculate),
+    },
+    Action {
+        name: "calculate and append",
+        run: Run::Immediate(Editor::calculate_and_append),
+    },
+    Action {
+        name: "open in new window",
+        run: Run::Immediate(Editor::open_in_new_window),
+    },
+    Action {
+        name: "toggle read-only",
+        run: Run::Immediate(Editor::toggle_readonly),
+    },
+    Action {
+        name: "toggle line numbers",
+        run: Run::Immediate(Editor::toggle_line_numbers),
+    },
+    Action {
+        name: "open file",
+        run: Run::Immediate(Editor::start_open_file),
+    },
+    Action {
+        name: "reload file",
+        run: Run::Immediate(Editor::reload_file),
+    },
+    Action {
+        name: "next buffer",
+        run: Run::Immediate(Editor::next_buffer),
+    },
+    Action {
+        name: "save as",
+        run: Run::Immediate(Editor::start_save_as),
+    },
+    Action {
+        name: "toggle soft wrap",
+        run: Run::Immediate(Editor::toggle_soft_wrap),
+    },
+    Action {
+        name: "word count",
+        run: Run::Immediate(Editor::word_count),
+    },
+    Action {
+        name: "toggle change tint",
+        run: Run::Immediate(Editor::toggle_recent_changes),
+    },
+    Action {
+        name: "jump to next changed line",
+        run: Run::Immediate(Editor::goto_next_changed_line),
+    },
+    Action {
+        name: "convert line endings to LF",
+        run: Run::Immediate(|editor| editor.convert_line_endings(LineEnding::Lf)),
+    },
+    Action {
+        name: "convert line endings to CRLF",
+        run: Run::Immediate(|editor| editor.convert_line_endings(LineEnding::CrLf)),
+    },
+    Action {
+        name: "set encoding",
+        run: Run::Immediate(Editor::start_set_encoding),
+    },
+    Action {
+        name: "toggle final newline",
+        run: Run::Immediate(Editor::toggle_final_newline),
+    },
+    Action {
+        name: "clear swap position",
+        run: Run::Immediate(Editor::clear_swap_position),
+    },
+    Action {
+        name: "toggle line highlight",
+        run: Run::Immediate(Editor::toggle_line_mark),
+    },
+    Action {
+        name: "list line highlights",
+        run: Run::Immediate(Editor::list_line_marks),
+    },
+    Action {
+        name: "clear line highlights",
+        run: Run::Immediate(Editor::clear_line_marks),
+    },
+    Action {
+        name: "show trash",
+        run: Run::Immediate(Editor::list_trash),
+    },
+    Action {
+        name: "check keybindings",
+        run: Run::Immediate(Editor::check_keybindings),
+    },
+    Action {
+        name: "show background tasks",
+        run: Run::Immediate(Editor::show_background_tasks),
+    },
+    Action {
+        name: "render auto",
+        run: Run::Immediate(|editor| editor.set_render_mode(RenderMode::Auto)),
+    },
+    Action {
+        name: "render full",
+        run: Run::Immediate(|editor| editor.set_render_mode(RenderMode::Full)),
+    },
+    Action {
+        name: "render degraded",
+        run: Run::Immediate(|editor| editor.set_render_mode(RenderMode::Degraded)),
+    },
+];
+
+/// 根据输入查找动作：优先完全匹配，其次是名称中包含输入的第一个动作。
+pub fn find(query: &str) -> Option<&'static Action> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return None;
+    }
+    ACTIONS
+        .iter()
+        .find(|action| action.name == query)
+        .or_else(|| ACTIONS.iter().find(|action| action.name.contains(&query)))
+}
+
+/// 列出所有动作的名称，用于提示。
+pub fn names() -> String {
+    ACTIONS
+        .iter()
+        .map(|action| action.name)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl Editor {
+    /// 复制当前文件的绝对路径。
+    fn copy_file_path(&mut self) {
+        if let Some(path) = self.absolute_file_path() {
+            self.copy_text(&path.display().to_string());
+        }
+    }
+
+    /// 复制当前文件相对于工作目录的路径。
+    fn copy_relative_path(&mut self) {
+        if let Some(path) = self.relative_file_path() {
+            self.copy_text(&path.display().to_string());
+        }
+    }
+
+    /// 复制当前文件所在的目录。
+    fn copy_directory(&mut self) {
+        if let Some(path) = self.absolute_file_path() {
+            let directory = path.parent().unwrap_or(&path);
+            self.copy_text(&directory.display().to_string());
+        }
+    }
+
+    /// 复制 `path:line:col` 形式的位置引用。
+    fn copy_position_reference(&mut self) {
+        if let Some(path) = self.relative_file_path() {
+            let location = self.view.text_location();
+            self.copy_text(&format!(
+                "{}:{}:{}",
+                path.display(),
+                location.line_index.saturating_add(1),
+                location.grapheme_index.saturating_add(1)
+            ));
+        }
+    }
+
+    /// 将最近一次暂存的内容恢复到一个未命名的缓冲区。
+    pub(super) fn recover_stash(&mut self) {
+        let (path, contents) = match stash::latest() {
+            Ok(stashed) => stashed,
+            Err(err) => {
+                self.message_bar.update_message(&format!("ERR: {err}"));
+                return;
+            }
+        };
+        let message = match self.view.load_unnamed(&contents) {
+            Ok(Some(_)) => format!(
+                "Recovered {} — your unsaved version was stashed",
+                path.display()
+            ),
+            Ok(None) => format!("Recovered {}", path.display()),
+            Err(err) => format!("ERR: Could not stash unsaved changes: {err}"),
+        };
+        self.message_bar.update_message(&message);
+    }
+
+    /// 在新的终端窗口中以只读方式打开当前文件，并定位到当前行。
+    ///
+    /// 终端程序取自 `$TERMINAL`，未设置时使用 `x-terminal-emulator`。新进程的标准输入输出
+    /// 不连接到当前终端，并放在单独的进程组中，不会与当前编辑器争用终端。
+    fn open_in_new_window(&mut self) {
+        let Some(path) = self.absolute_file_path() else {
+            return;
+        };
+        let executable = match env::current_exe() {
+            Ok(executable) => executable,
+            Err(err) => {
+                self.message_bar.update_message(&format!(
+                    "ERR: Cannot open a new window, executable path unknown: {err}"
+                ));
+                return;
+            }
+        };
+        let terminal = env::var("TERMINAL")
+            .ok()
+            .filter(|terminal| !terminal.is_empty())
+            .unwrap_or_else(|| String::from("x-terminal-emulator"));
+        let line = self.view.text_location().line_index.saturating_add(1);
+        let mut command = Command::new(&terminal);
+        command
+            .arg("-e")
+            .arg(executable)
+            .arg("--readonly")
+            .arg(format!("+{line}"))
+            .arg(path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            command.process_group(0);
+        }
+        match command.spawn() {
+            Ok(mut child) => {
+                // 在后台回收子进程，避免留下僵尸进程。
+                thread::spawn(move || child.wait());
+                let note = if self.view.get_status().is_modified {
+                    " (unsaved changes are not shown there)"
+                } else {
+                    ""
+                };
+                self.message_bar
+                    .update_message(&format!("Opened in a new {terminal} window{note}"));
+            }
+            Err(err) => self
+                .message_bar
+                .update_message(&format!("ERR: Could not start {terminal}: {err}")),
+        }
+    }
+
+    /// 切换只读模式；当前文件没有写权限时改为允许修改它。
+    pub(super) fn toggle_readonly(&mut self) {
+        let message = if self.view.is_read_only() && !self.readonly {
+            self.view.override_read_only();
+            "File is not writable, editing anyway — saving may fail (use save as)"
+        } else {
+            self.readonly = !self.readonly;
+            if self.readonly {
+                "Buffer is now read-only"
+            } else {
+                "Buffer is now editable"
+            }
+        };
+        self.message_bar.update_message(message);
+    }
+
+    /// 切换是否显示行号栏。
+    pub(super) fn toggle_line_numbers(&mut self) {
+        let message = if self.view.toggle_line_numbers() {
+            "Line numbers shown."
+        } else {
+            "Line numbers hidden."
+        };
+        self.message_bar.update_message(message);
+    }
+
+    /// 切换软换行。
+    pub(super) fn toggle_soft_wrap(&mut self) {
+        let message = if self.view.toggle_soft_wrap() {
+            "Soft wrap on."
+        } else {
+            "Soft wrap off."
+        };
+        self.message_bar.update_message(message);
+    }
+
+    /// 在消息栏中显示词数、字数和行数；有选区时只统计选区。
+    pub(super) fn word_count(&mut self) {
+        let (stats, selection) = self.view.text_stats();
+        self.message_bar.update_message(&format!(
+            "{}{} words, {} characters, {} lines",
+            if selection { "Selection: " } else { "" },
+            stats.words,
+            stats.graphemes,
+            stats.lines
+        ));
+    }
+
+    /// 修改保存时使用的换行符，缓冲区标记为已修改。
+    fn convert_line_endings(&mut self, line_ending: LineEnding) {
+        if !self.check_writable() {
+            return;
+        }
+        let format = self.view.text_format();
+        let message = if format.line_ending == line_ending && !format.mixed {
+            format!("Line endings are already {}.", line_ending.name())
+        } else {
+            self.view.set_line_ending(line_ending);
+            format!("Line endings will be saved as {}.", line_ending.name())
+        };
+        self.message_bar.update_message(&message);
+    }
+
+    /// 询问保存时使用的编码。
+    fn start_set_encoding(&mut self) {
+        if self.check_writable() {
+            self.enter_text_prompt(
+                PromptType::SetEncoding,
+                &format!("Encoding (now {}): ", self.view.encoding()),
+            );
+        }
+    }
+
+    /// 修改保存时使用的编码，如 `gbk`、`latin1`、`utf-16le`；已有的字符无法用新的编码表示时提醒。
+    pub(super) fn submit_set_encoding(&mut self, label: &str) {
+        if label.trim().is_empty() {
+            self.message_bar.update_message("Encoding unchanged.");
+            return;
+        }
+        let Some(encoding) = FileEncoding::for_label(label) else {
+            self.message_bar
+                .update_message(&format!("ERR: Unknown encoding \"{}\"", label.trim()));
+            return;
+        };
+        let message = if encoding == self.view.encoding() {
+            format!("Encoding is already {encoding}.")
+        } else {
+            self.view.set_encoding(encoding);
+            if self.view.is_lossy_save() {
+                format!("File will be saved as {encoding}; some characters cannot be represented.")
+            } else {
+                format!("File will be saved as {encoding}.")
+            }
+        };
+        self.message_bar.update_message(&message);
+    }
+
+    /// 切换保存时文件末尾是否有换行符。
+    fn toggle_final_newline(&mut self) {
+        if !self.check_writable() {
+            return;
+        }
+        let message = if self.view.toggle_final_newline() {
+            "File will end with a newline."
+        } else {
+            "File will not end with a newline."
+        };
+        self.message_bar.update_message(message);
+    }
+
+    /// 切换是否给最近修改过的行着色。
+    fn toggle_recent_changes(&mut self) {
+        let message = if self.view.toggle_recent_changes() {
+            "Recently changed lines are tinted."
+        } else {
+            "Change tint hidden."
+        };
+        self.message_bar.update_message(message);
+    }
+
+    /// 跳转到下一个修改过的行。
+    fn goto_next_changed_line(&mut self) {
+        if !self.view.goto_next_changed_line() {
+            self.message_bar.update_message("No changed lines.");
+        }
+    }
+
+    /// 清除 Ctrl-T 记下的切换位置。
+    fn clear_swap_position(&mut self) {
+        self.view.clear_swap_position();
+        self.message_bar.update_message("Swap position cleared.");
+    }
+
+    /// 手动设置渲染模式。
+    fn set_render_mode(&mut self, mode: RenderMode) {
+        self.config.render_mode = mode;
+        self.throttle.set_mode(mode);
+        self.apply_render_mode();
+        self.message_bar
+            .update_message(&format!("Render mode: {mode:?}"));
+    }
+
+    /// 获取当前文件的绝对路径，失败时在消息栏中报告原因。
+    fn absolute_file_path(&mut self) -> Option<PathBuf> {
+        let Some(path) = self.view.file_path() else {
+            self.message_bar
+                .update_message("ERR: Buffer has no file name");
+            return None;
+        };
+        match absolute(path) {
+            Ok(path) => Some(path),
+            Err(err) => {
+                self.message_bar.update_message(&format!("ERR: {err}"));
+                None
+            }
+        }
+    }
+
+    /// 获取当前文件相对于工作目录（或 `--cwd` 指定的目录）的路径。
+    fn relative_file_path(&mut self) -> Option<PathBuf> {
+        let path = self.absolute_file_path()?;
+        let base = self
+            .config
+            .cwd
+            .clone()
+            .or_else(|| env::current_dir().ok())
+            .and_then(|dir| absolute(dir).ok());
+        let relative = base.and_then(|base| path.strip_prefix(base).ok().map(Path::to_path_buf));
+        Some(relative.unwrap_or(path))
+    }
+
+    /// 将文本复制到剪贴板并报告结果。
+    fn copy_text(&mut self, text: &str) {
+        self.view.set_register(text);
+        if Terminal::copy_to_clipboard(text).is_ok() {
+            self.message_bar.update_message(&format!("Copied: {text}"));
+        } else {
+            self.message_bar
+                .update_message("ERR: Could not copy to clipboard");
+        }
+    }
+}
@@ -0,0 +1,384 @@
+use std::{
+    fs::{self, File, Metadata, OpenOptions, Permissions},
+    io::{Error, ErrorKind, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// 创建临时文件时最多尝试的名字个数；同名的文件可能是之前崩溃时留下的。
+const TEMP_ATTEMPTS: usize = 100;
+
+/// 临时文件名中的序号，同一进程先后创建的临时文件不会重名。
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// 将内容写入文件：先写入同一目录下的临时文件并同步到磁盘，再重命名覆盖原文件，
+/// 进程中途退出或磁盘写满时原文件保持完整。
+///
+/// 重命名会换掉文件的 inode，因此临时文件先恢复原文件的属主、扩展属性（包括 ACL 和
+/// `SELinux` 上下文）和权限位。以下情况改为直接截断并写入原文件，保持 inode 不变：
+/// 目录不可写（如只能修改文件本身的系统目录）、文件有多个硬链接，或者有属性无法恢复。
+/// 有属性无法恢复而原文件本身又不可写时只能替换它，返回丢失的属性，供调用者提示用户。
+///
+/// 文件已不存在时恢复调用者之前记下的属性 `saved`，这时没有可以退回的原文件。
+pub fn write(
+    path: &Path,
+    contents: &[u8],
+    saved: Option<&Attributes>,
+) -> Result<Vec<String>, Error> {
+    let metadata = fs::metadata(path).ok();
+    if metadata.as_ref().is_some_and(has_other_links) {
+        return fs::write(path, contents).map(|()| Vec::new());
+    }
+    let (temp, file) = match create_temp(path) {
+        Ok(created) => created,
+        Err(err) if err.kind() == ErrorKind::PermissionDenied => {
+            return fs::write(path, contents).map(|()| Vec::new());
+        }
+        Err(err) => return Err(err),
+    };
+    let attributes = metadata
+        .as_ref()
+        .map(|metadata| Attributes::read(path, metadata))
+        .or_else(|| saved.cloned());
+    let lost = attributes
+        .map(|attributes| attributes.restore(&file))
+        .unwrap_or_default();
+    if !lost.is_empty() && metadata.is_some() {
+        match fs::write(path, contents) {
+            Ok(()) => {
+                drop(file);
+                let _ = fs::remove_file(&temp);
+                return Ok(Vec::new());
+            }
+            // 原文件本身不可写，只能用临时文件替换它。
+            Err(err) if err.kind() == ErrorKind::PermissionDenied => {}
+            Err(err) => {
+                drop(file);
+                let _ = fs::remove_file(&temp);
+                return Err(err);
+            }
+        }
+    }
+    let result = write_and_replace(file, &temp, path, contents);
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result.map(|()| lost)
+}
+
+/// 扩展属性的名字和值。
+type Xattrs = Vec<(Vec<u8>, Vec<u8>)>;
+
+/// 重命名时需要带到新文件上的原文件属性。
+#[derive(Clone, Debug, Default)]
+pub struct Attributes {
+    permissions: Option<Permissions>, // 权限位。
+    owner: (u32, u32),                // 属主的用户和组。
+    xattrs: Option<Xattrs>,           // 扩展属性，无法读取时为 `None`。
+}
+
+impl Attributes {
+    /// 读取文件的属性，`metadata` 是同一个文件的元数据。
+    pub fn read(path: &Path, metadata: &Metadata) -> Self {
+        Self {
+            permissions: Some(metadata.permissions()),
+            owner: owner(metadata),
+            xattrs: read_xattrs(path).ok(),
+        }
+    }
+
+    /// 把属性恢复到新文件上，返回无法恢复的属性。
+    ///
+    /// 修改属主会清除 setuid 位，设置 ACL 也会改动权限位，所以最后设置权限位；
+    /// 权限位在写入内容之前设置，只有属主能读的文件的内容不会经过一个别人也能读的临时文件。
+    fn restore(&self, file: &File) -> Vec<String> {
+        let mut lost = Vec::new();
+        let (uid, gid) = self.owner;
+        if restore_owner(file, uid, gid).is_err() {
+            lost.push(String::from("owner"));
+        }
+        match &self.xattrs {
+            Some(xattrs) => lost.extend(
+                xattrs
+                    .iter()
+                    .filter(|(name, value)| restore_xattr(file, name, value).is_err())
+                    .map(|(name, _)| {
+                        format!("extended attribute {}", String::from_utf8_lossy(name))
+                    }),
+            ),
+            None => lost.push(String::from("extended attributes")),
+        }
+        if let Some(permissions) = &self.permissions
+            && file.set_permissions(permissions.clone()).is_err()
+        {
+            lost.push(String::from("permissions"));
+        }
+        lost
+    }
+}
+
+/// 写入临时文件并同步到磁盘，然后重命名覆盖原文件。
+fn write_and_replace(
+    mut file: File,
+    temp: &Path,
+    path: &Path,
+    contents: &[u8],
+) -> Result<(), Error> {
+    file.write_all(contents)?;
+    file.sync_all()?;
+    drop(file);
+    fs::rename(temp, path)?;
+    sync_parent(path);
+    Ok(())
+}
+
+/// 在原文件所在目录创建一个新的临时文件；名字已被占用时换一个序号重试。
+fn create_temp(path: &Path) -> Result<(PathBuf, File), Error> {
+    for _ in 0..TEMP_ATTEMPTS {
+        let temp = temp_path(path, TEMP_COUNTER.fetch_add(1, Ordering::Relaxed));
+        match OpenOptions::new().write(true).create_new(true).open(&temp) {
+            Ok(file) => return Ok((temp, file)),
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Err(Error::new(
+        ErrorKind::AlreadyExists,
+        format!("Could not create a temporary file next to {}", path.display()),
+    ))
+}
+
+/// 与原文件在同一目录下的临时文件，重命名时不会跨越文件系统。
+fn temp_path(path: &Path, counter: usize) -> PathBuf {
+    let name = path
+        .file_name()
+        .map_or_else(|| String::from("file"), |name| name.to_string_lossy().into_owned());
+    path.with_file_name(format!(".{name}.{}.{counter}.tmp", process::id()))
+}
+
+/// 文件是否还有其他硬链接；重命名会让它们继续指向旧的内容。
+#[cfg(unix)]
+fn has_other_links(metadata: &Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    metadata.nlink() > 1
+}
+
+#[cfg(not(unix))]
+const fn has_other_links(_metadata: &Metadata) -> bool {
+    false
+}
+
+/// 文件属主的用户和组。
+#[cfg(unix)]
+fn owner(metadata: &Metadata) -> (u32, u32) {
+    use std::os::unix::fs::MetadataExt;
+    (metadata.uid(), metadata.gid())
+}
+
+#[cfg(not(unix))]
+const fn owner(_metadata: &Metadata) -> (u32, u32) {
+    (0, 0)
+}
+
+/// 让新文件与原文件属于同一用户和组，已经相同时不做修改。
+#[cfg(unix)]
+fn restore_owner(file: &File, uid: u32, gid: u32) -> Result<(), Error> {
+    use std::os::unix::fs::{MetadataExt, fchown};
+    let current = file.metadata()?;
+    if current.uid() == uid && current.gid() == gid {
+        return Ok(());
+    }
+    fchown(file, Some(uid), Some(gid))
+}
+
+#[cfg(not(unix))]
+const fn restore_owner(_file: &File, _uid: u32, _gid: u32) -> Result<(), Error> {
+    Ok(())
+}
+
+/// 读取文件所有扩展属性的名字和值；文件系统不支持扩展属性时返回空列表。
+#[cfg(target_os = "linux")]
+fn read_xattrs(path: &Path) -> Result<Xattrs, Error> {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+    let path = CString::new(path.as_os_str().as_bytes())?;
+    // SAFETY: 路径以 NUL 结尾，缓冲区的长度与指针一致。
+    let names = match read_xattr_buffer(|buffer, len| unsafe {
+        libc::listxattr(path.as_ptr(), buffer.cast(), len)
+    }) {
+        Err(err) if err.raw_os_error() == Some(libc::ENOTSUP) => return Ok(Vec::new()),
+        result => result?,
+    };
+    names
+        .split(|&byte| byte == 0)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            let name = CString::new(name)?;
+            // SAFETY: 路径和属性名都以 NUL 结尾，缓冲区的长度与指针一致。
+            let value = read_xattr_buffer(|buffer, len| unsafe {
+                libc::getxattr(path.as_ptr(), name.as_ptr(), buffer.cast(), len)
+            })?;
+            Ok((name.into_bytes(), value))
+        })
+        .collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_xattrs(_path: &Path) -> Result<Xattrs, Error> {
+    Ok(Vec::new())
+}
+
+/// 把扩展属性设置到新文件上；新文件已经有相同的值（如继承来的 `SELinux` 上下文）时不做修改。
+#[cfg(target_os = "linux")]
+fn restore_xattr(file: &File, name: &[u8], value: &[u8]) -> Result<(), Error> {
+    use std::{ffi::CString, os::fd::AsRawFd};
+    let name = CString::new(name)?;
+    let fd = file.as_raw_fd();
+    // SAFETY: 属性名以 NUL 结尾，缓冲区的长度与指针一致。
+    let current = read_xattr_buffer(|buffer, len| unsafe {
+        libc::fgetxattr(fd, name.as_ptr(), buffer.cast(), len)
+    });
+    if current.is_ok_and(|current| current == value) {
+        return Ok(());
+    }
+    // SAFETY: 属性名以 NUL 结尾，值的长度与指针一致。
+    let result = unsafe { libc::fsetxattr(fd, name.as_ptr(), value.as_ptr().cast(), value.len(), 0) };
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(Error::last_os_error())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+const fn restore_xattr(_file: &File, _name: &[u8], _value: &[u8]) -> Result<(), Error> {
+    Ok(())
+}
+
+/// 调用 `listxattr` 一类的函数读取变长的结果：先查询长度，再读入缓冲区；
+/// 两次调用之间结果变长时重新查询。
+#[cfg(target_os = "linux")]
+fn read_xattr_buffer(mut call: impl FnMut(*mut u8, usize) -> isize) -> Result<Vec<u8>, Error> {
+    loop {
+        let len = usize::try_from(call(std::ptr::null_mut(), 0)).map_err(|_| Error::last_os_error())?;
+        if len == 0 {
+            return Ok(Vec::new());
+        }
+        let mut buffer = vec![0; len];
+        if let Ok(read) = usize::try_from(call(buffer.as_mut_ptr(), len)) {
+            buffer.truncate(read);
+            return Ok(buffer);
+        }
+        let err = Error::last_os_error();
+        if err.raw_os_error() != Some(libc::ERANGE) {
+            return Err(err);
+        }
+    }
+}
+
+/// 同步所在目录，确保重命名本身也已写入磁盘；失败时不影响保存的结果。
+#[cfg(unix)]
+fn sync_parent(path: &Path) {
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    if let Ok(dir) = File::open(dir) {
+        let _ = dir.sync_all();
+    }
+}
+
+#[cfg(not(unix))]
+const fn sync_parent(_path: &Path) {}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+    use tempfile::tempdir;
+
+    /// 设置一个扩展属性；文件系统不支持 `user.` 扩展属性时返回 `false`，调用的测试直接跳过。
+    #[cfg(target_os = "linux")]
+    fn set_xattr(path: &Path, name: &str, value: &[u8]) -> bool {
+        use std::{ffi::CString, os::unix::ffi::OsStrExt};
+        let path = CString::new(path.as_os_str().as_bytes()).unwrap();
+        let name = CString::new(name).unwrap();
+        // SAFETY: 路径和属性名都以 NUL 结尾，值的长度与指针一致。
+        let result = unsafe {
+            libc::setxattr(path.as_ptr(), name.as_ptr(), value.as_ptr().cast(), value.len(), 0)
+        };
+        result == 0
+    }
+
+    fn mode(path: &Path) -> u32 {
+        fs::metadata(path).unwrap().permissions().mode() & 0o7777
+    }
+
+    #[test]
+    fn replacing_the_file_keeps_its_mode() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        fs::write(&path, "old\n").unwrap();
+        fs::set_permissions(&path, Permissions::from_mode(0o640)).unwrap();
+        let inode = fs::metadata(&path).unwrap().ino();
+
+        let lost = write(&path, b"new\n", None).unwrap();
+
+        assert!(lost.is_empty());
+        assert_eq!(fs::read(&path).unwrap(), b"new\n");
+        assert_eq!(mode(&path), 0o640);
+        assert_ne!(fs::metadata(&path).unwrap().ino(), inode, "expected a rename over the file");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn replacing_the_file_keeps_its_extended_attributes() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        fs::write(&path, "old\n").unwrap();
+        if !set_xattr(&path, "user.snow.tag", b"kept") {
+            return;
+        }
+
+        let lost = write(&path, b"new\n", None).unwrap();
+
+        assert!(lost.is_empty());
+        assert_eq!(fs::read(&path).unwrap(), b"new\n");
+        assert!(read_xattrs(&path).unwrap().contains(&(b"user.snow.tag".to_vec(), b"kept".to_vec())));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn a_deleted_file_gets_the_attributes_recorded_before() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        fs::write(&path, "old\n").unwrap();
+        fs::set_permissions(&path, Permissions::from_mode(0o600)).unwrap();
+        let has_xattr = set_xattr(&path, "user.snow.tag", b"kept");
+        let saved = Attributes::read(&path, &fs::metadata(&path).unwrap());
+        fs::remove_file(&path).unwrap();
+
+        let lost = write(&path, b"new\n", Some(&saved)).unwrap();
+
+        assert!(lost.is_empty());
+        assert_eq!(mode(&path), 0o600);
+        if has_xattr {
+            assert!(read_xattrs(&path).unwrap().contains(&(b"user.snow.tag".to_vec(), b"kept".to_vec())));
+        }
+    }
+
+    #[test]
+    fn leftover_temporary_files_do_not_block_saving() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        fs::write(&path, "old\n").unwrap();
+        let next = TEMP_COUNTER.load(Ordering::Relaxed);
+        for counter in next..next.saturating_add(5) {
+            fs::write(temp_path(&path, counter), "stale").unwrap();
+        }
+
+        write(&path, b"new\n", None).unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), b"new\n");
+    }
+}
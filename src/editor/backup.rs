@@ -0,0 +1,48 @@
+use std::{
+    fs,
+    io::{Error, ErrorKind},
+    path::{self, Path, PathBuf},
+};
+
+/// 保存前备份磁盘上原文件的设置。
+#[derive(Clone, Default, Debug)]
+pub struct Backup {
+    pub enabled: bool,        // 每个缓冲区第一次保存之前是否备份磁盘上的原文件。
+    pub dir: Option<PathBuf>, // 存放备份的目录，未设置时备份放在原文件旁边。
+}
+
+impl Backup {
+    /// 文件的备份路径：默认是同一目录下的 `<文件名>~`，没有扩展名的文件同样如此。
+    ///
+    /// 设置了备份目录时，以 `!` 代替绝对路径中的分隔符作为文件名，不同目录下的同名文件
+    /// 不会互相覆盖；相对路径先按当前目录转为绝对路径。
+    pub fn path_for(&self, file: &Path) -> Option<PathBuf> {
+        let name = file.file_name()?.to_string_lossy().into_owned();
+        let Some(dir) = &self.dir else {
+            return Some(file.with_file_name(format!("{name}~")));
+        };
+        let absolute = path::absolute(file).ok()?;
+        let flattened: String = absolute
+            .to_string_lossy()
+            .chars()
+            .map(|ch| if path::is_separator(ch) { '!' } else { ch })
+            .collect();
+        Some(dir.join(format!("{flattened}~")))
+    }
+
+    /// 把磁盘上的文件复制到备份路径，覆盖之前的备份，返回备份的路径。
+    /// 文件还不存在时没有需要备份的内容，返回 `None`。
+    pub fn create(&self, file: &Path) -> Result<Option<PathBuf>, Error> {
+        if !file.exists() {
+            return Ok(None);
+        }
+        let backup = self
+            .path_for(file)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "No backup path for this file"))?;
+        if let Some(dir) = &self.dir {
+            fs::create_dir_all(dir)?;
+        }
+        fs::copy(file, &backup)?;
+        Ok(Some(backup))
+    }
+}
@@ -0,0 +1,131 @@
+use super::{
+    config::Config,
+    linerange::LineRange,
+    view::{View, saved_message, textformat::LineEnding},
+};
+use std::{fs::read_to_string, path::Path};
+
+/// 执行批处理脚本：每行一条命令，空行和以 `#` 开头的行被忽略。
+///
+/// 遇到第一个错误即停止，返回带有脚本行号的错误信息。
+pub fn run(script: &Path, file: Option<&Path>, config: &Config) -> Result<(), String> {
+    let commands = read_to_string(script)
+        .map_err(|err| format!("{}: {err}", script.display()))?;
+    let mut view = View::default();
+    view.set_break_symlinks(config.break_symlinks_on_save);
+    view.set_fallback_encoding(config.fallback_encoding);
+    view.set_trim_on_save(config.trim_trailing_whitespace);
+    if let Some(file) = file {
+        view.load(file)
+            .map_err(|err| format!("Could not open file {}: {err}", file.display()))?;
+    }
+
+    for (line_index, line) in commands.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        execute(&mut view, config, line).map_err(|err| {
+            format!("{}:{}: {err}", script.display(), line_index.saturating_add(1))
+        })?;
+    }
+    Ok(())
+}
+
+/// 执行一条批处理命令。
+fn execute(view: &mut View, config: &Config, line: &str) -> Result<(), String> {
+    let words = split_words(line)?;
+    let Some((verb, args)) = words.split_first() else {
+        return Ok(());
+    };
+    match (verb.as_str(), args) {
+        ("goto", [line]) => {
+            let line = parse_line_number(line, view.line_count())?;
+            view.goto_line(line);
+        }
+        ("replace-all", [query, replacement]) => {
+            let matches = view.find_all(query);
+            view.replace_matches(&matches, replacement);
+        }
+        ("delete-lines", [start, end]) => {
+            let start = parse_line_number(start, view.line_count())?;
+            let end = parse_line_number(end, view.line_count())?;
+            if start > end {
+                return Err(String::from("Backwards range"));
+            }
+            view.delete_lines(LineRange { start, end });
+        }
+        ("set", [option, value]) => match (option.as_str(), value.as_str()) {
+            ("eol", "lf") => view.set_line_ending(LineEnding::Lf),
+            ("eol", "crlf") => view.set_line_ending(LineEnding::CrLf),
+            ("eol", _) => return Err(format!("Unsupported line ending: {value}")),
+            _ => return Err(format!("Unknown option: {option}")),
+        },
+        ("save", []) => {
+            if view.file_path().is_none() {
+                return Err(String::from("Buffer has no file name, use save-as"));
+            }
+            let warnings = view.save().map_err(|err| format!("Error writing file: {err}"))?;
+            report_warnings(&warnings);
+        }
+        ("save-as", [file_name]) => {
+            let warnings = view
+                .save_as(&config.resolve_path(file_name))
+                .map_err(|err| format!("Error writing file: {err}"))?;
+            report_warnings(&warnings);
+        }
+        ("goto" | "replace-all" | "delete-lines" | "set" | "save" | "save-as", _) => {
+            return Err(format!("Wrong number of arguments for {verb}"));
+        }
+        ("palette" | "digraph" | "digraphs" | "replace" | "preview", _) => {
+            return Err(format!("{verb} is interactive and not available in batch mode"));
+        }
+        _ => return Err(format!("Unknown command: {verb}")),
+    }
+    Ok(())
+}
+
+/// 保存成功但有警告（如原文件的属性无法保留）时输出到标准错误，不中断脚本。
+fn report_warnings(warnings: &[String]) {
+    if !warnings.is_empty() {
+        eprintln!("{}", saved_message(warnings));
+    }
+}
+
+/// 解析从 1 开始的行号，返回从 0 开始的行索引。
+fn parse_line_number(text: &str, line_count: usize) -> Result<usize, String> {
+    match text.parse::<usize>() {
+        Ok(line) if (1..=line_count).contains(&line) => Ok(line.saturating_sub(1)),
+        Ok(_) => Err(format!("Invalid line: lines must be between 1 and {line_count}")),
+        Err(_) => Err(format!("Invalid line: {text}")),
+    }
+}
+
+/// 按空白拆分命令，双引号内的空白不拆分，`\` 转义下一个字符。
+fn split_words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut in_quotes = false;
+    let mut chars = line.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' => {
+                in_quotes = !in_quotes;
+                word.get_or_insert_with(String::new);
+            }
+            '\\' => {
+                let escaped = chars.next().ok_or("Trailing backslash")?;
+                word.get_or_insert_with(String::new).push(escaped);
+            }
+            ch if ch.is_whitespace() && !in_quotes => {
+                words.extend(word.take());
+            }
+            ch => word.get_or_insert_with(String::new).push(ch),
+        }
+    }
+    if in_quotes {
+        return Err(String::from("Unterminated quote"));
+    }
+    words.extend(word);
+    Ok(words)
+}
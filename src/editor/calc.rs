@@ -0,0 +1,285 @@
+use super::{Editor, view::LineChange};
+
+/// 表达式中的一个记号。
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Token {
+    Number(f64),
+    Operator(char), // `+ - * / %`，以及表示取负的 `~`。
+    Open,
+    Close,
+}
+
+/// 带有所在列（从 1 开始）的记号。
+type Positioned = (Token, usize);
+
+/// 计算简单的算术表达式：整数、小数、`0x` 开头的十六进制数，
+/// `+ - * / %` 和括号，支持一元负号。出错时返回带有列号的信息。
+pub fn evaluate(expression: &str) -> Result<f64, String> {
+    let tokens = tokenize(expression)?;
+    let output = to_postfix(&tokens, expression)?;
+    evaluate_postfix(&output)
+}
+
+/// 将计算结果格式化：整数不带小数点，小数去掉浮点误差带来的尾数。
+pub fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        return format!("{value}");
+    }
+    let text = format!("{value:.10}");
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// 将表达式拆分为记号。
+fn tokenize(expression: &str) -> Result<Vec<Positioned>, String> {
+    let mut tokens: Vec<Positioned> = Vec::new();
+    let mut chars = expression.chars().enumerate().peekable();
+    while let Some((index, ch)) = chars.next() {
+        let column = index.saturating_add(1);
+        let token = match ch {
+            // 选区可以跨行，换行与空白一样被忽略
+            ' ' | '\t' | '\n' | '\r' => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '-' if matches!(
+                tokens.last(),
+                None | Some((Token::Operator(_) | Token::Open, _))
+            ) =>
+            {
+                Token::Operator('~')
+            }
+            '+' | '-' | '*' | '/' | '%' => Token::Operator(ch),
+            '0'..='9' | '.' => {
+                let mut literal = String::from(ch);
+                while let Some((_, next)) =
+                    chars.next_if(|(_, next)| next.is_ascii_alphanumeric() || *next == '.')
+                {
+                    literal.push(next);
+                }
+                Token::Number(parse_number(&literal, column)?)
+            }
+            _ => return Err(format!("parse error at column {column}")),
+        };
+        tokens.push((token, column));
+    }
+    Ok(tokens)
+}
+
+/// 解析一个数字字面量。
+fn parse_number(literal: &str, column: usize) -> Result<f64, String> {
+    let error = || format!("invalid number at column {column}");
+    if let Some(hex) = literal
+        .strip_prefix("0x")
+        .or_else(|| literal.strip_prefix("0X"))
+    {
+        if hex.is_empty() {
+            return Err(error());
+        }
+        return hex.chars().try_fold(0.0_f64, |value, digit| {
+            let digit = digit.to_digit(16).ok_or_else(error)?;
+            Ok(value.mul_add(16.0, f64::from(digit)))
+        });
+    }
+    literal.parse::<f64>().map_err(|_| error())
+}
+
+/// 运算符的优先级。
+const fn precedence(operator: char) -> u8 {
+    match operator {
+        '~' => 3,
+        '*' | '/' | '%' => 2,
+        _ => 1,
+    }
+}
+
+/// 使用调度场算法将中缀记号转换为后缀表示，同时检查语法。
+fn to_postfix(tokens: &[Positioned], expression: &str) -> Result<Vec<Positioned>, String> {
+    let mut output = Vec::new();
+    let mut operators: Vec<Positioned> = Vec::new();
+    // 当前位置是否需要一个操作数（数字、左括号或一元负号）。
+    let mut expect_operand = true;
+    for &(token, column) in tokens {
+        match token {
+            Token::Number(_) if expect_operand => {
+                output.push((token, column));
+                expect_operand = false;
+            }
+            Token::Open if expect_operand => operators.push((token, column)),
+            Token::Operator('~') => operators.push((token, column)),
+            Token::Operator(operator) if !expect_operand => {
+                while let Some(&(Token::Operator(top), _)) = operators.last()
+                    && precedence(top) >= precedence(operator)
+                {
+                    output.extend(operators.pop());
+                }
+                operators.push((token, column));
+                expect_operand = true;
+            }
+            Token::Close if !expect_operand => loop {
+                match operators.pop() {
+                    Some((Token::Open, _)) => break,
+                    Some(operator) => output.push(operator),
+                    None => return Err(format!("unbalanced parenthesis at column {column}")),
+                }
+            },
+            _ => return Err(format!("parse error at column {column}")),
+        }
+    }
+    if expect_operand {
+        let column = expression.chars().count().saturating_add(1);
+        return Err(format!("parse error at column {column}"));
+    }
+    while let Some((token, column)) = operators.pop() {
+        if token == Token::Open {
+            return Err(format!("unbalanced parenthesis at column {column}"));
+        }
+        output.push((token, column));
+    }
+    Ok(output)
+}
+
+/// 计算后缀表示的表达式。
+fn evaluate_postfix(output: &[Positioned]) -> Result<f64, String> {
+    let mut stack: Vec<f64> = Vec::new();
+    for &(token, column) in output {
+        let value = match token {
+            Token::Number(value) => value,
+            Token::Operator('~') => -stack.pop().ok_or("empty expression")?,
+            Token::Operator(operator) => {
+                let right = stack.pop().ok_or("empty expression")?;
+                let left = stack.pop().ok_or("empty expression")?;
+                match operator {
+                    '+' => left + right,
+                    '-' => left - right,
+                    '*' => left * right,
+                    '/' | '%' if right == 0.0 => {
+                        return Err(format!("division by zero at column {column}"));
+                    }
+                    '/' => left / right,
+                    _ => left % right,
+                }
+            }
+            Token::Open | Token::Close => return Err(format!("parse error at column {column}")),
+        };
+        stack.push(value);
+    }
+    stack.pop().ok_or_else(|| String::from("empty expression"))
+}
+
+impl Editor {
+    /// 计算选区（没有选区时为当前行）中的表达式，在消息栏中显示结果。
+    pub(super) fn calculate(&mut self) {
+        let message = match self.calculate_expression() {
+            Ok((_, result)) => format!("= {result}"),
+            Err(err) => format!("ERR: {err}"),
+        };
+        self.message_bar.update_message(&message);
+    }
+
+    /// 计算当前行的表达式，并以 ` = 结果` 的形式追加到行尾（可以撤销）。
+    /// 行中已经有 `=` 时，替换它后面的旧结果。有选区时计算选区，结果插入到选区之后。
+    pub(super) fn calculate_and_append(&mut self) {
+        if !self.check_writable() {
+            return;
+        }
+        match self.calculate_expression() {
+            Ok((_, result)) if self.view.insert_after_selection(&format!(" = {result}")) => {
+                self.message_bar.update_message(&format!("= {result}"));
+            }
+            Ok((expression, result)) => {
+                let line_index = self.view.text_location().line_index;
+                let text = format!("{} = {result}", expression.trim_end());
+                self.view.apply_changes(&[LineChange { line_index, text }]);
+                self.message_bar.update_message(&format!("= {result}"));
+            }
+            Err(err) => self.message_bar.update_message(&format!("ERR: {err}")),
+        }
+    }
+
+    /// 计算选区（没有选区时为当前行）中 `=` 之前的表达式，返回表达式和格式化后的结果。
+    fn calculate_expression(&self) -> Result<(String, String), String> {
+        let text = self
+            .view
+            .selected_text()
+            .unwrap_or_else(|| self.view.line_text(self.view.text_location().line_index));
+        let expression = text.split('=').next().unwrap_or_default();
+        if expression.trim().is_empty() {
+            return Err(String::from("Nothing to calculate"));
+        }
+        let result = evaluate(expression)?;
+        if !result.is_finite() {
+            return Err(String::from("result is not a finite number"));
+        }
+        Ok((expression.to_string(), format_number(result)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(expression: &str) -> String {
+        format_number(evaluate(expression).unwrap())
+    }
+
+    fn error(expression: &str) -> String {
+        evaluate(expression).unwrap_err()
+    }
+
+    #[test]
+    fn operators_follow_precedence_and_associate_to_the_left() {
+        assert_eq!(value("2 + 3 * 4"), "14");
+        assert_eq!(value("(2 + 3) * 4"), "20");
+        assert_eq!(value("10 - 4 - 3"), "3");
+        assert_eq!(value("48 / 4 / 2"), "6");
+        assert_eq!(value("2 + 7 % 4 * 2"), "8");
+    }
+
+    #[test]
+    fn unary_minus_binds_tighter_than_binary_operators() {
+        assert_eq!(value("-3 * -2"), "6");
+        assert_eq!(value("-(2 + 3)"), "-5");
+        assert_eq!(value("4 - -1"), "5");
+        assert_eq!(value("--2"), "2");
+    }
+
+    #[test]
+    fn hex_literals_and_remainders_are_supported() {
+        assert_eq!(value("0x1F + 1"), "32");
+        assert_eq!(value("0XfF"), "255");
+        assert_eq!(value("17 % 5"), "2");
+        assert_eq!(value("7.5 % 2"), "1.5");
+        assert_eq!(error("0x"), "invalid number at column 1");
+        assert_eq!(error("1 + 0xg"), "invalid number at column 5");
+    }
+
+    #[test]
+    fn division_by_zero_reports_the_operator_column() {
+        assert_eq!(error("1 / (2 - 2)"), "division by zero at column 3");
+        assert_eq!(error("5 % 0"), "division by zero at column 3");
+    }
+
+    #[test]
+    fn errors_report_the_column_where_parsing_failed() {
+        assert_eq!(error("1 + * 2"), "parse error at column 5");
+        assert_eq!(error("1 +"), "parse error at column 4");
+        assert_eq!(error("2 $ 3"), "parse error at column 3");
+        assert_eq!(error("(1 + 2"), "unbalanced parenthesis at column 1");
+        assert_eq!(error("1 + 2)"), "unbalanced parenthesis at column 6");
+    }
+
+    #[test]
+    fn the_selection_is_evaluated_instead_of_the_whole_line() {
+        let mut editor = Editor::default();
+        editor.view.load_reader("total: 2 * (3 + 4) items\n".as_bytes(), "[test]").unwrap();
+        editor.view.goto_location(0, 7);
+        editor.view.toggle_selection_mode();
+        editor.view.goto_location(0, 18);
+
+        editor.calculate();
+        assert_eq!(editor.message_bar.text(), "= 14");
+
+        editor.calculate_and_append();
+        assert_eq!(editor.view.line_text(0), "total: 2 * (3 + 4) = 14 items");
+        assert!(editor.view.selected_range().is_none());
+    }
+}
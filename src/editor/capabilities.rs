@@ -0,0 +1,163 @@
+use std::{
+    env,
+    fmt::{self, Display},
+    io::{self, IsTerminal, Write},
+    time::{Duration, Instant},
+};
+
+/// 等待终端回答一次查询的最长时间。
+const QUERY_TIMEOUT: Duration = Duration::from_millis(300);
+
+/// 可以通过 DECRQM 查询的终端模式。
+#[derive(Clone, Copy)]
+pub enum Mode {
+    AlternateScreen = 1049,
+    SynchronizedOutput = 2026,
+    BracketedPaste = 2004,
+    FocusEvents = 1004,
+    Mouse = 1000,
+}
+
+/// 探测某项能力的结果。
+pub enum Support {
+    Supported,            // 终端明确表示支持。
+    Unsupported,          // 终端明确表示不支持。
+    NoResponse,           // 在超时之前没有得到回答。
+    Skipped(&'static str), // 无法探测，附带原因。
+}
+
+impl Display for Support {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Supported => write!(formatter, "supported"),
+            Self::Unsupported => write!(formatter, "not supported"),
+            Self::NoResponse => write!(formatter, "no response (timed out)"),
+            Self::Skipped(reason) => write!(formatter, "skipped: {reason}"),
+        }
+    }
+}
+
+/// 标准输入和输出是否都连接到终端；查询类的探测只有在终端上才有意义。
+pub fn is_interactive() -> bool {
+    io::stdin().is_terminal() && io::stdout().is_terminal()
+}
+
+/// 使用 DECRQM 查询终端是否支持某个模式。调用前终端应处于原始模式。
+pub fn query_mode(mode: Mode) -> Support {
+    #[allow(clippy::as_conversions)]
+    let number = mode as u16;
+    let Some(response) = query(&format!("\x1b[?{number}$p")) else {
+        return Support::NoResponse;
+    };
+    // 回答的格式为 `CSI ? 模式 ; 值 $ y`，值为 0 表示不认识该模式。
+    let prefix = format!("\x1b[?{number};");
+    let value = response
+        .find(&prefix)
+        .and_then(|start| response.get(start.saturating_add(prefix.len())..))
+        .and_then(|rest| rest.split("$y").next())
+        .and_then(|value| value.parse::<u8>().ok());
+    match value {
+        Some(1..=4) => Support::Supported,
+        _ => Support::Unsupported,
+    }
+}
+
+/// 查询终端是否支持 kitty 键盘协议。调用前终端应处于原始模式。
+pub fn query_kitty_keyboard() -> Support {
+    match query("\x1b[?u") {
+        Some(response) if response.contains("\x1b[?") && response.ends_with('u') => {
+            Support::Supported
+        }
+        Some(_) => Support::Unsupported,
+        None => Support::NoResponse,
+    }
+}
+
+/// 根据环境变量判断终端的颜色支持。
+pub fn color_support() -> String {
+    let colorterm = env::var("COLORTERM").unwrap_or_default();
+    let term = env::var("TERM").unwrap_or_default();
+    if matches!(colorterm.as_str(), "truecolor" | "24bit") {
+        format!("truecolor (COLORTERM={colorterm})")
+    } else if term.contains("256color") {
+        format!("256 colors (TERM={term})")
+    } else if term.is_empty() || term == "dumb" {
+        String::from("none (TERM is unset or dumb)")
+    } else {
+        format!("16 colors (TERM={term})")
+    }
+}
+
+/// 当前的区域设置及其是否使用 UTF-8。
+pub fn locale() -> (String, bool) {
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .find_map(|name| env::var(name).ok().filter(|value| !value.is_empty()))
+        .unwrap_or_default();
+    let upper = locale.to_ascii_uppercase();
+    let utf8 = upper.contains("UTF-8") || upper.contains("UTF8");
+    (locale, utf8)
+}
+
+/// 发送查询并读取回答。
+///
+/// 每个查询后面都追加一个所有终端都会回答的 DA1 查询：收到 DA1 的回答时，
+/// 之前没有收到的回答就不会再来，这样既不会一直等待，也不会把迟到的回答
+/// 误认为是下一个查询的。
+#[cfg(unix)]
+fn query(request: &str) -> Option<String> {
+    let mut stdout = io::stdout();
+    write!(stdout, "{request}\x1b[c").ok()?;
+    stdout.flush().ok()?;
+
+    let deadline = Instant::now().checked_add(QUERY_TIMEOUT)?;
+    let mut response = Vec::new();
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return None;
+        }
+        let mut poll_fd = libc::pollfd {
+            fd: libc::STDIN_FILENO,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let timeout = i32::try_from(remaining.as_millis()).unwrap_or(i32::MAX);
+        // SAFETY: `poll_fd` 在调用期间有效，数量为 1。
+        if unsafe { libc::poll(&raw mut poll_fd, 1, timeout) } <= 0 {
+            return None;
+        }
+        let mut buffer = [0_u8; 256];
+        // SAFETY: 读取的长度不超过 `buffer` 的大小。
+        let read = unsafe {
+            libc::read(
+                libc::STDIN_FILENO,
+                buffer.as_mut_ptr().cast(),
+                buffer.len(),
+            )
+        };
+        let read = usize::try_from(read).ok().filter(|read| *read > 0)?;
+        response.extend_from_slice(buffer.get(..read).unwrap_or_default());
+        let text = String::from_utf8_lossy(&response);
+        if let Some(start) = find_device_attributes(&text) {
+            return Some(text.get(..start).unwrap_or_default().to_string());
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn query(_request: &str) -> Option<String> {
+    None
+}
+
+/// 查找 DA1 回答（`CSI ? 数字;数字… c`）的起始位置。
+fn find_device_attributes(text: &str) -> Option<usize> {
+    text.match_indices("\x1b[?").map(|(start, _)| start).find(|&start| {
+        let rest = text.get(start.saturating_add(3)..).unwrap_or_default();
+        let parameters = rest
+            .chars()
+            .take_while(|ch| ch.is_ascii_digit() || *ch == ';')
+            .count();
+        rest.get(parameters..).is_some_and(|tail| tail.starts_with('c'))
+    })
+}
@@ -0,0 +1,188 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// 解析后的命令行参数。
+#[derive(Default)]
+pub struct Arguments {
+    pub file_name: Option<String>, // 要打开的文件名。
+    pub cwd: Option<PathBuf>,      // 用于解析相对路径的基准目录。
+    pub batch: Option<String>,     // 以批处理模式执行的脚本。
+    pub startup_timings: bool,     // 是否在启动后显示各阶段的耗时。
+    pub line: Option<usize>,       // `+N` 或 `文件:N` 指定的初始行号（从 1 开始）。
+    pub column: Option<usize>,     // `文件:N:C` 指定的初始列号（从 1 开始，按字计）。
+    pub readonly: bool,            // 是否以只读方式打开。
+    pub doctor: bool,              // 是否只打印终端环境的诊断报告。
+    pub check_roundtrip: Option<String>, // 检查能否原样加载并保存的文件。
+    pub escape_timeout: Option<Duration>, // 单独的 Esc 之后等待后续按键的时间。
+}
+
+impl Arguments {
+    /// 解析命令行参数（不包含程序名）。
+    ///
+    /// `--` 之后的参数都按字面作为文件名。`+N`、`文件:N` 和 `文件:N:C` 只有在不存在同名文件时
+    /// 才被解释为行号和列号，因此 `+notes.txt`、`a:1` 这样的文件仍然可以直接打开。
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut arguments = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                for arg in args.by_ref() {
+                    arguments.set_file_name(arg);
+                }
+            } else if arg == "--cwd" {
+                let dir = args.next().ok_or("Missing directory after --cwd")?;
+                arguments.cwd = Some(PathBuf::from(dir));
+            } else if let Some(dir) = arg.strip_prefix("--cwd=") {
+                arguments.cwd = Some(PathBuf::from(dir));
+            } else if arg == "--startup-timings" {
+                arguments.startup_timings = true;
+            } else if arg == "--batch" {
+                let script = args.next().ok_or("Missing script after --batch")?;
+                arguments.batch = Some(script);
+            } else if let Some(script) = arg.strip_prefix("--batch=") {
+                arguments.batch = Some(script.to_string());
+            } else if arg == "--readonly" || arg == "-R" {
+                arguments.readonly = true;
+            } else if arg == "--doctor" {
+                arguments.doctor = true;
+            } else if let Some(millis) = arg.strip_prefix("--escape-timeout=") {
+                let millis = millis
+                    .parse()
+                    .map_err(|_| format!("Invalid escape timeout: {arg}"))?;
+                arguments.escape_timeout = Some(Duration::from_millis(millis));
+            } else if arg == "--check-roundtrip" {
+                let file = args.next().ok_or("Missing file after --check-roundtrip")?;
+                arguments.check_roundtrip = Some(file);
+            } else if let Some(line) = arg.strip_prefix('+')
+                && !line.is_empty()
+                && line.bytes().all(|byte| byte.is_ascii_digit())
+                && !arguments.exists(&arg)
+            {
+                let line = line
+                    .parse()
+                    .map_err(|_| format!("Invalid line number: {arg}"))?;
+                arguments.line = Some(line);
+            } else if let Some((file_name, line, column)) = split_location(&arg)
+                && !arguments.exists(&arg)
+            {
+                arguments.set_file_name(file_name.to_string());
+                arguments.line = Some(line);
+                arguments.column = column;
+            } else {
+                arguments.set_file_name(arg);
+            }
+        }
+        Ok(arguments)
+    }
+
+    /// 记录要打开的文件名；只使用第一个文件名，其余的被忽略。
+    fn set_file_name(&mut self, file_name: String) {
+        if self.file_name.is_none() {
+            self.file_name = Some(file_name);
+        }
+    }
+
+    /// 相对于已经指定的基准目录，是否存在以参数为名的文件。
+    fn exists(&self, file_name: &str) -> bool {
+        let path = Path::new(file_name);
+        match &self.cwd {
+            Some(base) if path.is_relative() => base.join(path).exists(),
+            _ => path.exists(),
+        }
+    }
+}
+
+/// 拆分编译器和 grep 输出的位置 `文件:行` 或 `文件:行:列`，只在末尾的部分都是数字时拆分，
+/// 因此路径中其他位置的冒号（如 Windows 的盘符）不受影响。
+fn split_location(arg: &str) -> Option<(&str, usize, Option<usize>)> {
+    let number = |text: &str| {
+        (!text.is_empty() && text.bytes().all(|byte| byte.is_ascii_digit()))
+            .then(|| text.parse().ok())
+            .flatten()
+    };
+    let (rest, last) = arg.rsplit_once(':')?;
+    let last = number(last)?;
+    let location = match rest.rsplit_once(':') {
+        Some((file_name, line)) if let Some(line) = number(line) => (file_name, line, Some(last)),
+        _ => (rest, last, None),
+    };
+    (!location.0.is_empty()).then_some(location)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Arguments, String> {
+        Arguments::parse(args.iter().map(ToString::to_string))
+    }
+
+    #[test]
+    fn cwd_is_accepted_as_a_separate_or_joined_argument() {
+        let separate = parse(&["--cwd", "/srv/project", "notes.txt"]).unwrap();
+        let joined = parse(&["--cwd=/srv/project", "notes.txt"]).unwrap();
+
+        for arguments in [separate, joined] {
+            assert_eq!(arguments.cwd, Some(PathBuf::from("/srv/project")));
+            assert_eq!(arguments.file_name.as_deref(), Some("notes.txt"));
+        }
+        assert!(parse(&["--cwd"]).is_err());
+    }
+
+    #[test]
+    fn locations_are_checked_against_files_in_the_cwd() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a:1"), "").unwrap();
+        let cwd = dir.path().to_str().unwrap();
+
+        let literal = parse(&["--cwd", cwd, "a:1"]).unwrap();
+        let location = parse(&["a:1"]).unwrap();
+
+        assert_eq!(literal.file_name.as_deref(), Some("a:1"));
+        assert_eq!(literal.line, None);
+        assert_eq!(location.file_name.as_deref(), Some("a"));
+        assert_eq!(location.line, Some(1));
+    }
+
+    #[test]
+    fn arguments_after_the_end_of_options_are_literal_file_names() {
+        let arguments = parse(&["--", "--readonly"]).unwrap();
+        assert_eq!(arguments.file_name.as_deref(), Some("--readonly"));
+        assert!(!arguments.readonly);
+
+        let arguments = parse(&["-R", "--", "+12", "notes.txt:3"]).unwrap();
+        assert!(arguments.readonly);
+        assert_eq!(arguments.file_name.as_deref(), Some("+12"));
+        assert_eq!(arguments.line, None);
+
+        let arguments = parse(&["--", "--"]).unwrap();
+        assert_eq!(arguments.file_name.as_deref(), Some("--"));
+    }
+
+    #[test]
+    fn flag_like_and_oddly_spaced_names_are_kept_as_given() {
+        for name in ["-foo", "--help", "+notes.txt", " notes.txt ", "a\nb", "+"] {
+            let arguments = parse(&[name]).unwrap();
+            assert_eq!(arguments.file_name.as_deref(), Some(name));
+            assert_eq!(arguments.line, None);
+        }
+    }
+
+    #[test]
+    fn an_existing_file_named_like_a_line_number_wins() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("+5"), "").unwrap();
+        let cwd = dir.path().to_str().unwrap();
+
+        let literal = parse(&["--cwd", cwd, "+5"]).unwrap();
+        let line = parse(&["+5", "notes.txt"]).unwrap();
+
+        assert_eq!(literal.file_name.as_deref(), Some("+5"));
+        assert_eq!(literal.line, None);
+        assert_eq!(line.file_name.as_deref(), Some("notes.txt"));
+        assert_eq!(line.line, Some(5));
+    }
+}
@@ -0,0 +1,87 @@
+use std::{
+    env,
+    io::{Error, Write},
+    process::{Command, Stdio},
+};
+
+/// 生成将文本复制到系统剪贴板的 OSC 52 转义序列，由终端转交给剪贴板，在 SSH 会话中同样有效。
+pub fn osc52_sequence(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", base64_encode(text.as_bytes()))
+}
+
+/// 通过本机的剪贴板程序复制文本，作为终端不支持 OSC 52 时的补充。
+///
+/// 在 SSH 会话中或找不到可用的剪贴板程序时返回错误。
+pub fn copy_with_system_tool(text: &str) -> Result<(), Error> {
+    if env::var_os("SSH_CONNECTION").is_some() {
+        return Err(Error::other("No local clipboard in an SSH session"));
+    }
+    let mut last_error = Error::other("No clipboard program available");
+    for (program, args) in system_tools() {
+        match run_tool(program, args, text) {
+            Ok(()) => return Ok(()),
+            Err(err) => last_error = err,
+        }
+    }
+    Err(last_error)
+}
+
+/// 按当前平台和图形环境排列的剪贴板程序及其参数。
+fn system_tools() -> Vec<(&'static str, &'static [&'static str])> {
+    let mut tools: Vec<(&'static str, &'static [&'static str])> = Vec::new();
+    if cfg!(target_os = "macos") {
+        tools.push(("pbcopy", &[]));
+    } else if cfg!(windows) {
+        tools.push(("clip", &[]));
+    } else {
+        if env::var_os("WAYLAND_DISPLAY").is_some() {
+            tools.push(("wl-copy", &[]));
+        }
+        if env::var_os("DISPLAY").is_some() {
+            tools.push(("xclip", &["-selection", "clipboard"]));
+            tools.push(("xsel", &["--clipboard", "--input"]));
+        }
+    }
+    tools
+}
+
+/// 启动剪贴板程序并把文本写入它的标准输入。
+fn run_tool(program: &str, args: &[&str], text: &str) -> Result<(), Error> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    let status = child.wait()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(Error::other(format!("{program} exited with {status}")))
+    }
+}
+
+/// 将字节编码为 Base64 字符串。
+fn base64_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut result = String::new();
+    for chunk in bytes.chunks(3) {
+        let first = chunk.first().copied().unwrap_or_default();
+        let second = chunk.get(1).copied().unwrap_or_default();
+        let third = chunk.get(2).copied().unwrap_or_default();
+        let triple = u32::from(first) << 16 | u32::from(second) << 8 | u32::from(third);
+        for (index, shift) in [18, 12, 6, 0].into_iter().enumerate() {
+            if index <= chunk.len() {
+                let sextet = usize::try_from(triple >> shift & 0x3f).unwrap_or_default();
+                result.push(char::from(ALPHABET.get(sextet).copied().unwrap_or(b'=')));
+            } else {
+                result.push('=');
+            }
+        }
+    }
+    result
+}
@@ -1,7 +1,8 @@
 use crossterm::event::{
     Event,
     KeyCode::{
-        Backspace, Char, Delete, Down, End, Enter, Home, Left, PageDown, PageUp, Right, Tab, Up,
+        BackTab, Backspace, Char, Delete, Down, End, Enter, Esc, F, Home, Left, PageDown, PageUp, Right, Tab,
+        Up,
     },
     KeyEvent, KeyModifiers,
 };
@@ -9,24 +10,31 @@ use std::convert::TryFrom;
 
 use super::terminal::Size;
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 pub enum Move {
     PageDown,
     PageUp,
     StartOfLine,
     EndOfLine,
+    DocumentStart,
+    DocumentEnd,
     Up,
     Left,
     Right,
     Down,
+    WordLeft,
+    WordRight,
+    MatchingBracket,
 }
 
 impl TryFrom<KeyEvent> for Move {
     type Error = String;
+    /// 按住 Shift 的移动同样被接受，是否扩展选区由 `Command::Move` 的第二项表示。
     fn try_from(event: KeyEvent) -> Result<Self, Self::Error> {
         let KeyEvent {
             code, modifiers, ..
         } = event;
+        let modifiers = modifiers.difference(KeyModifiers::SHIFT);
 
         if modifiers == KeyModifiers::NONE {
             match code {
@@ -40,6 +48,21 @@ impl TryFrom<KeyEvent> for Move {
                 End => Ok(Self::EndOfLine),
                 _ => Err(format!("Unsupported code: {code:?}")),
             }
+        } else if modifiers == KeyModifiers::CONTROL && matches!(code, Home | End) {
+            Ok(if code == Home {
+                Self::DocumentStart
+            } else {
+                Self::DocumentEnd
+            })
+        } else if modifiers == KeyModifiers::CONTROL && matches!(code, Left | Right) {
+            Ok(if code == Left {
+                Self::WordLeft
+            } else {
+                Self::WordRight
+            })
+        } else if modifiers == KeyModifiers::CONTROL && matches!(code, Char('5' | ']')) {
+            // 终端通常把 Ctrl-5 作为 Ctrl-] 发送。
+            Ok(Self::MatchingBracket)
         } else {
             Err(format!(
                 "Unsupported key code {code:?} or modifier {modifiers:?}"
@@ -48,12 +71,28 @@ impl TryFrom<KeyEvent> for Move {
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum Edit {
     Insert(char),
+    InsertTab,
+    Dedent,
     InsertNewline,
     Delete,
     DeleteBackward,
+    ToggleBlockComment,
+    ToggleLineComment,
+    ConvertIndentation,
+    TrimTrailingWhitespace,
+    Paste,
+    PasteAligned,
+    CutLine,
+    DeleteLine,
+    DuplicateLine,
+    MoveLineUp,
+    MoveLineDown,
+    PasteLineAbove,
+    Undo,
+    Redo,
 }
 
 impl TryFrom<KeyEvent> for Edit {
@@ -64,7 +103,27 @@ impl TryFrom<KeyEvent> for Edit {
             (Char(character), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
                 Ok(Self::Insert(character))
             }
-            (Tab, KeyModifiers::NONE) => Ok(Self::Insert('\t')),
+            (Char('/'), KeyModifiers::ALT) => Ok(Self::ToggleBlockComment),
+            // 大多数终端把 Ctrl-/ 作为 Ctrl-_ 发送，crossterm 将其报告为 Ctrl-7。
+            (Char('/' | '7' | '_'), KeyModifiers::CONTROL) => Ok(Self::ToggleLineComment),
+            (Char('t'), KeyModifiers::ALT) => Ok(Self::ConvertIndentation),
+            (Char('v'), KeyModifiers::CONTROL) => Ok(Self::Paste),
+            (Char('v'), KeyModifiers::ALT) => Ok(Self::PasteAligned),
+            (Char('d'), KeyModifiers::ALT) => Ok(Self::DuplicateLine),
+            (Up, KeyModifiers::ALT) => Ok(Self::MoveLineUp),
+            (Down, KeyModifiers::ALT) => Ok(Self::MoveLineDown),
+            (Char('x'), KeyModifiers::CONTROL) => Ok(Self::CutLine),
+            // Ctrl-K 用于二合字母，删除整行使用 Ctrl-Shift-K。
+            (Char('k' | 'K'), modifiers) if modifiers == KeyModifiers::CONTROL | KeyModifiers::SHIFT => {
+                Ok(Self::DeleteLine)
+            }
+            (Char('u'), KeyModifiers::CONTROL) => Ok(Self::PasteLineAbove),
+            (Char('z'), KeyModifiers::CONTROL) => Ok(Self::Undo),
+            (Char('y'), KeyModifiers::CONTROL) => Ok(Self::Redo),
+            (Tab, KeyModifiers::NONE) => Ok(Self::InsertTab),
+            (BackTab, KeyModifiers::NONE | KeyModifiers::SHIFT) | (Tab, KeyModifiers::SHIFT) => {
+                Ok(Self::Dedent)
+            }
             (Enter, KeyModifiers::NONE) => Ok(Self::InsertNewline),
             (Backspace, KeyModifiers::NONE) => Ok(Self::DeleteBackward),
             (Delete, KeyModifiers::NONE) => Ok(Self::Delete),
@@ -76,11 +135,32 @@ impl TryFrom<KeyEvent> for Edit {
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 pub enum System {
     Save,
     Resize(Size),
     Quit,
+    Dismiss,
+    Palette,
+    Digraph,
+    Calculate,
+    ToggleLineMark,
+    Search,
+    FindNext,
+    FindPrevious,
+    Replace,
+    ToggleLineNumbers,
+    CopyLine,
+    ToggleSelection,
+    SwapPosition,
+    GotoLine,
+    ToggleSoftWrap,
+    SaveAs,
+    OpenFile,
+    NextBuffer,
+    Reload,
+    WordCount,
+    ToggleReadOnly,
 }
 
 impl TryFrom<KeyEvent> for System {
@@ -94,8 +174,41 @@ impl TryFrom<KeyEvent> for System {
             match code {
                 Char('d') => Ok(Self::Quit),
                 Char('s') => Ok(Self::Save),
+                Char('p') => Ok(Self::Palette),
+                Char('k') => Ok(Self::Digraph),
+                Char('f') => Ok(Self::Search),
+                // 一些终端把 Ctrl-H 当作退格发送，Ctrl-R 作为替换的备用按键。
+                Char('h' | 'r') => Ok(Self::Replace),
+                Char('n') => Ok(Self::ToggleLineNumbers),
+                Char('c') => Ok(Self::CopyLine),
+                Char(' ') => Ok(Self::ToggleSelection),
+                Char('t') => Ok(Self::SwapPosition),
+                Char('g') => Ok(Self::GotoLine),
+                Char('o') => Ok(Self::OpenFile),
+                Char('w') => Ok(Self::WordCount),
+                Tab => Ok(Self::NextBuffer),
                 _ => Err(format!("Unsupported CONTROL+{code:?} combination")),
             }
+        } else if modifiers == KeyModifiers::CONTROL | KeyModifiers::SHIFT
+            && matches!(code, Char('s' | 'S'))
+        {
+            Ok(Self::SaveAs)
+        } else if modifiers == KeyModifiers::ALT && code == Char('=') {
+            Ok(Self::Calculate)
+        } else if modifiers == KeyModifiers::ALT && code == Char('h') {
+            Ok(Self::ToggleLineMark)
+        } else if modifiers == KeyModifiers::ALT && code == Char('r') {
+            Ok(Self::Reload)
+        } else if modifiers == KeyModifiers::ALT && code == Char('w') {
+            Ok(Self::ToggleReadOnly)
+        } else if modifiers == KeyModifiers::ALT && code == Char('z') {
+            Ok(Self::ToggleSoftWrap)
+        } else if modifiers == KeyModifiers::NONE && matches!(code, Esc) {
+            Ok(Self::Dismiss)
+        } else if code == F(3) && modifiers == KeyModifiers::NONE {
+            Ok(Self::FindNext)
+        } else if code == F(3) && modifiers == KeyModifiers::SHIFT {
+            Ok(Self::FindPrevious)
         } else {
             Err(format!(
                 "Unsupported key code {code:?} or modifier {modifiers:?}"
@@ -106,7 +219,7 @@ impl TryFrom<KeyEvent> for System {
 
 #[derive(Clone, Copy)]
 pub enum Command {
-    Move(Move),
+    Move(Move, bool), // 移动光标，第二项为真时（按住 Shift）扩展选区。
     Edit(Edit),
     System(System),
 }
@@ -118,7 +231,14 @@ impl TryFrom<Event> for Command {
         match event {
             Event::Key(key_event) => Edit::try_from(key_event)
                 .map(Command::Edit)
-                .or_else(|_| Move::try_from(key_event).map(Command::Move))
+                .or_else(|_| {
+                    Move::try_from(key_event).map(|move_command| {
+                        Command::Move(
+                            move_command,
+                            key_event.modifiers.contains(KeyModifiers::SHIFT),
+                        )
+                    })
+                })
                 .or_else(|_| System::try_from(key_event).map(Command::System))
                 .map_err(|_err| format!("Event not supported: {key_event:?}")),
             Event::Resize(width_u16, height_u16) => Ok(Self::System(System::Resize(Size {
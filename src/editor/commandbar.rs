@@ -0,0 +1,89 @@
+use std::{cmp::min, io::Error};
+
+use super::{
+    command::Edit,
+    terminal::{Size, Terminal},
+    truncate::{EllipsisSide, text_width, truncate_to_width},
+    uicomponent::UIComponent,
+    view::line::{Line, TabWidth},
+};
+
+/// 命令栏，在消息栏所在的行上读取用户输入。
+#[derive(Default)]
+pub struct CommandBar {
+    prompt: String,    // 输入提示。
+    value: Line,       // 用户输入的内容。
+    needs_redraw: bool, // 是否需要重绘。
+    size: Size,        // 命令栏的尺寸。
+}
+
+impl CommandBar {
+    /// 处理编辑命令，只支持输入字符和向后删除。
+    pub fn handle_edit_command(&mut self, command: Edit) {
+        match command {
+            Edit::Insert(character) => {
+                self.value.insert_char(character, self.value.grapheme_count());
+            }
+            Edit::InsertTab => {
+                self.value.insert_char('\t', self.value.grapheme_count());
+            }
+            Edit::DeleteBackward => {
+                if let Some(last) = self.value.grapheme_count().checked_sub(1) {
+                    self.value.delete(last);
+                }
+            }
+            _ => return,
+        }
+        self.set_needs_redraw(true);
+    }
+
+    /// 获取光标所在的列。
+    pub fn caret_position_col(&self) -> usize {
+        let max_width = text_width(&self.prompt).saturating_add(self.value.width(TabWidth::default()));
+        min(max_width, self.size.width)
+    }
+
+    /// 获取用户输入的内容。
+    pub fn value(&self) -> String {
+        self.value.to_string()
+    }
+
+    /// 设置输入提示。
+    pub fn set_prompt(&mut self, prompt: &str) {
+        self.prompt = prompt.to_string();
+        self.set_needs_redraw(true);
+    }
+
+    /// 清空用户输入。
+    pub fn clear_value(&mut self) {
+        self.value = Line::default();
+        self.set_needs_redraw(true);
+    }
+}
+
+impl UIComponent for CommandBar {
+    fn set_needs_redraw(&mut self, value: bool) {
+        self.needs_redraw = value;
+    }
+
+    fn needs_redraw(&self) -> bool {
+        self.needs_redraw
+    }
+
+    fn set_size(&mut self, size: Size) {
+        self.size = size;
+    }
+
+    fn draw(&mut self, origin_y: usize) -> Result<(), Error> {
+        // 输入内容超出宽度时，只显示末尾部分；提示本身超出宽度时截断提示。
+        let prompt = truncate_to_width(&self.prompt, self.size.width, EllipsisSide::End);
+        let area_for_value = self.size.width.saturating_sub(text_width(&prompt));
+        let value_end = self.value.width(TabWidth::default());
+        let value_start = value_end.saturating_sub(area_for_value);
+        let message = format!(
+            "{prompt}{}",
+            self.value.get_visible_graphemes(value_start..value_end, TabWidth::default())
+        );
+        Terminal::print_row(origin_y, &message)
+    }
+}
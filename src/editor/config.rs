@@ -0,0 +1,358 @@
+use super::{
+    backup::Backup, encoding::FileEncoding, keybindings::strip_comment, throttle::RenderMode, view::trash::TrashLimits,
+};
+use std::{
+    env, fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// 主目录中配置文件的文件名。
+const CONFIG_FILE: &str = ".snow_edit.toml";
+
+/// 配置文件中一项的值。
+enum Value {
+    Bool(bool),
+    Integer(usize),
+    Text(String),
+}
+
+/// 编辑器的配置项。
+#[derive(Clone)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct Config {
+    pub lint_indentation_on_save: bool, // 保存前检查修改过的行是否存在可疑缩进。
+    pub cwd: Option<PathBuf>,           // 解析相对路径时使用的基准目录。
+    pub show_scroll_markers: bool,      // 行内容超出视口时是否显示延续标记。
+    pub show_line_numbers: bool,        // 启动时是否在左侧显示行号栏。
+    pub soft_wrap: bool,                // 启动时是否把长行折成多个显示行，而不是水平滚动。
+    pub tab_width: usize,               // 制表符对齐到的列数的倍数。
+    pub expand_tabs: bool,              // 按 Tab 时是否插入空格（补齐到下一个制表位）而不是制表符。
+    pub confirm_destructive_actions: bool, // 执行修改多行的破坏性动作之前是否需要确认。
+    pub render_mode: RenderMode,        // 渲染模式：自动检测或强制完整/降级。
+    pub slow_flush_threshold: Duration, // 单帧刷新超过该耗时即视为终端过慢，配置文件中以毫秒为单位。
+    pub title_status_template: Option<String>, // 追加到终端标题的状态模板，如 `{line}:{col}{dirty}`。
+    pub publish_status: bool,           // 是否将状态写入状态目录中的 `status` 文件或命名管道。
+    pub digraphs: Vec<(String, char)>,  // 自定义的二合字母，优先于内置表。
+    pub audible_bell: bool,             // 出错时（如未知的二合字母）是否发出提示音。
+    pub escape_timeout: Duration,       // 单独的 Esc 之后等待后续按键的时间，超时则视为取消。
+    pub trash: TrashLimits,             // 哪些删除放入回收站、回收站的容量以及是否在会话之间保留。
+    pub break_symlinks_on_save: bool,   // 保存通过符号链接打开的文件时，用普通文件替换链接而不是写入链接指向的文件。
+    pub scroll_lines: usize,            // 鼠标滚轮每格滚动的行数。
+    pub backup: Backup,                 // 是否在第一次保存之前备份原文件，以及备份放在哪里。
+    pub fallback_encoding: Option<FileEncoding>, // 文件不是有效的 UTF-8 时尝试的编码，如 `gbk`；没有配置时拒绝打开。
+    pub trim_trailing_whitespace: bool, // 保存前是否删除每一行行尾的空格和制表符。
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            lint_indentation_on_save: false,
+            cwd: None,
+            show_scroll_markers: true,
+            show_line_numbers: true,
+            soft_wrap: false,
+            tab_width: 4,
+            expand_tabs: false,
+            confirm_destructive_actions: true,
+            render_mode: RenderMode::Auto,
+            slow_flush_threshold: Duration::from_millis(40),
+            title_status_template: None,
+            publish_status: false,
+            digraphs: Vec::new(),
+            audible_bell: true,
+            escape_timeout: Duration::from_millis(50),
+            trash: TrashLimits::default(),
+            break_symlinks_on_save: false,
+            scroll_lines: 3,
+            backup: Backup::default(),
+            fallback_encoding: None,
+            trim_trailing_whitespace: false,
+        }
+    }
+}
+
+impl Config {
+    /// 将文件名解析为路径：相对路径基于 `cwd`，绝对路径保持不变。
+    pub fn resolve_path(&self, file_name: &str) -> PathBuf {
+        let path = Path::new(file_name);
+        match &self.cwd {
+            Some(base) if path.is_relative() => base.join(path),
+            _ => path.to_path_buf(),
+        }
+    }
+
+    /// 命令行指定了 `--cwd` 时，它优先于配置文件中的 `cwd`。
+    pub fn apply_cwd_argument(&mut self, cwd: Option<PathBuf>) {
+        if cwd.is_some() {
+            self.cwd = cwd;
+        }
+    }
+
+    /// 读取主目录中的 `.snow_edit.toml`，返回配置和需要提示给用户的警告。
+    ///
+    /// 文件不存在时使用默认配置；文件无法读取或格式有误时同样使用默认配置，并返回说明。
+    pub fn load() -> (Self, Option<String>) {
+        let Some(path) = env::var_os("HOME")
+            .filter(|home| !home.is_empty())
+            .map(|home| Path::new(&home).join(CONFIG_FILE))
+        else {
+            return (Self::default(), None);
+        };
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == ErrorKind::NotFound => return (Self::default(), None),
+            Err(err) => return (Self::default(), Some(format!("{}: {err}", path.display()))),
+        };
+        match Self::parse(&text) {
+            Ok(config) => (config, None),
+            Err(err) => (
+                Self::default(),
+                Some(format!("{}: {err}, using defaults", path.display())),
+            ),
+        }
+    }
+
+    /// 解析配置文件，每行形如 `tab_width = 4`；`#` 之后是注释，`[editor]` 这样的表头被忽略。
+    ///
+    /// 不认识的键被忽略，以便新旧版本共用同一个配置文件；值的类型不对时返回错误。
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut config = Self::default();
+        for (index, line) in text.lines().enumerate() {
+            let line = strip_comment(line).trim();
+            if line.is_empty() || line.starts_with('[') {
+                continue;
+            }
+            config
+                .parse_entry(line)
+                .map_err(|err| format!("line {}: {err}", index.saturating_add(1)))?;
+        }
+        Ok(config)
+    }
+
+    /// 解析一行 `key = value` 并写入对应的配置项。
+    ///
+    /// 键可以加引号，引号内可以出现 `=`，如 `"digraph.=>" = "⇒"`。
+    fn parse_entry(&mut self, line: &str) -> Result<(), String> {
+        let (key, value) = if let Some(rest) = line.strip_prefix('"') {
+            let (key, rest) = rest.split_once('"').ok_or("unterminated quoted key")?;
+            let value = rest.trim_start().strip_prefix('=').ok_or("expected `key = value`")?;
+            (key, value)
+        } else {
+            let (key, value) = line.split_once('=').ok_or("expected `key = value`")?;
+            (key.trim(), value)
+        };
+        let value = parse_value(value.trim())?;
+        if let Some(pair) = key.strip_prefix("digraph.") {
+            return self.set_digraph(pair, &value);
+        }
+        let expected = match (key, value) {
+            ("tab_width", Value::Integer(width)) if width > 0 => {
+                self.tab_width = width;
+                None
+            }
+            ("tab_width", _) => Some("a positive integer"),
+            ("trash_max_age_days", Value::Integer(days)) => {
+                let seconds = u64::try_from(days).unwrap_or(u64::MAX).saturating_mul(86_400);
+                self.trash.max_age = (days > 0).then(|| Duration::from_secs(seconds));
+                None
+            }
+            ("trash_max_age_days", _) => Some("an integer"),
+            ("slow_flush_threshold", Value::Integer(millis)) if millis > 0 => {
+                self.slow_flush_threshold =
+                    Duration::from_millis(u64::try_from(millis).unwrap_or(u64::MAX));
+                None
+            }
+            ("slow_flush_threshold", _) => Some("a positive number of milliseconds"),
+            ("title_status_template", Value::Text(template)) => {
+                self.title_status_template = Some(template);
+                None
+            }
+            ("fallback_encoding", Value::Text(label)) => match FileEncoding::for_label(&label) {
+                Some(encoding) => {
+                    self.fallback_encoding = Some(encoding);
+                    None
+                }
+                None => Some("a known encoding name"),
+            },
+            ("cwd", Value::Text(dir)) => {
+                self.cwd = Some(PathBuf::from(dir));
+                None
+            }
+            ("title_status_template" | "fallback_encoding" | "cwd", _) => Some("a string"),
+            (key, value) => match (self.bool_field(key), value) {
+                (Some(field), Value::Bool(flag)) => {
+                    *field = flag;
+                    None
+                }
+                (Some(_), _) => Some("true or false"),
+                (None, value) => match (self.integer_field(key), value) {
+                    (Some(field), Value::Integer(number)) => {
+                        *field = number;
+                        None
+                    }
+                    (Some(_), _) => Some("an integer"),
+                    (None, _) => None,
+                },
+            },
+        };
+        match expected {
+            Some(expected) => Err(format!("`{key}` must be {expected}")),
+            None => Ok(()),
+        }
+    }
+
+    /// 设置 `digraph.XY = "字"` 定义的二合字母，同名的条目只保留最后一个。
+    fn set_digraph(&mut self, pair: &str, value: &Value) -> Result<(), String> {
+        if pair.chars().count() != 2 {
+            return Err(format!("`digraph.{pair}` must name exactly two characters"));
+        }
+        let mut chars = match value {
+            Value::Text(text) => text.chars(),
+            _ => "".chars(),
+        };
+        let (Some(ch), None) = (chars.next(), chars.next()) else {
+            return Err(format!("`digraph.{pair}` must be a single character in quotes"));
+        };
+        self.digraphs.retain(|(key, _)| key != pair);
+        self.digraphs.push((pair.to_string(), ch));
+        Ok(())
+    }
+
+    /// 配置文件中布尔值的键对应的配置项；不认识的键返回 `None`。
+    fn bool_field(&mut self, key: &str) -> Option<&mut bool> {
+        Some(match key {
+            "expand_tabs" => &mut self.expand_tabs,
+            "show_line_numbers" => &mut self.show_line_numbers,
+            "soft_wrap" => &mut self.soft_wrap,
+            "show_scroll_markers" => &mut self.show_scroll_markers,
+            "lint_indentation_on_save" => &mut self.lint_indentation_on_save,
+            "confirm_destructive_actions" => &mut self.confirm_destructive_actions,
+            "audible_bell" => &mut self.audible_bell,
+            "break_symlinks_on_save" => &mut self.break_symlinks_on_save,
+            "publish_status" => &mut self.publish_status,
+            "backup" => &mut self.backup.enabled,
+            "trim_trailing_whitespace" => &mut self.trim_trailing_whitespace,
+            "trash_persist" => &mut self.trash.persist,
+            _ => return None,
+        })
+    }
+
+    /// 配置文件中整数值的键对应的配置项；不认识的键返回 `None`。
+    fn integer_field(&mut self, key: &str) -> Option<&mut usize> {
+        Some(match key {
+            "scroll_lines" => &mut self.scroll_lines,
+            "trash_min_lines" => &mut self.trash.min_lines,
+            "trash_min_bytes" => &mut self.trash.min_bytes,
+            "trash_capacity" => &mut self.trash.capacity,
+            "trash_max_bytes" => &mut self.trash.max_bytes,
+            _ => return None,
+        })
+    }
+}
+
+/// 解析配置项的值：`true`/`false`、非负整数或带引号的字符串。
+fn parse_value(value: &str) -> Result<Value, String> {
+    match value {
+        "true" => return Ok(Value::Bool(true)),
+        "false" => return Ok(Value::Bool(false)),
+        _ => {}
+    }
+    if let Some(text) = value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+    {
+        return Ok(Value::Text(text.to_string()));
+    }
+    value
+        .replace('_', "")
+        .parse()
+        .map(Value::Integer)
+        .map_err(|_| format!("invalid value `{value}`"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_paths_are_resolved_against_the_cwd() {
+        let config = Config {
+            cwd: Some(PathBuf::from("/srv/project")),
+            ..Config::default()
+        };
+
+        assert_eq!(config.resolve_path("src/main.rs"), Path::new("/srv/project/src/main.rs"));
+        assert_eq!(config.resolve_path("/etc/hosts"), Path::new("/etc/hosts"));
+        assert_eq!(Config::default().resolve_path("notes.txt"), Path::new("notes.txt"));
+    }
+
+    #[test]
+    fn trash_limits_are_read_from_the_config_file() {
+        let config = Config::parse(
+            "trash_min_lines = 5\ntrash_min_bytes = 256\ntrash_capacity = 3\n\
+             trash_max_bytes = 1024\ntrash_max_age_days = 2\ntrash_persist = true\n",
+        )
+        .unwrap();
+
+        assert_eq!(config.trash.min_lines, 5);
+        assert_eq!(config.trash.min_bytes, 256);
+        assert_eq!(config.trash.capacity, 3);
+        assert_eq!(config.trash.max_bytes, 1024);
+        assert_eq!(config.trash.max_age, Some(Duration::from_hours(48)));
+        assert!(config.trash.persist);
+        assert_eq!(
+            Config::parse("trash_capacity = \"ten\"\n").err().unwrap(),
+            "line 1: `trash_capacity` must be an integer"
+        );
+    }
+
+    #[test]
+    fn the_slow_flush_threshold_is_read_in_milliseconds() {
+        let config = Config::parse("slow_flush_threshold = 120\n").unwrap();
+        assert_eq!(config.slow_flush_threshold, Duration::from_millis(120));
+
+        assert_eq!(
+            Config::parse("slow_flush_threshold = 0\n").err().unwrap(),
+            "line 1: `slow_flush_threshold` must be a positive number of milliseconds"
+        );
+    }
+
+    #[test]
+    fn the_fallback_encoding_is_only_used_when_configured() {
+        assert!(Config::default().fallback_encoding.is_none());
+
+        let config = Config::parse("fallback_encoding = \"gbk\"\n").unwrap();
+        assert_eq!(config.fallback_encoding, FileEncoding::for_label("gbk"));
+    }
+
+    #[test]
+    fn the_cwd_argument_takes_precedence_over_the_config_file() {
+        let from_file = Config::parse("cwd = \"/srv/config\"\n").unwrap();
+        assert_eq!(from_file.resolve_path("a.txt"), Path::new("/srv/config/a.txt"));
+
+        let mut config = Config::parse("cwd = \"/srv/config\"\n").unwrap();
+        config.apply_cwd_argument(None);
+        assert_eq!(config.cwd, Some(PathBuf::from("/srv/config")));
+
+        config.apply_cwd_argument(Some(PathBuf::from("/srv/cli")));
+        assert_eq!(config.resolve_path("a.txt"), Path::new("/srv/cli/a.txt"));
+    }
+
+    #[test]
+    fn digraphs_are_read_from_the_config_file() {
+        let config = Config::parse(
+            "digraph.e: = \"ε\"\ndigraph.sn = \"☃\"\n\"digraph.=>\" = \"⟹\" # 覆盖内置的 ⇒\ndigraph.sn = \"❄\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            config.digraphs,
+            [("e:".to_string(), 'ε'), ("=>".to_string(), '⟹'), ("sn".to_string(), '❄')]
+        );
+
+        assert!(Config::parse("digraph.abc = \"x\"").err().unwrap().contains("two characters"));
+        assert!(Config::parse("digraph.ab = \"xy\"").err().unwrap().contains("single character"));
+        assert!(Config::parse("digraph.ab = 1").err().unwrap().contains("single character"));
+    }
+}
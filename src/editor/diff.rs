@@ -0,0 +1,107 @@
+/// 中间不同部分的行数乘积超过该值时不再逐行比较，整段视为删除后重新插入。
+const MAX_TABLE_SIZE: usize = 4_000_000;
+
+/// 差异中的一行。
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum DiffLine {
+    Removed(usize, String), // 只在旧内容中的行，附带它在旧内容中的行索引。
+    Added(usize, String),   // 只在新内容中的行，附带它在新内容中的行索引。
+}
+
+/// 逐行比较新旧两份内容，按位置顺序返回删除和新增的行（同一处先删除后新增）。
+///
+/// 先去掉共同的开头和结尾，中间部分按最长公共子序列对齐。
+pub fn diff_lines<T: AsRef<str>, U: AsRef<str>>(old: &[T], new: &[U]) -> Vec<DiffLine> {
+    let prefix = old
+        .iter()
+        .zip(new)
+        .take_while(|(a, b)| a.as_ref() == b.as_ref())
+        .count();
+    let old_rest = old.get(prefix..).unwrap_or_default();
+    let new_rest = new.get(prefix..).unwrap_or_default();
+    let suffix = old_rest
+        .iter()
+        .rev()
+        .zip(new_rest.iter().rev())
+        .take_while(|(a, b)| a.as_ref() == b.as_ref())
+        .count();
+    let old_middle = old_rest
+        .get(..old_rest.len().saturating_sub(suffix))
+        .unwrap_or_default();
+    let new_middle = new_rest
+        .get(..new_rest.len().saturating_sub(suffix))
+        .unwrap_or_default();
+
+    let removed = |index: usize| {
+        DiffLine::Removed(
+            prefix.saturating_add(index),
+            old_middle
+                .get(index)
+                .map(|line| line.as_ref().to_string())
+                .unwrap_or_default(),
+        )
+    };
+    let added = |index: usize| {
+        DiffLine::Added(
+            prefix.saturating_add(index),
+            new_middle
+                .get(index)
+                .map(|line| line.as_ref().to_string())
+                .unwrap_or_default(),
+        )
+    };
+
+    let rows = old_middle.len();
+    let cols = new_middle.len();
+    if rows.saturating_mul(cols) > MAX_TABLE_SIZE {
+        return (0..rows).map(removed).chain((0..cols).map(added)).collect();
+    }
+
+    // `table[i][j]` 是旧内容从第 i 行、新内容从第 j 行开始的最长公共子序列长度。
+    let width = cols.saturating_add(1);
+    let mut table = vec![0_usize; rows.saturating_add(1).saturating_mul(width)];
+    let cell = |i: usize, j: usize| i.saturating_mul(width).saturating_add(j);
+    for i in (0..rows).rev() {
+        for j in (0..cols).rev() {
+            let same =
+                old_middle.get(i).map(AsRef::as_ref) == new_middle.get(j).map(AsRef::as_ref);
+            let value = if same {
+                table
+                    .get(cell(i.saturating_add(1), j.saturating_add(1)))
+                    .copied()
+                    .unwrap_or_default()
+                    .saturating_add(1)
+            } else {
+                let down = table.get(cell(i.saturating_add(1), j)).copied().unwrap_or_default();
+                let right = table.get(cell(i, j.saturating_add(1))).copied().unwrap_or_default();
+                down.max(right)
+            };
+            if let Some(slot) = table.get_mut(cell(i, j)) {
+                *slot = value;
+            }
+        }
+    }
+
+    let mut result = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < rows || j < cols {
+        let same = i < rows
+            && j < cols
+            && old_middle.get(i).map(AsRef::as_ref) == new_middle.get(j).map(AsRef::as_ref);
+        if same {
+            i = i.saturating_add(1);
+            j = j.saturating_add(1);
+            continue;
+        }
+        let down = table.get(cell(i.saturating_add(1), j)).copied().unwrap_or_default();
+        let right = table.get(cell(i, j.saturating_add(1))).copied().unwrap_or_default();
+        if j >= cols || (i < rows && down >= right) {
+            result.push(removed(i));
+            i = i.saturating_add(1);
+        } else {
+            result.push(added(j));
+            j = j.saturating_add(1);
+        }
+    }
+    result
+}
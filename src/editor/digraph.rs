@@ -0,0 +1,235 @@
+use super::{
+    Editor, PromptType,
+    command::{
+        Command::{self, Edit, Move, System},
+        Edit::{Insert, InsertNewline},
+        System::Dismiss,
+    },
+    overlay::Overlay,
+    terminal::{StyledText, Terminal},
+    uicomponent::UIComponent,
+};
+
+/// RFC 1345 风格的内置二合字母表：两个字符组合表示一个字符。
+const DIGRAPHS: &[(&str, char)] = &[
+    ("!I", '¡'), ("Ct", '¢'), ("Pd", '£'), ("Cu", '¤'), ("Ye", '¥'), ("BB", '¦'),
+    ("SE", '§'), ("':", '¨'), ("Co", '©'), ("-a", 'ª'), ("<<", '«'), ("NO", '¬'),
+    ("Rg", '®'), ("'m", '¯'), ("DG", '°'), ("+-", '±'), ("2S", '²'), ("3S", '³'),
+    ("''", '´'), ("My", 'µ'), ("PI", '¶'), (".M", '·'), ("',", '¸'), ("1S", '¹'),
+    ("-o", 'º'), (">>", '»'), ("14", '¼'), ("12", '½'), ("34", '¾'), ("?I", '¿'),
+    ("A!", 'À'), ("A'", 'Á'), ("A>", 'Â'), ("A?", 'Ã'), ("A:", 'Ä'), ("AA", 'Å'),
+    ("AE", 'Æ'), ("C,", 'Ç'), ("E!", 'È'), ("E'", 'É'), ("E>", 'Ê'), ("E:", 'Ë'),
+    ("I!", 'Ì'), ("I'", 'Í'), ("I>", 'Î'), ("I:", 'Ï'), ("D-", 'Ð'), ("N?", 'Ñ'),
+    ("O!", 'Ò'), ("O'", 'Ó'), ("O>", 'Ô'), ("O?", 'Õ'), ("O:", 'Ö'), ("*X", '×'),
+    ("O/", 'Ø'), ("U!", 'Ù'), ("U'", 'Ú'), ("U>", 'Û'), ("U:", 'Ü'), ("Y'", 'Ý'),
+    ("TH", 'Þ'), ("ss", 'ß'), ("a!", 'à'), ("a'", 'á'), ("a>", 'â'), ("a?", 'ã'),
+    ("a:", 'ä'), ("aa", 'å'), ("ae", 'æ'), ("c,", 'ç'), ("e!", 'è'), ("e'", 'é'),
+    ("e>", 'ê'), ("e:", 'ë'), ("i!", 'ì'), ("i'", 'í'), ("i>", 'î'), ("i:", 'ï'),
+    ("d-", 'ð'), ("n?", 'ñ'), ("o!", 'ò'), ("o'", 'ó'), ("o>", 'ô'), ("o?", 'õ'),
+    ("o:", 'ö'), ("-:", '÷'), ("o/", 'ø'), ("u!", 'ù'), ("u'", 'ú'), ("u>", 'û'),
+    ("u:", 'ü'), ("y'", 'ý'), ("th", 'þ'), ("y:", 'ÿ'), ("A-", 'Ā'), ("a-", 'ā'),
+    ("A(", 'Ă'), ("a(", 'ă'), ("A;", 'Ą'), ("a;", 'ą'), ("C'", 'Ć'), ("c'", 'ć'),
+    ("C>", 'Ĉ'), ("c>", 'ĉ'), ("C.", 'Ċ'), ("c.", 'ċ'), ("C<", 'Č'), ("c<", 'č'),
+    ("D<", 'Ď'), ("d<", 'ď'), ("D/", 'Đ'), ("d/", 'đ'), ("E-", 'Ē'), ("e-", 'ē'),
+    ("E(", 'Ĕ'), ("e(", 'ĕ'), ("E.", 'Ė'), ("e.", 'ė'), ("E;", 'Ę'), ("e;", 'ę'),
+    ("E<", 'Ě'), ("e<", 'ě'), ("G>", 'Ĝ'), ("g>", 'ĝ'), ("G(", 'Ğ'), ("g(", 'ğ'),
+    ("G.", 'Ġ'), ("g.", 'ġ'), ("G,", 'Ģ'), ("g,", 'ģ'), ("H>", 'Ĥ'), ("h>", 'ĥ'),
+    ("H/", 'Ħ'), ("h/", 'ħ'), ("I?", 'Ĩ'), ("i?", 'ĩ'), ("I-", 'Ī'), ("i-", 'ī'),
+    ("I(", 'Ĭ'), ("i(", 'ĭ'), ("I;", 'Į'), ("i;", 'į'), ("I.", 'İ'), ("i.", 'ı'),
+    ("IJ", 'Ĳ'), ("ij", 'ĳ'), ("J>", 'Ĵ'), ("j>", 'ĵ'), ("K,", 'Ķ'), ("k,", 'ķ'),
+    ("kk", 'ĸ'), ("L'", 'Ĺ'), ("l'", 'ĺ'), ("L,", 'Ļ'), ("l,", 'ļ'), ("L<", 'Ľ'),
+    ("l<", 'ľ'), ("L.", 'Ŀ'), ("l.", 'ŀ'), ("L/", 'Ł'), ("l/", 'ł'), ("N'", 'Ń'),
+    ("n'", 'ń'), ("N,", 'Ņ'), ("n,", 'ņ'), ("N<", 'Ň'), ("n<", 'ň'), ("'n", 'ŉ'),
+    ("NG", 'Ŋ'), ("ng", 'ŋ'), ("O-", 'Ō'), ("o-", 'ō'), ("O(", 'Ŏ'), ("o(", 'ŏ'),
+    ("O\"", 'Ő'), ("o\"", 'ő'), ("OE", 'Œ'), ("oe", 'œ'), ("R'", 'Ŕ'), ("r'", 'ŕ'),
+    ("R,", 'Ŗ'), ("r,", 'ŗ'), ("R<", 'Ř'), ("r<", 'ř'), ("S'", 'Ś'), ("s'", 'ś'),
+    ("S>", 'Ŝ'), ("s>", 'ŝ'), ("S,", 'Ş'), ("s,", 'ş'), ("S<", 'Š'), ("s<", 'š'),
+    ("T,", 'Ţ'), ("t,", 'ţ'), ("T<", 'Ť'), ("t<", 'ť'), ("T/", 'Ŧ'), ("t/", 'ŧ'),
+    ("U?", 'Ũ'), ("u?", 'ũ'), ("U-", 'Ū'), ("u-", 'ū'), ("U(", 'Ŭ'), ("u(", 'ŭ'),
+    ("U0", 'Ů'), ("u0", 'ů'), ("U\"", 'Ű'), ("u\"", 'ű'), ("U;", 'Ų'), ("u;", 'ų'),
+    ("W>", 'Ŵ'), ("w>", 'ŵ'), ("Y>", 'Ŷ'), ("y>", 'ŷ'), ("Y:", 'Ÿ'), ("Z'", 'Ź'),
+    ("z'", 'ź'), ("Z.", 'Ż'), ("z.", 'ż'), ("Z<", 'Ž'), ("z<", 'ž'), ("A*", 'Α'),
+    ("B*", 'Β'), ("G*", 'Γ'), ("D*", 'Δ'), ("E*", 'Ε'), ("Z*", 'Ζ'), ("Y*", 'Η'),
+    ("H*", 'Θ'), ("I*", 'Ι'), ("K*", 'Κ'), ("L*", 'Λ'), ("M*", 'Μ'), ("N*", 'Ν'),
+    ("C*", 'Ξ'), ("O*", 'Ο'), ("P*", 'Π'), ("R*", 'Ρ'), ("S*", 'Σ'), ("T*", 'Τ'),
+    ("U*", 'Υ'), ("F*", 'Φ'), ("X*", 'Χ'), ("Q*", 'Ψ'), ("W*", 'Ω'), ("a*", 'α'),
+    ("b*", 'β'), ("g*", 'γ'), ("d*", 'δ'), ("e*", 'ε'), ("z*", 'ζ'), ("y*", 'η'),
+    ("h*", 'θ'), ("i*", 'ι'), ("k*", 'κ'), ("l*", 'λ'), ("m*", 'μ'), ("n*", 'ν'),
+    ("c*", 'ξ'), ("o*", 'ο'), ("p*", 'π'), ("r*", 'ρ'), ("*s", 'ς'), ("s*", 'σ'),
+    ("t*", 'τ'), ("u*", 'υ'), ("f*", 'φ'), ("x*", 'χ'), ("q*", 'ψ'), ("w*", 'ω'),
+    ("-N", '–'), ("-M", '—'), ("'6", '‘'), ("'9", '’'), (".9", '‚'), ("\"6", '“'),
+    ("\"9", '”'), (":9", '„'), ("/-", '†'), ("/=", '‡'), (",.", '…'), ("%0", '‰'),
+    ("<1", '‹'), (">1", '›'), ("Eu", '€'), ("e=", '€'), ("NS", '\u{a0}'), ("<-", '←'),
+    ("-!", '↑'), ("->", '→'), ("-v", '↓'), ("<>", '↔'), ("UD", '↕'), ("<=", '⇐'),
+    ("=>", '⇒'), ("==", '⇔'), ("FA", '∀'), ("dP", '∂'), ("TE", '∃'), ("/0", '∅'),
+    ("DE", '∆'), ("NB", '∇'), ("(-", '∈'), ("-)", '∋'), ("*P", '∏'), ("+Z", '∑'),
+    ("-2", '−'), ("-+", '∓'), ("*-", '∗'), ("Ob", '∘'), ("Sb", '∙'), ("RT", '√'),
+    ("0(", '∝'), ("00", '∞'), ("-L", '∟'), ("-V", '∠'), ("PP", '∥'), ("AN", '∧'),
+    ("OR", '∨'), ("(U", '∩'), (")U", '∪'), ("In", '∫'), ("DI", '∬'), ("Io", '∮'),
+    (".:", '∴'), (":.", '∵'), (":R", '∶'), ("::", '∷'), ("?1", '∼'), ("CG", '∾'),
+    ("?-", '≃'), ("?=", '≅'), ("?2", '≈'), ("=?", '≌'), ("!=", '≠'), ("=3", '≡'),
+    ("=<", '≤'), (">=", '≥'), ("<*", '≪'), ("*>", '≫'), ("!<", '≮'), ("!>", '≯'),
+    ("(C", '⊂'), (")C", '⊃'), ("(_", '⊆'), (")_", '⊇'), ("0.", '⊙'), ("-T", '⊥'),
+    (".P", '⋅'), ("oC", '℃'), ("co", '℅'), ("oF", '℉'), ("N0", '№'), ("PO", '℗'),
+    ("Rx", '℞'), ("SM", '℠'), ("TM", '™'), ("Om", 'Ω'), ("13", '⅓'), ("23", '⅔'),
+    ("15", '⅕'), ("25", '⅖'), ("35", '⅗'), ("45", '⅘'), ("16", '⅙'), ("56", '⅚'),
+    ("18", '⅛'), ("38", '⅜'), ("58", '⅝'), ("78", '⅞'), ("0S", '⁰'), ("4S", '⁴'),
+    ("5S", '⁵'), ("6S", '⁶'), ("7S", '⁷'), ("8S", '⁸'), ("9S", '⁹'), ("+S", '⁺'),
+    ("-S", '⁻'), ("=S", '⁼'), ("(S", '⁽'), (")S", '⁾'), ("nS", 'ⁿ'), ("0s", '₀'),
+    ("1s", '₁'), ("2s", '₂'), ("3s", '₃'), ("4s", '₄'), ("5s", '₅'), ("6s", '₆'),
+    ("7s", '₇'), ("8s", '₈'), ("9s", '₉'), ("+s", '₊'), ("-s", '₋'), ("=s", '₌'),
+    ("(s", '₍'), (")s", '₎'), ("Li", '₤'), ("Pt", '₧'), ("W=", '₩'), ("OK", '✓'),
+    ("XX", '✗'), ("-X", '✠'), ("*1", '☆'), ("*2", '★'), ("cH", '♥'), ("cS", '♠'),
+    ("cD", '♦'), ("cC", '♣'), ("Md", '♩'), ("M8", '♪'), ("M2", '♫'), ("Mb", '♭'),
+    ("Mx", '♮'), ("MX", '♯'), ("Sn", '☼'), ("Dt", '◆'), ("OS", '␣'),
+];
+
+/// 查找二合字母：优先使用配置中的自定义条目，两个字符顺序颠倒时同样可以匹配。
+pub fn lookup(first: char, second: char, custom: &[(String, char)]) -> Option<char> {
+    let find = |pair: &str| {
+        custom
+            .iter()
+            .find(|(key, _)| key == pair)
+            .map(|(_, ch)| *ch)
+            .or_else(|| {
+                DIGRAPHS
+                    .iter()
+                    .find(|(key, _)| *key == pair)
+                    .map(|(_, ch)| *ch)
+            })
+    };
+    find(&format!("{first}{second}")).or_else(|| find(&format!("{second}{first}")))
+}
+
+/// 列出组合或字符本身包含查询内容的二合字母，自定义条目覆盖同名的内置条目。
+pub fn search(query: &str, custom: &[(String, char)]) -> Vec<(String, char)> {
+    let builtin = DIGRAPHS
+        .iter()
+        .filter(|(key, _)| custom.iter().all(|(custom_key, _)| custom_key != key))
+        .map(|(key, ch)| ((*key).to_string(), *ch));
+    custom
+        .iter()
+        .cloned()
+        .chain(builtin)
+        .filter(|(key, ch)| query.is_empty() || key.contains(query) || query.contains(*ch))
+        .collect()
+}
+
+impl Editor {
+    /// 进入二合字母模式，接下来输入的两个字符组合成一个字符。
+    pub(super) fn start_digraph(&mut self) {
+        self.prompt_type = PromptType::Digraph(None);
+        self.status_bar.set_pending_keys("^K");
+    }
+
+    /// 处理二合字母模式中的按键。
+    pub(super) fn process_command_during_digraph(&mut self, command: Command, first: Option<char>) {
+        match (command, first) {
+            (Edit(Insert(first)), None) => {
+                self.prompt_type = PromptType::Digraph(Some(first));
+                self.status_bar.set_pending_keys(&format!("^K{first}"));
+            }
+            (Edit(Insert(second)), Some(first)) => {
+                self.leave_digraph();
+                if let Some(ch) = lookup(first, second, &self.config.digraphs) {
+                    self.apply_edit(Insert(ch));
+                } else {
+                    if self.config.audible_bell {
+                        let _ = Terminal::bell();
+                    }
+                    self.message_bar
+                        .update_message(&format!("Unknown digraph: {first}{second}"));
+                }
+            }
+            _ => self.leave_digraph(),
+        }
+    }
+
+    /// 开始在命令栏中输入查询，列出匹配的二合字母。
+    pub(super) fn start_digraph_search(&mut self) {
+        self.enter_text_prompt(PromptType::DigraphSearch, "Digraph: ");
+    }
+
+    /// 列出与查询匹配的二合字母。
+    pub(super) fn submit_digraph_search(&mut self, query: &str) {
+        self.digraph_matches = search(query, &self.config.digraphs);
+        if self.digraph_matches.is_empty() {
+            self.message_bar
+                .update_message(&format!("No digraphs match \"{query}\""));
+            return;
+        }
+        let title = format!(
+            "Digraphs matching \"{query}\": {} (Enter insert, Esc close)",
+            self.digraph_matches.len()
+        );
+        self.overlay = Overlay::new(&title, self.digraph_matches.len(), false, self.view_size());
+        self.prompt_type = PromptType::DigraphList;
+    }
+
+    /// 处理二合字母列表中的按键：回车插入选中的字符，Esc 关闭。
+    pub(super) fn process_command_during_digraph_list(&mut self, command: Command) {
+        match command {
+            Move(move_command, _) => self.overlay.handle_move_command(move_command),
+            Edit(InsertNewline) => {
+                let selected = self.digraph_matches.get(self.overlay.selected()).map(|(_, ch)| *ch);
+                self.close_digraph_list();
+                if let Some(ch) = selected {
+                    self.apply_edit(Insert(ch));
+                }
+            }
+            System(Dismiss) => self.close_digraph_list(),
+            _ => {}
+        }
+    }
+
+    /// 为可见区域内尚未生成内容的列表项生成内容。
+    pub(super) fn fill_digraph_list(&mut self) {
+        for index in self.overlay.missing_rows() {
+            if let Some((key, ch)) = self.digraph_matches.get(index) {
+                let row = vec![StyledText {
+                    text: format!("{key}  {ch}  U+{:04X}", u32::from(*ch)),
+                    style: None,
+                }];
+                self.overlay.set_row(index, row);
+            }
+        }
+    }
+
+    /// 离开二合字母模式。
+    fn leave_digraph(&mut self) {
+        self.prompt_type = PromptType::None;
+        self.status_bar.set_pending_keys("");
+    }
+
+    /// 关闭二合字母列表，恢复显示视图。
+    fn close_digraph_list(&mut self) {
+        self.prompt_type = PromptType::None;
+        self.digraph_matches.clear();
+        self.view.set_needs_redraw(true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_digraphs_match_in_either_order() {
+        assert_eq!(lookup('e', ':', &[]), Some('ë'));
+        assert_eq!(lookup(':', 'e', &[]), Some('ë'));
+        assert_eq!(lookup('-', '>', &[]), Some('→'));
+        assert_eq!(lookup('q', 'q', &[]), None);
+        assert_eq!(lookup('@', '~', &[]), None);
+    }
+
+    #[test]
+    fn custom_digraphs_override_the_built_in_table() {
+        let custom = [("e:".to_string(), 'ε'), ("sn".to_string(), '❄')];
+        assert_eq!(lookup('e', ':', &custom), Some('ε'));
+        assert_eq!(lookup('n', 's', &custom), Some('❄'));
+        assert_eq!(lookup('a', ':', &custom), Some('ä'));
+
+        let found = search("e:", &custom);
+        assert_eq!(found, [("e:".to_string(), 'ε')]);
+    }
+}
@@ -0,0 +1,118 @@
+use super::{
+    NAME, VERSION,
+    capabilities::{self, Mode, Support},
+    paths,
+};
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+use std::{
+    env,
+    io::{self, Write},
+};
+
+/// 报告中标签列的宽度。
+const LABEL_WIDTH: usize = 22;
+
+/// 报告中的一节：标题和若干 `(标签, 结果)`。
+type Section = (&'static str, Vec<(&'static str, String)>);
+
+/// 探测终端环境并将报告打印到标准输出。
+///
+/// 每一项探测都是独立的：查询类的探测各自有超时，某一项没有回答或失败
+/// 只会体现在该项的结果中，不影响其余各项。
+pub fn run() -> io::Result<()> {
+    let sections = [
+        ("Terminal", probe_terminal()),
+        (
+            "Configuration",
+            vec![(
+                "config file",
+                String::from("none (settings are built in, nothing to parse)"),
+            )],
+        ),
+        ("State", vec![("state directory", state_directory())]),
+        ("Locale", vec![("LC_ALL/LC_CTYPE/LANG", locale())]),
+    ];
+    print_report(&sections)
+}
+
+/// 探测终端的各项能力。
+fn probe_terminal() -> Vec<(&'static str, String)> {
+    let variable = |name| env::var(name).unwrap_or_else(|_| String::from("(unset)"));
+    let mut results = vec![
+        ("TERM", variable("TERM")),
+        ("COLORTERM", variable("COLORTERM")),
+        ("colors", capabilities::color_support()),
+    ];
+
+    let queries: [(&'static str, Option<Mode>); 6] = [
+        ("alternate screen", Some(Mode::AlternateScreen)),
+        ("synchronized output", Some(Mode::SynchronizedOutput)),
+        ("kitty keyboard", None),
+        ("mouse", Some(Mode::Mouse)),
+        ("bracketed paste", Some(Mode::BracketedPaste)),
+        ("focus events", Some(Mode::FocusEvents)),
+    ];
+    let raw_mode = if capabilities::is_interactive() {
+        enable_raw_mode().map_err(|err| err.to_string())
+    } else {
+        Err(String::from("stdin/stdout is not a terminal"))
+    };
+    match &raw_mode {
+        Ok(()) => {
+            results.push(("raw mode", String::from("ok")));
+            for (label, mode) in queries {
+                let support = mode.map_or_else(capabilities::query_kitty_keyboard, |mode| {
+                    capabilities::query_mode(mode)
+                });
+                results.push((label, support.to_string()));
+            }
+            if let Err(err) = disable_raw_mode() {
+                results.push(("restore terminal", format!("failed: {err}")));
+            }
+        }
+        Err(err) => {
+            results.push(("raw mode", format!("unavailable: {err}")));
+            for (label, _) in queries {
+                results.push((label, Support::Skipped("raw mode unavailable").to_string()));
+            }
+        }
+    }
+    results.push((
+        "OSC 52 clipboard",
+        String::from("unknown (terminals do not answer queries for it)"),
+    ));
+    results
+}
+
+/// 状态目录及其是否可写。
+fn state_directory() -> String {
+    paths::state_dir().map_or_else(
+        || String::from("none writable (stashes are kept in memory only)"),
+        |dir| format!("{} (writable)", dir.display()),
+    )
+}
+
+/// 区域设置及其是否使用 UTF-8。
+fn locale() -> String {
+    match capabilities::locale() {
+        (locale, _) if locale.is_empty() => {
+            String::from("(unset) — non-ASCII text may not display correctly")
+        }
+        (locale, true) => format!("{locale} (UTF-8)"),
+        (locale, false) => format!("{locale} — not UTF-8, non-ASCII text may not display correctly"),
+    }
+}
+
+/// 打印报告。
+fn print_report(sections: &[Section]) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    writeln!(stdout, "{NAME} {VERSION} doctor")?;
+    for (title, results) in sections {
+        writeln!(stdout)?;
+        writeln!(stdout, "{title}")?;
+        for (label, result) in results {
+            writeln!(stdout, "  {label:<LABEL_WIDTH$}{result}")?;
+        }
+    }
+    Ok(())
+}
@@ -1,10 +1,20 @@
+use super::{encoding::FileEncoding, view::textformat::TextFormat};
 
 #[derive(Default, Eq, PartialEq, Debug)]
 pub struct DocumentStatus{
     pub total_lines: usize,       // 文档的总行数。
     pub current_line_index: usize,      // 当前行号。
+    pub current_column_index: usize,    // 当前列号（字形索引）。
+    pub current_display_column: usize,  // 光标左侧内容的显示宽度，与字形索引在有制表符或全宽字符时不同。
     pub is_modified: bool,          // 文档是否被修改。
     pub file_name: String,   // 文档的文件名。
+    pub is_symlink: bool,    // 文档是否通过符号链接打开。
+    pub is_read_only: bool,  // 文件没有写权限或编辑器处于只读模式，拒绝修改。
+    pub text_format: TextFormat, // 保存时使用的换行符、结尾换行和字节顺序标记。
+    pub encoding: FileEncoding, // 文件的字符编码。
+    pub buffer_index: usize, // 当前缓冲区在打开的缓冲区中的序号，从 0 开始。
+    pub buffer_count: usize, // 打开的缓冲区的数量。
+    pub horizontal_offset: usize, // 视图向右滚动过的显示列数，软换行时为 0。
 }
 
 impl  DocumentStatus {
@@ -17,17 +27,78 @@ impl  DocumentStatus {
         }
     }
 
+    /// 只读时显示的标记。
+    pub fn read_only_indicator_to_string(&self) -> String {
+        if self.is_read_only {
+            String::from("[RO] ")
+        } else {
+            String::new()
+        }
+    }
+
+    /// 通过符号链接打开时显示的标记，保存会写入链接指向的文件。
+    pub fn symlink_indicator_to_string(&self) -> String {
+        if self.is_symlink {
+            String::from(" ->")
+        } else {
+            String::new()
+        }
+    }
+
+    /// 打开了多个缓冲区时显示当前是第几个，如 ` [2/3]`。
+    pub fn buffer_indicator_to_string(&self) -> String {
+        if self.buffer_count > 1 {
+            format!(" [{}/{}]", self.buffer_index.saturating_add(1), self.buffer_count)
+        } else {
+            String::new()
+        }
+    }
+
+    /// 视图向右滚动过时显示视图左边缘所在的列（从 1 开始），如 ` [col 41+]`。
+    pub fn scroll_indicator_to_string(&self) -> String {
+        if self.horizontal_offset > 0 {
+            format!(" [col {}+]", self.horizontal_offset.saturating_add(1))
+        } else {
+            String::new()
+        }
+    }
+
     /// 返回一共有多少行
     pub fn line_count_to_string(&self) -> String {
         format!("{} lines", self.total_lines)
     }
 
-    // 返回当前所在行号
-    pub fn position_indicator_to_string(&self) -> String{
+    /// 按模板生成状态文本，支持 `{file}`、`{line}`、`{col}` 和 `{dirty}` 占位符。
+    pub fn render_template(&self, template: &str) -> String {
+        template
+            .replace("{file}", &self.file_name)
+            .replace("{line}", &self.current_line_index.saturating_add(1).to_string())
+            .replace("{col}", &self.current_column_index.saturating_add(1).to_string())
+            .replace("{dirty}", if self.is_modified { "+" } else { "" })
+    }
+
+    /// 文件的编码和换行符，如 `utf-8 LF`、`gbk CRLF`；有字节顺序标记时标出 `BOM`，
+    /// 文件没有以换行符结尾时标出 `noeol`。
+    pub fn format_indicator_to_string(&self) -> String {
         format!(
-            "{}/{}",
-            self.current_line_index.saturating_add(1),
-            self.total_lines
+            "{}{} {}{}",
+            self.encoding,
+            if self.text_format.bom { " BOM" } else { "" },
+            self.text_format.line_ending.name(),
+            if self.text_format.final_newline { "" } else { " noeol" },
         )
     }
+
+    // 返回光标所在的行和列（从 1 开始）以及在文件中的百分比，如 `Ln 12, Col 40 (3%)`；
+    // 列是显示列，有制表符或全宽字符时与光标在屏幕上的位置一致，而不是字形的个数
+    pub fn position_indicator_to_string(&self) -> String{
+        let line = self.current_line_index.saturating_add(1);
+        let column = self.current_display_column.saturating_add(1);
+        let percent = line
+            .min(self.total_lines)
+            .saturating_mul(100)
+            .checked_div(self.total_lines)
+            .unwrap_or(100);
+        format!("Ln {line}, Col {column} ({percent}%)")
+    }
 }
\ No newline at end of file
@@ -0,0 +1,157 @@
+use super::{
+    Editor, PromptType,
+    command::{
+        Command::{self, Edit, Move, System},
+        Edit::{Insert, InsertNewline},
+        System::Dismiss,
+    },
+    overlay::{Overlay, change_row},
+    terminal::StyledText,
+    uicomponent::UIComponent,
+    view::{LineChange, View},
+};
+
+/// 计算破坏性动作将要进行的修改的方法。
+pub type Plan = fn(&View) -> Vec<LineChange>;
+
+/// 等待确认的破坏性动作。
+#[derive(Default)]
+pub struct DryRun {
+    name: &'static str,       // 动作的名称。
+    changes: Vec<LineChange>, // 试运行计算出的修改。
+}
+
+impl Editor {
+    /// 执行破坏性动作：先试运行计算修改，必要时请求确认后再应用。
+    pub(super) fn run_destructive(&mut self, name: &'static str, plan: Plan) {
+        if !self.check_writable() {
+            return;
+        }
+        let changes = plan(&self.view);
+        if changes.is_empty() {
+            self.message_bar.update_message("Nothing to change.");
+            return;
+        }
+        self.dry_run = DryRun { name, changes };
+        if self.config.confirm_destructive_actions {
+            self.message_bar.update_message(&format!(
+                "This will modify {} lines — proceed? (y/n / p to preview)",
+                self.dry_run.changes.len()
+            ));
+            self.prompt_type = PromptType::ConfirmChanges;
+        } else {
+            self.apply_dry_run(None);
+        }
+    }
+
+    /// 处理破坏性动作的确认：`y` 应用，`p` 预览，其他按键取消。
+    pub(super) fn process_command_during_confirm_changes(&mut self, command: Command) {
+        match command {
+            Edit(Insert('y' | 'Y')) => self.apply_dry_run(None),
+            Edit(Insert('p' | 'P')) => self.open_change_preview(),
+            _ => {
+                self.prompt_type = PromptType::None;
+                self.dry_run.changes.clear();
+                self.message_bar.update_message("Aborted.");
+            }
+        }
+    }
+
+    /// 处理修改预览中的按键：空格切换是否应用该行，回车应用，Esc 取消。
+    pub(super) fn process_command_during_change_preview(&mut self, command: Command) {
+        match command {
+            Move(move_command, _) => self.overlay.handle_move_command(move_command),
+            Edit(Insert(' ')) => {
+                self.overlay.toggle_selected();
+                self.update_change_preview_title();
+            }
+            Edit(InsertNewline) => {
+                let total = self.dry_run.changes.len();
+                self.view.set_needs_redraw(true);
+                self.apply_dry_run(Some(total));
+            }
+            System(Dismiss) => {
+                self.prompt_type = PromptType::None;
+                self.dry_run.changes.clear();
+                self.view.set_needs_redraw(true);
+                self.message_bar.update_message("Aborted.");
+            }
+            _ => {}
+        }
+    }
+
+    /// 为可见区域内尚未生成内容的预览项生成内容。
+    pub(super) fn fill_change_preview(&mut self) {
+        for index in self.overlay.missing_rows() {
+            if let Some(change) = self.dry_run.changes.get(index) {
+                let row = self.change_preview_row(change);
+                self.overlay.set_row(index, row);
+            }
+        }
+    }
+
+    /// 打开修改预览，复用替换预览的覆盖层。
+    fn open_change_preview(&mut self) {
+        self.overlay = Overlay::new("", self.dry_run.changes.len(), true, self.view_size());
+        self.update_change_preview_title();
+        self.prompt_type = PromptType::ChangePreview;
+        self.message_bar.update_message("");
+    }
+
+    /// 生成一行修改的预览，只高亮修改前后不同的部分。
+    fn change_preview_row(&self, change: &LineChange) -> Vec<StyledText> {
+        let before = self.view.line_text(change.line_index);
+        let after = change.text.as_str();
+        let prefix_len = before
+            .char_indices()
+            .zip(after.chars())
+            .find(|((_, old), new)| old != new)
+            .map_or_else(|| before.len().min(after.len()), |((index, _), _)| index);
+        let suffix_len = before
+            .get(prefix_len..)
+            .unwrap_or_default()
+            .chars()
+            .rev()
+            .zip(after.get(prefix_len..).unwrap_or_default().chars().rev())
+            .take_while(|(old, new)| old == new)
+            .map(|(old, _)| old.len_utf8())
+            .sum::<usize>();
+        let before_end = before.len().saturating_sub(suffix_len);
+        let after_end = after.len().saturating_sub(suffix_len);
+        change_row(
+            change.line_index,
+            before.get(..prefix_len).unwrap_or_default(),
+            before.get(prefix_len..before_end).unwrap_or_default(),
+            after.get(prefix_len..after_end).unwrap_or_default(),
+            before.get(before_end..).unwrap_or_default(),
+        )
+    }
+
+    /// 应用试运行计算出的修改；`total` 为预览中的修改总数，此时只应用被勾选的修改。
+    fn apply_dry_run(&mut self, total: Option<usize>) {
+        let changes: Vec<LineChange> = std::mem::take(&mut self.dry_run.changes)
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _)| total.is_none() || self.overlay.is_checked(*index))
+            .map(|(_, change)| change)
+            .collect();
+        let applied = self.view.apply_changes(&changes);
+        self.prompt_type = PromptType::None;
+        let message = match total {
+            Some(total) => format!("{}: {applied} of {total} lines changed.", self.dry_run.name),
+            None => format!("{}: {applied} lines changed.", self.dry_run.name),
+        };
+        self.message_bar.update_message(&message);
+    }
+
+    /// 更新预览标题中的计数。
+    fn update_change_preview_title(&mut self) {
+        let title = format!(
+            "{}: apply {} of {} (Space toggle, Enter apply, Esc cancel)",
+            self.dry_run.name,
+            self.overlay.checked_count(),
+            self.overlay.len(),
+        );
+        self.overlay.set_title(&title);
+    }
+}
@@ -0,0 +1,79 @@
+use encoding_rs::{Encoding, UTF_8, UTF_16BE, UTF_16LE};
+use std::{
+    borrow::Cow,
+    fmt::{self, Debug, Display},
+};
+
+/// 文件的字符编码：加载时识别，保存时按同样的编码写回。
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct FileEncoding(&'static Encoding);
+
+impl Default for FileEncoding {
+    fn default() -> Self {
+        Self::UTF8
+    }
+}
+
+impl FileEncoding {
+    /// UTF-8，新建的文件和能按 UTF-8 解码的文件都使用它。
+    pub const UTF8: Self = Self(UTF_8);
+
+    /// 按名称查找编码，如 `gbk`、`latin1`、`utf-16le`，不区分大小写。
+    pub fn for_label(label: &str) -> Option<Self> {
+        Encoding::for_label(label.trim().as_bytes()).map(Self)
+    }
+
+    /// 根据开头的字节顺序标记识别 UTF-16；UTF-8 的字节顺序标记由 `TextFormat` 处理。
+    pub fn from_utf16_bom(bytes: &[u8]) -> Option<Self> {
+        match Encoding::for_bom(bytes) {
+            Some((encoding, _)) if encoding == UTF_16LE || encoding == UTF_16BE => {
+                Some(Self(encoding))
+            }
+            _ => None,
+        }
+    }
+
+    /// 是否是 UTF-8。
+    pub fn is_utf8(self) -> bool {
+        self.0 == UTF_8
+    }
+
+    /// 按这种编码解码，字节顺序标记保留为文本开头的 `U+FEFF`；有无法解码的字节时返回 `None`。
+    pub fn decode(self, bytes: &[u8]) -> Option<Cow<'_, str>> {
+        self.0.decode_without_bom_handling_and_without_replacement(bytes)
+    }
+
+    /// 按这种编码编码文本；第二项表示是否有无法用这种编码表示、被替换掉的字符。
+    pub fn encode(self, text: &str) -> (Cow<'_, [u8]>, bool) {
+        // encoding_rs 的 UTF-16 编码器输出的是 UTF-8，需要自己转换。
+        let utf16 = |to_bytes: fn(u16) -> [u8; 2]| {
+            text.encode_utf16().flat_map(to_bytes).collect::<Vec<u8>>()
+        };
+        if self.0 == UTF_16LE {
+            return (Cow::Owned(utf16(u16::to_le_bytes)), false);
+        }
+        if self.0 == UTF_16BE {
+            return (Cow::Owned(utf16(u16::to_be_bytes)), false);
+        }
+        let (bytes, _, lossy) = self.0.encode(text);
+        (bytes, lossy)
+    }
+
+    /// 文本中是否有无法用这种编码表示的字符。
+    pub fn is_lossy(self, text: &str) -> bool {
+        !self.is_utf8() && self.encode(text).1
+    }
+}
+
+/// 显示为小写的编码名称，如 `utf-8`、`gbk`、`utf-16le`。
+impl Display for FileEncoding {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{}", self.0.name().to_ascii_lowercase())
+    }
+}
+
+impl Debug for FileEncoding {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "FileEncoding({})", self.0.name())
+    }
+}
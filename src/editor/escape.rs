@@ -0,0 +1,112 @@
+use super::terminal::Terminal;
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use std::{io::Error, time::Duration};
+
+/// 处理单独的 Esc：在 `timeout` 内等待下一个按键，判断它是取消还是 Alt 组合键的开头。
+///
+/// 终端以 `Esc` 加字符表示 Alt 组合键，两部分在同一次读取中到达时 crossterm 会直接
+/// 解析为 Alt 组合键；经过 tmux 等中间层时它们可能被拆开，先收到一个单独的 Esc。
+/// 此时若超时之前到达一个普通字符，就把两者合并为 Alt 组合键；否则 Esc 是单独的
+/// 取消键，提前读到的下一个事件原样返回，由调用者接着处理。
+///
+/// 所有按键都在这里统一判定一次，二合字母、命令栏、覆盖层等等待后续按键的状态
+/// 看到的都是同一个结果，不必各自猜测。
+pub fn resolve(event: Event, timeout: Duration) -> Result<(Event, Option<Event>), Error> {
+    if !is_bare_escape(&event) || timeout.is_zero() || !Terminal::poll(timeout)? {
+        return Ok((event, None));
+    }
+    let next = Terminal::read()?;
+    match next {
+        Event::Key(KeyEvent {
+            code: code @ KeyCode::Char(_),
+            modifiers: modifiers @ (KeyModifiers::NONE | KeyModifiers::SHIFT),
+            kind: KeyEventKind::Press,
+            ..
+        }) => Ok((
+            Event::Key(KeyEvent::new(code, modifiers | KeyModifiers::ALT)),
+            None,
+        )),
+        _ => Ok((event, Some(next))),
+    }
+}
+
+/// 是否是没有修饰键的 Esc 按下事件。
+fn is_bare_escape(event: &Event) -> bool {
+    matches!(
+        event,
+        Event::Key(KeyEvent {
+            code: KeyCode::Esc,
+            modifiers: KeyModifiers::NONE,
+            kind: KeyEventKind::Press,
+            ..
+        })
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::terminal::headless;
+
+    const TIMEOUT: Duration = Duration::from_millis(50);
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> Event {
+        Event::Key(KeyEvent::new(code, modifiers))
+    }
+
+    fn escape() -> Event {
+        key(KeyCode::Esc, KeyModifiers::NONE)
+    }
+
+    /// 之后从终端读到的事件，读完为止。
+    fn remaining() -> Vec<Event> {
+        let mut events = Vec::new();
+        while let Ok(event) = Terminal::read() {
+            events.push(event);
+        }
+        events
+    }
+
+    #[test]
+    fn a_joined_alt_combination_passes_through() {
+        let alt_x = key(KeyCode::Char('x'), KeyModifiers::ALT);
+
+        assert_eq!(resolve(alt_x.clone(), TIMEOUT).unwrap(), (alt_x, None));
+    }
+
+    #[test]
+    fn an_escape_split_from_the_next_character_becomes_an_alt_combination() {
+        headless::send_events([key(KeyCode::Char('x'), KeyModifiers::NONE)]);
+
+        let resolved = resolve(escape(), TIMEOUT).unwrap();
+
+        assert_eq!(resolved, (key(KeyCode::Char('x'), KeyModifiers::ALT), None));
+        assert!(remaining().is_empty());
+    }
+
+    #[test]
+    fn an_escape_with_nothing_after_it_within_the_timeout_is_a_cancel() {
+        // 下一个字符在间隙之后才到达，即超时之后。
+        headless::send_events([]);
+        headless::send_events([key(KeyCode::Char('x'), KeyModifiers::NONE)]);
+
+        assert_eq!(resolve(escape(), TIMEOUT).unwrap(), (escape(), None));
+        assert_eq!(remaining(), vec![key(KeyCode::Char('x'), KeyModifiers::NONE)]);
+    }
+
+    #[test]
+    fn an_escape_followed_by_a_non_character_key_hands_that_key_back() {
+        let up = key(KeyCode::Up, KeyModifiers::NONE);
+        headless::send_events([up.clone()]);
+
+        assert_eq!(resolve(escape(), TIMEOUT).unwrap(), (escape(), Some(up)));
+    }
+
+    #[test]
+    fn a_zero_timeout_never_waits_for_a_follow_up() {
+        headless::send_events([key(KeyCode::Char('x'), KeyModifiers::NONE)]);
+
+        assert_eq!(resolve(escape(), Duration::ZERO).unwrap(), (escape(), None));
+        assert_eq!(remaining(), vec![key(KeyCode::Char('x'), KeyModifiers::NONE)]);
+    }
+}
@@ -1,20 +1,142 @@
+use super::{atomicwrite::Attributes, encoding::FileEncoding, filetype::FileType};
 use std::{
     fmt::{self, Display},
-    path::PathBuf,
+    fs,
+    io::{Error, ErrorKind},
+    path::{Path, PathBuf},
+    time::SystemTime,
 };
 
+/// 解析符号链接时最多跟随的层数，超过时视为链接形成了循环。
+const MAX_SYMLINK_DEPTH: usize = 40;
+
 #[derive(Debug, Clone, Default)]
 pub struct FileInfo {
-    pub path: Option<PathBuf>, // 文件路径
+    pub path: Option<PathBuf>,   // 文件路径，打开符号链接时是链接本身的路径。
+    pub target: Option<PathBuf>, // 路径是符号链接时，链接最终指向的文件。
+    pub file_type: FileType,     // 文件类型
+    pub modified: Option<(SystemTime, u64)>, // 上次读取或保存时磁盘上文件的修改时间和大小。
+    pub label: Option<&'static str>, // 没有路径时代替文件名显示的名称，如 `[stdin]`。
+    pub read_only: bool,         // 磁盘上的文件存在但当前用户没有写权限，除非用户坚持，否则拒绝修改。
+    pub encoding: FileEncoding,  // 文件的字符编码，保存时按同样的编码写回。
+    pub attributes: Option<Attributes>, // 上次读取或保存时文件的权限位、属主和扩展属性，文件在保存前被删除时用来恢复。
 }
 
 impl FileInfo {
     /// 构造方法
-    pub fn from(file_name: &str) -> Self {
+    pub fn from(path: &Path) -> Self {
         Self {
-            path: Some(PathBuf::from(file_name)),
+            path: Some(path.to_path_buf()),
+            target: None,
+            file_type: FileType::from(path),
+            modified: None,
+            label: None,
+            read_only: false,
+            encoding: FileEncoding::default(),
+            attributes: None,
+        }
+    }
+
+    /// 构造方法，路径是符号链接时沿着链接找到最终指向的文件。
+    ///
+    /// 链接形成循环或指向不存在的文件时返回说明具体原因的错误；路径本身不存在时
+    /// 原样返回 `NotFound`，由调用者决定是否把它当作新文件。
+    pub fn resolve(path: &Path) -> Result<Self, Error> {
+        let mut info = Self::from(path);
+        let mut current = path.to_path_buf();
+        for _ in 0..MAX_SYMLINK_DEPTH {
+            match fs::symlink_metadata(&current) {
+                Ok(metadata) if metadata.file_type().is_symlink() => {
+                    let link = fs::read_link(&current)?;
+                    // 相对的链接目标相对于链接所在的目录。
+                    current = match current.parent() {
+                        Some(dir) if link.is_relative() => dir.join(link),
+                        _ => link,
+                    };
+                }
+                Ok(_) => {
+                    if current != path {
+                        info.target = Some(current);
+                    }
+                    return Ok(info);
+                }
+                Err(err) if err.kind() == ErrorKind::NotFound && current != path => {
+                    return Err(Error::new(
+                        ErrorKind::NotFound,
+                        format!(
+                            "Dangling symbolic link: {} -> {} does not exist",
+                            path.display(),
+                            current.display()
+                        ),
+                    ));
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        Err(Error::other(format!(
+            "Symbolic link loop: {} (more than {MAX_SYMLINK_DEPTH} links)",
+            path.display()
+        )))
+    }
+
+    /// 保存时写入的文件：符号链接保存到它指向的文件，保留链接本身。
+    pub fn save_path(&self) -> Option<&Path> {
+        self.target.as_deref().or(self.path.as_deref())
+    }
+
+    /// 磁盘上文件当前的修改时间和大小，文件不存在或无法读取时返回 `None`。
+    ///
+    /// 修改时间精度较粗的文件系统上，同一时刻内的修改只能靠大小的变化发现。
+    pub fn disk_modified(&self) -> Option<(SystemTime, u64)> {
+        let metadata = fs::metadata(self.save_path()?).ok()?;
+        Some((metadata.modified().ok()?, metadata.len()))
+    }
+
+    /// 记下磁盘上文件当前的修改时间、大小和属性，在每次读取和保存之后调用；
+    /// 文件不存在时保留之前记下的属性。
+    pub fn record_disk_state(&mut self) {
+        self.modified = self.disk_modified();
+        if let Some(path) = self.save_path()
+            && let Ok(metadata) = fs::metadata(path)
+        {
+            self.attributes = Some(Attributes::read(path, &metadata));
         }
     }
+
+    /// 检查当前用户能否写入磁盘上的文件，在读取文件或修改路径之后调用；文件不存在时视为可以写入。
+    pub fn detect_read_only(&mut self) {
+        self.read_only = self
+            .save_path()
+            .is_some_and(|path| path.exists() && !is_writable(path));
+    }
+
+    /// 上次读取或保存之后，磁盘上的文件是否被其他程序修改过；文件已被删除时不算。
+    pub fn changed_on_disk(&self) -> bool {
+        self.modified
+            .zip(self.disk_modified())
+            .is_some_and(|(known, current)| known != current)
+    }
+
+    /// 文件是否通过符号链接打开。
+    pub const fn is_symlink(&self) -> bool {
+        self.target.is_some()
+    }
+}
+
+/// 当前用户能否写入文件；与只看权限位不同，同样考虑属主、所在的组和 root。
+#[cfg(unix)]
+fn is_writable(path: &Path) -> bool {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+    let Ok(path) = CString::new(path.as_os_str().as_bytes()) else {
+        return true;
+    };
+    // SAFETY: `path` 是以 NUL 结尾的有效字符串，`access` 只读取它。
+    unsafe { libc::access(path.as_ptr(), libc::W_OK) == 0 }
+}
+
+#[cfg(not(unix))]
+fn is_writable(path: &Path) -> bool {
+    fs::metadata(path).is_ok_and(|metadata| !metadata.permissions().readonly())
 }
 
 impl Display for FileInfo {
@@ -24,7 +146,71 @@ impl Display for FileInfo {
             .as_ref()
             .and_then(|p| p.file_name())
             .and_then(|n| n.to_str())
+            .or(self.label)
             .unwrap_or("[No Name]");
+        // 文件名中的控制字符（如换行）会破坏状态栏和终端标题，显示为 `?`。
+        let name: String = name
+            .chars()
+            .map(|ch| if ch.is_control() { '?' } else { ch })
+            .collect();
         write!(formatter, "{name}")
     }
 }
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+
+    #[test]
+    fn a_link_resolves_to_its_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("target.txt");
+        fs::write(&target, "").unwrap();
+        let link = dir.path().join("link.txt");
+        symlink(&target, &link).unwrap();
+
+        let info = FileInfo::resolve(&link).unwrap();
+
+        assert_eq!(info.path.as_deref(), Some(link.as_path()));
+        assert_eq!(info.save_path(), Some(target.as_path()));
+        assert!(info.is_symlink());
+    }
+
+    #[test]
+    fn a_dangling_link_names_the_missing_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let link = dir.path().join("link.txt");
+        symlink(dir.path().join("gone.txt"), &link).unwrap();
+
+        let err = FileInfo::resolve(&link).unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        assert!(err.to_string().starts_with("Dangling symbolic link:"), "{err}");
+        assert!(err.to_string().ends_with("gone.txt does not exist"), "{err}");
+    }
+
+    #[test]
+    fn a_link_loop_is_reported_instead_of_followed_forever() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("first");
+        let second = dir.path().join("second");
+        symlink(&second, &first).unwrap();
+        symlink(&first, &second).unwrap();
+
+        let err = FileInfo::resolve(&first).unwrap_err();
+
+        assert!(err.to_string().starts_with("Symbolic link loop:"), "{err}");
+    }
+
+    #[test]
+    fn a_missing_plain_path_is_not_mistaken_for_a_dangling_link() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("new.txt");
+
+        let err = FileInfo::resolve(&path).unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        assert!(!err.to_string().contains("symbolic link"), "{err}");
+    }
+}
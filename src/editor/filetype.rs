@@ -0,0 +1,112 @@
+use std::{
+    fmt::{self, Display},
+    path::Path,
+};
+
+/// 块注释的语法。
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub struct BlockComment {
+    pub open: &'static str,  // 块注释的起始标记。
+    pub close: &'static str, // 块注释的结束标记。
+    pub nestable: bool,      // 是否允许嵌套。
+}
+
+/// 文件类型，由扩展名决定。
+#[derive(Default, Clone, Copy, Eq, PartialEq, Debug)]
+pub enum FileType {
+    Rust,
+    C,
+    JavaScript,
+    Css,
+    Html,
+    Markdown,
+    Python,
+    Shell,
+    Toml,
+    #[default]
+    Text,
+}
+
+impl FileType {
+    /// 根据文件扩展名推断文件类型。
+    pub fn from(path: &Path) -> Self {
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("rs") => Self::Rust,
+            Some("c" | "h" | "cc" | "cpp" | "hpp" | "java" | "go" | "cs" | "kt" | "swift") => {
+                Self::C
+            }
+            Some("js" | "jsx" | "ts" | "tsx" | "mjs") => Self::JavaScript,
+            Some("css" | "scss") => Self::Css,
+            Some("html" | "htm" | "xml" | "svg") => Self::Html,
+            Some("md" | "markdown") => Self::Markdown,
+            Some("py") => Self::Python,
+            Some("sh" | "bash" | "zsh") => Self::Shell,
+            Some("toml") => Self::Toml,
+            _ => Self::Text,
+        }
+    }
+
+    /// 获取该文件类型的块注释语法。
+    pub const fn block_comment(self) -> Option<BlockComment> {
+        match self {
+            Self::Rust => Some(BlockComment {
+                open: "/*",
+                close: "*/",
+                nestable: true,
+            }),
+            Self::C | Self::JavaScript | Self::Css => Some(BlockComment {
+                open: "/*",
+                close: "*/",
+                nestable: false,
+            }),
+            Self::Html | Self::Markdown => Some(BlockComment {
+                open: "<!--",
+                close: "-->",
+                nestable: false,
+            }),
+            Self::Python | Self::Shell | Self::Toml | Self::Text => None,
+        }
+    }
+
+    /// 获取该文件类型的行注释标记。
+    pub const fn line_comment(self) -> Option<&'static str> {
+        match self {
+            Self::Rust | Self::C | Self::JavaScript => Some("//"),
+            Self::Python | Self::Shell | Self::Toml => Some("#"),
+            Self::Css | Self::Html | Self::Markdown | Self::Text => None,
+        }
+    }
+
+    /// 获取该文件类型中字符串的引号。
+    pub const fn quotes(self) -> &'static [char] {
+        match self {
+            // Rust 中的 `'` 也用于生命周期，不作为字符串处理。
+            Self::Rust | Self::Html => &['"'],
+            Self::JavaScript => &['"', '\'', '`'],
+            Self::C | Self::Css | Self::Python | Self::Shell | Self::Toml => &['"', '\''],
+            Self::Markdown | Self::Text => &[],
+        }
+    }
+}
+
+impl Display for FileType {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Rust => "Rust",
+            Self::C => "C",
+            Self::JavaScript => "JavaScript",
+            Self::Css => "CSS",
+            Self::Html => "HTML",
+            Self::Markdown => "Markdown",
+            Self::Python => "Python",
+            Self::Shell => "Shell",
+            Self::Toml => "TOML",
+            Self::Text => "Text",
+        };
+        write!(formatter, "{name}")
+    }
+}
@@ -0,0 +1,532 @@
+use super::{
+    Editor,
+    command::{Command, Edit, Move, System},
+    paths,
+};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use std::{
+    collections::{HashMap, HashSet},
+    env, fs,
+    io::ErrorKind,
+    path::PathBuf,
+};
+
+/// 指定按键绑定文件路径的环境变量。
+const CONFIG_ENV: &str = "SNOW_EDIT_CONFIG";
+
+/// 配置目录中按键绑定文件的文件名。
+const KEYS_FILE: &str = "keys.toml";
+
+/// 按键绑定文件中可以使用的动作名称。
+const ACTIONS: [(&str, Command); 57] = [
+    ("save", Command::System(System::Save)),
+    ("save-as", Command::System(System::SaveAs)),
+    ("quit", Command::System(System::Quit)),
+    ("dismiss", Command::System(System::Dismiss)),
+    ("palette", Command::System(System::Palette)),
+    ("digraph", Command::System(System::Digraph)),
+    ("calculate", Command::System(System::Calculate)),
+    ("toggle-line-mark", Command::System(System::ToggleLineMark)),
+    ("search", Command::System(System::Search)),
+    ("find-next", Command::System(System::FindNext)),
+    ("find-previous", Command::System(System::FindPrevious)),
+    ("replace", Command::System(System::Replace)),
+    ("toggle-line-numbers", Command::System(System::ToggleLineNumbers)),
+    ("copy-line", Command::System(System::CopyLine)),
+    ("toggle-selection", Command::System(System::ToggleSelection)),
+    ("swap-position", Command::System(System::SwapPosition)),
+    ("goto-line", Command::System(System::GotoLine)),
+    ("toggle-soft-wrap", Command::System(System::ToggleSoftWrap)),
+    ("open-file", Command::System(System::OpenFile)),
+    ("next-buffer", Command::System(System::NextBuffer)),
+    ("reload", Command::System(System::Reload)),
+    ("word-count", Command::System(System::WordCount)),
+    ("toggle-read-only", Command::System(System::ToggleReadOnly)),
+    ("newline", Command::Edit(Edit::InsertNewline)),
+    ("tab", Command::Edit(Edit::InsertTab)),
+    ("dedent", Command::Edit(Edit::Dedent)),
+    ("delete", Command::Edit(Edit::Delete)),
+    ("delete-backward", Command::Edit(Edit::DeleteBackward)),
+    ("toggle-block-comment", Command::Edit(Edit::ToggleBlockComment)),
+    ("toggle-line-comment", Command::Edit(Edit::ToggleLineComment)),
+    ("convert-indentation", Command::Edit(Edit::ConvertIndentation)),
+    ("trim-trailing-whitespace", Command::Edit(Edit::TrimTrailingWhitespace)),
+    ("paste", Command::Edit(Edit::Paste)),
+    ("paste-aligned", Command::Edit(Edit::PasteAligned)),
+    ("cut-line", Command::Edit(Edit::CutLine)),
+    ("delete-line", Command::Edit(Edit::DeleteLine)),
+    ("duplicate-line", Command::Edit(Edit::DuplicateLine)),
+    ("move-line-up", Command::Edit(Edit::MoveLineUp)),
+    ("move-line-down", Command::Edit(Edit::MoveLineDown)),
+    ("paste-line-above", Command::Edit(Edit::PasteLineAbove)),
+    ("undo", Command::Edit(Edit::Undo)),
+    ("redo", Command::Edit(Edit::Redo)),
+    ("up", Command::Move(Move::Up, false)),
+    ("down", Command::Move(Move::Down, false)),
+    ("left", Command::Move(Move::Left, false)),
+    ("right", Command::Move(Move::Right, false)),
+    ("word-left", Command::Move(Move::WordLeft, false)),
+    ("word-right", Command::Move(Move::WordRight, false)),
+    ("page-up", Command::Move(Move::PageUp, false)),
+    ("page-down", Command::Move(Move::PageDown, false)),
+    ("start-of-line", Command::Move(Move::StartOfLine, false)),
+    ("end-of-line", Command::Move(Move::EndOfLine, false)),
+    ("home", Command::Move(Move::StartOfLine, false)),
+    ("end", Command::Move(Move::EndOfLine, false)),
+    ("document-start", Command::Move(Move::DocumentStart, false)),
+    ("document-end", Command::Move(Move::DocumentEnd, false)),
+    ("matching-bracket", Command::Move(Move::MatchingBracket, false)),
+];
+
+/// 按键绑定文件中可以使用的非字符按键名称。
+const KEY_NAMES: [(&str, KeyCode); 17] = [
+    ("up", KeyCode::Up),
+    ("down", KeyCode::Down),
+    ("left", KeyCode::Left),
+    ("right", KeyCode::Right),
+    ("home", KeyCode::Home),
+    ("end", KeyCode::End),
+    ("pageup", KeyCode::PageUp),
+    ("pagedown", KeyCode::PageDown),
+    ("tab", KeyCode::Tab),
+    ("backtab", KeyCode::BackTab),
+    ("enter", KeyCode::Enter),
+    ("backspace", KeyCode::Backspace),
+    ("delete", KeyCode::Delete),
+    ("insert", KeyCode::Insert),
+    ("esc", KeyCode::Esc),
+    ("space", KeyCode::Char(' ')),
+    ("minus", KeyCode::Char('-')),
+];
+
+/// 检查时尝试的修饰键组合。
+const MODIFIERS: [KeyModifiers; 6] = [
+    KeyModifiers::NONE,
+    KeyModifiers::SHIFT,
+    KeyModifiers::CONTROL,
+    KeyModifiers::ALT,
+    KeyModifiers::CONTROL.union(KeyModifiers::SHIFT),
+    KeyModifiers::CONTROL.union(KeyModifiers::ALT),
+];
+
+/// 检查时尝试的非字符按键。
+const SPECIAL_KEYS: [KeyCode; 15] = [
+    KeyCode::Up,
+    KeyCode::Down,
+    KeyCode::Left,
+    KeyCode::Right,
+    KeyCode::Home,
+    KeyCode::End,
+    KeyCode::PageUp,
+    KeyCode::PageDown,
+    KeyCode::Tab,
+    KeyCode::BackTab,
+    KeyCode::Enter,
+    KeyCode::Backspace,
+    KeyCode::Delete,
+    KeyCode::Insert,
+    KeyCode::Esc,
+];
+
+/// 检查按键绑定的结果。
+pub struct Report {
+    pub bound: usize,           // 有绑定的按键数。
+    pub conflicts: Vec<String>, // 同一个按键有多个绑定、只有一个生效的描述。
+    pub problems: Vec<String>,  // 按键绑定文件中无法使用的条目，以及让某个命令无法再用按键触发的条目。
+    pub unbound: Vec<String>,   // 没有任何按键可以触发的动作。
+}
+
+/// 检查内置的各层绑定和按键绑定文件中的自定义绑定。
+///
+/// 按键先查自定义绑定，再依次交给编辑、移动和系统三层解析，先接受的一层生效，
+/// 因此同一个按键在多层中都有绑定时，后面的绑定永远不会被触发。自定义绑定覆盖内置绑定时，
+/// 被覆盖的命令没有别的按键可用的话记为问题，报告中带有按键绑定文件的行号。
+pub fn check(key_map: &KeyMap) -> Report {
+    let mut bound = 0_usize;
+    let mut conflicts = Vec::new();
+    let mut reachable = HashSet::new();
+    let custom_keys = key_map
+        .bindings
+        .keys()
+        .map(|(code, modifiers)| KeyEvent::new(*code, *modifiers));
+    let mut keys = candidate_keys();
+    keys.extend(custom_keys.filter(|event| !keys.contains(event)).collect::<Vec<_>>());
+    for event in keys {
+        let layers: Vec<(&str, String)> = [
+            Edit::try_from(event).map(|command| ("edit", format!("{command:?}"))),
+            Move::try_from(event).map(|command| ("move", format!("{command:?}"))),
+            System::try_from(event).map(|command| ("system", format!("{command:?}"))),
+        ]
+        .into_iter()
+        .filter_map(Result::ok)
+        .collect();
+        let effective = key_map
+            .get(event)
+            .map(command_name)
+            .or_else(|| layers.first().map(|(_, command)| command.clone()));
+        let Some(effective) = effective else {
+            continue;
+        };
+        bound = bound.saturating_add(1);
+        reachable.insert(effective);
+        let Some(((winner_layer, winner), shadowed)) = layers.split_first() else {
+            continue;
+        };
+        for (layer, command) in shadowed {
+            conflicts.push(format!(
+                "{}: {command} ({layer}) is shadowed by {winner} ({winner_layer})",
+                describe(event)
+            ));
+        }
+    }
+
+    let mut problems = key_map.problems.clone();
+    let mut custom: Vec<_> = key_map.bindings.iter().collect();
+    custom.sort_by_key(|(_, (_, line))| *line);
+    for ((code, modifiers), (command, line)) in custom {
+        let event = KeyEvent::new(*code, *modifiers);
+        let Ok(built_in) = Command::try_from(Event::Key(event)).map(command_name) else {
+            continue;
+        };
+        let command = command_name(*command);
+        if built_in == command {
+            continue;
+        }
+        let key = describe(event);
+        if reachable.contains(&built_in) {
+            conflicts.push(format!("line {line}: {key} = {command} shadows built-in {built_in}"));
+        } else {
+            problems.push(format!(
+                "line {line}: {key} = {command} leaves {built_in} without a key"
+            ));
+        }
+    }
+
+    let unbound = ACTIONS
+        .iter()
+        .filter(|(_, command)| !reachable.contains(&command_name(*command)))
+        .map(|(name, _)| (*name).to_string())
+        .collect();
+    Report {
+        bound,
+        conflicts,
+        problems,
+        unbound,
+    }
+}
+
+/// 命令的名称，用于比较两个绑定是否触发同一个命令；移动命令不区分是否扩展选区。
+fn command_name(command: Command) -> String {
+    match command {
+        Command::Move(move_command, _) => format!("{move_command:?}"),
+        Command::Edit(edit) => format!("{edit:?}"),
+        Command::System(system) => format!("{system:?}"),
+    }
+}
+
+/// 所有要检查的按键：可打印的 ASCII 字符和常用的功能键，搭配各种修饰键。
+fn candidate_keys() -> Vec<KeyEvent> {
+    let codes: Vec<KeyCode> = (' '..='~')
+        .map(KeyCode::Char)
+        .chain((1..=12).map(KeyCode::F))
+        .chain(SPECIAL_KEYS)
+        .collect();
+    MODIFIERS
+        .iter()
+        .flat_map(|modifiers| {
+            codes
+                .iter()
+                .map(move |code| KeyEvent::new(*code, *modifiers))
+        })
+        .collect()
+}
+
+/// 按键的可读名称，如 `Ctrl+K`、`Alt+=`、`Left`。
+fn describe(event: KeyEvent) -> String {
+    let mut name = String::new();
+    for (modifier, label) in [
+        (KeyModifiers::CONTROL, "Ctrl+"),
+        (KeyModifiers::ALT, "Alt+"),
+        (KeyModifiers::SHIFT, "Shift+"),
+    ] {
+        if event.modifiers.contains(modifier) {
+            name.push_str(label);
+        }
+    }
+    let key = match event.code {
+        KeyCode::Char(' ') => String::from("Space"),
+        KeyCode::Char(ch) => ch.to_ascii_uppercase().to_string(),
+        KeyCode::F(number) => format!("F{number}"),
+        code => format!("{code:?}"),
+    };
+    name + &key
+}
+
+/// 用户在按键绑定文件中定义的绑定，优先于内置的绑定。
+#[derive(Default)]
+pub struct KeyMap {
+    bindings: HashMap<(KeyCode, KeyModifiers), (Command, usize)>, // 按键（字母统一为小写）到命令及其所在行号的映射。
+    problems: Vec<String>, // 被忽略的条目的说明，带有行号。
+}
+
+impl KeyMap {
+    /// 读取按键绑定文件：路径取自 `$SNOW_EDIT_CONFIG`，未设置时为配置目录下的 `keys.toml`。
+    ///
+    /// 文件不存在时没有自定义绑定；第二项是 `check` 发现的问题，各自带有文件名和行号。
+    pub fn load() -> (Self, Vec<String>) {
+        let Some(path) = env::var_os(CONFIG_ENV)
+            .map(PathBuf::from)
+            .or_else(|| paths::config_dir().map(|dir| dir.join(KEYS_FILE)))
+        else {
+            return (Self::default(), Vec::new());
+        };
+        match fs::read_to_string(&path) {
+            Ok(text) => {
+                let map = Self::parse(&text);
+                let problems = check(&map)
+                    .problems
+                    .into_iter()
+                    .map(|problem| format!("{}: {problem}", path.display()))
+                    .collect();
+                (map, problems)
+            }
+            Err(err) if err.kind() == ErrorKind::NotFound => (Self::default(), Vec::new()),
+            Err(err) => (Self::default(), vec![format!("{}: {err}", path.display())]),
+        }
+    }
+
+    /// 解析按键绑定，每行形如 `"ctrl-s" = "save"`；`#` 之后是注释，`[keys]` 这样的表头被忽略。
+    ///
+    /// 无法识别的条目被忽略；同一个按键绑定了多次时最后一次生效。这两种情况都记为问题。
+    pub fn parse(text: &str) -> Self {
+        let mut map = Self::default();
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index.saturating_add(1);
+            let line = strip_comment(line).trim();
+            if line.is_empty() || line.starts_with('[') {
+                continue;
+            }
+            let entry = split_entry(line).and_then(|(chord, action)| {
+                Ok((parse_chord(chord)?, parse_action(action)?))
+            });
+            match entry {
+                Ok((key, command)) => {
+                    if let Some((_, previous)) = map.bindings.insert(key, (command, line_number)) {
+                        map.problems.push(format!(
+                            "line {previous}: {} is bound again on line {line_number}, ignored",
+                            describe(KeyEvent::new(key.0, key.1))
+                        ));
+                    }
+                }
+                Err(err) => map.problems.push(format!("line {line_number}: {err}")),
+            }
+        }
+        map
+    }
+
+    /// 按键对应的自定义命令；绑定到移动命令时按住 Shift 同样扩展选区。
+    pub fn get(&self, event: KeyEvent) -> Option<Command> {
+        let (code, modifiers) = normalize(event.code, event.modifiers);
+        if let Some((command, _)) = self.bindings.get(&(code, modifiers)) {
+            return Some(*command);
+        }
+        let unshifted = (code, modifiers.difference(KeyModifiers::SHIFT));
+        match self.bindings.get(&unshifted) {
+            Some((Command::Move(move_command, _), _)) if modifiers.contains(KeyModifiers::SHIFT) => {
+                Some(Command::Move(*move_command, true))
+            }
+            _ => None,
+        }
+    }
+
+    /// 自定义绑定的数量。
+    pub fn len(&self) -> usize {
+        self.bindings.len()
+    }
+}
+
+/// 去掉行中不在引号内的 `#` 及其之后的注释。
+pub fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (index, ch) in line.char_indices() {
+        match ch {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return line.get(..index).unwrap_or(line),
+            _ => {}
+        }
+    }
+    line
+}
+
+/// 把 `"ctrl-s" = "save"` 拆成按键和动作名称；按键可以不加引号。
+fn split_entry(line: &str) -> Result<(&str, &str), String> {
+    let (chord, rest) = if let Some(rest) = line.strip_prefix('"') {
+        let end = rest.find('"').ok_or("unterminated quoted key")?;
+        (
+            rest.get(..end).unwrap_or_default(),
+            rest.get(end.saturating_add(1)..).unwrap_or_default(),
+        )
+    } else {
+        let end = line.find('=').unwrap_or(line.len());
+        (
+            line.get(..end).unwrap_or_default().trim(),
+            line.get(end..).unwrap_or_default(),
+        )
+    };
+    let action = rest
+        .trim_start()
+        .strip_prefix('=')
+        .ok_or("expected `key = \"action\"`")?
+        .trim();
+    let action = action
+        .strip_prefix('"')
+        .and_then(|action| action.strip_suffix('"'))
+        .ok_or("the action must be a quoted string")?;
+    Ok((chord, action))
+}
+
+/// 解析按键描述，如 `ctrl-s`、`alt-x`、`ctrl-shift-k`、`f5`、`shift-f3`、`pageup`。
+fn parse_chord(chord: &str) -> Result<(KeyCode, KeyModifiers), String> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut rest = chord.trim();
+    'prefixes: loop {
+        for (prefix, modifier) in [
+            ("ctrl", KeyModifiers::CONTROL),
+            ("alt", KeyModifiers::ALT),
+            ("shift", KeyModifiers::SHIFT),
+        ] {
+            let stripped = rest
+                .get(..prefix.len())
+                .filter(|head| head.eq_ignore_ascii_case(prefix))
+                .and_then(|_| rest.get(prefix.len()..))
+                .and_then(|tail| tail.strip_prefix(['-', '+']))
+                .filter(|tail| !tail.is_empty());
+            if let Some(tail) = stripped {
+                modifiers |= modifier;
+                rest = tail;
+                continue 'prefixes;
+            }
+        }
+        break;
+    }
+    let mut chars = rest.chars();
+    let code = if let (Some(ch), None) = (chars.next(), chars.next()) {
+        KeyCode::Char(ch)
+    } else {
+        let name = rest.to_ascii_lowercase();
+        KEY_NAMES
+            .iter()
+            .find(|(key_name, _)| *key_name == name)
+            .map(|(_, code)| *code)
+            .or_else(|| {
+                name.strip_prefix('f')
+                    .and_then(|number| number.parse::<u8>().ok())
+                    .filter(|number| (1..=24).contains(number))
+                    .map(KeyCode::F)
+            })
+            .ok_or_else(|| format!("unknown key \"{chord}\""))?
+    };
+    Ok(normalize(code, modifiers))
+}
+
+/// 动作名称对应的命令；名称不区分大小写，`_` 和空格等同于 `-`。
+fn parse_action(action: &str) -> Result<Command, String> {
+    let name = action.trim().to_ascii_lowercase().replace(['_', ' '], "-");
+    ACTIONS
+        .iter()
+        .find(|(action_name, _)| *action_name == name)
+        .map(|(_, command)| *command)
+        .ok_or_else(|| format!("unknown action \"{action}\""))
+}
+
+/// 统一按键的写法：大写字母视为小写字母加 Shift。
+fn normalize(code: KeyCode, modifiers: KeyModifiers) -> (KeyCode, KeyModifiers) {
+    match code {
+        KeyCode::Char(ch) if ch.is_ascii_uppercase() => (
+            KeyCode::Char(ch.to_ascii_lowercase()),
+            modifiers | KeyModifiers::SHIFT,
+        ),
+        code => (code, modifiers),
+    }
+}
+
+impl Editor {
+    /// 检查按键绑定的冲突，在消息栏中报告结果。
+    pub(super) fn check_keybindings(&mut self) {
+        let Report {
+            bound,
+            conflicts,
+            problems,
+            unbound,
+        } = check(&self.key_map);
+        let custom = self.key_map.len();
+        let mut parts = Vec::new();
+        if !problems.is_empty() {
+            parts.push(format!("{} problem(s) in keys.toml: {}", problems.len(), problems.join("; ")));
+        }
+        if !conflicts.is_empty() {
+            parts.push(format!("{} keybinding conflict(s): {}", conflicts.len(), conflicts.join("; ")));
+        }
+        if !unbound.is_empty() {
+            parts.push(format!("{} action(s) without a key: {}", unbound.len(), unbound.join(", ")));
+        }
+        let message = if parts.is_empty() {
+            format!("Keybindings OK: {bound} keys bound, {custom} custom, no conflicts.")
+        } else {
+            format!("{bound} keys bound, {custom} custom — {}", parts.join(" — "))
+        };
+        self.message_bar.update_message(&message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_bindings_have_no_conflicts_and_report_unbound_actions() {
+        let report = check(&KeyMap::default());
+        assert!(report.bound > 0);
+        assert!(report.conflicts.is_empty(), "{:?}", report.conflicts);
+        assert!(report.problems.is_empty());
+        assert!(report.unbound.contains(&"trim-trailing-whitespace".to_string()));
+
+        let map = KeyMap::parse("\"f6\" = \"trim-trailing-whitespace\"");
+        assert!(check(&map).unbound.is_empty(), "{:?}", check(&map).unbound);
+    }
+
+    #[test]
+    fn unusable_and_repeated_entries_are_reported_with_their_line() {
+        let map = KeyMap::parse(
+            "[keys]\n\"f5\" = \"reload\"\n\"ctrl-q\" = \"explode\"\n\"hyper-x\" = \"save\"\n\"F5\" = \"word-count\"\n",
+        );
+        let problems = check(&map).problems;
+        assert_eq!(
+            problems,
+            [
+                "line 3: unknown action \"explode\"",
+                "line 4: unknown key \"hyper-x\"",
+                "line 2: F5 is bound again on line 5, ignored",
+            ]
+        );
+        assert_eq!(map.len(), 1);
+        assert!(matches!(
+            map.get(KeyEvent::new(KeyCode::F(5), KeyModifiers::NONE)),
+            Some(Command::System(System::WordCount))
+        ));
+    }
+
+    #[test]
+    fn custom_bindings_that_hide_a_built_in_command_are_reported() {
+        // Ctrl+S 是保存唯一的按键，改绑之后保存只能从面板中执行。
+        let report = check(&KeyMap::parse("\"ctrl-s\" = \"quit\""));
+        assert_eq!(report.problems, ["line 1: Ctrl+S = Quit leaves Save without a key"]);
+        assert!(report.unbound.contains(&"save".to_string()));
+
+        // 保存还有别的按键时只是一个冲突。
+        let report = check(&KeyMap::parse("\"f2\" = \"save\"\n\"ctrl-s\" = \"quit\""));
+        assert!(report.problems.is_empty(), "{:?}", report.problems);
+        assert_eq!(report.conflicts, ["line 2: Ctrl+S = Quit shadows built-in Save"]);
+        assert!(!report.unbound.contains(&"save".to_string()));
+    }
+}
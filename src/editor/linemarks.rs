@@ -0,0 +1,101 @@
+use super::{
+    Editor, PromptType,
+    command::{
+        Command::{self, Edit, Move, System},
+        Edit::{Delete, DeleteBackward, InsertNewline},
+        System::Dismiss,
+    },
+    overlay::{Overlay, sanitize},
+    terminal::StyledText,
+    uicomponent::UIComponent,
+};
+
+impl Editor {
+    /// 切换当前行的持久高亮。
+    pub(super) fn toggle_line_mark(&mut self) {
+        let line_number = self.view.text_location().line_index.saturating_add(1);
+        let message = if self.view.toggle_line_mark() {
+            format!("Line {line_number} highlighted.")
+        } else {
+            format!("Highlight removed from line {line_number}.")
+        };
+        self.message_bar.update_message(&message);
+    }
+
+    /// 在列表中显示带有持久高亮的行。
+    pub(super) fn list_line_marks(&mut self) {
+        if self.view.marked_lines().is_empty() {
+            self.message_bar.update_message("No highlighted lines.");
+            return;
+        }
+        self.open_line_mark_list(0);
+        self.prompt_type = PromptType::LineMarkList;
+    }
+
+    /// 清除所有持久高亮。
+    pub(super) fn clear_line_marks(&mut self) {
+        let count = self.view.marked_lines().len();
+        self.view.clear_line_marks();
+        self.message_bar
+            .update_message(&format!("Cleared {count} highlighted line(s)."));
+    }
+
+    /// 处理高亮行列表中的按键：回车跳转到选中的行，Delete 清除选中行的高亮，Esc 关闭。
+    pub(super) fn process_command_during_line_mark_list(&mut self, command: Command) {
+        match command {
+            Move(move_command, _) => self.overlay.handle_move_command(move_command),
+            Edit(InsertNewline) => {
+                let selected = self.view.marked_lines().get(self.overlay.selected()).copied();
+                self.close_line_mark_list();
+                if let Some(line_index) = selected {
+                    self.view.goto_line(line_index);
+                }
+            }
+            Edit(Delete | DeleteBackward) => {
+                let selected = self.overlay.selected();
+                if let Some(line_index) = self.view.marked_lines().get(selected).copied() {
+                    self.view.remove_line_mark(line_index);
+                }
+                if self.view.marked_lines().is_empty() {
+                    self.close_line_mark_list();
+                    self.message_bar.update_message("No highlighted lines.");
+                } else {
+                    self.open_line_mark_list(selected);
+                }
+            }
+            System(Dismiss) => self.close_line_mark_list(),
+            _ => {}
+        }
+    }
+
+    /// 为可见区域内尚未生成内容的列表项生成内容。
+    pub(super) fn fill_line_mark_list(&mut self) {
+        let lines = self.view.marked_lines();
+        for index in self.overlay.missing_rows() {
+            if let Some(line_index) = lines.get(index) {
+                let text = sanitize(&self.view.line_text(*line_index));
+                let row = vec![StyledText {
+                    text: format!("{:>6}  {text}", line_index.saturating_add(1)),
+                    style: None,
+                }];
+                self.overlay.set_row(index, row);
+            }
+        }
+    }
+
+    /// 按当前的高亮行重新生成列表，并选中第 `selected` 项。
+    fn open_line_mark_list(&mut self, selected: usize) {
+        let count = self.view.marked_lines().len();
+        let title = format!(
+            "Highlighted lines: {count} (Enter jump, Delete clear, Esc close)"
+        );
+        self.overlay = Overlay::new(&title, count, false, self.view_size());
+        self.overlay.select(selected);
+    }
+
+    /// 关闭高亮行列表，恢复显示视图。
+    fn close_line_mark_list(&mut self) {
+        self.prompt_type = PromptType::None;
+        self.view.set_needs_redraw(true);
+    }
+}
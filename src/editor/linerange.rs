@@ -0,0 +1,162 @@
+/// 行范围命令要执行的操作。
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum RangeOperation {
+    Delete, // `d`：删除这些行。
+    Indent, // `>`：增加一级缩进。
+    Dedent, // `<`：减少一级缩进。
+    Yank,   // `y`：复制这些行。
+}
+
+/// 一个闭区间的行范围，行号从 0 开始。
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub struct LineRange {
+    pub start: usize, // 起始行。
+    pub end: usize,   // 结束行（包含）。
+}
+
+impl LineRange {
+    /// 范围内的行数。
+    pub const fn len(self) -> usize {
+        self.end.saturating_sub(self.start).saturating_add(1)
+    }
+}
+
+/// 解析形如 `:10,20d`、`:.,+3>`、`:,$y` 的行范围命令。
+///
+/// 地址支持行号（从 1 开始）、`.`（当前行）、`$`（最后一行）以及 `+n`/`-n` 偏移，
+/// 省略的地址表示当前行。
+pub fn parse(
+    input: &str,
+    current_line: usize,
+    line_count: usize,
+) -> Result<(LineRange, RangeOperation), String> {
+    let input = input.trim();
+    let input = input.strip_prefix(':').unwrap_or(input);
+    let Some(op_char) = input.chars().last() else {
+        return Err(String::from("Missing operation (d, >, <, y)"));
+    };
+    let operation = match op_char {
+        'd' => RangeOperation::Delete,
+        '>' => RangeOperation::Indent,
+        '<' => RangeOperation::Dedent,
+        'y' => RangeOperation::Yank,
+        _ => return Err(format!("Unknown operation: {op_char}")),
+    };
+    let addresses = input
+        .get(..input.len().saturating_sub(op_char.len_utf8()))
+        .unwrap_or_default();
+
+    // 地址按 1 开始的行号计算，`current` 和 `last` 也转换为 1 开始。
+    let current = current_line.saturating_add(1);
+    let last = line_count;
+    let (start, end) = if let Some((start, end)) = addresses.split_once(',') {
+        (
+            parse_address(start, current, last)?,
+            parse_address(end, current, last)?,
+        )
+    } else {
+        let line = parse_address(addresses, current, last)?;
+        (line, line)
+    };
+
+    if start == 0 || end == 0 || start > last || end > last {
+        return Err(format!("Invalid range: lines must be between 1 and {last}"));
+    }
+    if start > end {
+        return Err(String::from("Backwards range"));
+    }
+    Ok((
+        LineRange {
+            start: start.saturating_sub(1),
+            end: end.saturating_sub(1),
+        },
+        operation,
+    ))
+}
+
+/// 解析单个地址，返回从 1 开始的行号。
+fn parse_address(address: &str, current: usize, last: usize) -> Result<usize, String> {
+    let address = address.trim();
+    let invalid = || format!("Invalid address: {address}");
+
+    let base_len = address.find(['+', '-']).unwrap_or(address.len());
+    let (base, mut offsets) = address.split_at(base_len);
+    let mut line = match base {
+        "" | "." => current,
+        "$" => last,
+        number => number.parse::<usize>().map_err(|_| invalid())?,
+    };
+
+    while let Some(sign) = offsets.chars().next() {
+        if sign != '+' && sign != '-' {
+            return Err(invalid());
+        }
+        let rest = offsets.get(1..).unwrap_or_default();
+        let digits_len = rest
+            .find(|ch: char| !ch.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let (digits, remaining) = rest.split_at(digits_len);
+        // 省略数字时偏移量为 1，与 vim 一致。
+        let amount = if digits.is_empty() {
+            1
+        } else {
+            digits.parse::<usize>().map_err(|_| invalid())?
+        };
+        line = if sign == '+' {
+            line.saturating_add(amount)
+        } else {
+            line.checked_sub(amount).ok_or_else(invalid)?
+        };
+        offsets = remaining;
+    }
+    Ok(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(start: usize, end: usize) -> LineRange {
+        LineRange { start, end }
+    }
+
+    #[test]
+    fn each_operation_is_read_from_the_last_character() {
+        assert_eq!(parse(":2,3d", 0, 10), Ok((range(1, 2), RangeOperation::Delete)));
+        assert_eq!(parse("2,3>", 0, 10), Ok((range(1, 2), RangeOperation::Indent)));
+        assert_eq!(parse("2,3<", 0, 10), Ok((range(1, 2), RangeOperation::Dedent)));
+        assert_eq!(parse("2,3y", 0, 10), Ok((range(1, 2), RangeOperation::Yank)));
+        assert_eq!(parse(":", 0, 10), Err(String::from("Missing operation (d, >, <, y)")));
+        assert_eq!(parse("2,3x", 0, 10), Err(String::from("Unknown operation: x")));
+    }
+
+    #[test]
+    fn dot_dollar_and_omitted_addresses() {
+        assert_eq!(parse(".d", 4, 10), Ok((range(4, 4), RangeOperation::Delete)));
+        assert_eq!(parse("d", 4, 10), Ok((range(4, 4), RangeOperation::Delete)));
+        assert_eq!(parse("$y", 0, 10), Ok((range(9, 9), RangeOperation::Yank)));
+        assert_eq!(parse(":,$>", 2, 10), Ok((range(2, 9), RangeOperation::Indent)));
+        assert_eq!(parse(" 1 , . < ", 2, 10), Ok((range(0, 2), RangeOperation::Dedent)));
+    }
+
+    #[test]
+    fn offsets_move_from_their_base_address() {
+        assert_eq!(parse(".,+3d", 2, 10), Ok((range(2, 5), RangeOperation::Delete)));
+        assert_eq!(parse("$-2,$y", 0, 10), Ok((range(7, 9), RangeOperation::Yank)));
+        assert_eq!(parse(".-1,.+1>", 4, 10), Ok((range(3, 5), RangeOperation::Indent)));
+        // 省略数字时偏移 1，多个偏移依次累加。
+        assert_eq!(parse("+,++d", 0, 10), Ok((range(1, 2), RangeOperation::Delete)));
+        assert_eq!(parse("5+2-1y", 0, 10), Ok((range(5, 5), RangeOperation::Yank)));
+    }
+
+    #[test]
+    fn reversed_and_out_of_range_lines_are_rejected() {
+        assert_eq!(parse("5,3d", 0, 10), Err(String::from("Backwards range")));
+        let outside = Err(String::from("Invalid range: lines must be between 1 and 10"));
+        assert_eq!(parse("0d", 0, 10), outside);
+        assert_eq!(parse("3,11d", 0, 10), outside);
+        assert_eq!(parse("$+1y", 0, 10), outside);
+        assert_eq!(parse(".-5d", 1, 10), Err(String::from("Invalid address: .-5")));
+        assert_eq!(parse("a,3d", 0, 10), Err(String::from("Invalid address: a")));
+    }
+}
@@ -1,5 +1,7 @@
+use super::overlay::sanitize;
 use super::terminal::Size;
 use super::terminal::Terminal;
+use super::truncate::{EllipsisSide, truncate_to_width};
 use super::uicomponent::UIComponent;
 use std::io::Error;
 use std::time::Duration;
@@ -33,18 +35,25 @@ pub struct MessageBar {
     current_message: Message, // 当前显示的消息。
     needs_redraw: bool,      // 是否需要重绘。
     cleared_after_expiry: bool,
+    size: Size,              // 消息栏的尺寸。
 }
 
 impl MessageBar {
-    /// 更新状态
+    /// 更新状态，消息中的控制字符（如文件名中的换行）不会原样输出到终端。
     pub fn update_message(&mut self, new_message: &str) {
         self.current_message = Message{
-            text: new_message.to_string(),
+            text: sanitize(new_message),
             time: Instant::now(),
         };
         self.cleared_after_expiry = false;
         self.set_needs_redraw(true);
     }
+
+    /// 当前消息的文本，供测试检查提示的内容。
+    #[cfg(test)]
+    pub fn text(&self) -> &str {
+        &self.current_message.text
+    }
 }
 
 impl UIComponent for MessageBar {
@@ -59,7 +68,9 @@ impl UIComponent for MessageBar {
     }
 
     /// 设置组件大小。
-    fn set_size(&mut self, _size: Size) {}
+    fn set_size(&mut self, size: Size) {
+        self.size = size;
+    }
 
     /// 绘制组件。
     fn draw(&mut self, origin: usize) -> Result<(), Error> {
@@ -72,6 +83,26 @@ impl UIComponent for MessageBar {
             &self.current_message.text
         };
 
-        Terminal::print_row(origin, message)
+        Terminal::print_row(origin, &truncate_to_width(message, self.size.width, EllipsisSide::End))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::{terminal::headless, truncate::text_width};
+
+    #[test]
+    fn an_emoji_laden_message_is_cut_to_the_width_of_the_bar() {
+        let mut message_bar = MessageBar::default();
+        message_bar.update_message("🎉 保存成功 👍🏽 done");
+        for width in 0..=24 {
+            message_bar.resize(Size { height: 1, width });
+            message_bar.render(0).unwrap();
+
+            let text = headless::take_text();
+            assert!(text_width(&text) <= width, "{text:?} at {width}");
+            assert!(width < 19 || text == "🎉 保存成功 👍🏽 done", "{text:?} at {width}");
+        }
     }
 }
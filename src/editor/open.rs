@@ -0,0 +1,181 @@
+use std::io::{Error, ErrorKind};
+
+use super::{
+    Editor, PromptType,
+    command::{
+        Command::{self, Edit, System},
+        Edit::Insert,
+        System::Dismiss,
+    },
+    view::trash,
+};
+
+impl Editor {
+    /// 询问要打开的文件。
+    pub(super) fn start_open_file(&mut self) {
+        self.enter_text_prompt(PromptType::OpenFile, "Open: ");
+    }
+
+    /// 在新的缓冲区中打开输入的文件，当前缓冲区保持打开；文件不存在时询问是否新建。
+    pub(super) fn submit_open_file(&mut self, file_name: &str) {
+        let file_name = file_name.trim();
+        if file_name.is_empty() {
+            self.message_bar.update_message("Open cancelled.");
+            return;
+        }
+        let path = self.config.resolve_path(file_name);
+        match self.view.open(&path) {
+            Ok(()) => {
+                self.report_buffer("Opened");
+                self.check_swap_file();
+            }
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                self.message_bar.update_message(&format!(
+                    "{} does not exist — create a new buffer for it? (y/n)",
+                    path.display()
+                ));
+                self.pending_open = Some(path);
+                self.prompt_type = PromptType::ConfirmCreate;
+            }
+            Err(err) => self
+                .message_bar
+                .update_message(&format!("ERR: Could not open file {file_name}: {}", describe_error(&err))),
+        }
+    }
+
+    /// 处理是否为不存在的文件新建缓冲区的确认：`y` 新建，其他按键取消。
+    pub(super) fn process_command_during_confirm_create(&mut self, command: Command) {
+        self.prompt_type = PromptType::None;
+        let Some(path) = self.pending_open.take() else {
+            return;
+        };
+        match command {
+            Edit(Insert('y' | 'Y')) => {
+                self.view.open_new(&path);
+                self.report_buffer("New file");
+            }
+            Edit(Insert(_)) | System(Dismiss) => {
+                self.message_bar.update_message("Open cancelled.");
+            }
+            _ => {
+                self.pending_open = Some(path);
+                self.prompt_type = PromptType::ConfirmCreate;
+            }
+        }
+    }
+
+    /// 当前文件旁边留有之前（如编辑器崩溃时）的交换文件时，询问如何处理；只读打开时只作提示。
+    pub(super) fn check_swap_file(&mut self) {
+        let Some(modified) = self.view.stale_swap() else {
+            return;
+        };
+        let file_name = self.view.get_status().file_name;
+        let age = trash::age_of(modified);
+        if self.readonly {
+            self.message_bar.update_message(&format!(
+                "{file_name} has a swap file from {age} — open it without read-only to recover it"
+            ));
+            return;
+        }
+        self.prompt_type = PromptType::ConfirmRecoverSwap;
+        self.message_bar.update_message(&format!(
+            "{file_name} has unsaved changes from an earlier session ({age}) — (r)ecover, (o)pen the file on disk, (d)elete them"
+        ));
+    }
+
+    /// 处理交换文件的询问：`r` 恢复其中的内容，`o` 或 Esc 打开磁盘上的文件并保留交换文件，
+    /// `d` 删除交换文件。
+    pub(super) fn process_command_during_confirm_recover_swap(&mut self, command: Command) {
+        let message = match command {
+            Edit(Insert('r' | 'R')) => match self.view.recover_swap() {
+                Ok(()) => String::from("Recovered unsaved changes from the swap file (Ctrl-Z undoes)"),
+                Err(err) => format!("ERR: Could not read the swap file: {err}"),
+            },
+            Edit(Insert('o' | 'O')) | System(Dismiss) => {
+                self.view.keep_swap();
+                String::from("Opened the file on disk, the swap file is kept and not overwritten")
+            }
+            Edit(Insert('d' | 'D')) => match self.view.delete_swap() {
+                Ok(()) => String::from("Swap file deleted."),
+                Err(err) => format!("ERR: Could not delete the swap file: {err}"),
+            },
+            _ => return,
+        };
+        self.prompt_type = PromptType::None;
+        self.message_bar.update_message(&message);
+    }
+
+    /// 从磁盘重新读取当前文件；有未保存的修改时先询问是否放弃。
+    pub(super) fn reload_file(&mut self) {
+        if self.view.file_path().is_none() {
+            self.message_bar.update_message("No file to reload");
+        } else if self.view.get_status().is_modified {
+            self.prompt_type = PromptType::ConfirmReload;
+            self.message_bar
+                .update_message("Unsaved changes! Discard them and reload from disk? (y/n)");
+        } else {
+            self.reload();
+        }
+    }
+
+    /// 处理重新读取前的确认：`y` 放弃修改并重新读取，其他按键取消。
+    pub(super) fn process_command_during_confirm_reload(&mut self, command: Command) {
+        match command {
+            Edit(Insert('y' | 'Y')) => {
+                self.prompt_type = PromptType::None;
+                self.reload();
+            }
+            Edit(Insert(_)) | System(Dismiss) => {
+                self.prompt_type = PromptType::None;
+                self.message_bar.update_message("Reload cancelled.");
+            }
+            _ => {}
+        }
+    }
+
+    /// 重新读取当前文件并报告结果。
+    fn reload(&mut self) {
+        let file_name = self.view.get_status().file_name;
+        let message = match self.view.reload() {
+            Ok(Some(_)) => format!("Reloaded {file_name} — your unsaved version was stashed"),
+            Ok(None) => format!("Reloaded {file_name}"),
+            Err(err) => format!("ERR: Could not reload {file_name}: {}", describe_error(&err)),
+        };
+        self.message_bar.update_message(&message);
+    }
+
+    /// 切换到下一个打开的缓冲区。
+    pub(super) fn next_buffer(&mut self) {
+        if self.view.next_buffer() {
+            self.report_buffer("Switched to");
+        } else {
+            self.message_bar
+                .update_message("Only one buffer is open (Ctrl-O opens another).");
+        }
+    }
+
+    /// 在消息栏中显示当前缓冲区的文件名和它是第几个缓冲区。
+    fn report_buffer(&mut self, action: &str) {
+        let status = self.view.get_status();
+        self.message_bar.update_message(&format!(
+            "{action} {} ({}/{})",
+            status.file_name,
+            status.buffer_index.saturating_add(1),
+            status.buffer_count
+        ));
+    }
+}
+
+/// 打开文件失败的原因：系统错误只显示简短的说明，如 `Permission denied`、`Not found`；
+/// 编辑器自己给出的错误（如二进制文件、无效的 UTF-8、悬空的符号链接）原样显示。
+pub fn describe_error(err: &Error) -> String {
+    if err.get_ref().is_some() {
+        return err.to_string();
+    }
+    match err.kind() {
+        ErrorKind::NotFound => String::from("Not found"),
+        ErrorKind::PermissionDenied => String::from("Permission denied"),
+        ErrorKind::IsADirectory => String::from("Is a directory"),
+        _ => err.to_string(),
+    }
+}
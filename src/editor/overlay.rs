@@ -0,0 +1,239 @@
+use std::{cmp::min, io::Error, ops::Range};
+
+use super::{
+    command::Move,
+    terminal::{Position, Size, Style, StyledText, Terminal},
+    truncate::{text_width, truncate_segments},
+    uicomponent::UIComponent,
+};
+
+/// 预览中变化部分两侧最多保留的字符数。
+const PREVIEW_CONTEXT: usize = 20;
+
+/// 覆盖在视图之上的可滚动列表。
+///
+/// 列表项的内容按需生成：只有滚动到可见区域的项才需要通过 `set_row` 填充，
+/// 因此即使条目很多，打开列表的代价也很小。
+#[derive(Default)]
+pub struct Overlay {
+    title: String,                       // 标题，显示在第一行。
+    rows: Vec<Option<Vec<StyledText>>>,  // 各项的内容，`None` 表示尚未生成。
+    checked: Option<Vec<bool>>,          // 各项是否被勾选，`None` 表示列表不可勾选。
+    selected: usize,                     // 当前选中的项。
+    scroll_top: usize,                   // 第一个可见项的索引。
+    size: Size,                          // 覆盖层的尺寸。
+    needs_redraw: bool,                  // 是否需要重绘。
+}
+
+impl Overlay {
+    /// 创建包含 `len` 项的列表，`checkable` 为真时每一项默认被勾选。
+    pub fn new(title: &str, len: usize, checkable: bool, size: Size) -> Self {
+        let mut rows = Vec::new();
+        rows.resize_with(len, || None);
+        Self {
+            title: title.to_string(),
+            rows,
+            checked: checkable.then(|| vec![true; len]),
+            selected: 0,
+            scroll_top: 0,
+            size,
+            needs_redraw: true,
+        }
+    }
+
+    /// 设置标题。
+    pub fn set_title(&mut self, title: &str) {
+        if self.title != title {
+            self.title = title.to_string();
+            self.set_needs_redraw(true);
+        }
+    }
+
+    /// 列表项的数量。
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// 当前选中的项。
+    pub const fn selected(&self) -> usize {
+        self.selected
+    }
+
+    /// 光标位置：选中项所在行的开头。
+    pub const fn caret_position(&self) -> Position {
+        Position {
+            row: self.selected.saturating_sub(self.scroll_top).saturating_add(1),
+            col: 0,
+        }
+    }
+
+    /// 可见区域内尚未生成内容的项。
+    pub fn missing_rows(&self) -> Vec<usize> {
+        self.visible_range()
+            .filter(|index| self.rows.get(*index).is_some_and(Option::is_none))
+            .collect()
+    }
+
+    /// 填充某一项的内容。
+    pub fn set_row(&mut self, index: usize, segments: Vec<StyledText>) {
+        if let Some(row) = self.rows.get_mut(index) {
+            *row = Some(segments);
+            self.set_needs_redraw(true);
+        }
+    }
+
+    /// 某一项是否被勾选；不可勾选的列表中所有项都视为被勾选。
+    pub fn is_checked(&self, index: usize) -> bool {
+        self.checked
+            .as_ref()
+            .is_none_or(|checked| checked.get(index).copied().unwrap_or(false))
+    }
+
+    /// 被勾选的项数。
+    pub fn checked_count(&self) -> usize {
+        (0..self.len()).filter(|index| self.is_checked(*index)).count()
+    }
+
+    /// 切换当前选中项的勾选状态。
+    pub fn toggle_selected(&mut self) {
+        if let Some(checked) = self
+            .checked
+            .as_mut()
+            .and_then(|checked| checked.get_mut(self.selected))
+        {
+            *checked = !*checked;
+            self.set_needs_redraw(true);
+        }
+    }
+
+    /// 移动选中项，并在需要时滚动列表。
+    pub fn handle_move_command(&mut self, command: Move) {
+        let page = self.list_height().saturating_sub(1).max(1);
+        let last = self.len().saturating_sub(1);
+        let selected = match command {
+            Move::Up | Move::Left => self.selected.saturating_sub(1),
+            Move::Down | Move::Right => min(self.selected.saturating_add(1), last),
+            Move::PageUp => self.selected.saturating_sub(page),
+            Move::PageDown => min(self.selected.saturating_add(page), last),
+            Move::StartOfLine | Move::DocumentStart => 0,
+            Move::EndOfLine | Move::DocumentEnd => last,
+            Move::WordLeft | Move::WordRight | Move::MatchingBracket => self.selected,
+        };
+        self.select(selected);
+    }
+
+    /// 选中某一项（超出范围时选中最后一项），并在需要时滚动列表。
+    pub fn select(&mut self, index: usize) {
+        self.selected = min(index, self.len().saturating_sub(1));
+        let height = self.list_height();
+        if self.selected < self.scroll_top {
+            self.scroll_top = self.selected;
+        } else if self.selected >= self.scroll_top.saturating_add(height) {
+            self.scroll_top = self.selected.saturating_add(1).saturating_sub(height);
+        }
+        self.set_needs_redraw(true);
+    }
+
+    /// 列表部分的高度（除去标题行）。
+    const fn list_height(&self) -> usize {
+        self.size.height.saturating_sub(1)
+    }
+
+    /// 当前可见的项的索引范围。
+    fn visible_range(&self) -> Range<usize> {
+        let end = min(
+            self.scroll_top.saturating_add(self.list_height()),
+            self.len(),
+        );
+        self.scroll_top..end
+    }
+}
+
+impl UIComponent for Overlay {
+    fn set_needs_redraw(&mut self, value: bool) {
+        self.needs_redraw = value;
+    }
+
+    fn needs_redraw(&self) -> bool {
+        self.needs_redraw
+    }
+
+    fn set_size(&mut self, size: Size) {
+        self.size = size;
+    }
+
+    fn draw(&mut self, origin_y: usize) -> Result<(), Error> {
+        Terminal::print_inverted_row(origin_y, &self.title)?;
+        let visible = self.visible_range();
+        for offset in 0..self.list_height() {
+            let row = origin_y.saturating_add(offset).saturating_add(1);
+            let index = self.scroll_top.saturating_add(offset);
+            if !visible.contains(&index) {
+                Terminal::print_row(row, "")?;
+                continue;
+            }
+
+            let marker = if index == self.selected { "> " } else { "  " };
+            let checkbox = match &self.checked {
+                Some(_) if self.is_checked(index) => "[x] ",
+                Some(_) => "[ ] ",
+                None => "",
+            };
+            let prefix = StyledText {
+                text: format!("{marker}{checkbox}"),
+                style: (index == self.selected).then_some(Style::Inverted),
+            };
+            let content_width = self.size.width.saturating_sub(text_width(&prefix.text));
+            Terminal::print_styled_row(row, &[prefix])?;
+            if let Some(Some(content)) = self.rows.get(index) {
+                Terminal::print_styled(&truncate_segments(content, content_width))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 生成一行修改的预览：`行号: 修改前 → 修改后`，变化的部分高亮显示。
+///
+/// `prefix` 和 `suffix` 是修改前后相同的部分，只保留靠近变化处的一小段。
+pub fn change_row(
+    line_index: usize,
+    prefix: &str,
+    removed: &str,
+    added: &str,
+    suffix: &str,
+) -> Vec<StyledText> {
+    let prefix = sanitize(prefix);
+    let prefix = match prefix.char_indices().rev().nth(PREVIEW_CONTEXT) {
+        Some((index, _)) => format!("…{}", prefix.get(index..).unwrap_or_default()),
+        None => prefix,
+    };
+    let suffix = sanitize(suffix);
+    let suffix = match suffix.char_indices().nth(PREVIEW_CONTEXT) {
+        Some((index, _)) => format!("{}…", suffix.get(..index).unwrap_or_default()),
+        None => suffix,
+    };
+    let plain = |text: String| StyledText { text, style: None };
+    let highlighted = |text: String| StyledText {
+        text,
+        style: Some(Style::Inverted),
+    };
+    vec![
+        plain(format!("{}: {prefix}", line_index.saturating_add(1))),
+        highlighted(sanitize(removed)),
+        plain(format!("{suffix} → {prefix}")),
+        highlighted(sanitize(added)),
+        plain(suffix),
+    ]
+}
+
+/// 将制表符显示为 `»`，其他控制字符替换为空格，避免破坏终端的输出。
+pub fn sanitize(text: &str) -> String {
+    text.chars()
+        .map(|ch| match ch {
+            '\t' => '»',
+            ch if ch.is_control() => ' ',
+            ch => ch,
+        })
+        .collect()
+}
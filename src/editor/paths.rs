@@ -0,0 +1,207 @@
+use std::{
+    env,
+    ffi::OsString,
+    fs::{DirBuilder, OpenOptions, remove_file},
+    path::{Path, PathBuf},
+    process,
+    sync::OnceLock,
+};
+
+/// 解析得到的状态目录，只在第一次使用时检查一次。
+static STATE_DIR: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+/// 状态目录，用于暂存、状态文件等需要在会话之间保留的数据。
+///
+/// 依次尝试：
+/// 1. `$XDG_STATE_HOME/snows_edit`（必须是绝对路径）；
+/// 2. `$HOME/.local/state/snows_edit`；
+/// 3. 临时目录下按用户区分的 `snows_edit-<uid>`。
+///
+/// 使用第一个可以创建并写入的目录；都不可用时返回 `None`，
+/// 此时各项持久化功能只在内存中工作。
+pub fn state_dir() -> Option<&'static Path> {
+    #[cfg(test)]
+    if NO_STATE_DIR.get() {
+        return None;
+    }
+    STATE_DIR
+        .get_or_init(|| {
+            resolve_state_dir(
+                env::var_os("XDG_STATE_HOME"),
+                env::var_os("HOME"),
+                &env::temp_dir(),
+            )
+        })
+        .as_deref()
+}
+
+/// 按 `state_dir` 中说明的顺序，从给定的环境变量和临时目录中选出状态目录。
+fn resolve_state_dir(
+    xdg: Option<OsString>,
+    home: Option<OsString>,
+    temp: &Path,
+) -> Option<PathBuf> {
+    let name = env!("CARGO_PKG_NAME");
+    let xdg = xdg
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .map(|dir| dir.join(name));
+    let home = home
+        .filter(|home| !home.is_empty())
+        .map(|home| Path::new(&home).join(".local/state").join(name));
+    let temp = temp.join(format!("{name}-{}", user_key()));
+    xdg.into_iter()
+        .chain(home)
+        .chain([temp])
+        .find(|dir| is_usable(dir))
+}
+
+#[cfg(test)]
+thread_local! {
+    /// 为真时当前线程上的 `state_dir` 返回 `None`，模拟状态目录不可用。
+    static NO_STATE_DIR: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+/// 在状态目录不可用的情况下执行 `f`（只影响当前线程）。
+#[cfg(test)]
+pub fn without_state_dir<T>(f: impl FnOnce() -> T) -> T {
+    NO_STATE_DIR.set(true);
+    let result = f();
+    NO_STATE_DIR.set(false);
+    result
+}
+
+/// 配置目录：`$XDG_CONFIG_HOME/snows_edit`（必须是绝对路径），否则为 `$HOME/.config/snows_edit`；
+/// 都无法确定时返回 `None`。目录不一定存在。
+pub fn config_dir() -> Option<PathBuf> {
+    let name = env!("CARGO_PKG_NAME");
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| {
+            env::var_os("HOME")
+                .filter(|home| !home.is_empty())
+                .map(|home| Path::new(&home).join(".config"))
+        })
+        .map(|dir| dir.join(name))
+}
+
+/// 检查目录是否可以创建并写入。
+fn is_usable(dir: &Path) -> bool {
+    let mut builder = DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    if builder.create(dir).is_err() || !is_owned(dir) {
+        return false;
+    }
+    let probe = dir.join(format!(".probe-{}", process::id()));
+    let writable = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe)
+        .is_ok();
+    let _ = remove_file(&probe);
+    writable
+}
+
+/// 目录是否属于当前用户；共享的临时目录中可能有其他用户预先创建的同名目录。
+#[cfg(unix)]
+fn is_owned(dir: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    dir.metadata().is_ok_and(|metadata| metadata.uid() == uid())
+}
+
+#[cfg(not(unix))]
+const fn is_owned(_dir: &Path) -> bool {
+    true
+}
+
+/// 当前用户的 UID。
+#[cfg(unix)]
+fn uid() -> u32 {
+    // SAFETY: `getuid` 没有任何前置条件，且总是成功。
+    unsafe { libc::getuid() }
+}
+
+/// 在临时目录中区分用户的标识。
+#[cfg(unix)]
+fn user_key() -> String {
+    uid().to_string()
+}
+
+#[cfg(not(unix))]
+fn user_key() -> String {
+    env::var("USERNAME").unwrap_or_else(|_| String::from("user"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// 在 `temp` 下回退时使用的目录。
+    fn temp_namespace(temp: &Path) -> PathBuf {
+        temp.join(format!("{}-{}", env!("CARGO_PKG_NAME"), user_key()))
+    }
+
+    #[test]
+    fn without_home_the_state_dir_falls_back_to_the_temp_namespace() {
+        let temp = tempfile::tempdir().unwrap();
+        assert_eq!(
+            resolve_state_dir(None, None, temp.path()),
+            Some(temp_namespace(temp.path()))
+        );
+        assert_eq!(
+            resolve_state_dir(None, Some(OsString::new()), temp.path()),
+            Some(temp_namespace(temp.path()))
+        );
+        // 相对路径的 `XDG_STATE_HOME` 按规范忽略。
+        assert_eq!(
+            resolve_state_dir(Some(OsString::from("state")), None, temp.path()),
+            Some(temp_namespace(temp.path()))
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn a_read_only_state_dir_is_skipped() {
+        use std::os::unix::fs::PermissionsExt;
+        let temp = tempfile::tempdir().unwrap();
+        let xdg = temp.path().join("read-only");
+        fs::create_dir(&xdg).unwrap();
+        fs::set_permissions(&xdg, fs::Permissions::from_mode(0o500)).unwrap();
+        if fs::write(xdg.join("probe"), "").is_ok() {
+            // 以 root 运行时权限不起作用，无法模拟只读目录。
+            return;
+        }
+        let home = temp.path().join("home");
+
+        let resolved = resolve_state_dir(
+            Some(xdg.into_os_string()),
+            Some(home.clone().into_os_string()),
+            temp.path(),
+        );
+
+        assert_eq!(resolved, Some(home.join(".local/state").join(env!("CARGO_PKG_NAME"))));
+    }
+
+    #[test]
+    fn no_usable_directory_means_no_state_dir() {
+        let temp = tempfile::tempdir().unwrap();
+        // 父路径是普通文件，目录无论如何都无法创建。
+        let file = temp.path().join("file");
+        fs::write(&file, "").unwrap();
+
+        let resolved = resolve_state_dir(
+            Some(file.clone().into_os_string()),
+            Some(file.clone().into_os_string()),
+            &file,
+        );
+
+        assert_eq!(resolved, None);
+    }
+}
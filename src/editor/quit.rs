@@ -0,0 +1,116 @@
+use super::{
+    Editor, PromptType,
+    command::{
+        Command::{self, Edit, Move, System},
+        Edit::{Insert, InsertNewline},
+        System::{Dismiss, Quit},
+    },
+    diff::DiffLine,
+    overlay::{Overlay, sanitize},
+    terminal::{Style, StyledText},
+};
+
+/// 有未保存的修改时退出前显示的询问。
+const CONFIRM_QUIT_MESSAGE: &str =
+    "Unsaved changes! Quit? (y discard, s save and quit, d show changes, n cancel)";
+
+impl Editor {
+    /// 有未保存的修改时询问是否退出；多个缓冲区有修改时先给出数量，`y` 放弃全部的修改。
+    pub(super) fn confirm_quit(&mut self) {
+        self.prompt_type = PromptType::ConfirmQuit;
+        let count = self.view.modified_buffer_count();
+        if count > 1 {
+            self.message_bar
+                .update_message(&format!("{count} buffers modified — {CONFIRM_QUIT_MESSAGE}"));
+        } else {
+            self.message_bar.update_message(CONFIRM_QUIT_MESSAGE);
+        }
+    }
+
+    /// 处理退出前的询问：`y`（或再按一次 Ctrl-D）放弃修改退出，`s` 保存后退出，
+    /// `d` 查看未保存的修改后回到这里，`n` 或 Esc 取消退出。
+    pub(super) fn process_command_during_confirm_quit(&mut self, command: Command) {
+        match command {
+            Edit(Insert('y' | 'Y')) | System(Quit) => self.should_quit = true,
+            Edit(Insert('s' | 'S')) => {
+                self.prompt_type = PromptType::None;
+                if self.view.file_path().is_none() {
+                    self.message_bar
+                        .update_message("ERR: Buffer has no file name, quit cancelled");
+                    return;
+                }
+                self.save();
+                // 保存前还需要确认（如文件在磁盘上被修改过）时，先处理那个提示。
+                if self.prompt_type == PromptType::None {
+                    self.handle_quit();
+                }
+            }
+            Edit(Insert('d' | 'D')) => self.show_unsaved_changes(),
+            Edit(Insert('n' | 'N')) | System(Dismiss) => {
+                self.prompt_type = PromptType::None;
+                self.message_bar.update_message("Quit cancelled.");
+            }
+            _ => {}
+        }
+    }
+
+    /// 在列表中显示与磁盘上的文件相比未保存的修改，关闭列表后回到退出前的询问。
+    fn show_unsaved_changes(&mut self) {
+        let diff = match self.view.unsaved_diff() {
+            Ok(diff) => diff,
+            Err(err) => {
+                self.message_bar.update_message(&format!(
+                    "ERR: Could not read file: {err} — {CONFIRM_QUIT_MESSAGE}"
+                ));
+                return;
+            }
+        };
+        if diff.is_empty() {
+            self.message_bar.update_message(&format!(
+                "No changed lines, only the file format differs — {CONFIRM_QUIT_MESSAGE}"
+            ));
+            return;
+        }
+        self.suspend_prompt(CONFIRM_QUIT_MESSAGE);
+        let title = format!("Unsaved changes: {} line(s) (Esc back)", diff.len());
+        self.overlay = Overlay::new(&title, diff.len(), false, self.view_size());
+        self.unsaved_diff = diff;
+        self.prompt_type = PromptType::UnsavedChanges;
+    }
+
+    /// 浏览未保存的修改时，Esc 或回车回到退出前的询问。
+    pub(super) fn process_command_during_unsaved_changes(&mut self, command: Command) {
+        match command {
+            Move(move_command, _) => self.overlay.handle_move_command(move_command),
+            Edit(InsertNewline | Insert('q')) | System(Dismiss) => {
+                self.unsaved_diff.clear();
+                self.resume_prompt();
+            }
+            _ => {}
+        }
+    }
+
+    /// 为可见区域内尚未生成内容的列表项生成内容：删除的行暗淡显示，新增的行反色显示。
+    pub(super) fn fill_unsaved_changes(&mut self) {
+        for index in self.overlay.missing_rows() {
+            let Some(line) = self.unsaved_diff.get(index) else {
+                continue;
+            };
+            let (sign, line_index, text, style) = match line {
+                DiffLine::Removed(line_index, text) => ('-', line_index, text, Style::Dim),
+                DiffLine::Added(line_index, text) => ('+', line_index, text, Style::Inverted),
+            };
+            let row = vec![
+                StyledText {
+                    text: format!("{sign} {:>6}  ", line_index.saturating_add(1)),
+                    style: None,
+                },
+                StyledText {
+                    text: sanitize(text),
+                    style: Some(style),
+                },
+            ];
+            self.overlay.set_row(index, row);
+        }
+    }
+}
@@ -0,0 +1,241 @@
+use super::{
+    Editor, PromptType,
+    command::{
+        Command::{self, Edit, Move, System},
+        Edit::{Insert, InsertNewline},
+        System::Dismiss,
+    },
+    overlay::{Overlay, change_row, sanitize},
+    terminal::StyledText,
+    uicomponent::UIComponent,
+    view::{Location, Match},
+};
+
+/// 替换的状态：查找内容、替换内容、全部替换的所有匹配以及逐个替换的进度。
+#[derive(Default)]
+pub struct ReplaceState {
+    query: String,          // 查找的文本。
+    replacement: String,    // 替换为的文本。
+    matches: Vec<Match>,    // 所有匹配的位置。
+    current: Option<Match>, // 逐个替换时等待确认的匹配。
+    replaced: usize,        // 逐个替换时已经替换的数量。
+}
+
+impl Editor {
+    /// 开始全部替换：先输入查找内容。
+    pub(super) fn start_replace_all(&mut self) {
+        if !self.check_writable() {
+            return;
+        }
+        self.enter_text_prompt(PromptType::ReplaceSearch, "Replace: ");
+    }
+
+    /// 记录查找内容，继续输入替换内容。
+    pub(super) fn submit_replace_query(&mut self, query: &str) {
+        if query.is_empty() {
+            self.message_bar.update_message("Replace cancelled.");
+            return;
+        }
+        self.replace_state.query = query.to_string();
+        self.enter_text_prompt(PromptType::ReplaceWith, "Replace with: ");
+    }
+
+    /// 记录替换内容，查找所有匹配并打开预览。
+    pub(super) fn submit_replacement(&mut self, replacement: &str) {
+        self.replace_state.replacement = replacement.to_string();
+        self.replace_state.matches = self.view.find_all(&self.replace_state.query);
+        if self.replace_state.matches.is_empty() {
+            self.message_bar.update_message(&format!(
+                "No matches for \"{}\"",
+                self.replace_state.query
+            ));
+            return;
+        }
+        self.overlay = Overlay::new(
+            "",
+            self.replace_state.matches.len(),
+            true,
+            self.view_size(),
+        );
+        self.update_replace_preview_title();
+        self.prompt_type = PromptType::ReplacePreview;
+    }
+
+    /// 处理替换预览中的按键：空格切换是否替换该处，回车应用，Esc 取消。
+    pub(super) fn process_command_during_replace_preview(&mut self, command: Command) {
+        match command {
+            Move(move_command, _) => self.overlay.handle_move_command(move_command),
+            Edit(Insert(' ')) => {
+                self.overlay.toggle_selected();
+                self.update_replace_preview_title();
+            }
+            Edit(InsertNewline) => self.apply_replace_preview(),
+            System(Dismiss) => {
+                self.close_replace_preview();
+                self.message_bar.update_message("Replace cancelled.");
+            }
+            _ => {}
+        }
+    }
+
+    /// 为可见区域内尚未生成内容的预览项生成内容。
+    pub(super) fn fill_replace_preview(&mut self) {
+        for index in self.overlay.missing_rows() {
+            if let Some(&found) = self.replace_state.matches.get(index) {
+                let row = self.replace_preview_row(found);
+                self.overlay.set_row(index, row);
+            }
+        }
+    }
+
+    /// 生成一处匹配的预览。
+    fn replace_preview_row(&self, found: Match) -> Vec<StyledText> {
+        let text = self.view.line_text(found.line_index);
+        let end = found.byte_index.saturating_add(found.byte_len);
+        change_row(
+            found.line_index,
+            text.get(..found.byte_index).unwrap_or_default(),
+            text.get(found.byte_index..end).unwrap_or_default(),
+            &self.replace_state.replacement,
+            text.get(end..).unwrap_or_default(),
+        )
+    }
+
+    /// 应用预览中被勾选的替换。
+    fn apply_replace_preview(&mut self) {
+        let selected: Vec<Match> = self
+            .replace_state
+            .matches
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| self.overlay.is_checked(*index))
+            .map(|(_, found)| *found)
+            .collect();
+        let total = self.replace_state.matches.len();
+        let replaced = self
+            .view
+            .replace_matches(&selected, &self.replace_state.replacement);
+        self.close_replace_preview();
+        self.message_bar
+            .update_message(&format!("Replaced {replaced} of {total} occurrences."));
+    }
+
+    /// 关闭预览，恢复显示视图。
+    fn close_replace_preview(&mut self) {
+        self.prompt_type = PromptType::None;
+        self.replace_state.matches.clear();
+        self.view.set_needs_redraw(true);
+        self.message_bar.set_needs_redraw(true);
+    }
+
+    /// 更新预览标题中的计数。
+    fn update_replace_preview_title(&mut self) {
+        let title = format!(
+            "Replace \"{}\" → \"{}\": apply {} of {} (Space toggle, Enter apply, Esc cancel)",
+            sanitize(&self.replace_state.query),
+            sanitize(&self.replace_state.replacement),
+            self.overlay.checked_count(),
+            self.overlay.len(),
+        );
+        self.overlay.set_title(&title);
+    }
+
+    // ==================== 逐个替换 ====================
+
+    /// 开始逐个替换：先输入查找内容。
+    pub(super) fn start_replace_each(&mut self) {
+        if !self.check_writable() {
+            return;
+        }
+        self.enter_text_prompt(PromptType::ReplaceEachSearch, "Replace: ");
+    }
+
+    /// 记录查找内容，继续输入替换内容。
+    pub(super) fn submit_replace_each_query(&mut self, query: &str) {
+        if query.is_empty() {
+            self.message_bar.update_message("Replace cancelled.");
+            return;
+        }
+        self.replace_state.query = query.to_string();
+        self.enter_text_prompt(PromptType::ReplaceEachWith, "Replace with: ");
+    }
+
+    /// 记录替换内容，从光标处开始逐个确认匹配。
+    pub(super) fn submit_replace_each_replacement(&mut self, replacement: &str) {
+        self.replace_state.replacement = replacement.to_string();
+        self.replace_state.replaced = 0;
+        let from = self.view.text_location();
+        if self.view.find_from(&self.replace_state.query, from).is_none() {
+            self.message_bar.update_message(&format!(
+                "No matches for \"{}\"",
+                self.replace_state.query
+            ));
+            return;
+        }
+        self.ask_replace_from(from);
+    }
+
+    /// 处理逐个替换中的按键：`y` 替换、`n` 跳过、`a` 替换剩余的全部、`q` 或 Esc 结束。
+    pub(super) fn process_command_during_confirm_replace(&mut self, command: Command) {
+        let Some(found) = self.replace_state.current else {
+            self.finish_replace_each();
+            return;
+        };
+        match command {
+            Edit(Insert('y' | 'Y')) => {
+                self.view.replace_match(found, &self.replace_state.replacement);
+                self.replace_state.replaced = self.replace_state.replaced.saturating_add(1);
+                self.ask_replace_from(self.view.text_location());
+            }
+            Edit(Insert('n' | 'N')) => {
+                let after = self.view.location_at_byte(
+                    found.line_index,
+                    found.byte_index.saturating_add(found.byte_len),
+                );
+                self.ask_replace_from(after);
+            }
+            Edit(Insert('a' | 'A')) => {
+                let remaining: Vec<Match> = self
+                    .view
+                    .find_all(&self.replace_state.query)
+                    .into_iter()
+                    .filter(|other| {
+                        (other.line_index, other.byte_index) >= (found.line_index, found.byte_index)
+                    })
+                    .collect();
+                let replaced = self
+                    .view
+                    .replace_matches(&remaining, &self.replace_state.replacement);
+                self.replace_state.replaced = self.replace_state.replaced.saturating_add(replaced);
+                self.finish_replace_each();
+            }
+            Edit(Insert('q' | 'Q')) | System(Dismiss) => self.finish_replace_each(),
+            _ => {}
+        }
+    }
+
+    /// 高亮指定位置之后的下一个匹配并询问是否替换；没有更多匹配时结束。
+    fn ask_replace_from(&mut self, from: Location) {
+        let found = self.view.find_from(&self.replace_state.query, from);
+        self.replace_state.current = found;
+        if found.is_none() {
+            self.finish_replace_each();
+            return;
+        }
+        self.view.highlight_match(found);
+        self.prompt_type = PromptType::ConfirmReplace;
+        self.message_bar
+            .update_message("Replace this occurrence? (y)es / (n)o / (a)ll / (q)uit");
+    }
+
+    /// 结束逐个替换，取消高亮并报告替换的数量。
+    fn finish_replace_each(&mut self) {
+        self.prompt_type = PromptType::None;
+        self.replace_state.current = None;
+        self.view.highlight_match(None);
+        self.message_bar.update_message(&format!(
+            "Replaced {} occurrences.",
+            self.replace_state.replaced
+        ));
+    }
+}
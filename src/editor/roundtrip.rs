@@ -0,0 +1,131 @@
+use super::{encoding::FileEncoding, view::View};
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+/// 差异处前后显示的字节数。
+const CONTEXT: usize = 16;
+
+/// 加载文件并按保存时的方式重新拼接，报告与原文件的逐字节差异，不写入任何内容。
+///
+/// 文件不是有效的 UTF-8 时与编辑器一样按 `fallback` 解码。文件能够原样往返时返回 `Ok(true)`。
+pub fn check(path: &Path, fallback: Option<FileEncoding>) -> Result<bool, String> {
+    let (original, view, saved) = load(path, fallback)?;
+    let report = report(&original, &saved);
+    let mut stdout = io::stdout().lock();
+    let write = |stdout: &mut io::StdoutLock, text: &str| {
+        writeln!(stdout, "{text}").map_err(|err| err.to_string())
+    };
+    write(
+        &mut stdout,
+        &format!(
+            "{}: {} bytes ({}, {})",
+            path.display(),
+            original.len(),
+            view.encoding(),
+            view.text_format().describe()
+        ),
+    )?;
+    match &report {
+        None => write(&mut stdout, "round-trip: identical")?,
+        Some(lines) => {
+            for line in lines {
+                write(&mut stdout, line)?;
+            }
+        }
+    }
+    Ok(report.is_none())
+}
+
+/// 读取文件的原始内容，加载到视图中，并取得保存时会写入的内容。
+fn load(path: &Path, fallback: Option<FileEncoding>) -> Result<(Vec<u8>, View, Vec<u8>), String> {
+    let original = fs::read(path).map_err(|err| format!("{}: {err}", path.display()))?;
+    let mut view = View::default();
+    view.set_fallback_encoding(fallback);
+    view.load(path)
+        .map_err(|err| format!("{}: {err}", path.display()))?;
+    let saved = view
+        .save_snapshot()
+        .map(|snapshot| snapshot.contents)
+        .unwrap_or_default();
+    Ok((original, view, saved))
+}
+
+/// 比较原文件和保存的内容，相同时返回 `None`，否则返回差异摘要。
+fn report(original: &[u8], saved: &[u8]) -> Option<Vec<String>> {
+    let offset = original
+        .iter()
+        .zip(saved)
+        .position(|(a, b)| a != b)
+        .or_else(|| (original.len() != saved.len()).then(|| original.len().min(saved.len())))?;
+    let before = original.get(..offset).unwrap_or_default();
+    let line = before.split(|byte| *byte == b'\n').count();
+    let column = before
+        .iter()
+        .rposition(|byte| *byte == b'\n')
+        .map_or(offset, |newline| offset.saturating_sub(newline).saturating_sub(1))
+        .saturating_add(1);
+    Some(vec![
+        format!(
+            "round-trip: differs (original {} bytes, saved {} bytes)",
+            original.len(),
+            saved.len()
+        ),
+        format!("first difference at byte {offset} (line {line}, byte column {column})"),
+        format!("  original: {}", excerpt(original, offset)),
+        format!("  saved:    {}", excerpt(saved, offset)),
+    ])
+}
+
+/// 差异处附近的一小段内容，控制字符以转义形式显示。
+fn excerpt(bytes: &[u8], offset: usize) -> String {
+    let start = offset.saturating_sub(CONTEXT);
+    let end = offset.saturating_add(CONTEXT).min(bytes.len());
+    let text = String::from_utf8_lossy(bytes.get(start..end).unwrap_or_default());
+    format!("\"{}\"", text.escape_debug())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// 必须逐字节原样往返的样例文件。
+    fn corpus() -> Vec<PathBuf> {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/roundtrip");
+        let mut files: Vec<PathBuf> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "txt"))
+            .collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn the_fixture_corpus_round_trips_unchanged() {
+        let files = corpus();
+        assert!(files.len() >= 8);
+        for path in files {
+            let (original, _, saved) = load(&path, FileEncoding::for_label("latin1")).unwrap();
+            assert_eq!(report(&original, &saved), None, "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn mixed_line_endings_are_normalized_and_the_difference_is_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mixed.txt");
+        fs::write(&path, "one\r\ntwo\nthree\r\n").unwrap();
+
+        let (original, view, saved) = load(&path, FileEncoding::for_label("latin1")).unwrap();
+
+        assert_eq!(saved, b"one\r\ntwo\r\nthree\r\n");
+        assert_eq!(view.text_format().describe(), "CRLF, normalized from mixed line endings");
+        let report = report(&original, &saved).unwrap();
+        assert_eq!(report[0], "round-trip: differs (original 16 bytes, saved 17 bytes)");
+        assert_eq!(report[1], "first difference at byte 8 (line 2, byte column 4)");
+    }
+}
@@ -0,0 +1,57 @@
+use std::time::{Duration, Instant};
+
+/// 从启动到首帧绘制完成的时间预算。
+const FIRST_FRAME_BUDGET: Duration = Duration::from_millis(30);
+
+/// 记录启动过程中各阶段的耗时，由 `--startup-timings` 开启显示。
+pub struct StartupTimer {
+    started: Instant,                      // 启动的时间。
+    last: Instant,                         // 上一个阶段结束的时间。
+    phases: Vec<(&'static str, Duration)>, // 各阶段的名称和耗时。
+    first_frame: Option<Duration>,         // 从启动到首帧绘制完成的耗时。
+}
+
+impl StartupTimer {
+    /// 开始计时。
+    pub fn start() -> Self {
+        let now = Instant::now();
+        Self {
+            started: now,
+            last: now,
+            phases: Vec::new(),
+            first_frame: None,
+        }
+    }
+
+    /// 记录一个阶段的结束。
+    pub fn phase(&mut self, name: &'static str) {
+        let now = Instant::now();
+        self.phases.push((name, now.saturating_duration_since(self.last)));
+        self.last = now;
+    }
+
+    /// 记录首帧绘制完成。
+    pub fn first_frame(&mut self) {
+        self.phase("first frame");
+        self.first_frame = Some(self.last.saturating_duration_since(self.started));
+    }
+
+    /// 生成耗时报告，首帧超出预算时给出提示。
+    pub fn report(&self) -> String {
+        let phases = self
+            .phases
+            .iter()
+            .map(|(name, duration)| format!("{name} {duration:.1?}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let first_frame = self.first_frame.unwrap_or_default();
+        let verdict = if first_frame > FIRST_FRAME_BUDGET {
+            "over budget"
+        } else {
+            "within budget"
+        };
+        format!(
+            "Startup: {phases} | first frame {first_frame:.1?} ({verdict} {FIRST_FRAME_BUDGET:?})"
+        )
+    }
+}
@@ -0,0 +1,98 @@
+use super::paths;
+use std::{
+    fs::{create_dir_all, read_dir, read_to_string, write},
+    io::{Error, ErrorKind},
+    path::{Path, PathBuf},
+    process,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// 状态目录不可用或写入失败时，暂存的内容保留在内存中，直到编辑器退出。
+#[cfg(not(test))]
+static MEMORY: Mutex<Vec<(PathBuf, String)>> = Mutex::new(Vec::new());
+
+#[cfg(test)]
+thread_local! {
+    /// 测试中每个线程各有一份，并行的测试不会读到彼此暂存的内容。
+    static MEMORY: Mutex<Vec<(PathBuf, String)>> = const { Mutex::new(Vec::new()) };
+}
+
+/// 访问保存在内存中的暂存内容。
+#[cfg(not(test))]
+fn with_memory<T>(f: impl FnOnce(&mut Vec<(PathBuf, String)>) -> T) -> Result<T, Error> {
+    let mut memory = MEMORY
+        .lock()
+        .map_err(|_| Error::other("Stash is unavailable"))?;
+    Ok(f(&mut memory))
+}
+
+/// 访问保存在内存中的暂存内容。
+#[cfg(test)]
+fn with_memory<T>(f: impl FnOnce(&mut Vec<(PathBuf, String)>) -> T) -> Result<T, Error> {
+    MEMORY.with(|memory| {
+        let mut memory = memory
+            .lock()
+            .map_err(|_| Error::other("Stash is unavailable"))?;
+        Ok(f(&mut memory))
+    })
+}
+
+/// 暂存目录：状态目录下的 `stash`，未保存的内容在被替换之前保存在这里。
+fn stash_dir() -> Result<PathBuf, Error> {
+    paths::state_dir()
+        .map(|dir| dir.join("stash"))
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, "No state directory"))
+}
+
+/// 将未保存的内容写入暂存目录，返回暂存文件的路径。
+///
+/// 无法写入磁盘时改为保存在内存中，返回的路径以 `<memory>` 开头，仅用于显示。
+pub fn save(name: &str, contents: &str) -> Result<PathBuf, Error> {
+    let file_name = file_name(name);
+    if let Ok(path) = write_to_disk(&file_name, contents) {
+        return Ok(path);
+    }
+    let path = Path::new("<memory>").join(file_name);
+    with_memory(|memory| memory.push((path.clone(), contents.to_string())))?;
+    Ok(path)
+}
+
+/// 将暂存内容写入暂存目录。
+fn write_to_disk(file_name: &str, contents: &str) -> Result<PathBuf, Error> {
+    let dir = stash_dir()?;
+    create_dir_all(&dir)?;
+    let path = dir.join(file_name);
+    write(&path, contents)?;
+    Ok(path)
+}
+
+/// 暂存文件的名称：时间戳、进程号以及清理过的原文件名。
+fn file_name(name: &str) -> String {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis());
+    let name: String = name
+        .chars()
+        .map(|ch| if ch.is_alphanumeric() || ch == '.' { ch } else { '_' })
+        .collect();
+    format!("{timestamp}-{}-{name}", process::id())
+}
+
+/// 读取最近一次暂存的内容及其路径；保存在内存中的暂存总是比磁盘上的更新。
+pub fn latest() -> Result<(PathBuf, String), Error> {
+    if let Ok(Some(latest)) = with_memory(|memory| memory.last().cloned()) {
+        return Ok(latest);
+    }
+    let latest = read_dir(stash_dir()?)?
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let modified = entry.metadata().ok()?.modified().ok()?;
+            Some((modified, entry.path()))
+        })
+        .max()
+        .map(|(_, path)| path)
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, "No stashed version"))?;
+    let contents = read_to_string(&latest)?;
+    Ok((latest, contents))
+}
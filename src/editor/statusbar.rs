@@ -1,6 +1,8 @@
 use super::{
     documentstatus::DocumentStatus,
-    terminal::{Size, Terminal}, uicomponent::UIComponent,
+    terminal::{Size, Terminal},
+    truncate::{EllipsisSide, text_width, truncate_to_width},
+    uicomponent::UIComponent,
 };
 
 #[derive(Default)]
@@ -8,6 +10,8 @@ pub struct Statusbar {
     current_status: DocumentStatus,
     needs_redraw: bool,
     size: Size,
+    degraded: bool,       // 是否处于降级渲染模式。
+    pending_keys: String, // 尚未完成的按键序列，例如二合字母的前缀。
 }
 
 impl Statusbar {
@@ -18,6 +22,22 @@ impl Statusbar {
             self.set_needs_redraw(true);
         }
     }
+
+    /// 设置是否显示降级渲染标志。
+    pub fn set_degraded(&mut self, degraded: bool) {
+        if self.degraded != degraded {
+            self.degraded = degraded;
+            self.set_needs_redraw(true);
+        }
+    }
+
+    /// 设置尚未完成的按键序列，为空时不显示。
+    pub fn set_pending_keys(&mut self, keys: &str) {
+        if self.pending_keys != keys {
+            self.pending_keys = keys.to_string();
+            self.set_needs_redraw(true);
+        }
+    }
 }
 
 impl UIComponent for Statusbar {
@@ -34,22 +54,75 @@ impl UIComponent for Statusbar {
     fn draw(&mut self, origin_y: usize) -> Result<(), std::io::Error> {
         let line_count = self.current_status.line_count_to_string();
         let modified_indicator = self.current_status.modified_indicator_to_string();
+        let read_only_indicator = self.current_status.read_only_indicator_to_string();
+        let symlink_indicator = self.current_status.symlink_indicator_to_string();
+        let buffer_indicator = self.current_status.buffer_indicator_to_string();
+        let scroll_indicator = self.current_status.scroll_indicator_to_string();
+        let degraded_indicator = if self.degraded { " [slow]" } else { "" };
+        let pending_indicator = if self.pending_keys.is_empty() {
+            String::new()
+        } else {
+            format!(" [{}]", self.pending_keys)
+        };
         let beginning = format!(
-            "{} - {line_count} {modified_indicator}",
+            "{}{symlink_indicator}{buffer_indicator} - {line_count} {read_only_indicator}{modified_indicator}{scroll_indicator}{degraded_indicator}{pending_indicator}",
             self.current_status.file_name
         );
 
-        let position_indicator = self.current_status.position_indicator_to_string();
-        let remainder_len = self.size.width.saturating_sub(beginning.len());
-        let status = format!("{beginning}{position_indicator:>remainder_len$}");
-
-        let to_print = if status.len() <= self.size.width {
-            status
+        // 编码、换行符和位置信息靠右对齐，宽度不够时截断左侧的文件名等信息；
+        // 连右侧都放不下时只保留位置信息。
+        let width = self.size.width;
+        let position_indicator = format!(
+            "{} {}",
+            self.current_status.format_indicator_to_string(),
+            self.current_status.position_indicator_to_string()
+        );
+        let position_indicator = if text_width(&position_indicator) > width {
+            self.current_status.position_indicator_to_string()
         } else {
-            String::new()
+            position_indicator
         };
+        let position_width = text_width(&position_indicator);
+        let beginning = truncate_to_width(
+            &beginning,
+            width.saturating_sub(position_width),
+            EllipsisSide::End,
+        );
+        let padding = width
+            .saturating_sub(text_width(&beginning))
+            .saturating_sub(position_width);
+        let status = format!("{beginning}{}{position_indicator}", " ".repeat(padding));
+        let to_print = truncate_to_width(&status, width, EllipsisSide::End);
         Terminal::print_inverted_row(origin_y, &to_print)?;
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::terminal::headless;
+
+    #[test]
+    fn a_cjk_file_name_fills_the_bar_exactly_at_every_width() {
+        let mut status_bar = Statusbar::default();
+        status_bar.update_status(DocumentStatus {
+            file_name: String::from("中文文件名.txt"),
+            total_lines: 3,
+            is_modified: true,
+            ..DocumentStatus::default()
+        });
+        for width in 0..=60 {
+            headless::set_size(Size { height: 1, width });
+            status_bar.resize(Size { height: 1, width });
+            status_bar.render(0).unwrap();
+
+            let text = headless::take_text();
+            assert_eq!(text_width(&text), width, "{text:?}");
+            if width == 60 {
+                assert!(text.starts_with("中文文件名.txt"), "{text:?}");
+            }
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use super::documentstatus::DocumentStatus;
+use std::{
+    fs::{OpenOptions, create_dir_all, remove_file, symlink_metadata},
+    io::{Result, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// 两次写入状态文件之间的最小间隔。
+const DEBOUNCE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 当前正在写入的状态文件，供 panic 钩子清理。
+static PUBLISHED_PATH: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// 将光标位置等状态写入文件或命名管道，供 tmux 状态栏等外部程序读取。
+///
+/// 每次写入一行 `file=…<TAB>line=…<TAB>col=…<TAB>dirty=0|1`，文件名中的反斜杠、制表符和
+/// 换行符写成 `\\`、`\t`、`\n`、`\r`，不会拆开字段或行。写入命名管道时使用
+/// 非阻塞方式打开，没有读取端时直接跳过本次更新，不会阻塞界面。
+#[derive(Default)]
+pub struct StatusPublisher {
+    path: Option<PathBuf>,         // 状态文件的路径，`None` 表示不发布。
+    published: String,             // 上一次写入的内容。
+    pending: Option<String>,       // 防抖期间尚未写入的内容。
+    last_write: Option<Instant>,   // 上一次写入的时间。
+}
+
+impl StatusPublisher {
+    /// 构造方法，`path` 为 `None` 时不发布任何状态。
+    pub fn new(path: Option<PathBuf>) -> Self {
+        if let Ok(mut published_path) = PUBLISHED_PATH.lock() {
+            published_path.clone_from(&path);
+        }
+        Self {
+            path,
+            published: String::new(),
+            pending: None,
+            last_write: None,
+        }
+    }
+
+    /// 发布新的状态；内容未变化时忽略，距上次写入过近时推迟到防抖期结束。
+    pub fn publish(&mut self, status: &DocumentStatus) {
+        if self.path.is_none() {
+            return;
+        }
+        let line = format!(
+            "file={}\tline={}\tcol={}\tdirty={}\n",
+            escape_field(&status.file_name),
+            status.current_line_index.saturating_add(1),
+            status.current_column_index.saturating_add(1),
+            u8::from(status.is_modified),
+        );
+        if line == self.published {
+            self.pending = None;
+            return;
+        }
+        self.pending = Some(line);
+        if self.pending_deadline().is_some_and(|deadline| deadline <= Instant::now()) {
+            self.flush_pending();
+        }
+    }
+
+    /// 尚未写入的状态应当写入的时间。
+    pub fn pending_deadline(&self) -> Option<Instant> {
+        self.pending.as_ref()?;
+        Some(self.last_write.map_or_else(Instant::now, |last| {
+            last.checked_add(DEBOUNCE_INTERVAL).unwrap_or(last)
+        }))
+    }
+
+    /// 写入尚未写入的状态。
+    pub fn flush_pending(&mut self) {
+        let (Some(path), Some(line)) = (&self.path, self.pending.take()) else {
+            return;
+        };
+        self.last_write = Some(Instant::now());
+        // 命名管道没有读取端或管道已满时写入会失败，此时跳过本次更新。
+        if write_nonblocking(path, &line).is_ok() {
+            self.published = line;
+        }
+    }
+}
+
+impl Drop for StatusPublisher {
+    fn drop(&mut self) {
+        if self.path.is_some() {
+            cleanup();
+        }
+    }
+}
+
+/// 删除编辑器写入的状态文件；用户创建的命名管道保持不变。
+pub fn cleanup() {
+    let Ok(mut published_path) = PUBLISHED_PATH.lock() else {
+        return;
+    };
+    if let Some(path) = published_path.take()
+        && symlink_metadata(&path).is_ok_and(|metadata| metadata.is_file())
+    {
+        let _ = remove_file(path);
+    }
+}
+
+/// 转义字段中的反斜杠、制表符和换行符，使每个字段都不含分隔符。
+fn escape_field(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+/// 以非阻塞方式打开文件并写入内容。
+fn write_nonblocking(path: &Path, text: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.custom_flags(libc::O_NONBLOCK);
+    }
+    options.open(path)?.write_all(text.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn separators_in_the_file_name_are_escaped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("status");
+        let mut publisher = StatusPublisher::new(Some(path));
+
+        publisher.publish(&DocumentStatus {
+            file_name: String::from("a\tb\nc\rd\\e.txt"),
+            current_line_index: 2,
+            is_modified: true,
+            ..DocumentStatus::default()
+        });
+
+        // 检查写入成功后记下的内容，而不是再读回文件：panic 钩子的测试会清理当前的状态文件。
+        assert_eq!(
+            publisher.published,
+            "file=a\\tb\\nc\\rd\\\\e.txt\tline=3\tcol=1\tdirty=1\n"
+        );
+    }
+}
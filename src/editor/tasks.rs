@@ -0,0 +1,372 @@
+use super::{
+    Editor, PromptType,
+    command::{
+        Command::{self, Edit, System},
+        Edit::Insert,
+        System::Dismiss,
+    },
+    view::saved_message,
+};
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+/// 退出时等待关键任务结束的最长时间，超时后询问是否强制退出。
+const QUIT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 后台任务完成后，编辑器需要进行的后续处理。
+#[derive(Clone, Debug)]
+pub enum Completion {
+    // 保存完成，附带保存的缓冲区编号、快照对应的缓冲区版本、是否替换了符号链接，
+    // 以及开始保存前产生的警告（如备份原文件失败的原因）。
+    Save { buffer_id: usize, revision: u64, replace_link: bool, warnings: Vec<String> },
+}
+
+/// 一个正在后台运行的任务。
+struct Task {
+    description: String,      // 显示给用户的描述，如 `Saving notes.txt`。
+    critical: bool,           // 关键任务（如保存）不能取消，退出前必须等待。
+    cancel: Arc<AtomicBool>,  // 请求取消的标志，由任务自行检查。
+    handle: JoinHandle<Result<Vec<String>, String>>, // 任务所在的线程，成功时返回需要提示的警告。
+    started: Instant,         // 开始的时间。
+    completion: Completion,   // 完成后的处理。
+}
+
+/// 正在进行中的后台任务。
+#[derive(Default)]
+pub struct Tasks {
+    tasks: Vec<Task>,
+}
+
+impl Tasks {
+    /// 在后台线程中启动任务。`work` 应定期检查传入的取消标志。
+    pub fn spawn(
+        &mut self,
+        description: String,
+        critical: bool,
+        completion: Completion,
+        work: impl FnOnce(&AtomicBool) -> Result<Vec<String>, String> + Send + 'static,
+    ) {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&cancel);
+        let handle = thread::spawn(move || work(&flag));
+        self.tasks.push(Task {
+            description,
+            critical,
+            cancel,
+            handle,
+            started: Instant::now(),
+            completion,
+        });
+    }
+
+    /// 是否没有进行中的任务。
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// 是否有进行中的保存。
+    pub fn is_saving(&self) -> bool {
+        self.tasks
+            .iter()
+            .any(|task| matches!(task.completion, Completion::Save { .. }))
+    }
+
+    /// 取出已经结束的任务及其结果。
+    pub fn take_finished(&mut self) -> Vec<(Completion, Result<Vec<String>, String>)> {
+        let (finished, running) = std::mem::take(&mut self.tasks)
+            .into_iter()
+            .partition(|task: &Task| task.handle.is_finished());
+        self.tasks = running;
+        finished
+            .into_iter()
+            .map(|task| {
+                let result = task
+                    .handle
+                    .join()
+                    .unwrap_or_else(|_| Err(format!("{} failed unexpectedly", task.description)));
+                (task.completion, result)
+            })
+            .collect()
+    }
+
+    /// 请求取消所有可以取消的任务。
+    pub fn cancel_cancellable(&self) {
+        for task in self.tasks.iter().filter(|task| !task.critical) {
+            task.cancel.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// 列出进行中的任务及其已运行的时间。
+    pub fn describe(&self) -> Vec<String> {
+        self.tasks
+            .iter()
+            .map(|task| {
+                let elapsed = task.started.elapsed();
+                let cancelled = if task.cancel.load(Ordering::Relaxed) {
+                    ", cancelling"
+                } else {
+                    ""
+                };
+                format!("{} ({:.1}s{cancelled})", task.description, elapsed.as_secs_f64())
+            })
+            .collect()
+    }
+
+    /// 下一次检查任务状态的间隔，没有任务时返回 `None`。
+    pub fn poll_interval(&self) -> Option<Duration> {
+        (!self.is_empty()).then_some(Duration::from_millis(50))
+    }
+}
+
+impl Editor {
+    /// 在后台保存当前缓冲区；有字符无法用文件的编码表示，或磁盘上的文件被其他程序修改过时，
+    /// 先询问是否仍然保存。
+    pub(super) fn save(&mut self) {
+        if self.tasks.is_saving() {
+            self.message_bar
+                .update_message("A save is already in progress.");
+            return;
+        }
+        if self.view.is_lossy_save() {
+            self.prompt_type = PromptType::ConfirmLossySave;
+            self.message_bar.update_message(&format!(
+                "Some characters cannot be saved as {} and will be replaced — save anyway? (y/n)",
+                self.view.encoding()
+            ));
+            return;
+        }
+        self.save_unless_changed_on_disk();
+    }
+
+    /// 处理是否保存会丢失字符的内容的确认：`y` 继续保存，`n` 或 Esc 取消。
+    pub(super) fn process_command_during_confirm_lossy_save(&mut self, command: Command) {
+        match command {
+            Edit(Insert('y' | 'Y')) => {
+                self.prompt_type = PromptType::None;
+                self.save_unless_changed_on_disk();
+            }
+            Edit(Insert(_)) | System(Dismiss) => {
+                self.prompt_type = PromptType::None;
+                self.message_bar.update_message("Save cancelled.");
+            }
+            _ => {}
+        }
+    }
+
+    /// 在后台保存当前缓冲区；磁盘上的文件被其他程序修改过时先询问是否覆盖。
+    fn save_unless_changed_on_disk(&mut self) {
+        if self.view.changed_on_disk() {
+            self.prompt_type = PromptType::ConfirmOverwrite;
+            self.message_bar
+                .update_message("File changed on disk — save anyway? (y overwrite, r reload, n cancel)");
+            return;
+        }
+        self.write_in_background();
+    }
+
+    /// 处理是否覆盖磁盘上被修改过的文件的确认：`y` 覆盖，`r` 改为重新读取磁盘上的文件，
+    /// `n` 或 Esc 取消保存。
+    pub(super) fn process_command_during_confirm_overwrite(&mut self, command: Command) {
+        match command {
+            Edit(Insert('y' | 'Y')) => {
+                self.prompt_type = PromptType::None;
+                self.write_in_background();
+            }
+            Edit(Insert('r' | 'R')) => {
+                self.prompt_type = PromptType::None;
+                self.reload_file();
+            }
+            Edit(Insert(_)) | System(Dismiss) => {
+                self.prompt_type = PromptType::None;
+                self.message_bar.update_message("Save cancelled.");
+            }
+            _ => {}
+        }
+    }
+
+    /// 拍下当前缓冲区的快照，在后台写入文件。
+    fn write_in_background(&mut self) {
+        let warnings = self.view.prepare_save();
+        let Some(snapshot) = self.view.save_snapshot() else {
+            self.message_bar
+                .update_message("ERR: Buffer has no file name");
+            return;
+        };
+        let description = format!("Saving {}", snapshot.path.display());
+        self.message_bar.update_message(&format!("{description}…"));
+        let completion = Completion::Save {
+            buffer_id: self.view.buffer_id(),
+            revision: snapshot.revision(),
+            replace_link: snapshot.replace_link(),
+            warnings,
+        };
+        self.tasks.spawn(description, true, completion, move |_| {
+            snapshot.write().map_err(|err| err.to_string())
+        });
+    }
+
+    /// 处理已经结束的后台任务；正在等待退出时，任务全部结束后继续退出。
+    pub(super) fn check_tasks(&mut self) {
+        for (completion, result) in self.tasks.take_finished() {
+            match (completion, result) {
+                (Completion::Save { buffer_id, revision, replace_link, mut warnings }, Ok(lost)) => {
+                    self.view.finish_buffer_save(buffer_id, revision, replace_link);
+                    warnings.extend(lost);
+                    self.message_bar.update_message(&saved_message(&warnings));
+                }
+                (Completion::Save { .. }, Err(err)) => {
+                    self.message_bar
+                        .update_message(&format!("Error writing file: {err}"));
+                }
+            }
+        }
+        if self.prompt_type != PromptType::WaitForTasks {
+            return;
+        }
+        if self.tasks.is_empty() {
+            self.prompt_type = PromptType::None;
+            self.handle_quit();
+        } else if self.quit_deadline.is_some_and(|deadline| deadline <= Instant::now()) {
+            self.prompt_type = PromptType::ConfirmForceQuit;
+            self.message_bar.update_message(&format!(
+                "Still running: {} — force quit and abandon them? (y/n)",
+                self.tasks.describe().join(", ")
+            ));
+        } else {
+            self.message_bar.update_message(&format!(
+                "Waiting for {} to finish… (Esc to cancel)",
+                self.tasks.describe().join(", ")
+            ));
+        }
+    }
+
+    /// 退出前还有后台任务：取消可以取消的任务，等待其余的任务结束。
+    pub(super) fn wait_for_tasks(&mut self) {
+        self.tasks.cancel_cancellable();
+        self.quit_deadline = Instant::now().checked_add(QUIT_TIMEOUT);
+        self.prompt_type = PromptType::WaitForTasks;
+        self.check_tasks();
+    }
+
+    /// 等待后台任务结束期间，Esc 取消退出，其他按键被忽略。
+    pub(super) fn process_command_during_wait_for_tasks(&mut self, command: Command) {
+        if let System(Dismiss) = command {
+            self.prompt_type = PromptType::None;
+            self.message_bar.update_message("Quit cancelled.");
+        }
+    }
+
+    /// 处理是否强制退出的确认：`y` 放弃仍在运行的任务并退出，其他按键取消退出。
+    pub(super) fn process_command_during_confirm_force_quit(&mut self, command: Command) {
+        if let Edit(Insert('y' | 'Y')) = command {
+            self.should_quit = true;
+        } else {
+            self.prompt_type = PromptType::None;
+            self.message_bar.update_message("Quit cancelled.");
+        }
+    }
+
+    /// 在消息栏中列出后台任务。
+    pub(super) fn show_background_tasks(&mut self) {
+        let tasks = self.tasks.describe();
+        let message = if tasks.is_empty() {
+            String::from("No background tasks.")
+        } else {
+            format!("Background tasks: {}", tasks.join(", "))
+        };
+        self.message_bar.update_message(&message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::{Receiver, channel};
+
+    /// 一个假的慢速保存：直到 `release` 收到消息（或发送端被丢弃）才结束。
+    fn slow_save(editor: &mut Editor, release: Receiver<()>) {
+        let completion = Completion::Save {
+            buffer_id: editor.view.buffer_id(),
+            revision: 0,
+            replace_link: false,
+            warnings: Vec::new(),
+        };
+        editor.tasks.spawn(String::from("Saving slow.txt"), true, completion, move |_| {
+            let _ = release.recv();
+            Ok(Vec::new())
+        });
+    }
+
+    /// 等待后台任务全部结束，并处理它们的结果。
+    fn settle(editor: &mut Editor) {
+        while !editor.tasks.tasks.iter().all(|task| task.handle.is_finished()) {
+            thread::sleep(Duration::from_millis(1));
+        }
+        editor.check_tasks();
+    }
+
+    #[test]
+    fn quitting_waits_for_a_slow_save_and_then_quits() {
+        let mut editor = Editor::default();
+        let (release, waiting) = channel();
+        slow_save(&mut editor, waiting);
+
+        editor.handle_quit();
+        assert!(editor.prompt_type == PromptType::WaitForTasks);
+        assert!(editor.message_bar.text().starts_with("Waiting for Saving slow.txt"));
+        assert!(!editor.should_quit);
+
+        release.send(()).unwrap();
+        settle(&mut editor);
+
+        assert!(editor.should_quit);
+    }
+
+    #[test]
+    fn a_save_that_outlives_the_timeout_asks_before_force_quitting() {
+        let mut editor = Editor::default();
+        let (release, waiting) = channel();
+        slow_save(&mut editor, waiting);
+
+        editor.handle_quit();
+        editor.quit_deadline = Some(Instant::now());
+        editor.check_tasks();
+
+        assert!(editor.prompt_type == PromptType::ConfirmForceQuit);
+        let message = editor.message_bar.text();
+        assert!(message.starts_with("Still running: Saving slow.txt"), "{message}");
+        assert!(!editor.should_quit);
+        editor.process_command(Edit(Insert('y')));
+        assert!(editor.should_quit);
+        drop(release);
+    }
+
+    #[test]
+    fn quitting_cancels_cancellable_tasks_but_not_saves() {
+        let mut editor = Editor::default();
+        let (_release, waiting) = channel();
+        slow_save(&mut editor, waiting);
+        let completion = editor.tasks.tasks[0].completion.clone();
+        editor.tasks.spawn(String::from("Searching"), false, completion, |cancel| {
+            while !cancel.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(1));
+            }
+            Err(String::from("cancelled"))
+        });
+
+        editor.handle_quit();
+
+        let described = editor.tasks.describe();
+        assert!(described[0].starts_with("Saving slow.txt") && !described[0].contains("cancelling"));
+        assert!(described[1].contains("cancelling"));
+        editor.process_command(System(Dismiss));
+        assert!(editor.prompt_type == PromptType::None);
+        assert_eq!(editor.message_bar.text(), "Quit cancelled.");
+    }
+}
@@ -1,11 +1,36 @@
 use crossterm::cursor::{Hide, MoveTo, Show};
-use crossterm::style::{Attribute, Print};
-use crossterm::terminal::{disable_raw_mode, enable_raw_mode, size, Clear, ClearType, DisableLineWrap, EnableLineWrap, EnterAlternateScreen, LeaveAlternateScreen, SetTitle};
-use crossterm::{Command, queue};
-use std::io::{Error, Write, stdout};
+use crossterm::event::{
+    DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture, Event,
+};
+#[cfg(not(test))]
+use crossterm::event::{poll, read};
+use crossterm::style::{
+    Attribute, Color, Print, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor,
+};
+use crossterm::terminal::{Clear, ClearType, DisableLineWrap, EnableLineWrap, EnterAlternateScreen, LeaveAlternateScreen, SetTitle};
+#[cfg(not(test))]
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode, size};
+use crossterm::Command;
+#[cfg(not(test))]
+use crossterm::queue;
+use std::io::Error;
+#[cfg(not(test))]
+use std::io::{Write, stdout};
+use std::mem::take;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use super::{NAME, clipboard};
+use super::truncate::{EllipsisSide, text_width, truncate_to_width};
+
+/// 持久高亮的行的背景色。
+const MARKED_LINE_BACKGROUND: Color = Color::DarkGrey;
+
+/// 最近修改过的行在行号栏中的颜色，按修改的远近排列。
+const CHANGE_COLORS: [Color; 3] = [Color::Green, Color::DarkGreen, Color::DarkCyan];
 
 /// 表示终端的尺寸（宽度和高度）。
-#[derive(Default, Clone, Copy, PartialEq, Eq)]
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
 pub struct Size {
     pub height: usize,  // 终端的高度（行数）。
     pub width: usize,   // 终端的宽度（列数）。
@@ -28,6 +53,60 @@ impl Position {
     }
 }
 
+/// 文本的显示样式。
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Style {
+    Dim,      // 暗淡显示，用于提示性的标记和注释。
+    Italic,   // 斜体显示，用于字符串。
+    Inverted, // 反色显示，用于高亮。
+    Changed(usize), // 最近修改过的行的行号栏，数值越小修改越近。
+}
+
+/// 一段带样式的文本。
+#[derive(Clone)]
+pub struct StyledText {
+    pub text: String,         // 文本内容。
+    pub style: Option<Style>, // 显示样式，`None` 表示默认样式。
+}
+
+/// `initialize` 开启的终端状态。
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Capability {
+    RawMode,         // 原始模式。
+    AlternateScreen, // 替代屏幕。
+    NoLineWrap,      // 关闭自动换行。
+    BracketedPaste,  // 括号粘贴。
+    MouseCapture,    // 接收鼠标事件。
+}
+
+/// 已经开启的终端状态，按开启的顺序排列。
+#[cfg(not(test))]
+static ENABLED: Mutex<Vec<Capability>> = Mutex::new(Vec::new());
+
+#[cfg(test)]
+thread_local! {
+    /// 测试中每个线程各有一个无头终端，开启的状态也各自记录。
+    static ENABLED: Mutex<Vec<Capability>> = const { Mutex::new(Vec::new()) };
+}
+
+/// 访问已经开启的终端状态；锁中毒（在持有锁时崩溃）时照常访问，恢复终端仍然要进行。
+#[cfg(not(test))]
+fn with_enabled<T>(f: impl FnOnce(&mut Vec<Capability>) -> T) -> T {
+    match ENABLED.lock() {
+        Ok(mut enabled) => f(&mut enabled),
+        Err(poisoned) => f(&mut poisoned.into_inner()),
+    }
+}
+
+/// 访问已经开启的终端状态；锁中毒（在持有锁时崩溃）时照常访问，恢复终端仍然要进行。
+#[cfg(test)]
+fn with_enabled<T>(f: impl FnOnce(&mut Vec<Capability>) -> T) -> T {
+    ENABLED.with(|enabled| match enabled.lock() {
+        Ok(mut enabled) => f(&mut enabled),
+        Err(poisoned) => f(&mut poisoned.into_inner()),
+    })
+}
+
 /// `Terminal` 结构体封装了终端的行为和操作。
 pub struct Terminal;
 
@@ -35,25 +114,73 @@ impl Terminal {
     // ==================== 初始化和终止 ====================
 
     /// 初始化终端，进入原始模式并清理屏幕。
+    ///
+    /// 每开启一项终端状态就记录下来，`terminate` 只恢复确实开启过的状态。
     pub fn initialize() -> Result<(), Error> {
-        enable_raw_mode()?;
-        Self::enter_alternate_screen()?;
-        Self::disable_line_wrap()?;
+        for capability in [
+            Capability::RawMode,
+            Capability::AlternateScreen,
+            Capability::NoLineWrap,
+            Capability::BracketedPaste,
+            Capability::MouseCapture,
+        ] {
+            Self::enable(capability)?;
+        }
         Self::clear_screen()?;
         Self::execute()?;
         Ok(())
     }
 
     /// 终止终端，恢复到正常模式。
+    ///
+    /// 按与开启相反的顺序逐项恢复 `initialize` 开启过的状态；某一项失败时继续恢复其余各项，
+    /// 最后在标准错误中列出失败的项。可以重复调用（例如先在 panic 钩子中、再在退出时），
+    /// 已经恢复的状态不会再次恢复。
     pub fn terminate() -> Result<(), Error> {
-        Self::leave_alternate_screen()?;
-        Self::enable_line_wrap()?;
-        Self::show_caret()?;
-        Self::execute()?;
-        disable_raw_mode()?;
+        let enabled = with_enabled(take);
+        let _ = Self::show_caret();
+        let mut failures = Vec::new();
+        for capability in enabled.into_iter().rev() {
+            if let Err(err) = Self::restore(capability) {
+                failures.push(format!("{capability:?}: {err}"));
+            }
+        }
+        if failures.is_empty() {
+            return Ok(());
+        }
+        let message = format!("could not restore terminal ({})", failures.join(", "));
+        eprintln!("{NAME}: {message}");
+        Err(Error::other(message))
+    }
+
+    /// 开启一项终端状态，成功后记录下来。
+    fn enable(capability: Capability) -> Result<(), Error> {
+        match capability {
+            Capability::RawMode => Self::set_raw_mode(true)?,
+            Capability::AlternateScreen => Self::enter_alternate_screen()?,
+            Capability::NoLineWrap => Self::disable_line_wrap()?,
+            Capability::BracketedPaste => Self::queue_command(EnableBracketedPaste)?,
+            Capability::MouseCapture => Self::queue_command(EnableMouseCapture)?,
+        }
+        with_enabled(|enabled| enabled.push(capability));
         Ok(())
     }
 
+    /// 恢复一项终端状态并立即输出，使各项按顺序生效。
+    fn restore(capability: Capability) -> Result<(), Error> {
+        match capability {
+            Capability::RawMode => Self::set_raw_mode(false),
+            Capability::AlternateScreen => Self::leave_alternate_screen().and_then(|()| Self::execute()),
+            Capability::NoLineWrap => Self::enable_line_wrap().and_then(|()| Self::execute()),
+            Capability::BracketedPaste => {
+                Self::queue_command(DisableBracketedPaste).and_then(|()| Self::execute())
+            }
+            Capability::MouseCapture => {
+                Self::queue_command(DisableMouseCapture).and_then(|()| Self::execute())
+            }
+        }
+    }
+
     // ==================== 屏幕操作 ====================
 
     /// 清理整个屏幕。
@@ -105,9 +232,75 @@ impl Terminal {
         Ok(())
     }
 
+    /// 在指定行打印由多段带样式文本组成的内容。
+    pub fn print_styled_row(row: usize, segments: &[StyledText]) -> Result<(), Error> {
+        Self::move_caret_to(Position { col: 0, row })?;
+        Self::clear_line()?;
+        Self::print_styled(segments)
+    }
+
+    /// 在指定行打印带背景色的内容，用于带有持久高亮的行。
+    ///
+    /// 先设置背景色再清除该行，使背景色延伸到行尾；每段样式重置后重新设置背景色。
+    pub fn print_tinted_row(row: usize, segments: &[StyledText]) -> Result<(), Error> {
+        Self::move_caret_to(Position { col: 0, row })?;
+        Self::queue_command(SetBackgroundColor(MARKED_LINE_BACKGROUND))?;
+        Self::clear_line()?;
+        for segment in segments {
+            if let Some(style) = segment.style {
+                Self::set_style(style)?;
+                Self::print(&segment.text)?;
+                Self::queue_command(SetAttribute(Attribute::Reset))?;
+                Self::queue_command(SetBackgroundColor(MARKED_LINE_BACKGROUND))?;
+            } else {
+                Self::print(&segment.text)?;
+            }
+        }
+        Self::queue_command(ResetColor)
+    }
+
+    /// 在当前光标位置继续打印带样式的文本。
+    pub fn print_styled(segments: &[StyledText]) -> Result<(), Error> {
+        for segment in segments {
+            if let Some(style) = segment.style {
+                Self::set_style(style)?;
+                Self::print(&segment.text)?;
+                Self::queue_command(SetAttribute(Attribute::Reset))?;
+            } else {
+                Self::print(&segment.text)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 设置之后输出的文本的样式，输出后用 `Attribute::Reset` 恢复。
+    fn set_style(style: Style) -> Result<(), Error> {
+        match style {
+            Style::Dim => Self::queue_command(SetAttribute(Attribute::Dim)),
+            Style::Italic => Self::queue_command(SetAttribute(Attribute::Italic)),
+            Style::Inverted => Self::queue_command(SetAttribute(Attribute::Reverse)),
+            Style::Changed(bucket) => {
+                let color = CHANGE_COLORS
+                    .get(bucket)
+                    .or(CHANGE_COLORS.last())
+                    .copied()
+                    .unwrap_or(Color::Reset);
+                Self::queue_command(SetForegroundColor(color))
+            }
+        }
+    }
+
     // ==================== 尺寸获取 ====================
 
     /// 获取终端的尺寸（宽度和高度）。
+    #[cfg(test)]
+    #[allow(clippy::unnecessary_wraps)]
+    pub fn size() -> Result<Size, Error> {
+        Ok(headless::size())
+    }
+
+    /// 获取终端的尺寸（宽度和高度）。
+    #[cfg(not(test))]
     pub fn size() -> Result<Size, Error> {
         let (width_u16, height_u16) = size()?;
         #[allow(clippy::as_conversions)]
@@ -117,20 +310,81 @@ impl Terminal {
         Ok(Size { height, width })
     }
 
+    // ==================== 输入事件 ====================
+
+    /// 在 `timeout` 内等待输入事件，有事件可读时返回 `true`。
+    #[cfg(not(test))]
+    pub fn poll(timeout: Duration) -> Result<bool, Error> {
+        poll(timeout)
+    }
+
+    /// 在 `timeout` 内等待输入事件，有事件可读时返回 `true`。
+    #[cfg(test)]
+    #[allow(clippy::unnecessary_wraps)]
+    pub fn poll(_timeout: Duration) -> Result<bool, Error> {
+        Ok(headless::poll_event())
+    }
+
+    /// 读取一个输入事件，没有事件时阻塞。
+    #[cfg(not(test))]
+    pub fn read() -> Result<Event, Error> {
+        read()
+    }
+
+    /// 读取一个输入事件；无头终端中没有事件时返回错误，而不是永远阻塞。
+    #[cfg(test)]
+    pub fn read() -> Result<Event, Error> {
+        headless::read_event()
+    }
+
     // ==================== 内部辅助方法 ====================
 
     /// 刷新终端，执行所有排队的命令。
+    #[cfg(not(test))]
     pub fn execute() -> Result<(), Error> {
         stdout().flush()?;
         Ok(())
     }
 
+    /// 刷新终端，执行所有排队的命令。
+    #[cfg(test)]
+    pub fn execute() -> Result<(), Error> {
+        headless::write(None)
+    }
+
     /// 将命令加入队列。
+    #[cfg(not(test))]
     fn queue_command(command: impl Command) -> Result<(), Error> {
         queue!(stdout(), command)?;
         Ok(())
     }
 
+    /// 将命令加入队列。
+    #[cfg(test)]
+    fn queue_command(command: impl Command) -> Result<(), Error> {
+        let mut ansi = String::new();
+        command.write_ansi(&mut ansi).map_err(Error::other)?;
+        headless::write(Some(&ansi))
+    }
+
+    /// 进入或退出原始模式。
+    #[cfg(not(test))]
+    fn set_raw_mode(enabled: bool) -> Result<(), Error> {
+        if enabled {
+            enable_raw_mode()
+        } else {
+            disable_raw_mode()
+        }
+    }
+
+    /// 进入或退出原始模式。
+    #[cfg(test)]
+    fn set_raw_mode(enabled: bool) -> Result<(), Error> {
+        headless::write(None)?;
+        headless::with(|terminal| terminal.raw_mode = enabled);
+        Ok(())
+    }
+
     /// 进入替代屏幕。
     pub fn enter_alternate_screen() -> Result<(), Error> {
         Self::queue_command(EnterAlternateScreen)?;
@@ -155,19 +409,248 @@ impl Terminal {
         Ok(())
     }
 
-    /// 设置终端标题。
+    /// 将文本复制到系统剪贴板：总是发送 OSC 52 转义序列，在本机会话中同时交给剪贴板程序，
+    /// 以覆盖不支持 OSC 52 的终端。
+    pub fn copy_to_clipboard(text: &str) -> Result<(), Error> {
+        Self::print(&clipboard::osc52_sequence(text))?;
+        let _ = clipboard::copy_with_system_tool(text);
+        Ok(())
+    }
+
+    /// 发出提示音。
+    pub fn bell() -> Result<(), Error> {
+        Self::print("\x07")
+    }
+
+    /// 设置终端标题。控制字符会提前结束或破坏设置标题的转义序列，因此被去掉。
     pub fn set_title(title: &str) -> Result<(), Error> {
+        let title: String = title.chars().filter(|ch| !ch.is_control()).collect();
         Self::queue_command(SetTitle(title))?;
         Ok(())
     }
 
+    /// 在指定行反色打印文本，按显示宽度截断或用空格补齐到整行。
     pub fn print_inverted_row(row: usize, line_text: &str) -> Result<(), Error>{
         let width = Self::size()?.width;
+        let text = truncate_to_width(line_text, width, EllipsisSide::End);
+        let padding = width.saturating_sub(text_width(&text));
         Self::print_row(row, &format!(
-            "{}{:width$.width$}{}",
+            "{}{text}{}{}",
             Attribute::Reverse,
-            line_text,
+            " ".repeat(padding),
             Attribute::Reset,
         ))
     }
-}
\ No newline at end of file
+}
+
+/// 测试中代替真实终端的无头终端：输出记录在当前线程的缓冲区中，尺寸固定，
+/// 还可以让之后的第 K 次写入失败，模拟终端在一帧中途断开。
+#[cfg(test)]
+pub mod headless {
+    use super::Size;
+    use crossterm::event::Event;
+    use std::{cell::RefCell, collections::VecDeque, io::Error};
+
+    /// 无头终端的状态，每个测试线程各有一份。
+    pub struct Headless {
+        pub output: String,          // 到目前为止输出的内容，包括转义序列。
+        pub size: Size,              // 报告的终端尺寸。
+        pub raw_mode: bool,          // 是否处于原始模式。
+        pub fail_after: Option<usize>, // 再成功写入这么多次之后，下一次写入失败。
+        pub events: VecDeque<Option<Event>>, // 待读取的输入事件，`None` 表示输入的间隙。
+    }
+
+    thread_local! {
+        static TERMINAL: RefCell<Headless> = const {
+            RefCell::new(Headless {
+                output: String::new(),
+                size: Size { height: 24, width: 80 },
+                raw_mode: false,
+                fail_after: None,
+                events: VecDeque::new(),
+            })
+        };
+    }
+
+    /// 读取或修改当前线程的无头终端。
+    pub fn with<T>(f: impl FnOnce(&mut Headless) -> T) -> T {
+        TERMINAL.with_borrow_mut(f)
+    }
+
+    /// 设置报告的终端尺寸。
+    pub fn set_size(size: Size) {
+        with(|terminal| terminal.size = size);
+    }
+
+    /// 报告的终端尺寸。
+    pub fn size() -> Size {
+        with(|terminal| terminal.size)
+    }
+
+    /// 取出到目前为止的输出。
+    pub fn take_output() -> String {
+        with(|terminal| std::mem::take(&mut terminal.output))
+    }
+
+    /// 从现在起第 `k` 次写入（从 1 开始计）以及之后的写入都失败，刷新也算一次写入。
+    pub fn fail_on_write(k: usize) {
+        with(|terminal| terminal.fail_after = Some(k.saturating_sub(1)));
+    }
+
+    /// 让写入重新成功。
+    pub fn stop_failing() {
+        with(|terminal| terminal.fail_after = None);
+    }
+
+    /// 取出到目前为止输出的可见文本，去掉转义序列。
+    pub fn take_text() -> String {
+        let output = take_output();
+        let mut text = String::new();
+        let mut chars = output.chars();
+        while let Some(ch) = chars.next() {
+            if ch != '\x1b' {
+                text.push(ch);
+                continue;
+            }
+            // `ESC [ ... 字母` 为控制序列，`ESC ] ... BEL` 为标题等操作系统命令。
+            match chars.next() {
+                Some('[') => {
+                    let _ = chars.find(char::is_ascii_alphabetic);
+                }
+                Some(']') => {
+                    let _ = chars.find(|ch| *ch == '\x07');
+                }
+                _ => {}
+            }
+        }
+        text
+    }
+
+    /// 加入一批同时到达的输入事件，之后是一个间隙：`poll` 在间隙处返回 `false`，
+    /// 就像真实终端上下一批输入还没有到达。
+    pub fn send_events(events: impl IntoIterator<Item = Event>) {
+        with(|terminal| {
+            terminal.events.extend(events.into_iter().map(Some));
+            terminal.events.push_back(None);
+        });
+    }
+
+    /// 是否有输入事件可读；遇到间隙时消耗掉它并返回 `false`。
+    pub(super) fn poll_event() -> bool {
+        with(|terminal| match terminal.events.front() {
+            Some(Some(_)) => true,
+            Some(None) => {
+                terminal.events.pop_front();
+                false
+            }
+            None => false,
+        })
+    }
+
+    /// 读取下一个输入事件，跳过间隙。
+    pub(super) fn read_event() -> Result<Event, Error> {
+        with(|terminal| {
+            while let Some(event) = terminal.events.pop_front() {
+                if let Some(event) = event {
+                    return Ok(event);
+                }
+            }
+            Err(Error::other("headless terminal: no more input"))
+        })
+    }
+
+    /// 一次写入：输出命令的转义序列，或者只是刷新（`None`）。
+    pub(super) fn write(ansi: Option<&str>) -> Result<(), Error> {
+        with(|terminal| {
+            match &mut terminal.fail_after {
+                Some(0) => return Err(Error::other("headless terminal: injected write failure")),
+                Some(remaining) => *remaining = remaining.saturating_sub(1),
+                None => {}
+            }
+            terminal.output.push_str(ansi.unwrap_or_default());
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 命令输出的转义序列。
+    fn ansi(command: impl Command) -> String {
+        let mut ansi = String::new();
+        command.write_ansi(&mut ansi).unwrap();
+        ansi
+    }
+
+    /// 恢复一项状态时输出的转义序列；原始模式不经过输出。
+    fn restore_sequence(capability: Capability) -> String {
+        match capability {
+            Capability::RawMode => String::new(),
+            Capability::AlternateScreen => ansi(LeaveAlternateScreen),
+            Capability::NoLineWrap => ansi(EnableLineWrap),
+            Capability::BracketedPaste => ansi(DisableBracketedPaste),
+            Capability::MouseCapture => ansi(DisableMouseCapture),
+        }
+    }
+
+    #[test]
+    fn terminate_restores_exactly_what_was_enabled_in_reverse_order() {
+        let mut subsets = Vec::new();
+        // 让初始化在第 k 次写入时失败，得到开启了不同子集的终端，再像 panic 钩子那样恢复。
+        for k in 1..=20 {
+            headless::fail_on_write(k);
+            let initialized = Terminal::initialize();
+            headless::stop_failing();
+            headless::take_output();
+            let enabled = with_enabled(|enabled| enabled.clone());
+            assert_eq!(headless::with(|terminal| terminal.raw_mode), !enabled.is_empty());
+
+            Terminal::terminate().unwrap();
+
+            let expected: String = enabled.iter().rev().copied().map(restore_sequence).collect();
+            assert_eq!(headless::take_output(), format!("{}{expected}", ansi(Show)));
+            assert!(!headless::with(|terminal| terminal.raw_mode));
+            // 已经恢复的状态不会再次恢复。
+            Terminal::terminate().unwrap();
+            assert_eq!(headless::take_output(), ansi(Show));
+
+            let complete = initialized.is_ok();
+            subsets.push(enabled);
+            if complete {
+                break;
+            }
+        }
+        let sizes: Vec<usize> = subsets.iter().map(Vec::len).collect();
+        assert_eq!(sizes, vec![0, 1, 2, 3, 4, 5, 5, 5]);
+    }
+
+    #[test]
+    fn failed_restores_do_not_stop_the_others() {
+        Terminal::initialize().unwrap();
+        headless::take_output();
+
+        // 显示光标之后的写入全部失败：每一项仍然按顺序尝试，并全部列出。
+        headless::fail_on_write(2);
+        let result = Terminal::terminate();
+        headless::stop_failing();
+
+        let err = result.unwrap_err().to_string();
+        let failed: Vec<&str> = err
+            .split(", ")
+            .filter_map(|part| part.split(':').next())
+            .collect();
+        assert_eq!(
+            failed,
+            [
+                "could not restore terminal (MouseCapture",
+                "BracketedPaste",
+                "NoLineWrap",
+                "AlternateScreen",
+                "RawMode"
+            ]
+        );
+        assert!(with_enabled(|enabled| enabled.is_empty()));
+    }
+}
@@ -0,0 +1,163 @@
+use std::time::{Duration, Instant};
+
+/// 降级模式下两帧之间的最小间隔（约 20fps）。
+const DEGRADED_FRAME_INTERVAL: Duration = Duration::from_millis(50);
+/// 连续多少帧刷新过慢后进入降级模式。
+const SLOW_FRAMES_TO_DEGRADE: u8 = 3;
+/// 连续多少帧刷新恢复正常后退出降级模式。
+const FAST_FRAMES_TO_RECOVER: u8 = 10;
+
+/// 渲染模式：自动检测，或手动强制为完整/降级模式。
+#[derive(Default, Clone, Copy, Eq, PartialEq, Debug)]
+pub enum RenderMode {
+    #[default]
+    Auto,
+    Full,
+    Degraded,
+}
+
+/// 根据每帧刷新终端所花的时间，自动在完整渲染和降级渲染之间切换。
+///
+/// 降级模式下会合并重绘并关闭装饰性的渲染，用于高延迟的终端连接。
+#[derive(Default)]
+pub struct RenderThrottle {
+    mode: RenderMode,           // 当前的渲染模式设置。
+    threshold: Duration,        // 判定一帧“过慢”的刷新耗时。
+    degraded: bool,             // 自动检测的结果是否为降级。
+    slow_frames: u8,            // 连续过慢的帧数。
+    fast_frames: u8,            // 连续正常的帧数。
+    last_frame: Option<Instant>, // 上一帧完成的时间。
+}
+
+impl RenderThrottle {
+    /// 构造方法。
+    pub fn new(mode: RenderMode, threshold: Duration) -> Self {
+        Self {
+            mode,
+            threshold,
+            ..Self::default()
+        }
+    }
+
+    /// 设置渲染模式。
+    pub const fn set_mode(&mut self, mode: RenderMode) {
+        self.mode = mode;
+    }
+
+    /// 记录一帧刷新所花的时间，并更新降级状态。
+    pub fn record_flush(&mut self, duration: Duration) {
+        self.last_frame = Some(Instant::now());
+        if duration > self.threshold {
+            self.fast_frames = 0;
+            self.slow_frames = self.slow_frames.saturating_add(1);
+            if self.slow_frames >= SLOW_FRAMES_TO_DEGRADE {
+                self.degraded = true;
+            }
+        } else {
+            self.slow_frames = 0;
+            self.fast_frames = self.fast_frames.saturating_add(1);
+            if self.fast_frames >= FAST_FRAMES_TO_RECOVER {
+                self.degraded = false;
+            }
+        }
+    }
+
+    /// 当前是否处于降级模式。
+    pub const fn is_degraded(&self) -> bool {
+        match self.mode {
+            RenderMode::Auto => self.degraded,
+            RenderMode::Full => false,
+            RenderMode::Degraded => true,
+        }
+    }
+
+    /// 降级模式下允许绘制下一帧的最早时间。
+    pub fn next_frame_at(&self) -> Instant {
+        self.last_frame
+            .map_or_else(Instant::now, |last| {
+                last.checked_add(DEGRADED_FRAME_INTERVAL).unwrap_or(last)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{io::Write, thread};
+
+    const THRESHOLD: Duration = Duration::from_millis(10);
+    const SLOW: Duration = Duration::from_millis(20);
+
+    /// 模拟的终端连接：每次刷新先等待 `delay`，像高延迟的 SSH 连接一样。
+    struct SlowWriter {
+        delay: Duration,
+    }
+
+    impl Write for SlowWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            thread::sleep(self.delay);
+            Ok(())
+        }
+    }
+
+    /// 像主循环一样输出并刷新一帧，把刷新的耗时交给 `throttle`。
+    fn frame(throttle: &mut RenderThrottle, writer: &mut SlowWriter) {
+        writer.write_all(b"frame").unwrap();
+        let started = Instant::now();
+        writer.flush().unwrap();
+        throttle.record_flush(started.elapsed());
+    }
+
+    #[test]
+    fn consistently_slow_flushes_degrade_and_fast_ones_recover() {
+        let mut throttle = RenderThrottle::new(RenderMode::Auto, THRESHOLD);
+        let mut slow = SlowWriter { delay: SLOW };
+        let mut fast = SlowWriter { delay: Duration::ZERO };
+
+        frame(&mut throttle, &mut slow);
+        frame(&mut throttle, &mut slow);
+        frame(&mut throttle, &mut fast);
+        frame(&mut throttle, &mut slow);
+        frame(&mut throttle, &mut slow);
+        assert!(!throttle.is_degraded(), "a fast frame resets the slow streak");
+        frame(&mut throttle, &mut slow);
+        assert!(throttle.is_degraded());
+
+        for _ in 1..FAST_FRAMES_TO_RECOVER {
+            frame(&mut throttle, &mut fast);
+        }
+        assert!(throttle.is_degraded());
+        frame(&mut throttle, &mut fast);
+        assert!(!throttle.is_degraded());
+    }
+
+    #[test]
+    fn degraded_frames_are_spaced_to_about_twenty_per_second() {
+        let mut throttle = RenderThrottle::new(RenderMode::Auto, THRESHOLD);
+        let before = Instant::now();
+
+        frame(&mut throttle, &mut SlowWriter { delay: Duration::ZERO });
+
+        assert!(throttle.next_frame_at() >= before + DEGRADED_FRAME_INTERVAL);
+    }
+
+    #[test]
+    fn manual_modes_override_the_measurements() {
+        let mut throttle = RenderThrottle::new(RenderMode::Full, THRESHOLD);
+        let mut slow = SlowWriter { delay: SLOW };
+        for _ in 0..SLOW_FRAMES_TO_DEGRADE {
+            frame(&mut throttle, &mut slow);
+        }
+        assert!(!throttle.is_degraded());
+
+        throttle.set_mode(RenderMode::Auto);
+        assert!(throttle.is_degraded());
+        throttle.set_mode(RenderMode::Degraded);
+        frame(&mut throttle, &mut SlowWriter { delay: Duration::ZERO });
+        assert!(throttle.is_degraded());
+    }
+}
@@ -0,0 +1,92 @@
+use super::{
+    Editor, PromptType,
+    command::{
+        Command::{self, Edit, Move, System},
+        Edit::{Delete, DeleteBackward, InsertNewline},
+        System::Dismiss,
+    },
+    overlay::{Overlay, sanitize},
+    terminal::{Style, StyledText},
+    uicomponent::UIComponent,
+};
+
+impl Editor {
+    /// 在列表中显示回收站中的删除。
+    pub(super) fn list_trash(&mut self) {
+        if self.view.trash().entries().is_empty() {
+            self.message_bar.update_message("Trash is empty.");
+            return;
+        }
+        self.open_trash_list(0);
+        self.prompt_type = PromptType::TrashList;
+    }
+
+    /// 处理回收站列表中的按键：回车将选中的删除插入到当前行之前，Delete 丢弃，Esc 关闭。
+    pub(super) fn process_command_during_trash_list(&mut self, command: Command) {
+        match command {
+            Move(move_command, _) => self.overlay.handle_move_command(move_command),
+            Edit(InsertNewline) => {
+                let selected = self.overlay.selected();
+                self.close_trash_list();
+                if !self.check_writable() {
+                    return;
+                }
+                if let Some(lines) = self.view.restore_trash(selected) {
+                    self.message_bar
+                        .update_message(&format!("Restored {lines} lines from trash."));
+                }
+            }
+            Edit(Delete | DeleteBackward) => {
+                let selected = self.overlay.selected();
+                self.view.remove_trash(selected);
+                if self.view.trash().entries().is_empty() {
+                    self.close_trash_list();
+                    self.message_bar.update_message("Trash is empty.");
+                } else {
+                    self.open_trash_list(selected);
+                }
+            }
+            System(Dismiss) => self.close_trash_list(),
+            _ => {}
+        }
+    }
+
+    /// 为可见区域内尚未生成内容的列表项生成内容。
+    pub(super) fn fill_trash_list(&mut self) {
+        for index in self.overlay.missing_rows() {
+            if let Some(entry) = self.view.trash().entries().get(index) {
+                let first_line = entry.text.lines().next().unwrap_or_default();
+                let row = vec![
+                    StyledText {
+                        text: format!(
+                            "{:>12}  {:>6} lines  {}  ",
+                            entry.age(),
+                            entry.lines,
+                            sanitize(&entry.source)
+                        ),
+                        style: None,
+                    },
+                    StyledText {
+                        text: sanitize(first_line),
+                        style: Some(Style::Dim),
+                    },
+                ];
+                self.overlay.set_row(index, row);
+            }
+        }
+    }
+
+    /// 按当前的回收站重新生成列表，并选中第 `selected` 项。
+    fn open_trash_list(&mut self, selected: usize) {
+        let count = self.view.trash().entries().len();
+        let title = format!("Trash: {count} deletion(s) (Enter insert, Delete discard, Esc close)");
+        self.overlay = Overlay::new(&title, count, false, self.view_size());
+        self.overlay.select(selected);
+    }
+
+    /// 关闭回收站列表，恢复显示视图。
+    fn close_trash_list(&mut self) {
+        self.prompt_type = PromptType::None;
+        self.view.set_needs_redraw(true);
+    }
+}
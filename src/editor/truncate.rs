@@ -0,0 +1,149 @@
+use super::{
+    terminal::StyledText,
+    view::line::{Line, TabWidth},
+};
+
+/// 截断时省略号的位置。
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum EllipsisSide {
+    Start, // 保留末尾，省略开头，适合路径等末尾更重要的文本。
+    End,   // 保留开头，省略末尾。
+}
+
+/// 截断时显示的省略号，占一列。
+const ELLIPSIS: char = '…';
+
+/// 文本在终端中的显示宽度。
+pub fn text_width(text: &str) -> usize {
+    Line::from(text).width(TabWidth::default())
+}
+
+/// 将文本截断到不超过 `width` 列，被截掉的一侧显示省略号。
+///
+/// 宽度按字形计算，不会拆开全角字符或组合字符；放不下的全角字符整个省略，
+/// 因此结果可能比 `width` 少一列。
+pub fn truncate_to_width(text: &str, width: usize, side: EllipsisSide) -> String {
+    let line = Line::from(text);
+    let tab_width = TabWidth::default();
+    let total = line.width(tab_width);
+    if total <= width {
+        return text.to_string();
+    }
+    if width == 0 {
+        return String::new();
+    }
+    let keep = width.saturating_sub(1);
+    let text = line.to_string();
+    match side {
+        EllipsisSide::End => {
+            let mut end = line.grapheme_index_at_width(keep, tab_width);
+            if line.width_until(end, tab_width) > keep {
+                end = end.saturating_sub(1);
+            }
+            let end = line.byte_index_at_grapheme(end);
+            format!("{}{ELLIPSIS}", text.get(..end).unwrap_or_default())
+        }
+        EllipsisSide::Start => {
+            let start = line.grapheme_index_at_width(total.saturating_sub(keep), tab_width);
+            let start = line.byte_index_at_grapheme(start);
+            format!("{ELLIPSIS}{}", text.get(start..).unwrap_or_default())
+        }
+    }
+}
+
+/// 将多段带样式的文本截断到不超过 `width` 列，在末尾显示省略号。
+pub fn truncate_segments(segments: &[StyledText], width: usize) -> Vec<StyledText> {
+    let total: usize = segments
+        .iter()
+        .map(|segment| text_width(&segment.text))
+        .fold(0, usize::saturating_add);
+    if total <= width {
+        return segments.to_vec();
+    }
+    let mut remaining = width;
+    let mut truncated = Vec::new();
+    for segment in segments {
+        let segment_width = text_width(&segment.text);
+        // 最后一段要留出省略号的位置，恰好用完剩余宽度的一段也需要截断。
+        if segment_width < remaining {
+            remaining = remaining.saturating_sub(segment_width);
+            truncated.push(segment.clone());
+            continue;
+        }
+        truncated.push(StyledText {
+            text: truncate_to_width(&format!("{}{ELLIPSIS}", segment.text), remaining, EllipsisSide::End),
+            style: segment.style,
+        });
+        break;
+    }
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::terminal::Style;
+
+    const FILE_NAME: &str = "中文文件名.txt";
+    const MESSAGE: &str = "🎉 保存成功 👍🏽 done";
+
+    #[test]
+    fn cjk_file_names_are_cut_between_characters() {
+        assert_eq!(truncate_to_width(FILE_NAME, 14, EllipsisSide::End), FILE_NAME);
+        assert_eq!(truncate_to_width(FILE_NAME, 13, EllipsisSide::End), "中文文件名.t…");
+        // 放不下的全角字符整个省略，结果比给定的宽度少一列。
+        assert_eq!(truncate_to_width(FILE_NAME, 6, EllipsisSide::End), "中文…");
+        assert_eq!(truncate_to_width(FILE_NAME, 6, EllipsisSide::Start), "….txt");
+        assert_eq!(truncate_to_width(FILE_NAME, 7, EllipsisSide::Start), "…名.txt");
+    }
+
+    #[test]
+    fn emoji_and_modifier_sequences_stay_whole() {
+        assert_eq!(truncate_to_width(MESSAGE, 3, EllipsisSide::End), "🎉…");
+        assert_eq!(truncate_to_width(MESSAGE, 15, EllipsisSide::End), "🎉 保存成功 👍🏽…");
+        assert_eq!(truncate_to_width(MESSAGE, 8, EllipsisSide::Start), "…👍🏽 done");
+        assert_eq!(truncate_to_width("cafe\u{301}s!", 5, EllipsisSide::End), "cafe\u{301}…");
+    }
+
+    #[test]
+    fn widths_zero_and_one_leave_nothing_or_only_the_ellipsis() {
+        for side in [EllipsisSide::Start, EllipsisSide::End] {
+            assert_eq!(truncate_to_width(FILE_NAME, 0, side), "");
+            assert_eq!(truncate_to_width(FILE_NAME, 1, side), "…");
+            let two = truncate_to_width(MESSAGE, 2, side);
+            assert_eq!(two.matches(ELLIPSIS).count(), 1, "{two:?}");
+            assert_eq!(truncate_to_width("", 0, side), "");
+            assert_eq!(truncate_to_width("a", 1, side), "a");
+        }
+    }
+
+    #[test]
+    fn the_result_never_exceeds_the_width_and_keeps_one_side_intact() {
+        for text in [FILE_NAME, MESSAGE] {
+            for width in 0..=text_width(text).saturating_add(1) {
+                let end = truncate_to_width(text, width, EllipsisSide::End);
+                let start = truncate_to_width(text, width, EllipsisSide::Start);
+                assert!(text_width(&end) <= width, "{end:?} at {width}");
+                assert!(text_width(&start) <= width, "{start:?} at {width}");
+                assert!(text.starts_with(end.trim_end_matches(ELLIPSIS)));
+                assert!(text.ends_with(start.trim_start_matches(ELLIPSIS)));
+            }
+        }
+    }
+
+    #[test]
+    fn styled_segments_are_cut_in_the_segment_that_overflows() {
+        let segments = [
+            StyledText { text: String::from("文件 "), style: None },
+            StyledText { text: String::from("修改过"), style: Some(Style::Dim) },
+        ];
+
+        let truncated = truncate_segments(&segments, 8);
+
+        assert_eq!(truncated.len(), 2);
+        assert_eq!(truncated[0].text, "文件 ");
+        assert_eq!(truncated[1].text, "修…");
+        assert!(matches!(truncated[1].style, Some(Style::Dim)));
+        assert!(truncate_segments(&segments, 0).iter().all(|segment| segment.text.is_empty()));
+    }
+}
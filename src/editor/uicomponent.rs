@@ -10,18 +10,13 @@ pub trait UIComponent{
         self.set_needs_redraw(true);
     }
     fn set_size(&mut self, size: Size);
-    fn render(&mut self, origin_y: usize){
+    /// 需要时重绘组件；绘制失败时保持需要重绘的状态，下一帧重新完整绘制。
+    fn render(&mut self, origin_y: usize) -> Result<(), Error> {
         if self.needs_redraw() {
-            match self.draw(origin_y) {
-                Ok(()) => self.set_needs_redraw(false),
-                Err(err) => {
-                    #[cfg(debug_assertions)]
-                    {
-                        panic!("Could not render component: {err:?}");
-                    }
-                }
-            }
+            self.draw(origin_y)?;
+            self.set_needs_redraw(false);
         }
+        Ok(())
     }
     fn draw(&mut self, origin_y: usize) -> Result<(), Error>;
 }
\ No newline at end of file
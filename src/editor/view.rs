@@ -1,30 +1,155 @@
-use std::{cmp::min, io::Error};
+use std::{
+    cmp::{Ordering, min},
+    fs,
+    io::{Error, ErrorKind, Read},
+    iter,
+    ops::Range,
+    path::{Path, PathBuf},
+    time::{Instant, SystemTime},
+};
 mod buffer;
-mod line;
+mod bufferlist;
+mod highlighter;
+mod history;
+mod indentation;
+pub mod line;
+mod linemarks;
+mod recentchanges;
+pub mod swapfile;
+mod swappoint;
+pub mod textformat;
+pub mod trash;
 use super::{
     NAME, VERSION,
+    atomicwrite::{self, Attributes},
+    backup::Backup,
+    config::Config,
+    encoding::FileEncoding,
+    diff::{DiffLine, diff_lines},
     documentstatus::DocumentStatus,
+    linerange::LineRange,
+    stash,
     command::{Edit, Move},
-    terminal::{Position, Size, Terminal},
+    terminal::{Position, Size, Style, StyledText, Terminal},
     uicomponent::UIComponent,
 };
-use buffer::Buffer;
-use line::Line;
+use buffer::{Buffer, TextStats};
+use bufferlist::BufferSlot;
+use highlighter::{LOOKAHEAD, Span};
+use history::{CaretState, StepKind};
+use line::{Line, TabWidth};
+use textformat::{LineEnding, TextFormat};
+use unicode_width::UnicodeWidthStr;
+use trash::{Trash, TrashLimits};
+
+const LEFT_MARKER: char = '‹';  // 左侧有被隐藏内容时显示的标记。
+const RIGHT_MARKER: char = '›'; // 右侧有被隐藏内容时显示的标记。
+const CHANGE_MARKER: char = '▎'; // 不显示行号时，最近修改过的行在行号栏中的标记。
+const SWAP_POINT_MARKER: char = '•'; // 行号栏中标出记下的切换位置所在的行。
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Default, Eq, PartialEq, Debug)]
 pub struct Location {
     pub grapheme_index: usize, // 当前光标所在的字形索引。
     pub line_index: usize,     // 当前光标所在的行索引。
 }
 
+/// 滚动视图的方向。
+#[derive(Clone, Copy)]
+pub enum ScrollDirection {
+    Up,   // 向文件开头滚动。
+    Down, // 向文件末尾滚动。
+}
+
+/// 文本中一处匹配的位置，以字节为单位。
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub struct Match {
+    pub line_index: usize, // 匹配所在的行索引。
+    pub byte_index: usize, // 匹配在行内的起始字节。
+    pub byte_len: usize,   // 匹配的字节长度。
+}
+
+/// 对某一行的修改：将该行替换为新的文本。
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct LineChange {
+    pub line_index: usize, // 被修改的行索引。
+    pub text: String,      // 修改后的文本。
+}
+
+/// 保存时写入文件的内容快照，可以交给后台线程写入。
+pub struct SaveSnapshot {
+    pub path: PathBuf,     // 写入的文件。
+    pub contents: Vec<u8>, // 写入的内容，已按文件的编码编码。
+    revision: u64,         // 拍下快照时缓冲区的版本。
+    replace_link: bool,    // `path` 是符号链接时，是否用普通文件替换链接本身。
+    attributes: Option<Attributes>, // 上次读取或保存时记下的文件属性，文件已不存在时恢复到新文件上。
+}
+
+impl SaveSnapshot {
+    /// 将快照写入文件，返回需要提示用户的警告，如原文件无法保留的属性。
+    pub fn write(&self) -> Result<Vec<String>, Error> {
+        // 直接写入符号链接会写到链接指向的文件，替换链接时需要先删除它。
+        if self.replace_link {
+            fs::remove_file(&self.path)?;
+        }
+        let lost = atomicwrite::write(&self.path, &self.contents, self.attributes.as_ref())?;
+        if lost.is_empty() {
+            return Ok(Vec::new());
+        }
+        Ok(vec![format!("could not keep the {}", lost.join(", "))])
+    }
+
+    /// 拍下快照时缓冲区的版本。
+    pub const fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// 保存时是否用普通文件替换了符号链接本身。
+    pub const fn replace_link(&self) -> bool {
+        self.replace_link
+    }
+}
+
+/// 保存完成时提示的消息，附带保存过程中的警告。
+pub fn saved_message(warnings: &[String]) -> String {
+    if warnings.is_empty() {
+        String::from("File saved successfully.")
+    } else {
+        format!("File saved, but {}.", warnings.join("; "))
+    }
+}
+
 /// `View` 结构体定义了编辑器的视图。
 #[derive(Default)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct View {
     buffer: Buffer,          // 当前缓冲区，存储文本内容。
     needs_redraw: bool,      // 标记是否需要重新渲染。
     size: Size,              // 当前视图的尺寸（宽度和高度）。
     text_location: Location, // 当前光标的位置。
     scroll_offset: Position, // 滚动偏移量，用于确定视图的起始位置。
+    show_scroll_markers: bool, // 是否在行的两侧显示水平延续标记。
+    register: Option<String>, // 复制的文本，用于粘贴。
+    flash: Option<Range<usize>>, // 撤销或重做后高亮显示的行，下次按键时清除。
+    last_search: Option<String>, // 上一次查找的文本，用于查找下一个或上一个。
+    match_highlight: Option<Match>, // 逐个替换时高亮显示的当前匹配。
+    show_line_numbers: bool, // 是否在左侧显示行号栏。
+    show_recent_changes: bool, // 是否在行号栏中给最近修改过的行着色。
+    trash: Trash,            // 最近几次较大删除的内容，独立于撤销历史。
+    selection_anchor: Option<Location>, // 选区的另一端，光标所在的一端随移动变化。
+    selecting: bool,         // 是否处于选择模式：不按 Shift 移动光标也会扩展选区。
+    break_symlinks: bool,    // 保存通过符号链接打开的文件时是否用普通文件替换链接。
+    trim_on_save: bool,      // 保存前是否删除行尾的空白。
+    backup: Backup,          // 保存前备份原文件的设置。
+    expand_tabs: bool,       // 按 Tab 时是否插入空格而不是制表符。
+    tab_width: TabWidth,     // 制表符对齐到的列数的倍数。
+    soft_wrap: bool,         // 是否把长行折成多个显示行，而不是水平滚动。
+    wrap_row: usize,         // 软换行时视图顶部显示的是 `scroll_offset.row` 行的第几个显示行。
+    desired_col: Option<(usize, Location)>, // 上下移动时要回到的显示列，以及记下它之后光标所在的位置。
+    buffer_id: usize,        // 当前缓冲区的编号。
+    other_buffers: Vec<BufferSlot>, // 其他打开的缓冲区，按打开的顺序排列，不含当前缓冲区。
+    active_buffer: usize,    // 当前缓冲区在所有打开的缓冲区中的序号。
+    next_buffer_id: usize,   // 最近分配的缓冲区编号。
+    fallback_encoding: Option<FileEncoding>, // 文件不是有效的 UTF-8 时尝试的编码，没有时拒绝打开。
 }
 
 impl View {
@@ -35,19 +160,138 @@ impl View {
         Terminal::print_row(at, line_text)
     }
 
+    /// 渲染由多段带样式文本组成的一行。
+    fn render_segments(at: usize, segments: &[StyledText], tinted: bool) -> Result<(), Error> {
+        if tinted {
+            Terminal::print_tinted_row(at, segments)
+        } else {
+            Terminal::print_styled_row(at, segments)
+        }
+    }
+
+    /// 单行文本的可见部分，当该行在视口左侧或右侧还有内容时带有延续标记。
+    fn segments_with_markers(
+        line: &Line,
+        range: Range<usize>,
+        spans: &[Span],
+        tab_width: TabWidth,
+    ) -> Vec<StyledText> {
+        let (hidden_left, hidden_right) = line.hidden_sides(&range, tab_width);
+        if range.len() < 2 || (!hidden_left && !hidden_right) {
+            return line.get_visible_segments(range, spans, tab_width);
+        }
+
+        let start = if hidden_left {
+            range.start.saturating_add(1)
+        } else {
+            range.start
+        };
+        let end = if hidden_right {
+            range.end.saturating_sub(1)
+        } else {
+            range.end
+        };
+        let marker = |character: char| StyledText {
+            text: character.to_string(),
+            style: Some(Style::Dim),
+        };
+
+        let mut segments = Vec::new();
+        if hidden_left {
+            segments.push(marker(LEFT_MARKER));
+        }
+        segments.extend(line.get_visible_segments(start..end, spans, tab_width));
+        if hidden_right {
+            segments.push(marker(RIGHT_MARKER));
+        }
+        segments
+    }
+
+    /// 行号栏中指定行的内容：显示行号时为右对齐的行号并以一列空格与文本分隔，
+    /// 否则为一列修改标记。最近修改过的行按修改的远近着色。
+    fn gutter_segment(&self, line_index: usize, gutter_width: usize) -> StyledText {
+        let bucket = self
+            .show_recent_changes
+            .then(|| self.buffer.changes.bucket(line_index))
+            .flatten();
+        let swap_point = self
+            .buffer
+            .swap_point
+            .get()
+            .is_some_and(|location| location.line_index == line_index);
+        let text = if self.show_line_numbers {
+            let digits = gutter_width.saturating_sub(1);
+            let marker = if swap_point { SWAP_POINT_MARKER } else { ' ' };
+            format!("{:>digits$}{marker}", line_index.saturating_add(1))
+        } else if swap_point {
+            String::from(SWAP_POINT_MARKER)
+        } else if bucket.is_some() {
+            String::from(CHANGE_MARKER)
+        } else {
+            String::from(" ")
+        };
+        StyledText {
+            text,
+            style: Some(bucket.map_or(Style::Dim, Style::Changed)),
+        }
+    }
+
+    /// 视图中从上到下每一显示行的内容：所在的行、显示的列范围，以及是否是该行的第一个显示行。
+    fn screen_rows(&self, text_width: usize) -> Vec<(usize, Range<usize>, bool)> {
+        let height = self.size.height;
+        if !self.soft_wrap {
+            let left = self.scroll_offset.col;
+            let right = left.saturating_add(text_width);
+            return (self.scroll_offset.row..self.buffer.height())
+                .take(height)
+                .map(|line_index| (line_index, left..right, true))
+                .collect();
+        }
+        let mut rows = Vec::new();
+        let (mut line_index, mut row) = self.wrapped_top();
+        while rows.len() < height {
+            let Some(line) = self.buffer.lines.get(line_index) else {
+                break;
+            };
+            let starts = line.wrap_starts(text_width, self.tab_width);
+            for (index, start) in starts.iter().enumerate().skip(row) {
+                if rows.len() >= height {
+                    break;
+                }
+                let end = starts
+                    .get(index.saturating_add(1))
+                    .copied()
+                    .unwrap_or_else(|| line.grapheme_count());
+                let columns = line.width_until(*start, self.tab_width)..line.width_until(end, self.tab_width);
+                rows.push((line_index, columns, index == 0));
+            }
+            line_index = line_index.saturating_add(1);
+            row = 0;
+        }
+        rows
+    }
+
     /// 生成欢迎信息。
     fn build_welcome_message(width: usize) -> String {
+        Self::center_welcome(&format!("{NAME} editor -- version {VERSION}"), width)
+    }
+
+    /// 把欢迎信息居中放在一行 `width` 列宽的 `~` 之后，放不下时只显示 `~`。
+    fn center_welcome(welcome_message: &str, width: usize) -> String {
         if width == 0 {
             return String::new();
         }
-        let welcome_message = format!("{NAME} editor -- version {VERSION}");
-        let len = welcome_message.len();
+        // 按显示列而不是字节数居中，名称中含有全角字符时同样对齐。
+        let len = welcome_message.width();
         let remaining_width = width.saturating_sub(1);
         if remaining_width < len {
             return "~".to_string();
         }
 
-        format!("{:<1}{:^remaining_width$}", "~", welcome_message)
+        let padding = remaining_width.saturating_sub(len);
+        let left = padding.checked_div(2).unwrap_or_default();
+        let right = padding.saturating_sub(left);
+        format!("~{}{welcome_message}{}", " ".repeat(left), " ".repeat(right))
     }
 
     /// 获取文档状态。
@@ -55,93 +299,1307 @@ impl View {
         DocumentStatus {
             total_lines: self.buffer.height(),
             current_line_index: self.text_location.line_index,
+            current_column_index: self.text_location.grapheme_index,
+            current_display_column: self
+                .buffer
+                .lines
+                .get(self.text_location.line_index)
+                .map_or(0, |line| line.width_until(self.text_location.grapheme_index, self.tab_width)),
             file_name: format!("{}", self.buffer.file_info),
+            is_symlink: self.buffer.file_info.is_symlink(),
+            is_read_only: self.buffer.file_info.read_only,
+            text_format: self.buffer.format(),
+            encoding: self.buffer.file_info.encoding,
+            horizontal_offset: if self.soft_wrap {
+                0
+            } else {
+                self.scroll_offset.col
+            },
+            buffer_index: self.active_buffer(),
+            buffer_count: self.buffer_count(),
             is_modified: self.buffer.dirty,
         }
     }
 
+    /// 统计选区的行数、字数和词数，没有选区时统计整个缓冲区；第二项表示是否是选区。
+    pub fn text_stats(&self) -> (TextStats, bool) {
+        self.selected_range().map_or_else(
+            || (self.buffer.stats(), false),
+            |(start, end)| (TextStats::of(&self.buffer.extract_range(start, end)), true),
+        )
+    }
+
+    /// 获取当前文件的路径。
+    pub fn file_path(&self) -> Option<&Path> {
+        self.buffer.file_info.path.as_deref()
+    }
+
+    /// 已经切分过字形的行数和总行数。
+    pub fn segmented_lines(&self) -> (usize, usize) {
+        (self.buffer.segmented_lines(), self.buffer.height())
+    }
+
+    /// 获取光标在文本中的位置。
+    pub const fn text_location(&self) -> Location {
+        self.text_location
+    }
+
+    /// 切换是否显示行号栏，返回切换后是否显示。
+    pub fn toggle_line_numbers(&mut self) -> bool {
+        self.set_show_line_numbers(!self.show_line_numbers);
+        self.show_line_numbers
+    }
+
+    /// 设置是否显示行号栏。
+    pub fn set_show_line_numbers(&mut self, show: bool) {
+        self.show_line_numbers = show;
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+    }
+
+    /// 切换是否给最近修改过的行着色，返回切换后是否着色。
+    pub fn toggle_recent_changes(&mut self) -> bool {
+        self.show_recent_changes = !self.show_recent_changes;
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+        self.show_recent_changes
+    }
+
+    /// 光标移动到当前行之后的下一个修改过的行，到末尾后从头开始；没有修改过的行时返回 `false`。
+    pub fn goto_next_changed_line(&mut self) -> bool {
+        let Some(line_index) = self.buffer.changes.next_after(self.text_location.line_index) else {
+            return false;
+        };
+        self.goto_line(line_index);
+        true
+    }
+
+    /// 在光标和记下的位置之间切换：第一次只记下光标所在的位置并返回 `false`；
+    /// 之后光标跳到记下的位置，原来的位置被记下，返回 `true`。
+    pub fn swap_position(&mut self) -> bool {
+        self.set_needs_redraw(true);
+        let Some(target) = self.buffer.swap_point.replace(self.text_location) else {
+            return false;
+        };
+        self.clear_selection();
+        self.text_location = target;
+        self.normalize_after_change();
+        true
+    }
+
+    /// 清除记下的切换位置。
+    pub fn clear_swap_position(&mut self) {
+        self.buffer.swap_point.clear();
+        self.set_needs_redraw(true);
+    }
+
+    /// 切换软换行，返回切换后是否启用。
+    pub fn toggle_soft_wrap(&mut self) -> bool {
+        self.set_soft_wrap(!self.soft_wrap);
+        self.soft_wrap
+    }
+
+    /// 设置是否软换行；切换后从头重新滚动到光标处并重绘整个视图。
+    pub fn set_soft_wrap(&mut self, soft_wrap: bool) {
+        self.soft_wrap = soft_wrap;
+        // 软换行时记下的是显示行内的列，与不换行时的列含义不同。
+        self.desired_col = None;
+        self.scroll_offset.col = 0;
+        self.wrap_row = 0;
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+    }
+
+    /// 设置文件不是有效的 UTF-8 时尝试的编码；为 `None` 时拒绝打开这样的文件。
+    pub const fn set_fallback_encoding(&mut self, encoding: Option<FileEncoding>) {
+        self.fallback_encoding = encoding;
+    }
+
+    /// 按配置设置行号栏、软换行、缩进、保存和回收站等选项。
+    pub fn apply_config(&mut self, config: &Config) {
+        self.set_fallback_encoding(config.fallback_encoding);
+        self.set_trash_limits(config.trash);
+        self.set_break_symlinks(config.break_symlinks_on_save);
+        self.set_trim_on_save(config.trim_trailing_whitespace);
+        self.set_backup(config.backup.clone());
+        self.set_expand_tabs(config.expand_tabs);
+        self.tab_width = TabWidth::new(config.tab_width);
+        self.set_show_line_numbers(config.show_line_numbers);
+        self.set_soft_wrap(config.soft_wrap);
+    }
+
+    /// 设置是否显示水平延续标记。
+    pub fn set_show_scroll_markers(&mut self, show: bool) {
+        if self.show_scroll_markers != show {
+            self.show_scroll_markers = show;
+            self.set_needs_redraw(true);
+        }
+    }
+
     // ==================== 编辑器命令相关方法 ====================
 
-    pub fn handle_edit_command(&mut self, command: Edit) {
-        match command {
-            Edit::Insert(character) => self.insert_char(character),
-            Edit::Delete => self.delete(),
-            Edit::DeleteBackward => self.delete_backward(),
-            Edit::InsertNewline => self.insert_newline(),
+    /// 处理编辑命令，失败时返回需要提示给用户的信息。
+    pub fn handle_edit_command(&mut self, command: Edit) -> Result<(), String> {
+        let kind = match command {
+            Edit::Undo => return self.undo(),
+            Edit::Redo => return self.redo(),
+            Edit::Insert(character) if character != '\n' => StepKind::Typing,
+            Edit::InsertTab => StepKind::Typing,
+            _ => StepKind::Other,
+        };
+        if command == Edit::ToggleLineComment {
+            return self.toggle_line_comment();
         }
+        if command == Edit::ToggleBlockComment {
+            return self.toggle_block_comment();
+        }
+        if command == Edit::TrimTrailingWhitespace {
+            self.trim_trailing_whitespace();
+            return Ok(());
+        }
+        if matches!(command, Edit::InsertTab | Edit::Dedent)
+            && let Some((start, end)) = self.selected_range()
+        {
+            self.shift_selected_lines(start, end, command == Edit::InsertTab);
+            return Ok(());
+        }
+        if matches!(command, Edit::Delete | Edit::DeleteBackward)
+            && let Some((start, end)) = self.selected_range()
+        {
+            self.step(StepKind::Other, |view| {
+                view.buffer.delete_range(start, end);
+                view.text_location = start;
+            });
+            self.normalize_after_change();
+            return Ok(());
+        }
+        let old_height = self.buffer.height();
+        self.step(kind, |view| match command {
+            Edit::InsertTab => {
+                view.insert_tab();
+                Ok(())
+            }
+            Edit::Dedent => {
+                view.dedent_current_line();
+                Ok(())
+            }
+            Edit::Insert(character) => {
+                view.insert_char(character);
+                Ok(())
+            }
+            Edit::Delete => {
+                view.delete();
+                Ok(())
+            }
+            Edit::DeleteBackward => {
+                view.delete_backward();
+                Ok(())
+            }
+            Edit::InsertNewline => {
+                view.insert_newline();
+                Ok(())
+            }
+            Edit::ConvertIndentation => {
+                view.convert_indentation();
+                Ok(())
+            }
+            Edit::Paste => view.paste(false),
+            Edit::PasteAligned => view.paste(true),
+            Edit::CutLine => view.cut_line(),
+            Edit::DeleteLine => view.delete_line(),
+            Edit::DuplicateLine => view.duplicate_line(),
+            Edit::MoveLineUp => {
+                view.move_line(true);
+                Ok(())
+            }
+            Edit::MoveLineDown => {
+                view.move_line(false);
+                Ok(())
+            }
+            Edit::PasteLineAbove => view.paste_line_above(),
+            Edit::ToggleLineComment
+            | Edit::ToggleBlockComment
+            | Edit::TrimTrailingWhitespace
+            | Edit::Undo
+            | Edit::Redo => Ok(()),
+        })?;
+        // 行数变化后，滚动偏移可能指向已经不存在的内容。
+        if self.buffer.height() != old_height {
+            self.normalize_after_change();
+        }
+        Ok(())
+    }
+
+    /// 处理移动命令；`select` 为真时从移动前的位置开始或扩展选区，否则取消选区。
+    pub fn handle_move_command(&mut self, command: Move, select: bool) {
+        if select || self.selecting {
+            self.selection_anchor.get_or_insert(self.text_location);
+            self.set_needs_redraw(true);
+        } else {
+            self.clear_selection();
+        }
+        self.move_caret(command);
+    }
+
+    /// 进入或退出选择模式：进入时在光标处放下选区的锚点，之后的移动都会扩展选区，直到修改或取消。
+    pub fn toggle_selection_mode(&mut self) -> bool {
+        if self.selecting {
+            self.clear_selection();
+        } else {
+            self.selecting = true;
+            self.selection_anchor = Some(self.text_location);
+            self.set_needs_redraw(true);
+        }
+        self.selecting
+    }
+
+    /// 取消选区并退出选择模式。
+    pub fn clear_selection(&mut self) {
+        self.selecting = false;
+        if self.selection_anchor.take().is_some() {
+            self.set_needs_redraw(true);
+        }
+    }
+
+    /// 选区的范围，开头在前；没有选区或选区为空时返回 `None`。
+    pub fn selected_range(&self) -> Option<(Location, Location)> {
+        let anchor = self.selection_anchor?;
+        let caret = self.text_location;
+        let key = |location: Location| (location.line_index, location.grapheme_index);
+        match key(anchor).cmp(&key(caret)) {
+            Ordering::Less => Some((anchor, caret)),
+            Ordering::Greater => Some((caret, anchor)),
+            Ordering::Equal => None,
+        }
+    }
+
+    /// 选区在指定行内的字节范围；跨行的选区在中间各行覆盖整行。
+    fn selection_bytes(&self, line_index: usize) -> Option<Range<usize>> {
+        let (start, end) = self.selected_range()?;
+        if line_index < start.line_index || line_index > end.line_index {
+            return None;
+        }
+        let line = self.buffer.lines.get(line_index)?;
+        let from = if line_index == start.line_index {
+            line.byte_index_at_grapheme(start.grapheme_index)
+        } else {
+            0
+        };
+        let to = if line_index == end.line_index {
+            line.byte_index_at_grapheme(end.grapheme_index)
+        } else {
+            line.byte_index_at_grapheme(line.grapheme_count())
+        };
+        Some(from..to)
     }
 
-    pub fn handle_move_command(&mut self, command: Move){
+    /// 指定行内反色显示的部分：选区、逐个替换时的当前匹配以及与光标处的括号配对的括号。
+    fn marked_spans(&self, line_index: usize, bracket: Option<Location>) -> Vec<Span> {
+        let found = self
+            .match_highlight
+            .filter(|found| found.line_index == line_index)
+            .map(|found| found.byte_index..found.byte_index.saturating_add(found.byte_len));
+        let bracket = bracket
+            .filter(|bracket| bracket.line_index == line_index)
+            .zip(self.buffer.lines.get(line_index))
+            .map(|(bracket, line)| {
+                line.byte_index_at_grapheme(bracket.grapheme_index)
+                    ..line.byte_index_at_grapheme(bracket.grapheme_index.saturating_add(1))
+            });
+        self.selection_bytes(line_index)
+            .into_iter()
+            .chain(found)
+            .chain(bracket)
+            .map(|bytes| Span {
+                bytes,
+                style: Style::Inverted,
+            })
+            .collect()
+    }
+
+    /// 按移动命令移动光标，不改变选区。
+    fn move_caret(&mut self, command: Move) {
         let Size { height, .. } = self.size;
         match command {
-             Move::Up => self.move_up(1),
-             Move::Down => self.move_down(1),
+             Move::Up => self.move_up_rows(1),
+             Move::Down => self.move_down_rows(1),
              Move::Left => self.move_left(),
              Move::Right => self.move_right(),
-             Move::PageUp => self.move_up(height.saturating_sub(1)),
-             Move::PageDown => self.move_down(height.saturating_sub(1)),
+             Move::WordLeft => self.move_word_left(),
+             Move::WordRight => self.move_word_right(),
+             Move::PageUp => self.move_up_rows(height.saturating_sub(1)),
+             Move::PageDown => self.move_down_rows(height.saturating_sub(1)),
              Move::StartOfLine => self.move_to_start_of_line(),
              Move::EndOfLine => self.move_to_end_of_line(),
+             Move::DocumentStart => self.move_to_document_start(),
+             Move::DocumentEnd => self.move_to_document_end(),
+             Move::MatchingBracket => self.move_to_matching_bracket(),
+         }
+         if matches!(command, Move::EndOfLine) {
+             // 与 vim 的 `$` 一样，之后上下移动时停在各行的行尾。
+             self.desired_col = Some((usize::MAX, self.text_location));
          }
          self.scroll_text_location_into_view();
     }
 
-    /// 加载文件。
-    pub fn load(&mut self, file_name: &str) -> Result<(), Error> {
-        let buffer = Buffer::load(file_name)?;
-        self.buffer = buffer;
-        self.set_needs_redraw(true);
+    /// 加载文件。缓冲区有未保存的修改时会先将其暂存，返回暂存文件的路径。
+    pub fn load(&mut self, path: &Path) -> Result<Option<PathBuf>, Error> {
+        let buffer = Buffer::load(path, self.fallback_encoding)?;
+        self.replace_buffer(buffer)
+    }
+
+    /// 用给定的内容创建一个未命名的缓冲区，同样会先暂存未保存的修改。
+    pub fn load_unnamed(&mut self, contents: &str) -> Result<Option<PathBuf>, Error> {
+        self.replace_buffer(Buffer::from_text(contents))
+    }
+
+    /// 把读到的全部输入（如管道传来的标准输入）作为未命名的缓冲区，状态栏中显示为 `label`。
+    pub fn load_reader(&mut self, reader: impl Read, label: &'static str) -> Result<(), Error> {
+        let mut buffer = Buffer::from_reader(reader)?;
+        buffer.file_info.label = Some(label);
+        self.replace_buffer(buffer)?;
+        Ok(())
+    }
+
+    /// 从磁盘重新读取当前文件，光标尽量留在原来的位置。
+    ///
+    /// 与其他替换缓冲区的操作一样，未保存的修改会先被暂存，返回暂存文件的路径。
+    pub fn reload(&mut self) -> Result<Option<PathBuf>, Error> {
+        let Some(path) = self.file_path().map(Path::to_path_buf) else {
+            return Err(Error::new(ErrorKind::NotFound, "No file to reload"));
+        };
+        let text_location = self.text_location;
+        let scroll_offset = self.scroll_offset;
+        let stashed = self.replace_buffer(Buffer::load(&path, self.fallback_encoding)?)?;
+        self.text_location = text_location;
+        self.scroll_offset = scroll_offset;
+        self.normalize_after_change();
+        Ok(stashed)
+    }
+
+    /// 缓冲区有未保存的修改时将其写入暂存目录，返回暂存文件的路径。
+    pub fn stash_unsaved(&self) -> Result<Option<PathBuf>, Error> {
+        Self::stash_buffer(&self.buffer)
+    }
+
+    /// 指定的缓冲区有未保存的修改时将其写入暂存目录，返回暂存文件的路径。
+    ///
+    /// 暂存的副本保留文件原来的换行符和结尾换行，但总是按 UTF-8 写出，不使用文件的编码，
+    /// 恢复时才能作为文本读回；恢复出的未命名缓冲区因此也按 UTF-8 保存。
+    fn stash_buffer(buffer: &Buffer) -> Result<Option<PathBuf>, Error> {
+        if !buffer.dirty {
+            return Ok(None);
+        }
+        let contents = buffer.contents();
+        stash::save(&buffer.file_info.to_string(), &contents).map(Some)
+    }
+
+    /// 替换当前缓冲区。
+    ///
+    /// 所有替换缓冲区内容的操作都必须经过这里：当前缓冲区有未保存的修改时，
+    /// 先将其写入暂存目录，暂存失败则放弃替换，保证修改不会被悄悄丢弃。
+    fn replace_buffer(&mut self, buffer: Buffer) -> Result<Option<PathBuf>, Error> {
+        let stashed = self.stash_unsaved()?;
+        self.buffer = buffer;
+        self.buffer_id = self.new_buffer_id();
+        self.text_location = Location::default();
+        self.clear_selection();
+        self.scroll_offset = Position::default();
+        self.wrap_row = 0;
+        self.normalize_after_change();
+        Ok(stashed)
+    }
+
+    // ==================== 文本编辑相关方法 ====================
+
+    /// 插入新字符，光标停在插入的字形之后。
+    ///
+    /// 光标按新增的字形数前进，而不是借用向右移动：插入的字符与前一个字形合并
+    /// （如组合符号）时光标不动，并且在行尾插入时不会跳到下一行。前进之后立即按
+    /// 渲染宽度滚动，行尾插入全角字符时光标同一帧就出现在字形之后。
+    fn insert_char(&mut self, character: char) {
+        let old_len = self
+            .buffer
+            .lines
+            .get(self.text_location.line_index)
+            .map_or(0, Line::grapheme_count);
+        self.buffer.insert_char(character, self.text_location);
+        let new_len = self
+            .buffer
+            .lines
+            .get(self.text_location.line_index)
+            .map_or(0, Line::grapheme_count);
+        let grapheme_delta = new_len.saturating_sub(old_len);
+        self.text_location.grapheme_index = self
+            .text_location
+            .grapheme_index
+            .saturating_add(grapheme_delta)
+            .min(new_len);
+        self.desired_col = None;
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+    }
+
+    /// 插入制表符；`expand_tabs` 打开时改为插入空格，补齐到下一个制表位。
+    fn insert_tab(&mut self) {
+        if !self.expand_tabs {
+            self.insert_char('\t');
+            return;
+        }
+        let width = self.tab_width.get();
+        let column = self
+            .buffer
+            .lines
+            .get(self.text_location.line_index)
+            .map_or(0, |line| line.width_until(self.text_location.grapheme_index, self.tab_width));
+        let padding = width.saturating_sub(column.checked_rem(width).unwrap_or_default());
+        for _ in 0..padding {
+            self.insert_char(' ');
+        }
+    }
+
+    /// 为选区涉及的各行增加（`indent` 为真时）或减少一级缩进。
+    fn shift_selected_lines(&mut self, start: Location, end: Location, indent: bool) {
+        self.edit_selected_lines(start, end, |buffer, first, last| {
+            if indent {
+                buffer.indent_lines(first, last)
+            } else {
+                buffer.dedent_lines(first, last)
+            }
+        });
+    }
+
+    /// 对选区涉及的各行（第一行和最后一行的索引）进行修改；结束于某行行首的选区不包含那一行。
+    ///
+    /// 选区保持不变：开头移到第一行的行首以包含行首新增的内容，结尾随所在行的内容一起移动。
+    fn edit_selected_lines<T>(
+        &mut self,
+        start: Location,
+        end: Location,
+        edit: impl FnOnce(&mut Buffer, usize, usize) -> T,
+    ) -> T {
+        let last_line = if end.grapheme_index == 0 && end.line_index > start.line_index {
+            end.line_index.saturating_sub(1)
+        } else {
+            end.line_index
+        };
+        let count = |view: &Self| view.buffer.lines.get(end.line_index).map_or(0, Line::grapheme_count);
+        let old_count = count(self);
+        let anchor_at_start = self.selection_anchor == Some(start);
+        let selecting = self.selecting;
+        let result = self.step(StepKind::Other, |view| {
+            let result = edit(&mut view.buffer, start.line_index, last_line);
+            let new_start = Location {
+                grapheme_index: 0,
+                line_index: start.line_index,
+            };
+            let new_end = Location {
+                grapheme_index: end
+                    .grapheme_index
+                    .saturating_add(count(view))
+                    .saturating_sub(old_count),
+                line_index: end.line_index,
+            };
+            let (anchor, caret) = if anchor_at_start {
+                (new_start, new_end)
+            } else {
+                (new_end, new_start)
+            };
+            view.selection_anchor = Some(anchor);
+            view.text_location = caret;
+            view.selecting = selecting;
+            result
+        });
+        self.normalize_after_change();
+        result
+    }
+
+    /// 切换选区涉及的各行或当前行的行注释，光标随行的内容一起移动。
+    fn toggle_line_comment(&mut self) -> Result<(), String> {
+        let file_type = self.buffer.file_info.file_type;
+        let Some(token) = file_type.line_comment() else {
+            return Err(format!("No line comment syntax for {file_type} files"));
+        };
+        if let Some((start, end)) = self.selected_range() {
+            return self
+                .edit_selected_lines(start, end, |buffer, first, last| {
+                    buffer.toggle_line_comment(first, last, token)
+                })
+                .map_err(str::to_string);
+        }
+        let line_index = self.text_location.line_index;
+        let count = |view: &Self| view.buffer.lines.get(line_index).map_or(0, Line::grapheme_count);
+        let old_count = count(self);
+        self.step(StepKind::Other, |view| {
+            view.buffer.toggle_line_comment(line_index, line_index, token)?;
+            view.text_location.grapheme_index = view
+                .text_location
+                .grapheme_index
+                .saturating_add(count(view))
+                .saturating_sub(old_count);
+            Ok(())
+        })
+        .map_err(str::to_string)?;
+        self.normalize_after_change();
+        Ok(())
+    }
+
+    /// 当前行减少一级缩进，光标随行的内容一起左移。
+    fn dedent_current_line(&mut self) {
+        let line_index = self.text_location.line_index;
+        let count = |view: &Self| view.buffer.lines.get(line_index).map_or(0, Line::grapheme_count);
+        let old_count = count(self);
+        if self.buffer.dedent_lines(line_index, line_index) == 0 {
+            return;
+        }
+        let removed = old_count.saturating_sub(count(self));
+        self.text_location.grapheme_index = self.text_location.grapheme_index.saturating_sub(removed);
+        self.set_needs_redraw(true);
+    }
+
+    /// 插入新行
+    fn insert_newline(&mut self) {
+        self.buffer.insert_newline(self.text_location);
+        self.move_caret(Move::Right);
+        self.set_needs_redraw(true);
+    }
+
+    /// 文件保存
+    ///
+    /// 返回需要提示用户的警告，见 `prepare_save`、`SaveSnapshot::write`。
+    pub fn save(&mut self) -> Result<Vec<String>, Error> {
+        let mut warnings = self.prepare_save();
+        warnings.extend(self.buffer.save(self.break_symlinks)?);
+        Ok(warnings)
+    }
+
+    /// 写入文件之前的准备：按设置删除行尾空白，第一次保存前备份原文件。
+    ///
+    /// 备份失败不阻止保存，返回的警告在保存完成时一并提示。
+    pub fn prepare_save(&mut self) -> Vec<String> {
+        self.trim_before_save();
+        self.back_up()
+            .err()
+            .map(|err| format!("the backup could not be created: {err}"))
+            .into_iter()
+            .collect()
+    }
+
+    /// 获取保存所需的内容快照，缓冲区没有文件名时返回 `None`。
+    pub fn save_snapshot(&self) -> Option<SaveSnapshot> {
+        self.buffer.snapshot(self.break_symlinks)
+    }
+
+    /// 当前文件是否因为没有写权限而拒绝修改。
+    pub const fn is_read_only(&self) -> bool {
+        self.buffer.file_info.read_only
+    }
+
+    /// 允许修改没有写权限的文件，保存时可能失败（可以另存为其他文件）。
+    pub const fn override_read_only(&mut self) {
+        self.buffer.file_info.read_only = false;
+    }
+
+    /// 当前缓冲区的未保存修改应当写入交换文件的时间，见 `Buffer::swap_deadline`。
+    pub fn swap_deadline(&self) -> Option<Instant> {
+        self.buffer.swap_deadline()
+    }
+
+    /// 按需写入或删除当前缓冲区的交换文件，见 `Buffer::sync_swap`。
+    pub fn sync_swap(&mut self) {
+        self.buffer.sync_swap();
+    }
+
+    /// 当前文件旁边有之前（如编辑器崩溃时）留下的交换文件时，返回它的修改时间。
+    pub fn stale_swap(&self) -> Option<SystemTime> {
+        if self.buffer.swap.is_written() || self.buffer.swap.is_disabled() {
+            return None;
+        }
+        swapfile::find(self.buffer.file_info.save_path()?)
+    }
+
+    /// 用交换文件中的内容替换当前缓冲区的内容，可以撤销；之后的修改照常写入交换文件，保存后删除它。
+    pub fn recover_swap(&mut self) -> Result<(), Error> {
+        let Some(path) = self.buffer.file_info.save_path().map(Path::to_path_buf) else {
+            return Err(Error::new(ErrorKind::NotFound, "Buffer has no file name"));
+        };
+        let text = swapfile::read(&path)?;
+        self.step(StepKind::Other, |view| view.buffer.replace_text(&text));
+        self.normalize_after_change();
+        Ok(())
+    }
+
+    /// 删除当前文件旁边之前留下的交换文件。
+    pub fn delete_swap(&mut self) -> Result<(), Error> {
+        let Some(path) = self.buffer.file_info.save_path().map(Path::to_path_buf) else {
+            return Err(Error::new(ErrorKind::NotFound, "Buffer has no file name"));
+        };
+        swapfile::delete(&path)
+    }
+
+    /// 保留当前文件旁边之前留下的交换文件，这个缓冲区不再写入交换文件，以免覆盖它。
+    pub const fn keep_swap(&mut self) {
+        self.buffer.swap.disable();
+    }
+
+    /// 上次读取或保存之后，磁盘上的文件是否被其他程序修改过。
+    pub fn changed_on_disk(&self) -> bool {
+        self.buffer.file_info.changed_on_disk()
+    }
+
+    /// 设置按 Tab 时是否插入空格而不是制表符。
+    pub const fn set_expand_tabs(&mut self, expand_tabs: bool) {
+        self.expand_tabs = expand_tabs;
+    }
+
+    /// 设置保存通过符号链接打开的文件时，是否用普通文件替换链接本身而不是写入链接指向的文件。
+    pub const fn set_break_symlinks(&mut self, break_symlinks: bool) {
+        self.break_symlinks = break_symlinks;
+    }
+
+    /// 设置保存前是否删除每一行行尾的空格和制表符。
+    pub const fn set_trim_on_save(&mut self, trim_on_save: bool) {
+        self.trim_on_save = trim_on_save;
+    }
+
+    /// 设置保存前是否以及在哪里备份磁盘上的原文件。
+    pub fn set_backup(&mut self, backup: Backup) {
+        self.backup = backup;
+    }
+
+    /// 当前缓冲区第一次保存之前，把磁盘上的原文件复制一份作为备份，返回备份的路径；
+    /// 未开启备份、已经备份过或文件还不存在时返回 `None`。
+    ///
+    /// 备份失败时同样视为已经备份过，之后的保存不再重试，以免每次保存都提示。
+    pub fn back_up(&mut self) -> Result<Option<PathBuf>, Error> {
+        if !self.backup.enabled || self.buffer.backed_up {
+            return Ok(None);
+        }
+        let Some(path) = self.buffer.file_info.save_path().map(Path::to_path_buf) else {
+            return Ok(None);
+        };
+        self.buffer.backed_up = true;
+        self.backup.create(&path)
+    }
+
+    /// 修改缓冲区对应的文件，下次保存时写入该文件。
+    pub fn set_path(&mut self, path: &Path) {
+        self.buffer.set_path(path);
+        self.set_needs_redraw(true);
+    }
+
+    /// 将缓冲区保存到新的路径，之后的保存也写入该路径。
+    pub fn save_as(&mut self, path: &Path) -> Result<Vec<String>, Error> {
+        self.buffer.set_path(path);
+        self.save()
+    }
+
+    /// 将光标移动到指定行的行首。
+    pub fn goto_line(&mut self, line_index: usize) {
+        self.text_location = Location {
+            grapheme_index: 0,
+            line_index,
+        };
+        self.normalize_after_change();
+    }
+
+    /// 将光标移动到指定的行和字素，超出范围时落到最近的有效位置并滚动到可见区域。
+    pub fn goto_location(&mut self, line_index: usize, grapheme_index: usize) {
+        self.text_location = Location {
+            grapheme_index,
+            line_index,
+        };
+        self.normalize_after_change();
+    }
+
+    /// 统计自上次保存以来缩进可疑的行数。
+    pub fn suspicious_indentation_count(&self) -> usize {
+        self.buffer.suspicious_indentation().len()
+    }
+
+    /// 将可疑缩进统一为缓冲区的缩进风格。
+    pub fn fix_indentation(&mut self) {
+        let line_indices = self.buffer.suspicious_indentation();
+        self.step(StepKind::Other, |view| view.buffer.fix_indentation(&line_indices));
+        self.normalize_after_change();
+    }
+
+    /// 将整个缓冲区的缩进统一为检测到的缩进风格。
+    fn convert_indentation(&mut self) {
+        let changes = self.indentation_changes();
+        self.apply_changes(&changes);
+    }
+
+    /// 删除每一行行尾的空格和制表符，整个操作作为撤销历史中的一步，返回修改的行数。
+    ///
+    /// 光标原先停在被删除的空白中时移动到行尾。
+    pub fn trim_trailing_whitespace(&mut self) -> usize {
+        let changed = self.step(StepKind::Other, |view| view.buffer.trim_trailing_whitespace());
+        if changed > 0 {
+            self.normalize_after_change();
+        }
+        changed
+    }
+
+    /// 开启了保存前删除行尾空白时执行删除，返回修改的行数。
+    pub fn trim_before_save(&mut self) -> usize {
+        if self.trim_on_save {
+            self.trim_trailing_whitespace()
+        } else {
+            0
+        }
+    }
+
+    /// 计算统一缩进风格时将会进行的修改。
+    pub fn indentation_changes(&self) -> Vec<LineChange> {
+        self.buffer.indentation_changes()
+    }
+
+    /// 计算删除行尾空白时将会进行的修改。
+    pub fn trailing_whitespace_changes(&self) -> Vec<LineChange> {
+        self.buffer.trailing_whitespace_changes()
+    }
+
+    /// 应用一组修改，返回修改的行数。
+    pub fn apply_changes(&mut self, changes: &[LineChange]) -> usize {
+        let applied = self.step(StepKind::Other, |view| view.buffer.apply_changes(changes));
+        self.normalize_after_change();
+        applied
+    }
+
+    /// 获取缓冲区的行数。
+    pub fn line_count(&self) -> usize {
+        self.buffer.height()
+    }
+
+    /// 获取指定范围内各行的文本。
+    pub fn lines_text(&self, range: LineRange) -> String {
+        self.buffer.lines_text(range.start, range.end)
+    }
+
+    /// 删除指定范围内的行，返回删除的行数。
+    pub fn delete_lines(&mut self, range: LineRange) -> usize {
+        let removed = self.step(StepKind::Other, |view| view.buffer.remove_lines(range.start, range.end));
+        self.normalize_after_change();
+        removed
+    }
+
+    /// 为指定范围内的行增加一级缩进，返回修改的行数。
+    pub fn indent_lines(&mut self, range: LineRange) -> usize {
+        let changed = self.step(StepKind::Other, |view| view.buffer.indent_lines(range.start, range.end));
+        self.normalize_after_change();
+        changed
+    }
+
+    /// 为指定范围内的行减少一级缩进，返回修改的行数。
+    pub fn dedent_lines(&mut self, range: LineRange) -> usize {
+        let changed = self.step(StepKind::Other, |view| view.buffer.dedent_lines(range.start, range.end));
+        self.normalize_after_change();
+        changed
+    }
+
+    /// 获取指定行的文本。
+    pub fn line_text(&self, line_index: usize) -> String {
+        self.buffer.lines_text(line_index, line_index)
+    }
+
+    /// 与磁盘上的文件逐行比较，返回未保存的修改；文件不存在或缓冲区没有文件名时所有行都是新增的。
+    pub fn unsaved_diff(&self) -> Result<Vec<DiffLine>, Error> {
+        let lines: Vec<String> = self.buffer.lines.iter().map(ToString::to_string).collect();
+        // 按文件的编码解码磁盘上的内容，UTF-16 和后备编码的文件同样可以比较。
+        let encoding = self.buffer.file_info.encoding;
+        let disk = match self.buffer.file_info.save_path().map(fs::File::open) {
+            Some(Ok(file)) => Buffer::decode(file, encoding)?.1,
+            Some(Err(err)) if err.kind() != ErrorKind::NotFound => return Err(err),
+            _ => Vec::new(),
+        };
+        Ok(diff_lines(&disk, &lines))
+    }
+
+    /// 文件的文本格式。
+    pub const fn text_format(&self) -> TextFormat {
+        self.buffer.format()
+    }
+
+    /// 当前文件的编码。
+    pub const fn encoding(&self) -> FileEncoding {
+        self.buffer.file_info.encoding
+    }
+
+    /// 修改保存时使用的编码。
+    pub fn set_encoding(&mut self, encoding: FileEncoding) {
+        self.buffer.set_encoding(encoding);
+    }
+
+    /// 保存时是否有无法用文件的编码表示、会被替换掉的字符。
+    pub fn is_lossy_save(&self) -> bool {
+        self.buffer.is_lossy()
+    }
+
+    /// 修改保存时使用的换行符。
+    pub fn set_line_ending(&mut self, line_ending: LineEnding) {
+        self.buffer.set_line_ending(line_ending);
+    }
+
+    /// 切换保存时最后一行之后是否有换行符，返回切换后是否有。
+    pub fn toggle_final_newline(&mut self) -> bool {
+        let final_newline = !self.buffer.format().final_newline;
+        self.buffer.set_final_newline(final_newline);
+        final_newline
+    }
+
+    /// 查找文本并记住它，光标移动到光标处或之后的第一个匹配（到末尾后从头开始）。
+    pub fn search(&mut self, query: &str) -> Result<(), String> {
+        self.last_search = Some(query.to_string());
+        self.search_from(query, true)
+    }
+
+    /// 从光标之后查找上一次查找的文本，到末尾后从头开始。
+    pub fn search_forward(&mut self) -> Result<(), String> {
+        let query = self.last_search.clone().ok_or("No previous search")?;
+        self.search_from(&query, false)
+    }
+
+    /// 从光标之前反向查找上一次查找的文本，到开头后从末尾开始。
+    pub fn search_backward(&mut self) -> Result<(), String> {
+        let query = self.last_search.clone().ok_or("No previous search")?;
+        let Location {
+            line_index,
+            grapheme_index,
+        } = self.text_location;
+        let in_line = |line_index: usize| {
+            self.buffer
+                .lines
+                .get(line_index)
+                .map(|line| line.find_all(&query))
+                .unwrap_or_default()
+        };
+        let found = in_line(line_index)
+            .into_iter()
+            .rev()
+            .find(|start| *start < grapheme_index)
+            .map(|start| (line_index, start))
+            .or_else(|| {
+                (0..line_index)
+                    .rev()
+                    .chain((line_index..self.buffer.height()).rev())
+                    .find_map(|index| in_line(index).last().map(|start| (index, *start)))
+            });
+        self.move_to_match(&query, found)
+    }
+
+    /// 从光标处（`inclusive` 为真时包括光标所在位置）向后查找。
+    fn search_from(&mut self, query: &str, inclusive: bool) -> Result<(), String> {
+        let Location {
+            line_index,
+            grapheme_index,
+        } = self.text_location;
+        let in_line = |line_index: usize| {
+            self.buffer
+                .lines
+                .get(line_index)
+                .map(|line| line.find_all(query))
+                .unwrap_or_default()
+        };
+        let found = in_line(line_index)
+            .into_iter()
+            .find(|start| *start > grapheme_index || (inclusive && *start == grapheme_index))
+            .map(|start| (line_index, start))
+            .or_else(|| {
+                (line_index.saturating_add(1)..self.buffer.height())
+                    .chain(0..=line_index)
+                    .find_map(|index| in_line(index).first().map(|start| (index, *start)))
+            });
+        self.move_to_match(query, found)
+    }
+
+    /// 光标移动到找到的匹配处；没有找到时返回错误信息。
+    fn move_to_match(&mut self, query: &str, found: Option<(usize, usize)>) -> Result<(), String> {
+        let (line_index, grapheme_index) = found.ok_or_else(|| format!("\"{query}\" not found"))?;
+        self.text_location = Location {
+            grapheme_index,
+            line_index,
+        };
+        self.scroll_text_location_into_view();
+        Ok(())
+    }
+
+    /// 从指定位置（包括该位置）向后查找第一个匹配，不从头开始。
+    pub fn find_from(&self, query: &str, from: Location) -> Option<Match> {
+        if query.is_empty() {
+            return None;
+        }
+        self.buffer
+            .lines
+            .iter()
+            .enumerate()
+            .skip(from.line_index)
+            .find_map(|(line_index, line)| {
+                line.to_string()
+                    .match_indices(query)
+                    .map(|(byte_index, _)| byte_index)
+                    .find(|byte_index| {
+                        line_index > from.line_index
+                            || line.grapheme_index_at_byte(*byte_index) >= from.grapheme_index
+                    })
+                    .map(|byte_index| Match {
+                        line_index,
+                        byte_index,
+                        byte_len: query.len(),
+                    })
+            })
+    }
+
+    /// 行内指定字节所在的位置。
+    pub fn location_at_byte(&self, line_index: usize, byte_index: usize) -> Location {
+        let grapheme_index = self
+            .buffer
+            .lines
+            .get(line_index)
+            .map_or(0, |line| line.grapheme_index_at_byte(byte_index));
+        Location {
+            grapheme_index,
+            line_index,
+        }
+    }
+
+    /// 高亮显示一处匹配并将光标移动到它的开头；传入 `None` 时取消高亮。
+    pub fn highlight_match(&mut self, found: Option<Match>) {
+        if let Some(found) = found {
+            self.text_location = self.location_at_byte(found.line_index, found.byte_index);
+            self.scroll_text_location_into_view();
+        }
+        self.match_highlight = found;
+        self.set_needs_redraw(true);
+    }
+
+    /// 查找文本的所有出现位置。
+    pub fn find_all(&self, query: &str) -> Vec<Match> {
+        self.buffer.find_all(query)
+    }
+
+    /// 将指定的匹配替换为新文本，返回替换的数量。
+    pub fn replace_matches(&mut self, matches: &[Match], replacement: &str) -> usize {
+        let replaced = self.step(StepKind::Other, |view| view.buffer.replace_matches(matches, replacement));
+        self.normalize_after_change();
+        replaced
+    }
+
+    /// 替换一处匹配并取消高亮，光标移动到替换后的文本之后。
+    pub fn replace_match(&mut self, found: Match, replacement: &str) {
+        let at = self.location_at_byte(found.line_index, found.byte_index);
+        let end = self.location_at_byte(
+            found.line_index,
+            found.byte_index.saturating_add(found.byte_len),
+        );
+        let match_len = end.grapheme_index.saturating_sub(at.grapheme_index);
+        self.match_highlight = None;
+        self.step(StepKind::Other, |view| {
+            view.text_location = view.buffer.replace(at, match_len, replacement);
+        });
+        self.normalize_after_change();
+    }
+
+    /// 将文本存入寄存器。
+    pub fn set_register(&mut self, text: &str) {
+        self.register = Some(text.to_string());
+    }
+
+    /// 在光标处粘贴寄存器中的文本；`aligned` 为真时后续各行与光标对齐到同一显示列。
+    fn paste(&mut self, aligned: bool) -> Result<(), String> {
+        let Some(text) = self.register.clone() else {
+            return Err(String::from("Nothing to paste"));
+        };
+        self.text_location = if aligned {
+            self.buffer.insert_str_aligned(self.text_location, &text, self.tab_width)
+        } else {
+            self.buffer.insert_str(self.text_location, &text)
+        };
+        self.normalize_after_change();
+        Ok(())
+    }
+
+    /// 将当前行复制到寄存器，返回复制的文本。
+    pub fn copy_line(&mut self) -> Result<String, String> {
+        let line = self
+            .buffer
+            .lines
+            .get(self.text_location.line_index)
+            .ok_or("Nothing to copy")?
+            .to_string();
+        self.register = Some(line.clone());
+        Ok(line)
+    }
+
+    /// 将选区中的文本复制到寄存器，返回复制的文本；没有选区时返回 `None`。
+    pub fn copy_selection(&mut self) -> Option<String> {
+        let (start, end) = self.selected_range()?;
+        let text = self.buffer.extract_range(start, end);
+        self.register = Some(text.clone());
+        Some(text)
+    }
+
+    /// 选区中的文本，不改变寄存器；没有选区时返回 `None`。
+    pub fn selected_text(&self) -> Option<String> {
+        let (start, end) = self.selected_range()?;
+        Some(self.buffer.extract_range(start, end))
+    }
+
+    /// 在选区末尾插入文本并取消选区，作为撤销历史中的一步；没有选区时返回 `false`。
+    pub fn insert_after_selection(&mut self, text: &str) -> bool {
+        let Some((_, end)) = self.selected_range() else {
+            return false;
+        };
+        self.step(StepKind::Other, |view| {
+            view.text_location = view.buffer.insert_str(end, text);
+        });
+        self.normalize_after_change();
+        true
+    }
+
+    /// 在光标处插入从终端粘贴的文本，作为撤销历史中的一步；`\r\n` 和 `\r` 视为换行。
+    ///
+    /// 整段文本一次拆分成行插入，只重绘一次。制表符和控制字符原样保留，
+    /// 与手动输入的一样在显示时替换（见 `Line::replace_character`）。
+    pub fn insert_pasted(&mut self, text: &str) {
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        if text.is_empty() {
+            return;
+        }
+        self.step(StepKind::Other, |view| {
+            view.text_location = view.buffer.insert_str(view.text_location, &text);
+        });
+        self.normalize_after_change();
+    }
+
+    /// 将当前行剪切到寄存器；剪切的是最后一行时，光标移动到新的最后一行。
+    fn cut_line(&mut self) -> Result<(), String> {
+        let line_index = self.text_location.line_index;
+        let line = self.buffer.remove_line(line_index).ok_or("Nothing to cut")?;
+        self.register = Some(line.to_string());
+        self.text_location.line_index = min(line_index, self.buffer.height().saturating_sub(1));
+        self.normalize_after_change();
+        Ok(())
+    }
+
+    /// 在当前行之下插入它的副本，光标移到副本中相同的位置。
+    fn duplicate_line(&mut self) -> Result<(), String> {
+        if !self.buffer.duplicate_line(self.text_location.line_index) {
+            return Err(String::from("Nothing to duplicate"));
+        }
+        self.text_location.line_index = self.text_location.line_index.saturating_add(1);
+        self.normalize_after_change();
+        Ok(())
+    }
+
+    /// 将当前行与上一行或下一行交换，光标随行移动；已经在第一行或最后一行时什么也不做。
+    fn move_line(&mut self, up: bool) {
+        let line_index = self.text_location.line_index;
+        let moved = if up {
+            self.buffer.move_line_up(line_index)
+        } else {
+            self.buffer.move_line_down(line_index)
+        };
+        if !moved {
+            return;
+        }
+        self.text_location.line_index = if up {
+            line_index.saturating_sub(1)
+        } else {
+            line_index.saturating_add(1)
+        };
+        self.normalize_after_change();
+    }
+
+    /// 删除当前行，不放入寄存器；光标留在同一行号上，超出文件末尾时落在最后一行。
+    fn delete_line(&mut self) -> Result<(), String> {
+        let line_index = self.text_location.line_index;
+        self.buffer.remove_line(line_index).ok_or("Nothing to delete")?;
+        self.text_location.line_index = min(line_index, self.buffer.height().saturating_sub(1));
+        self.normalize_after_change();
+        Ok(())
+    }
+
+    /// 在当前行之前粘贴寄存器中的文本，作为完整的一行或多行，光标停在粘贴的第一行。
+    fn paste_line_above(&mut self) -> Result<(), String> {
+        let Some(text) = self.register.clone() else {
+            return Err(String::from("Nothing to paste"));
+        };
+        let at = Location {
+            grapheme_index: 0,
+            line_index: self.text_location.line_index,
+        };
+        let text = text.strip_suffix('\n').unwrap_or(&text);
+        self.buffer.insert_str(at, &format!("{text}\n"));
+        self.text_location = at;
+        self.normalize_after_change();
+        Ok(())
+    }
+
+    /// 切换选区涉及的各行或当前行的块注释，整个操作是撤销历史中的一步。
+    fn toggle_block_comment(&mut self) -> Result<(), String> {
+        let file_type = self.buffer.file_info.file_type;
+        let Some(comment) = file_type.block_comment() else {
+            return Err(format!("No block comment syntax for {file_type} files"));
+        };
+        if let Some((start, end)) = self.selected_range() {
+            return self
+                .edit_selected_lines(start, end, |buffer, first, last| {
+                    buffer.toggle_block_comment(first, last, comment)
+                })
+                .map_err(str::to_string);
+        }
+        let line_index = self.text_location.line_index;
+        self.step(StepKind::Other, |view| {
+            view.buffer.toggle_block_comment(line_index, line_index, comment)
+        })
+        .map_err(str::to_string)?;
+        self.normalize_after_change();
+        Ok(())
+    }
+
+    /// 删除光标左侧的字符。
+    fn delete_backward(&mut self) {
+        if self.text_location.line_index != 0 || self.text_location.grapheme_index != 0 {
+            self.move_caret(Move::Left);
+            self.delete();
+        }
+    }
+
+    /// 删除光标上的字符
+    fn delete(&mut self) {
+        self.buffer.delete(self.text_location);
+        self.set_needs_redraw(true);
+    }
+
+    // ==================== 回收站 ====================
+
+    /// 设置放入回收站的阈值和容量。
+    pub fn set_trash_limits(&mut self, limits: TrashLimits) {
+        self.trash.set_limits(limits);
+    }
+
+    /// 回收站。
+    pub const fn trash(&self) -> &Trash {
+        &self.trash
+    }
+
+    /// 从状态目录读取上一次会话保存的回收站。
+    pub fn load_trash(&mut self) -> Result<(), Error> {
+        self.trash.load()
+    }
+
+    /// 将回收站中的一次删除插入到当前行之前，返回插入的行数。
+    pub fn restore_trash(&mut self, index: usize) -> Option<usize> {
+        let entry = self.trash.entries().get(index)?.clone();
+        let at = Location {
+            grapheme_index: 0,
+            line_index: self.text_location.line_index,
+        };
+        self.step(StepKind::Other, |view| {
+            view.buffer.insert_str(at, &format!("{}\n", entry.text));
+            view.text_location = at;
+        });
+        self.normalize_after_change();
+        Some(entry.lines)
+    }
+
+    /// 从回收站中移除一次删除。
+    pub fn remove_trash(&mut self, index: usize) {
+        self.trash.remove(index);
+    }
+
+    // ==================== 撤销和重做 ====================
+
+    /// 将一次修改作为撤销历史中的一步执行，记录修改前后的光标状态。
+    ///
+    /// 修改会取消选区，撤销时恢复。这一步删除的内容超过阈值时，被替换掉的行同时放入回收站。
+    fn step<T>(&mut self, kind: StepKind, edit: impl FnOnce(&mut Self) -> T) -> T {
+        self.buffer.begin_step(kind, self.caret_state());
+        self.clear_selection();
+        self.desired_col = None;
+        let result = edit(self);
+        let caret = self.caret_state();
+        let removed = self.buffer.end_step(caret).and_then(|step| {
+            let (lines, bytes) = step.deleted_size();
+            self.trash.wants(lines, bytes).then(|| step.removed_text())
+        });
+        if let Some((text, lines)) = removed {
+            self.trash.push(text, lines, self.buffer.file_info.to_string());
+        }
+        result
+    }
+
+    /// 当前的光标状态。
+    const fn caret_state(&self) -> CaretState {
+        CaretState {
+            location: self.text_location,
+            anchor: self.selection_anchor,
+        }
+    }
+
+    /// 撤销上一步，恢复修改前的光标并高亮恢复出的行。
+    fn undo(&mut self) -> Result<(), String> {
+        let step = self.buffer.undo().ok_or("Nothing to undo")?;
+        self.restore(step.before, step.removed_lines());
+        Ok(())
+    }
+
+    /// 重做上一次撤销的一步，恢复修改后的光标并高亮修改后的行。
+    fn redo(&mut self) -> Result<(), String> {
+        let step = self.buffer.redo().ok_or("Nothing to redo")?;
+        self.restore(step.after, step.inserted_lines());
         Ok(())
     }
 
-    // ==================== 文本编辑相关方法 ====================
+    /// 恢复光标状态并高亮指定的行；行范围为空（整行被删除）时高亮删除处的行。
+    fn restore(&mut self, caret: CaretState, lines: Option<(usize, usize)>) {
+        self.text_location = caret.location;
+        self.selection_anchor = caret.anchor;
+        self.flash = lines.map(|(start, end)| start..end.max(start.saturating_add(1)));
+        self.normalize_after_change();
+    }
 
-    /// 插入新字符。
-    fn insert_char(&mut self, character: char) {
-        let old_len = self
-            .buffer
-            .lines
-            .get(self.text_location.line_index)
-            .map_or(0, Line::grapheme_count);
-        self.buffer.insert_char(character, self.text_location);
-        let new_len = self
-            .buffer
-            .lines
-            .get(self.text_location.line_index)
-            .map_or(0, Line::grapheme_count);
-        let grapheme_delta = new_len.saturating_sub(old_len);
-        if grapheme_delta > 0 {
-            self.handle_move_command(Move::Right);
-        }
-        self.set_needs_redraw(true);
+    /// 是否还有需要在空闲时完成的语法高亮。
+    pub fn has_pending_highlight(&self) -> bool {
+        self.buffer.has_pending_highlight()
     }
 
-    /// 插入新行
-    fn insert_newline(&mut self) {
-        self.buffer.insert_newline(self.text_location);
-        self.handle_move_command(Move::Right);
+    /// 空闲时继续语法高亮。
+    pub fn highlight_idle(&mut self) {
+        self.buffer.highlight_idle();
+    }
+
+    /// 切换当前行的持久高亮，返回该行现在是否带有高亮。
+    pub fn toggle_line_mark(&mut self) -> bool {
         self.set_needs_redraw(true);
+        self.buffer.marks.toggle(self.text_location.line_index)
     }
 
-    /// 文件保存
-    pub fn save(&mut self) -> Result<(), Error> {
-        self.buffer.save()
+    /// 按行号顺序列出带有持久高亮的行。
+    pub fn marked_lines(&self) -> Vec<usize> {
+        self.buffer.marks.lines()
     }
 
-    /// 删除光标左侧的字符。
-    fn delete_backward(&mut self) {
-        if self.text_location.line_index != 0 || self.text_location.grapheme_index != 0 {
-            self.handle_move_command(Move::Left);
-            self.delete();
-        }
+    /// 清除某一行的持久高亮。
+    pub fn remove_line_mark(&mut self, line_index: usize) {
+        self.buffer.marks.remove(line_index);
+        self.set_needs_redraw(true);
     }
 
-    /// 删除光标上的字符
-    fn delete(&mut self) {
-        self.buffer.delete(self.text_location);
+    /// 清除所有持久高亮。
+    pub fn clear_line_marks(&mut self) {
+        self.buffer.marks.clear();
         self.set_needs_redraw(true);
     }
 
+    /// 清除撤销或重做后的高亮。
+    pub fn clear_flash(&mut self) {
+        if self.flash.take().is_some() {
+            self.set_needs_redraw(true);
+        }
+    }
+
     // ==================== 光标移动相关方法 ====================
 
     /// 光标向上移动
@@ -157,6 +1615,103 @@ impl View {
         self.snap_to_valid_line();
     }
 
+    /// 光标向上移动若干显示行：软换行时按显示行移动，否则按行移动。
+    ///
+    /// 光标尽量回到 `desired_column` 所在的列，经过较短的行时也不会丢失。
+    fn move_up_rows(&mut self, step: usize) {
+        let col = self.desired_column();
+        if self.soft_wrap {
+            let (row, _) = self.wrapped_position(self.text_location);
+            let (line_index, row) =
+                self.wrapped_row_before((self.text_location.line_index, row), step);
+            self.move_to_wrapped(line_index, row, col);
+        } else {
+            self.move_up(step);
+            self.move_to_column(col);
+        }
+        self.desired_col = Some((col, self.text_location));
+    }
+
+    /// 光标向下移动若干显示行：软换行时按显示行移动，否则按行移动。
+    ///
+    /// 光标尽量回到 `desired_column` 所在的列，经过较短的行时也不会丢失。
+    fn move_down_rows(&mut self, step: usize) {
+        let col = self.desired_column();
+        if self.soft_wrap {
+            self.move_down_wrapped(step, col);
+        } else {
+            self.move_down(step);
+            self.move_to_column(col);
+        }
+        self.desired_col = Some((col, self.text_location));
+    }
+
+    /// 上下移动时要回到的显示列：光标自上次上下移动（或 End）之后没有被移动过时沿用记下的列，
+    /// 否则取光标当前所在的列；软换行时是显示行内的列。
+    fn desired_column(&self) -> usize {
+        match self.desired_col {
+            Some((col, at)) if at == self.text_location => col,
+            _ if self.soft_wrap => self.wrapped_position(self.text_location).1,
+            _ => self
+                .buffer
+                .lines
+                .get(self.text_location.line_index)
+                .map_or(0, |line| line.width_until(self.text_location.grapheme_index, self.tab_width)),
+        }
+    }
+
+    /// 光标移到当前行中第 `col` 显示列所在的字上，行不够长时移到行尾。
+    fn move_to_column(&mut self, col: usize) {
+        let Some(line) = self.buffer.lines.get(self.text_location.line_index) else {
+            return;
+        };
+        let mut grapheme_index = line.grapheme_index_at_width(col, self.tab_width);
+        // 这一列落在宽字符或制表符的中间时停在这个字上，而不是越过它。
+        if line.width_until(grapheme_index, self.tab_width) > col {
+            grapheme_index = grapheme_index.saturating_sub(1);
+        }
+        self.text_location.grapheme_index = grapheme_index;
+    }
+
+    /// 软换行时光标向下移动若干显示行，停在第 `col` 列附近。
+    fn move_down_wrapped(&mut self, step: usize, col: usize) {
+        let (mut row, _) = self.wrapped_position(self.text_location);
+        let mut line_index = self.text_location.line_index;
+        for _ in 0..step {
+            if row.saturating_add(1) < self.wrap_starts(line_index).len() {
+                row = row.saturating_add(1);
+            } else if line_index.saturating_add(1) < self.buffer.height() {
+                line_index = line_index.saturating_add(1);
+                row = 0;
+            } else {
+                break;
+            }
+        }
+        self.move_to_wrapped(line_index, row, col);
+    }
+
+    /// 光标移动到软换行后指定行的第 `row` 个显示行中尽量接近第 `col` 列的位置。
+    fn move_to_wrapped(&mut self, line_index: usize, row: usize, col: usize) {
+        let starts = self.wrap_starts(line_index);
+        let start = starts.get(row).copied().unwrap_or_default();
+        let grapheme_index = self.buffer.lines.get(line_index).map_or(0, |line| {
+            let index = line.grapheme_index_at_width(
+                line.width_until(start, self.tab_width).saturating_add(col),
+                self.tab_width,
+            );
+            // 不是最后一个显示行时，光标不能落在下一显示行的开头。
+            starts
+                .get(row.saturating_add(1))
+                .map_or(index, |next| index.min(next.saturating_sub(1)))
+        });
+        self.text_location = Location {
+            grapheme_index,
+            line_index,
+        };
+        self.snap_to_valid_line();
+        self.snap_to_valid_grapheme();
+    }
+
     /// 光标向右移动
     #[allow(clippy::arithmetic_side_effects)]
     fn move_right(&mut self) {
@@ -168,7 +1723,7 @@ impl View {
         if self.text_location.grapheme_index < line_width {
             self.text_location.grapheme_index += 1;
         } else {
-            self.move_to_start_of_line();
+            self.text_location.grapheme_index = 0;
             self.move_down(1);
         }
     }
@@ -184,9 +1739,50 @@ impl View {
         }
     }
 
-    /// 光标移动到行首
+    /// 光标移动到前一个词的开头，已经在行首时移动到上一行的行尾。
+    fn move_word_left(&mut self) {
+        let grapheme_index = self.text_location.grapheme_index;
+        if grapheme_index == 0 {
+            self.move_left();
+            return;
+        }
+        self.text_location.grapheme_index = self
+            .buffer
+            .lines
+            .get(self.text_location.line_index)
+            .and_then(|line| line.previous_word_start(grapheme_index))
+            .unwrap_or(0);
+    }
+
+    /// 光标移动到后一个词的开头，后面没有词时移动到行尾，已经在行尾时移动到下一行的行首。
+    fn move_word_right(&mut self) {
+        let Some(line) = self.buffer.lines.get(self.text_location.line_index) else {
+            return;
+        };
+        let grapheme_index = self.text_location.grapheme_index;
+        if grapheme_index < line.grapheme_count() {
+            self.text_location.grapheme_index = line
+                .next_word_start(grapheme_index)
+                .unwrap_or_else(|| line.grapheme_count());
+        } else if self.text_location.line_index.saturating_add(1) < self.buffer.height() {
+            self.move_right();
+        }
+    }
+
+    /// 光标移动到行首：先移到第一个不是空白的字，已经在那里时再移到第 0 列；
+    /// 整行都是空白时直接移到第 0 列。
     fn move_to_start_of_line(&mut self) {
-        self.text_location.grapheme_index = 0;
+        let first = self
+            .buffer
+            .lines
+            .get(self.text_location.line_index)
+            .and_then(Line::first_non_whitespace_index)
+            .unwrap_or(0);
+        self.text_location.grapheme_index = if self.text_location.grapheme_index == first {
+            0
+        } else {
+            first
+        };
     }
 
     /// 光标移动到行尾
@@ -198,6 +1794,82 @@ impl View {
             .map_or(0, Line::grapheme_count);
     }
 
+    /// 光标移动到文档的第一行行首。
+    const fn move_to_document_start(&mut self) {
+        self.text_location = Location {
+            line_index: 0,
+            grapheme_index: 0,
+        };
+    }
+
+    /// 光标移动到文档最后一行的行尾。
+    fn move_to_document_end(&mut self) {
+        self.text_location.line_index = self.buffer.height().saturating_sub(1);
+        self.move_to_end_of_line();
+        self.snap_to_valid_line();
+        self.snap_to_valid_grapheme();
+    }
+
+    /// 光标移到视图中被点击的位置；点在行号栏上时移到行首，点在行尾之后时移到行尾，
+    /// 点在文件末尾之后时移到最后一行。
+    pub fn click(&mut self, at: Position) {
+        if at.row >= self.size.height {
+            return;
+        }
+        let gutter_width = self.gutter_width();
+        let rows = self.screen_rows(self.text_width());
+        let Some((line_index, columns, _)) = rows.get(at.row).or_else(|| rows.last()).cloned()
+        else {
+            return;
+        };
+        let Some(line) = self.buffer.lines.get(line_index) else {
+            return;
+        };
+        let column = columns
+            .start
+            .saturating_add(at.col.saturating_sub(gutter_width));
+        // 点在宽字符或制表符的后半部分时仍然落在这个字上。
+        let mut grapheme_index = line.grapheme_index_at_width(column, self.tab_width);
+        if line.width_until(grapheme_index, self.tab_width) > column {
+            grapheme_index = grapheme_index.saturating_sub(1);
+        }
+        let row_end = line.grapheme_index_at_width(columns.end, self.tab_width);
+        if self.soft_wrap && row_end < line.grapheme_count() {
+            // 软换行时行尾位置显示在下一显示行的开头，点在本显示行末尾之后停在最后一个字上。
+            grapheme_index = min(grapheme_index, row_end.saturating_sub(1));
+        }
+        self.clear_selection();
+        self.text_location = Location {
+            grapheme_index,
+            line_index,
+        };
+        self.snap_to_valid_grapheme();
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+    }
+
+    /// 光标跳到配对的括号处（见 `bracket_pair`），在整个缓冲区中查找，没有配对时不动。
+    fn move_to_matching_bracket(&mut self) {
+        if let Some((_, partner)) = self.bracket_pair(0..self.buffer.height()) {
+            self.text_location = partner;
+        }
+    }
+
+    /// 光标处的括号和与它配对的括号，只在 `within` 范围内的行中查找；
+    /// 光标处不是括号时看光标前的字，例如刚输入的闭括号。
+    fn bracket_pair(&self, within: Range<usize>) -> Option<(Location, Location)> {
+        let at = self.text_location;
+        let before = at
+            .grapheme_index
+            .checked_sub(1)
+            .map(|grapheme_index| Location { grapheme_index, ..at });
+        iter::once(at).chain(before).find_map(|bracket| {
+            self.buffer
+                .matching_bracket(bracket, within.clone())
+                .map(|partner| (bracket, partner))
+        })
+    }
+
     // ==================== 滚动相关方法 ====================
 
     /// 竖直滚动
@@ -219,12 +1891,20 @@ impl View {
 
     /// 水平滚动
     fn scroll_horizontally(&mut self, to: usize) {
-        let Size { width, .. } = self.size;
-        let offset_changed = if to < self.scroll_offset.col {
-            self.scroll_offset.col = to;
+        let width = self.text_width();
+        // 显示延续标记时，两侧各有一列被标记占用，光标不能停在标记上，
+        // 否则输入法的候选窗口会锚定在错误的位置。
+        let margin = usize::from(self.show_scroll_markers && width > 2);
+        let offset_changed = if to < self.scroll_offset.col.saturating_add(margin)
+            && self.scroll_offset.col > 0
+        {
+            self.scroll_offset.col = to.saturating_sub(margin);
             true
-        } else if to >= self.scroll_offset.col.saturating_add(width) {
-            self.scroll_offset.col = to.saturating_sub(width).saturating_add(1);
+        } else if to.saturating_add(margin) >= self.scroll_offset.col.saturating_add(width) {
+            self.scroll_offset.col = to
+                .saturating_add(margin)
+                .saturating_sub(width)
+                .saturating_add(1);
             true
         } else {
             false
@@ -234,26 +1914,217 @@ impl View {
         }
     }
 
+    /// 缓冲区被大范围修改后，将光标和滚动偏移恢复到有效范围。
+    ///
+    /// 光标尽量保持在原来的行号上，若该行已不存在则移动到最近的剩余行；
+    /// 只要缓冲区中还有内容，视图就不会停留在完全空白的位置。
+    fn normalize_after_change(&mut self) {
+        let height = self.buffer.height();
+        // 原来的行已被删除时停在最后一行上，而不是末尾之后的空行。
+        if self.text_location.line_index > height {
+            self.text_location.line_index = height.saturating_sub(1);
+        }
+        self.snap_to_valid_grapheme();
+
+        if self.scroll_offset.row >= height {
+            self.scroll_offset.row = height.saturating_sub(self.size.height);
+        }
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+    }
+
     /// 滚动文本位置到可见区域。
     fn scroll_text_location_into_view(&mut self) {
+        if self.soft_wrap {
+            self.scroll_wrapped_into_view();
+            return;
+        }
         let Position { row, col } = self.text_location_to_position();
         self.scroll_vertically(row);
         self.scroll_horizontally(col);
     }
 
+    /// 软换行时按显示行竖直滚动，使光标所在的显示行可见。
+    fn scroll_wrapped_into_view(&mut self) {
+        let (row, _) = self.wrapped_position(self.text_location);
+        let target = (self.text_location.line_index, row);
+        let top = self.wrapped_top();
+        let height = self.size.height.max(1);
+        let new_top = if target < top {
+            target
+        } else if self.wrapped_distance(top, target, height) < height {
+            top
+        } else {
+            self.wrapped_row_before(target, height.saturating_sub(1))
+        };
+        if new_top != (self.scroll_offset.row, self.wrap_row) {
+            (self.scroll_offset.row, self.wrap_row) = new_top;
+            self.set_needs_redraw(true);
+        }
+    }
+
+    /// 滚动视图而不移动光标，最多滚动到最后一行位于视图顶部；返回视图是否滚动了。
+    ///
+    /// 软换行时按显示行滚动。光标之后一旦移动或编辑，视图会重新滚动到光标处。
+    pub fn scroll_screen(&mut self, direction: ScrollDirection, amount: usize) -> bool {
+        let top = if self.soft_wrap {
+            self.wrapped_top()
+        } else {
+            (self.scroll_offset.row, 0)
+        };
+        let last_line = self.buffer.height().saturating_sub(1);
+        let new_top = match (direction, self.soft_wrap) {
+            (ScrollDirection::Up, true) => self.wrapped_row_before(top, amount),
+            (ScrollDirection::Down, true) => self.wrapped_row_after(top, amount),
+            (ScrollDirection::Up, false) => (top.0.saturating_sub(amount), 0),
+            (ScrollDirection::Down, false) => (min(top.0.saturating_add(amount), last_line), 0),
+        };
+        if new_top == top {
+            return false;
+        }
+        (self.scroll_offset.row, self.wrap_row) = new_top;
+        self.set_needs_redraw(true);
+        true
+    }
+
+    /// 光标是否在视图的可见范围内；用滚轮滚动视图之后光标可能在视图之外。
+    pub fn caret_visible(&self) -> bool {
+        let height = self.size.height;
+        if self.soft_wrap {
+            let (row, _) = self.wrapped_position(self.text_location);
+            let target = (self.text_location.line_index, row);
+            let top = self.wrapped_top();
+            target >= top && self.wrapped_distance(top, target, height) < height
+        } else {
+            let line_index = self.text_location.line_index;
+            line_index >= self.scroll_offset.row
+                && line_index < self.scroll_offset.row.saturating_add(height)
+        }
+    }
+
+    // ==================== 软换行相关方法 ====================
+
+    /// 软换行时指定行拆分成的各显示行开始处的字形索引。
+    fn wrap_starts(&self, line_index: usize) -> Vec<usize> {
+        self.buffer
+            .lines
+            .get(line_index)
+            .map_or_else(|| vec![0], |line| line.wrap_starts(self.text_width(), self.tab_width))
+    }
+
+    /// 软换行时视图顶部的行和显示行，显示行超出该行的范围（如缩小了宽度之后）时取最后一个。
+    fn wrapped_top(&self) -> (usize, usize) {
+        let line_index = self.scroll_offset.row;
+        let last = self.wrap_starts(line_index).len().saturating_sub(1);
+        (line_index, self.wrap_row.min(last))
+    }
+
+    /// 软换行时文本位置所在的显示行（在该行中的序号）和在显示行中的列。
+    fn wrapped_position(&self, location: Location) -> (usize, usize) {
+        let starts = self.wrap_starts(location.line_index);
+        let row = starts
+            .partition_point(|&start| start <= location.grapheme_index)
+            .saturating_sub(1);
+        let start = starts.get(row).copied().unwrap_or_default();
+        let col = self.buffer.lines.get(location.line_index).map_or(0, |line| {
+            line.width_until(location.grapheme_index, self.tab_width)
+                .saturating_sub(line.width_until(start, self.tab_width))
+        });
+        (row, col)
+    }
+
+    /// 软换行时从显示行 `from` 向下数到 `to` 相隔的显示行数，最多数到 `limit`。
+    fn wrapped_distance(&self, from: (usize, usize), to: (usize, usize), limit: usize) -> usize {
+        let (mut line_index, mut row) = from;
+        let mut distance: usize = 0;
+        while (line_index, row) < to && distance < limit {
+            if line_index == to.0 {
+                distance = distance.saturating_add(to.1.saturating_sub(row));
+                break;
+            }
+            let rows = self.wrap_starts(line_index).len();
+            distance = distance.saturating_add(rows.saturating_sub(row));
+            line_index = line_index.saturating_add(1);
+            row = 0;
+        }
+        distance.min(limit)
+    }
+
+    /// 软换行时显示行 `from` 向上 `count` 个显示行的位置，到达文件开头时停下。
+    fn wrapped_row_before(&self, from: (usize, usize), count: usize) -> (usize, usize) {
+        let (mut line_index, mut row) = from;
+        let mut count = count;
+        while count > row {
+            if line_index == 0 {
+                return (0, 0);
+            }
+            count = count.saturating_sub(row).saturating_sub(1);
+            line_index = line_index.saturating_sub(1);
+            row = self.wrap_starts(line_index).len().saturating_sub(1);
+        }
+        (line_index, row.saturating_sub(count))
+    }
+
+    /// 软换行时显示行 `from` 向下 `count` 个显示行的位置，到达最后一行的最后一个显示行时停下。
+    fn wrapped_row_after(&self, from: (usize, usize), count: usize) -> (usize, usize) {
+        let (mut line_index, mut row) = from;
+        let mut count = count;
+        let last_line = self.buffer.height().saturating_sub(1);
+        loop {
+            let available = self
+                .wrap_starts(line_index)
+                .len()
+                .saturating_sub(1)
+                .saturating_sub(row);
+            if count <= available || line_index >= last_line {
+                return (line_index, row.saturating_add(count.min(available)));
+            }
+            count = count.saturating_sub(available).saturating_sub(1);
+            line_index = line_index.saturating_add(1);
+            row = 0;
+        }
+    }
+
     // ==================== 辅助方法 ====================
 
     /// 获取当前光标位置。
     pub fn caret_position(&self) -> Position {
-        self.text_location_to_position()
-            .saturating_sub(self.scroll_offset)
+        if self.soft_wrap {
+            let (row, col) = self.wrapped_position(self.text_location);
+            let target = (self.text_location.line_index, row);
+            return Position {
+                row: self.wrapped_distance(self.wrapped_top(), target, self.size.height),
+                col: col.saturating_add(self.gutter_width()),
+            };
+        }
+        let Position { row, col } = self
+            .text_location_to_position()
+            .saturating_sub(self.scroll_offset);
+        Position {
+            row,
+            col: col.saturating_add(self.gutter_width()),
+        }
+    }
+
+    /// 行号栏的宽度：最大行号的位数加上一列分隔；只给修改过的行着色时为一列，都不显示时为 0。
+    fn gutter_width(&self) -> usize {
+        if self.show_line_numbers {
+            self.buffer.height().max(1).to_string().len().saturating_add(1)
+        } else {
+            usize::from(self.show_recent_changes || self.buffer.swap_point.get().is_some())
+        }
+    }
+
+    /// 扣除行号栏之后用于显示文本的宽度。
+    fn text_width(&self) -> usize {
+        self.size.width.saturating_sub(self.gutter_width())
     }
 
     /// 获取当前光标在缓冲区中的位置。
     fn text_location_to_position(&self) -> Position {
         let row = self.text_location.line_index;
         let col = self.buffer.lines.get(row).map_or(0, |line| {
-            line.width_until(self.text_location.grapheme_index)
+            line.width_until(self.text_location.grapheme_index, self.tab_width)
         });
         Position { col, row }
     }
@@ -296,20 +2167,475 @@ impl UIComponent for View {
         #[allow(clippy::integer_division)]
         let top_third = height / 3;
         let scroll_top = self.scroll_offset.row;
-        for current_row in origin_y..end_y {
-            let line_idx = current_row
-                .saturating_sub(origin_y)
-                .saturating_add(scroll_top);
-            if let Some(line) = self.buffer.lines.get(line_idx) {
-                let left = self.scroll_offset.col;
-                let right = self.scroll_offset.col.saturating_add(width);
-                Self::render_line(current_row, &line.get_visible_graphemes(left..right))?;
+        let gutter_width = self.gutter_width();
+        let text_width = width.saturating_sub(gutter_width);
+        self.buffer
+            .highlight_until(scroll_top.saturating_add(height).saturating_add(LOOKAHEAD));
+        let rows = self.screen_rows(text_width);
+        // 每帧都要查找配对的括号，只查找可见的行及其前后一段，以免没有配对时扫描整个文件。
+        let bracket_lines = scroll_top.saturating_sub(LOOKAHEAD)
+            ..scroll_top.saturating_add(height).saturating_add(LOOKAHEAD);
+        let bracket = self.bracket_pair(bracket_lines).map(|(_, partner)| partner);
+        for (offset, current_row) in (origin_y..end_y).enumerate() {
+            if let Some((line_idx, columns, first)) = rows.get(offset).cloned()
+                && let Some(line) = self.buffer.lines.get(line_idx)
+            {
+                let Range { start: left, end: right } = columns;
+                let gutter = (gutter_width > 0).then(|| {
+                    if first {
+                        self.gutter_segment(line_idx, gutter_width)
+                    } else {
+                        // 软换行的后续显示行不重复行号。
+                        StyledText {
+                            text: " ".repeat(gutter_width),
+                            style: None,
+                        }
+                    }
+                });
+                if self.flash.as_ref().is_some_and(|flash| flash.contains(&line_idx)) {
+                    let number = gutter.map(|gutter| gutter.text).unwrap_or_default();
+                    Terminal::print_inverted_row(
+                        current_row,
+                        &format!("{number}{}", line.get_visible_graphemes(left..right, self.tab_width)),
+                    )?;
+                } else {
+                    let mut spans = self.buffer.highlight_spans(line_idx);
+                    let highlighted;
+                    let marked = self.marked_spans(line_idx, bracket);
+                    if !marked.is_empty() {
+                        // 选区、当前匹配和配对括号的高亮放在最前面，优先于语法高亮。
+                        highlighted = marked
+                            .into_iter()
+                            .chain(spans.iter().cloned())
+                            .collect::<Vec<_>>();
+                        spans = &highlighted;
+                    }
+                    let tinted = self.buffer.marks.contains(line_idx);
+                    let mut segments: Vec<StyledText> = gutter.into_iter().collect();
+                    if self.show_scroll_markers && !self.soft_wrap {
+                        segments.extend(Self::segments_with_markers(line, left..right, spans, self.tab_width));
+                    } else {
+                        segments.extend(line.get_visible_segments(left..right, spans, self.tab_width));
+                    }
+                    Self::render_segments(current_row, &segments, tinted)?;
+                }
             } else if current_row == top_third && self.buffer.is_empty() {
                 Self::render_line(current_row, &Self::build_welcome_message(width))?;
             } else {
-                Self::render_line(current_row, "~")?;
+                // 文件末尾之后的行号栏留空。
+                Self::render_line(current_row, &format!("{}~", " ".repeat(gutter_width)))?;
             }
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn lines(view: &View) -> Vec<String> {
+        view.buffer.lines.iter().map(ToString::to_string).collect()
+    }
+
+    /// 有 `count` 行文本、视口为 80x20 的视图。
+    fn numbered_view(count: usize) -> View {
+        let text: Vec<String> = (0..count).map(|index| format!("line {index}")).collect();
+        let mut view = View {
+            buffer: Buffer::from_text(&text.join("\n")),
+            ..View::default()
+        };
+        view.resize(Size { height: 20, width: 80 });
+        view
+    }
+
+    /// 视口中至少有一行显示的是缓冲区的内容，而不全是 `~`。
+    fn shows_content(view: &View) -> bool {
+        view.scroll_offset.row < view.buffer.height()
+    }
+
+    const fn row_col(position: Position) -> (usize, usize) {
+        (position.row, position.col)
+    }
+
+    fn type_text(view: &mut View, text: &str) {
+        for character in text.chars() {
+            view.handle_edit_command(Edit::Insert(character)).unwrap();
+        }
+    }
+
+    #[test]
+    fn unsaved_diff_decodes_the_file_on_disk_with_its_encoding() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("utf16.txt");
+        let utf16: Vec<u8> = "\u{feff}alpha\nbeta\n"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        fs::write(&path, utf16).unwrap();
+
+        let mut view = View::default();
+        view.load(&path).unwrap();
+        view.goto_line(1);
+        view.handle_edit_command(Edit::Insert('x')).unwrap();
+
+        let diff = view.unsaved_diff().unwrap();
+        assert_eq!(
+            diff,
+            vec![
+                DiffLine::Removed(1, String::from("beta")),
+                DiffLine::Added(1, String::from("xbeta")),
+            ]
+        );
+    }
+
+    #[test]
+    fn a_run_of_typing_is_undone_in_one_step() {
+        let mut view = View::default();
+        type_text(&mut view, "hello 世界");
+
+        view.handle_edit_command(Edit::Undo).unwrap();
+
+        assert!(lines(&view).is_empty());
+        assert_eq!(view.text_location, Location::default());
+        assert!(view.handle_edit_command(Edit::Undo).is_err());
+        view.handle_edit_command(Edit::Redo).unwrap();
+        assert_eq!(lines(&view), vec![String::from("hello 世界")]);
+    }
+
+    #[test]
+    fn a_new_edit_after_undo_clears_the_redo_stack() {
+        let mut view = View::default();
+        type_text(&mut view, "ab");
+        view.handle_edit_command(Edit::Undo).unwrap();
+
+        type_text(&mut view, "x");
+
+        assert!(view.handle_edit_command(Edit::Redo).is_err());
+        assert_eq!(lines(&view), vec![String::from("x")]);
+    }
+
+    #[test]
+    fn undoing_back_to_the_saved_state_clears_the_modified_flag() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        fs::write(&path, "alpha\nbeta\n").unwrap();
+        let mut view = View::default();
+        view.load(&path).unwrap();
+        view.goto_line(1);
+
+        type_text(&mut view, "xy");
+        assert!(view.buffer.dirty);
+        view.handle_edit_command(Edit::Undo).unwrap();
+        assert!(!view.buffer.dirty);
+        assert_eq!(view.text_location, Location { grapheme_index: 0, line_index: 1 });
+
+        view.handle_edit_command(Edit::Redo).unwrap();
+        view.save().unwrap();
+        type_text(&mut view, " z");
+        view.handle_edit_command(Edit::Undo).unwrap();
+        assert!(!view.buffer.dirty);
+        view.handle_edit_command(Edit::Undo).unwrap();
+        assert!(view.buffer.dirty);
+        assert_eq!(lines(&view), vec![String::from("alpha"), String::from("beta")]);
+    }
+
+    #[test]
+    fn block_comment_wraps_the_selected_lines_in_one_undo_step() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("main.c");
+        fs::write(&path, "int a;\nint b;\nint c;\n").unwrap();
+        let mut view = View::default();
+        view.load(&path).unwrap();
+        view.handle_move_command(Move::Down, true);
+        view.handle_move_command(Move::Down, true);
+
+        view.handle_edit_command(Edit::ToggleBlockComment).unwrap();
+        assert_eq!(lines(&view), vec!["/* int a;", "int b; */", "int c;"]);
+
+        view.handle_edit_command(Edit::Undo).unwrap();
+        assert_eq!(lines(&view), vec!["int a;", "int b;", "int c;"]);
+    }
+
+    #[test]
+    fn collapsing_the_buffer_keeps_the_caret_and_content_visible() {
+        let mut view = numbered_view(1000);
+        view.goto_line(900);
+        assert!(view.scroll_offset.row > 800);
+
+        view.delete_lines(LineRange { start: 3, end: 999 });
+
+        assert_eq!(view.buffer.height(), 3);
+        assert_eq!(view.text_location.line_index, 2);
+        assert_eq!(view.scroll_offset.row, 0);
+        assert!(shows_content(&view));
+    }
+
+    #[test]
+    fn deleting_lines_above_the_viewport_keeps_content_on_screen() {
+        let mut view = numbered_view(1000);
+        view.goto_line(960);
+        let top = view.scroll_offset.row;
+
+        view.delete_lines(LineRange { start: 100, end: top.saturating_sub(1) });
+
+        let caret = view.text_location.line_index;
+        assert!(shows_content(&view));
+        assert!(caret < view.buffer.height());
+        assert!((view.scroll_offset.row..view.scroll_offset.row.saturating_add(20)).contains(&caret));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn saving_through_a_chain_of_links_writes_the_final_target_and_keeps_the_links() {
+        use std::os::unix::fs::symlink;
+        let dir = tempdir().unwrap();
+        let target = dir.path().join("target.txt");
+        std::fs::write(&target, "text\n").unwrap();
+        std::fs::create_dir(dir.path().join("dotfiles")).unwrap();
+        // 相对的链接目标相对于链接所在的目录。
+        symlink("../target.txt", dir.path().join("dotfiles/middle.txt")).unwrap();
+        let link = dir.path().join("link.txt");
+        symlink("dotfiles/middle.txt", &link).unwrap();
+        let mut view = View::default();
+        view.load(&link).unwrap();
+        assert!(view.get_status().is_symlink);
+        assert_eq!(view.get_status().symlink_indicator_to_string(), " ->");
+
+        type_text(&mut view, "more ");
+        view.save().unwrap();
+
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "more text\n");
+        assert!(link.symlink_metadata().unwrap().file_type().is_symlink());
+        let middle = dir.path().join("dotfiles/middle.txt").symlink_metadata().unwrap();
+        assert!(middle.file_type().is_symlink());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn breaking_links_on_save_replaces_the_link_with_a_regular_file() {
+        use std::os::unix::fs::symlink;
+        let dir = tempdir().unwrap();
+        let target = dir.path().join("target.txt");
+        std::fs::write(&target, "text\n").unwrap();
+        let link = dir.path().join("link.txt");
+        symlink(&target, &link).unwrap();
+        let mut view = View::default();
+        view.set_break_symlinks(true);
+        view.load(&link).unwrap();
+
+        type_text(&mut view, "mine ");
+        view.save().unwrap();
+
+        assert!(link.symlink_metadata().unwrap().file_type().is_file());
+        assert_eq!(std::fs::read_to_string(&link).unwrap(), "mine text\n");
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "text\n");
+        assert!(!view.get_status().is_symlink);
+    }
+
+    /// 中英文长短交错的几行，用来检查上下移动时的列。
+    fn mixed_width_view() -> View {
+        let mut view = View {
+            buffer: Buffer::from_text("abcdefgh\n好好\n\n中文中文中文\nabcdefghij"),
+            ..View::default()
+        };
+        view.resize(Size { height: 20, width: 80 });
+        view
+    }
+
+    fn moved(view: &mut View, moves: &[Move]) -> (usize, usize) {
+        for &command in moves {
+            view.move_caret(command);
+        }
+        (view.text_location.line_index, view.text_location.grapheme_index)
+    }
+
+    #[test]
+    fn the_column_survives_short_and_wide_lines_in_both_directions() {
+        let mut view = mixed_width_view();
+        view.goto_location(0, 6);
+
+        assert_eq!(moved(&mut view, &[Move::Down]), (1, 2));
+        assert_eq!(moved(&mut view, &[Move::Down]), (2, 0));
+        // 第 6 列在宽字符之间，落在第三个字之后。
+        assert_eq!(moved(&mut view, &[Move::Down]), (3, 3));
+        assert_eq!(moved(&mut view, &[Move::Down]), (4, 6));
+        assert_eq!(moved(&mut view, &[Move::Up, Move::Up, Move::Up, Move::Up]), (0, 6));
+    }
+
+    #[test]
+    fn a_column_inside_a_wide_character_stops_on_it_and_is_still_remembered() {
+        let mut view = mixed_width_view();
+        view.goto_location(0, 5);
+
+        assert_eq!(moved(&mut view, &[Move::Down, Move::Down, Move::Down]), (3, 2));
+        assert_eq!(moved(&mut view, &[Move::Down]), (4, 5));
+    }
+
+    #[test]
+    fn end_sticks_to_line_ends_and_home_to_line_starts() {
+        let mut view = mixed_width_view();
+        view.goto_location(1, 0);
+
+        assert_eq!(moved(&mut view, &[Move::EndOfLine]), (1, 2));
+        assert_eq!(moved(&mut view, &[Move::Down]), (2, 0));
+        assert_eq!(moved(&mut view, &[Move::Down]), (3, 6));
+        assert_eq!(moved(&mut view, &[Move::Down]), (4, 10));
+        assert_eq!(moved(&mut view, &[Move::StartOfLine, Move::Up]), (3, 0));
+    }
+
+    #[test]
+    fn page_moves_keep_the_column_and_horizontal_moves_reset_it() {
+        let mut view = mixed_width_view();
+        view.resize(Size { height: 3, width: 80 });
+        view.goto_location(0, 6);
+
+        // 视口三行高，翻页移动两行。
+        assert_eq!(moved(&mut view, &[Move::PageDown]), (2, 0));
+        assert_eq!(moved(&mut view, &[Move::PageDown]), (4, 6));
+        assert_eq!(moved(&mut view, &[Move::Up]), (3, 3));
+        // 在短行上左右移动后，新的位置成为要回到的列。
+        assert_eq!(moved(&mut view, &[Move::Left, Move::Down]), (4, 4));
+        assert_eq!(moved(&mut view, &[Move::PageUp]), (2, 0));
+        assert_eq!(moved(&mut view, &[Move::PageUp]), (0, 4));
+    }
+
+    #[test]
+    fn typing_on_a_short_line_sets_a_new_column() {
+        let mut view = mixed_width_view();
+        view.goto_location(0, 6);
+        moved(&mut view, &[Move::Down]);
+
+        type_text(&mut view, "!");
+
+        assert_eq!(moved(&mut view, &[Move::Down, Move::Down]), (3, 2));
+        assert_eq!(moved(&mut view, &[Move::Up, Move::Up, Move::Up]), (0, 5));
+    }
+
+    #[test]
+    fn drawing_a_large_file_segments_only_the_visible_lines() {
+        let dir = tempdir().unwrap();
+        let height = 10;
+        for name in ["large.txt", "large.rs"] {
+            let path = dir.path().join(name);
+            let text: Vec<String> = (0..100_000)
+                .map(|index| format!("let line_{index} = \"好 text\"; // {index}"))
+                .collect();
+            std::fs::write(&path, text.join("\n")).unwrap();
+            let mut view = View::default();
+            view.load(&path).unwrap();
+            // 打开时最多只切分光标所在的一行。
+            let (segmented, total) = view.segmented_lines();
+            assert!(segmented <= 1, "{name}: {segmented} lines segmented on load");
+            assert_eq!(total, 100_000);
+
+            view.resize(Size { height, width: 40 });
+            view.render(0).unwrap();
+            let (segmented, _) = view.segmented_lines();
+            assert!(segmented <= height + LOOKAHEAD, "{name}: {segmented} lines segmented");
+
+            // 跳到文件中间再绘制，也只增加新的可见区域。
+            view.goto_location(50_000, 0);
+            view.render(0).unwrap();
+            let (segmented, _) = view.segmented_lines();
+            assert!(segmented <= 2 * (height + LOOKAHEAD), "{name}: {segmented} lines segmented");
+        }
+        super::super::terminal::headless::take_output();
+    }
+
+    #[test]
+    fn welcome_message_with_wide_characters_is_centered_by_columns() {
+        for message in ["雪 editor -- version 1.0", "❄️ editor", "雪花编辑器"] {
+            let len = message.width();
+            for width in 0..40 {
+                let line = View::center_welcome(message, width);
+                assert!(line.width() <= width, "{message:?} at {width}: {line:?}");
+                if width <= len {
+                    assert_eq!(line, if width == 0 { "" } else { "~" });
+                    continue;
+                }
+                // 放得下时正好占满整行，左边的空白不多于右边。
+                assert_eq!(line.width(), width, "{message:?} at {width}: {line:?}");
+                let rest = line.strip_prefix('~').unwrap();
+                let left = rest.len().saturating_sub(rest.trim_start().len());
+                let right = rest.len().saturating_sub(rest.trim_end().len());
+                assert_eq!(rest.trim(), message);
+                assert!(left <= right && right <= left.saturating_add(1), "{message:?} at {width}: {left}/{right}");
+            }
+        }
+        assert_eq!(View::center_welcome("雪花", 8), "~ 雪花  ");
+    }
+
+    #[test]
+    fn inserting_wide_characters_keeps_the_caret_right_after_them() {
+        let mut view = View::default();
+        view.resize(Size { height: 5, width: 10 });
+        type_text(&mut view, "你好");
+        assert_eq!(view.text_location.grapheme_index, 2);
+        assert_eq!(row_col(view.text_location_to_position()), (0, 4));
+        assert_eq!(row_col(view.caret_position()), (0, 4));
+
+        // 一直输入到超出视口，光标每一步都紧跟在刚插入的字形之后并保持可见。
+        for (count, character) in "你好你好你好".chars().enumerate() {
+            view.handle_edit_command(Edit::Insert(character)).unwrap();
+            let col = count.saturating_add(3).saturating_mul(2);
+            assert_eq!(view.text_location_to_position().col, col);
+            let caret = view.caret_position();
+            assert_eq!(caret.col, col.saturating_sub(view.scroll_offset.col));
+            assert!(caret.col < view.text_width(), "caret at {} after {count} characters", caret.col);
+        }
+    }
+
+    #[test]
+    fn combining_marks_and_tabs_place_the_caret_by_rendered_width() {
+        let mut view = View::default();
+        view.resize(Size { height: 5, width: 20 });
+        type_text(&mut view, "e\u{301}");
+        assert_eq!(view.text_location.grapheme_index, 1);
+        assert_eq!(row_col(view.caret_position()), (0, 1));
+        type_text(&mut view, "\t");
+        assert_eq!(row_col(view.caret_position()), (0, 4));
+        type_text(&mut view, "好");
+        assert_eq!(row_col(view.caret_position()), (0, 6));
+        assert_eq!(lines(&view), ["e\u{301}\t好"]);
+    }
+
+    #[test]
+    fn each_view_keeps_its_own_tab_width() {
+        let mut narrow = View::default();
+        let mut wide = View::default();
+        wide.apply_config(&Config {
+            tab_width: 8,
+            ..Config::default()
+        });
+        for view in [&mut narrow, &mut wide] {
+            view.resize(Size { height: 5, width: 40 });
+            type_text(view, "a\tb");
+        }
+        assert_eq!(row_col(narrow.caret_position()).1, narrow.gutter_width().saturating_add(5));
+        assert_eq!(row_col(wide.caret_position()).1, wide.gutter_width().saturating_add(9));
+    }
+
+    #[test]
+    fn configured_trash_limits_apply_to_deletions_in_the_view() {
+        let mut view = View::default();
+        view.load_reader("a\nb\nc\nd\n".as_bytes(), "[test]").unwrap();
+        view.apply_config(&Config {
+            trash: TrashLimits {
+                min_lines: 1,
+                capacity: 2,
+                ..TrashLimits::default()
+            },
+            ..Config::default()
+        });
+
+        for _ in 0..3 {
+            view.handle_edit_command(Edit::DeleteLine).unwrap();
+        }
+
+        let deleted: Vec<&str> =
+            view.trash().entries().iter().map(|entry| entry.text.as_str()).collect();
+        assert_eq!(deleted, vec!["c", "b"]);
+    }
+}
@@ -1,39 +1,191 @@
-use std::fs::{read_to_string, File};
-use std::io::Error;
-use std::io::Write;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Error, ErrorKind, Read};
+use std::ops::Range;
+use std::path::Path;
+use std::thread;
+use std::time::Instant;
+use unicode_segmentation::UnicodeSegmentation;
+use crate::editor::encoding::FileEncoding;
 use crate::editor::fileinfo::FileInfo;
+use crate::editor::filetype::BlockComment;
 
-use super::line::Line;
-use super::Location;
+use super::highlighter::{Highlighter, Span};
+use super::history::{CaretState, Change, History, Splice, Step, StepKind, TextEdit};
+use super::indentation::IndentStyle;
+use super::line::{Line, TabWidth};
+use super::linemarks::LineMarks;
+use super::recentchanges::RecentChanges;
+use super::swapfile::SwapFile;
+use super::swappoint::SwapPoint;
+use super::textformat::{LineEnding, TextFormat};
+use super::{LineChange, Location, Match, SaveSnapshot};
+
+/// 检查文件开头这么多字节中有没有 NUL，以识别二进制文件。
+const BINARY_PROBE_LEN: usize = 8 * 1024;
+
+/// 可以配对的括号。
+const BRACKETS: [(u8, u8); 3] = [(b'(', b')'), (b'[', b']'), (b'{', b'}')];
+
+/// 文本的统计结果。
+pub struct TextStats {
+    pub lines: usize,     // 行数。
+    pub graphemes: usize, // 字数（字形），不计换行符。
+    pub words: usize,     // 词数：被空白分隔开的连续非空白字符。
+}
+
+impl TextStats {
+    /// 统计一段以 `\n` 分行的文本。
+    pub fn of(text: &str) -> Self {
+        Self {
+            lines: text.split('\n').count(),
+            graphemes: text.graphemes(true).filter(|grapheme| *grapheme != "\n").count(),
+            words: text.split_whitespace().count(),
+        }
+    }
+}
 
 /// 存储文本内容,进行底层交互。
 #[derive(Default)]
+
 pub struct Buffer {
     pub lines: Vec<Line>,               // 存储文本内容的行向量。
     pub file_info: FileInfo,      // 文件信息
     pub dirty: bool,                    // 标志是否已经被修改（脏数据）。
+    history: History,                   // 撤销和重做的历史。
+    highlighter: Highlighter,           // 语法高亮。
+    revision: u64,                      // 每次修改内容时递增，用于判断保存期间是否有新的修改。
+    pub marks: LineMarks,               // 带有持久高亮的行。
+    pub changes: RecentChanges,         // 每一行最后一次被修改的代数，用于给最近修改过的行着色。
+    pub swap_point: SwapPoint,          // 与光标来回切换的位置。
+    format: TextFormat,                 // 文件的换行符、结尾换行和字节顺序标记，保存时保持不变。
+    pub backed_up: bool,                // 是否已经为这个文件备份过磁盘上的原文件，每个文件只备份一次。
+    pub swap: SwapFile,                 // 崩溃后用于恢复未保存修改的交换文件。
 }
 
 impl Buffer {
     /// 读取文件，加载到缓冲区。
-    pub fn load(file_name: &str) -> Result<Self, Error> {
-        // 读取文件内容为字符串
-        let contents = read_to_string(file_name)?;
-        let mut lines = Vec::new();
+    ///
+    /// 有 UTF-16 字节顺序标记时按 UTF-16 解码，否则按 UTF-8 读取；不是有效的 UTF-8 时
+    /// 改用后备编码 `fallback`，没有配置后备编码时拒绝打开。识别出的编码记在 `file_info` 中，
+    /// 保存时按同样的编码写回。
+    pub fn load(path: &Path, fallback: Option<FileEncoding>) -> Result<Self, Error> {
+        // 路径是符号链接时读取它最终指向的文件
+        let mut file_info = FileInfo::resolve(path)?;
+        let source = file_info.save_path().unwrap_or(path).to_path_buf();
+        let file = File::open(&source)?;
+        file_info.record_disk_state();
+        file_info.detect_read_only();
 
-        // 将文件内容按行分割并存储到 `lines` 向量中
-        for value in contents.lines() {
-            lines.push(Line::from(value));
-        }
+        let mut reader = BufReader::with_capacity(BINARY_PROBE_LEN, file);
+        let head = reader.fill_buf()?;
+        let (format, values) = if let Some(encoding) = FileEncoding::from_utf16_bom(head) {
+            file_info.encoding = encoding;
+            Self::decode(reader, encoding)?
+        } else if head.contains(&0) {
+            // 拒绝打开二进制文件，以免显示成乱码后又被原样保存而损坏
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "Binary file (contains NUL bytes), not opened",
+            ));
+        } else {
+            // 逐行读取文件并识别文件的格式；各行的字形在显示或编辑时才切分
+            match TextFormat::read(reader) {
+                Ok(read) => read,
+                Err(err) if err.kind() == ErrorKind::InvalidData => {
+                    // 没有配置后备编码时不猜测编码，以免乱码被原样保存而损坏文件
+                    let Some(fallback) = fallback.filter(|fallback| !fallback.is_utf8()) else {
+                        return Err(Error::new(
+                            ErrorKind::InvalidData,
+                            format!("{err}, not opened (set fallback_encoding to open it)"),
+                        ));
+                    };
+                    file_info.encoding = fallback;
+                    Self::decode(File::open(&source)?, fallback).map_err(|_| {
+                        Error::new(ErrorKind::InvalidData, format!("{err}, nor valid {fallback}"))
+                    })?
+                }
+                Err(err) => return Err(err),
+            }
+        };
+        let lines = values.into_iter().map(Line::from_string).collect();
 
         // 返回包含行数据的 `Buffer` 实例
         Ok(Self { 
             lines ,
-            file_info: FileInfo::from(file_name),
+            highlighter: Highlighter::new(file_info.file_type),
+            file_info,
             dirty: false,
+            history: History::default(),
+            revision: 0,
+            marks: LineMarks::default(),
+            changes: RecentChanges::default(),
+            swap_point: SwapPoint::default(),
+            format,
+            backed_up: false,
+            swap: SwapFile::default(),
         })
     }
 
+    /// 用给定的文本创建未命名的缓冲区，视为尚未保存；换行符等格式从文本中识别。
+    pub fn from_text(text: &str) -> Self {
+        let mut history = History::default();
+        history.forget_saved();
+        let (format, values) = TextFormat::parse(text);
+        Self {
+            lines: values.into_iter().map(Line::from).collect(),
+            file_info: FileInfo::default(),
+            dirty: true,
+            history,
+            highlighter: Highlighter::default(),
+            revision: 0,
+            marks: LineMarks::default(),
+            changes: RecentChanges::default(),
+            swap_point: SwapPoint::default(),
+            format,
+            backed_up: false,
+            swap: SwapFile::default(),
+        }
+    }
+
+    /// 读取全部内容并按指定的编码解码，识别格式并按行拆分。
+    pub fn decode(
+        mut reader: impl Read,
+        encoding: FileEncoding,
+    ) -> Result<(TextFormat, Vec<String>), Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        let text = encoding
+            .decode(&bytes)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, format!("Not valid {encoding}")))?;
+        let (format, lines) = TextFormat::parse(&text);
+        Ok((format, lines.into_iter().map(str::to_string).collect()))
+    }
+
+    /// 读取全部输入作为未命名的缓冲区，例如通过管道传来的标准输入；
+    /// 内容与输入一致，因此不视为修改过，保存时询问文件名。
+    pub fn from_reader(reader: impl Read) -> Result<Self, Error> {
+        let (format, values) = TextFormat::read(BufReader::new(reader))?;
+        let mut buffer = Self::default();
+        buffer.lines = values.into_iter().map(Line::from_string).collect();
+        buffer.format = format;
+        Ok(buffer)
+    }
+
+    /// 修改缓冲区对应的文件，语法高亮随新的文件类型重新开始。
+    ///
+    /// 新路径是指向已有文件的符号链接时同样保存到链接指向的文件；新的文件同样会在第一次保存前备份。
+    pub fn set_path(&mut self, path: &Path) {
+        self.backed_up = false;
+        if let Some(old) = self.file_info.save_path().map(Path::to_path_buf) {
+            self.swap.remove(&old);
+        }
+        let encoding = self.file_info.encoding;
+        self.file_info = FileInfo::resolve(path).unwrap_or_else(|_| FileInfo::from(path));
+        self.file_info.encoding = encoding;
+        self.file_info.detect_read_only();
+        self.highlighter = Highlighter::new(self.file_info.file_type);
+    }
+
     /// 检查缓冲区是否为空。
     pub fn is_empty(&self) -> bool {
         self.lines.is_empty()
@@ -44,61 +196,1217 @@ impl Buffer {
         self.lines.len()
     }
 
+    /// 已经切分过字形的行数，其余的行只保存原始文本。
+    pub fn segmented_lines(&self) -> usize {
+        self.lines.iter().filter(|line| line.is_segmented()).count()
+    }
+
     /// 插入字符串到指定位置。
     pub fn insert_char(&mut self, character: char, at: Location){
         if at.line_index > self.height() {
             return;
         }
         if at.line_index == self.height() {
-            self.lines.push(Line::from(&character.to_string()));
-            self.dirty = true;
-        } else if let Some(line) = self.lines.get_mut(at.line_index) {
-            line.insert_char(character, at.grapheme_index);
-            self.dirty = true;
+            self.splice(at.line_index, 0, vec![character.to_string()]);
+        } else {
+            let at_grapheme = at.grapheme_index..at.grapheme_index;
+            self.splice_text(at.line_index, at_grapheme, character.encode_utf8(&mut [0; 4]));
+        }
+    }
+
+    /// 在指定位置插入一段可能包含多行的文本，返回插入内容末尾的位置。
+    pub fn insert_str(&mut self, at: Location, text: &str) -> Location {
+        if at.line_index > self.height() {
+            return at;
+        }
+        if at.line_index < self.height() && !text.contains('\n') {
+            let old_count = self.lines.get(at.line_index).map_or(0, Line::grapheme_count);
+            let at_grapheme = at.grapheme_index..at.grapheme_index;
+            self.splice_text(at.line_index, at_grapheme, text.strip_suffix('\r').unwrap_or(text));
+            let new_count = self.lines.get(at.line_index).map_or(0, Line::grapheme_count);
+            return Location {
+                grapheme_index: at
+                    .grapheme_index
+                    .saturating_add(new_count.saturating_sub(old_count))
+                    .min(new_count),
+                ..at
+            };
+        }
+        let (mut line, count) = self
+            .lines
+            .get(at.line_index)
+            .map_or_else(|| (Line::default(), 0), |line| (Line::from(&line.to_string()), 1));
+        let rest = line.split(at.grapheme_index);
+        let mut new_lines = Vec::new();
+        for (offset, segment) in text.split('\n').enumerate() {
+            let segment = segment.strip_suffix('\r').unwrap_or(segment);
+            if offset == 0 {
+                line.insert_str(segment, at.grapheme_index);
+            } else {
+                new_lines.push(line.to_string());
+                line = Line::from(segment);
+            }
+        }
+        let end = Location {
+            grapheme_index: line.grapheme_count(),
+            line_index: at.line_index.saturating_add(new_lines.len()),
+        };
+        line.append(&rest);
+        new_lines.push(line.to_string());
+        self.splice(at.line_index, count, new_lines);
+        end
+    }
+
+    /// 删除指定位置开始的 `match_len` 个字形并在该处插入新文本，返回插入内容末尾的位置。
+    pub fn replace(&mut self, at: Location, match_len: usize, replacement: &str) -> Location {
+        let Some(line) = self.lines.get(at.line_index) else {
+            return at;
+        };
+        let old_count = line.grapheme_count();
+        let matched = at.grapheme_index..at.grapheme_index.saturating_add(match_len).min(old_count);
+        let kept = old_count.saturating_sub(matched.len());
+        self.splice_text(at.line_index, matched, replacement);
+        let new_count = self.lines.get(at.line_index).map_or(0, Line::grapheme_count);
+        Location {
+            grapheme_index: at
+                .grapheme_index
+                .saturating_add(new_count.saturating_sub(kept))
+                .min(new_count),
+            line_index: at.line_index,
         }
     }
 
+    /// 按列对齐插入多行文本：第一行插入到指定位置，之后的每一行插入到
+    /// 下面各行的同一显示列，行不够长时用空格补齐，行不存在时新建。
+    /// 返回插入内容末尾的位置。
+    pub fn insert_str_aligned(&mut self, at: Location, text: &str, tab_width: TabWidth) -> Location {
+        if at.line_index > self.height() {
+            return at;
+        }
+        let column = self
+            .lines
+            .get(at.line_index)
+            .map_or(0, |line| line.width_until(at.grapheme_index, tab_width));
+        let text = text.strip_suffix('\n').unwrap_or(text);
+        let mut end = at;
+        let mut new_lines = Vec::new();
+        let mut count: usize = 0;
+        for (offset, segment) in text.split('\n').enumerate() {
+            let segment = segment.strip_suffix('\r').unwrap_or(segment);
+            let line_index = at.line_index.saturating_add(offset);
+            let mut line = match self.lines.get(line_index) {
+                Some(line) => {
+                    count = count.saturating_add(1);
+                    Line::from(&line.to_string())
+                }
+                None => Line::default(),
+            };
+            let padding = column.saturating_sub(line.width(tab_width));
+            let grapheme_index = if offset == 0 {
+                at.grapheme_index
+            } else {
+                line.grapheme_index_at_width(column, tab_width)
+            };
+            let old_count = line.grapheme_count();
+            line.insert_str(&format!("{}{segment}", " ".repeat(padding)), grapheme_index);
+            end = Location {
+                grapheme_index: grapheme_index
+                    .saturating_add(line.grapheme_count().saturating_sub(old_count)),
+                line_index,
+            };
+            new_lines.push(line.to_string());
+        }
+        self.splice(at.line_index, count, new_lines);
+        end
+    }
+
     /// 删除字符。
     pub fn delete(&mut self, at: Location){
         if let Some(line) = self.lines.get(at.line_index){
             if at.grapheme_index >= line.grapheme_count()
-            && self.height() > at.line_index.saturating_add(1){
-                let next_line = self.lines.remove(at.line_index.saturating_add(1));
-
-                #[allow(clippy::indexing_slicing)]
-                self.lines[at.line_index].append(&next_line);
-                self.dirty = true;
-
+            && let Some(next_line) = self.lines.get(at.line_index.saturating_add(1)) {
+                let joined = format!("{line}{next_line}");
+                self.splice(at.line_index, 2, vec![joined]);
             } else if at.grapheme_index < line.grapheme_count() {
-                #[allow(clippy::indexing_slicing)]
-                self.lines[at.line_index].delete(at.grapheme_index);
-                self.dirty = true;
+                let at_grapheme = at.grapheme_index..at.grapheme_index.saturating_add(1);
+                self.splice_text(at.line_index, at_grapheme, "");
             }
         }
     }
 
+    /// 删除从 `start` 到 `end`（不含）之间的文本；跨行时第一行剩下的开头与最后一行剩下的结尾合并为一行。
+    pub fn delete_range(&mut self, start: Location, end: Location) {
+        let (Some(first), Some(last)) = (self.lines.get(start.line_index), self.lines.get(end.line_index))
+        else {
+            return;
+        };
+        if end.line_index < start.line_index {
+            return;
+        }
+        if end.line_index == start.line_index {
+            self.splice_text(start.line_index, start.grapheme_index..end.grapheme_index, "");
+            return;
+        }
+        let mut joined = Line::from(&first.to_string());
+        joined.split(start.grapheme_index);
+        let rest = Line::from(&last.to_string()).split(end.grapheme_index);
+        joined.append(&rest);
+        let count = end.line_index.saturating_sub(start.line_index).saturating_add(1);
+        self.splice(start.line_index, count, vec![joined.to_string()]);
+    }
+
+    /// 从 `start` 到 `end`（不含）之间的文本，跨行时以换行符连接。
+    pub fn extract_range(&self, start: Location, end: Location) -> String {
+        let mut parts = Vec::new();
+        for line_index in start.line_index..=end.line_index {
+            let Some(line) = self.lines.get(line_index) else {
+                break;
+            };
+            let text = line.to_string();
+            let from = if line_index == start.line_index {
+                line.byte_index_at_grapheme(start.grapheme_index)
+            } else {
+                0
+            };
+            let to = if line_index == end.line_index {
+                line.byte_index_at_grapheme(end.grapheme_index)
+            } else {
+                text.len()
+            };
+            parts.push(text.get(from..to.max(from)).unwrap_or_default().to_string());
+        }
+        parts.join("\n")
+    }
+
     /// 插入一行
     pub fn insert_newline(&mut self, at: Location){
         if at.line_index == self.height() {
-            self.lines.push(Line::default());
-            self.dirty = true;
-        } else if let Some(line) = self.lines.get_mut(at.line_index){
+            self.splice(at.line_index, 0, vec![String::new()]);
+        } else if let Some(line) = self.lines.get(at.line_index){
+            let mut line = Line::from(&line.to_string());
             let new = line.split(at.grapheme_index);
-            self.lines.insert(at.line_index.saturating_add(1), new);
+            self.splice(at.line_index, 1, vec![line.to_string(), new.to_string()]);
+        }
+    }
+
+    /// 保存缓冲区内容到文件，返回需要提示用户的警告。
+    ///
+    /// 先写入临时文件再重命名覆盖原文件，写到一半时出错不会损坏原文件；重命名前恢复
+    /// 原文件的属主、扩展属性（包括 ACL 和 `SELinux` 上下文）和权限位，无法做到时退回直接写入
+    /// 原文件，见 `atomicwrite::write`。文件在读取之后被删除（或替换符号链接时删除了链接）的话，
+    /// 改为恢复读取或上次保存时记下的属性，无法恢复的属性作为警告返回。新建的文件遵循进程的 umask。
+    ///
+    /// 磁盘上的文件在上次读取或保存之后被其他程序修改过时拒绝保存，以免覆盖别人的修改。
+    pub fn save(&mut self, break_link: bool) -> Result<Vec<String>, Error> {
+        if self.file_info.changed_on_disk() {
+            return Err(Error::other("File changed on disk since it was opened"));
+        }
+        let Some(snapshot) = self.snapshot(break_link) else {
+            return Ok(Vec::new());
+        };
+        let warnings = snapshot.write()?;
+        self.finish_save(snapshot.revision(), snapshot.replace_link());
+        Ok(warnings)
+    }
+
+    /// 快照写入文件之后的收尾，同步保存和后台保存完成时都调用它：快照之后没有新的修改时
+    /// 标记为已保存并删除交换文件；替换了符号链接的话文件不再是链接；记下磁盘上文件新的状态。
+    pub fn finish_save(&mut self, revision: u64, replace_link: bool) {
+        self.mark_saved(revision);
+        if replace_link {
+            self.file_info.target = None;
+        }
+        self.file_info.record_disk_state();
+        self.sync_swap();
+    }
+
+    /// 内容的版本，每次修改时递增。
+    pub const fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// 未保存的修改应当写入交换文件的时间；没有文件名、没有未保存的修改或交换文件已是最新时返回 `None`。
+    pub fn swap_deadline(&self) -> Option<Instant> {
+        self.file_info.save_path()?;
+        if !self.dirty {
+            return None;
+        }
+        self.swap.deadline(self.revision)
+    }
+
+    /// 有未保存的修改时按间隔把内容写入交换文件；修改都已保存（或撤销回保存时的状态）时删除它。
+    pub fn sync_swap(&mut self) {
+        let Some(file) = self.file_info.save_path().map(Path::to_path_buf) else {
+            return;
+        };
+        if !self.dirty {
+            self.swap.remove(&file);
+        } else if self.swap_deadline().is_some_and(|deadline| deadline <= Instant::now()) {
+            self.swap.write(&file, &self.contents(), self.revision);
+        }
+    }
+
+    /// 获取保存所需的内容快照，缓冲区没有文件名时返回 `None`。
+    ///
+    /// 通过符号链接打开的文件默认写入链接指向的文件；`break_link` 为真时改为用普通文件替换链接本身。
+    pub fn snapshot(&self, break_link: bool) -> Option<SaveSnapshot> {
+        let replace_link = break_link && self.file_info.is_symlink();
+        let path = if replace_link {
+            self.file_info.path.clone()?
+        } else {
+            self.file_info.save_path()?.to_path_buf()
+        };
+        Some(SaveSnapshot {
+            path,
+            contents: self.encoded_contents(),
+            revision: self.revision,
+            replace_link,
+            attributes: self.file_info.attributes.clone(),
+        })
+    }
+
+    /// 按文件原来的格式拼接出保存时写入的内容。
+    pub fn contents(&self) -> String {
+        self.format
+            .serialize(self.lines.iter().map(ToString::to_string))
+    }
+
+    /// 按文件的编码编码后保存时写入的字节。
+    pub fn encoded_contents(&self) -> Vec<u8> {
+        self.file_info.encoding.encode(&self.contents()).0.into_owned()
+    }
+
+    /// 内容中是否有无法用文件的编码表示、保存时会被替换掉的字符。
+    pub fn is_lossy(&self) -> bool {
+        self.file_info.encoding.is_lossy(&self.contents())
+    }
+
+    /// 修改保存时使用的编码，下次保存时生效。
+    pub fn set_encoding(&mut self, encoding: FileEncoding) {
+        if self.file_info.encoding != encoding {
+            self.file_info.encoding = encoding;
+            self.dirty = true;
+            self.history.forget_saved();
+        }
+    }
+
+    /// 文件的文本格式。
+    pub const fn format(&self) -> TextFormat {
+        self.format
+    }
+
+    /// 修改保存时使用的换行符，下次保存时生效。
+    pub fn set_line_ending(&mut self, line_ending: LineEnding) {
+        if self.format.line_ending != line_ending || self.format.mixed {
+            self.format.line_ending = line_ending;
+            self.format.mixed = false;
             self.dirty = true;
+            self.history.forget_saved();
+        }
+    }
+
+    /// 修改保存时最后一行之后是否有换行符，下次保存时生效。
+    pub fn set_final_newline(&mut self, final_newline: bool) {
+        if self.format.final_newline != final_newline {
+            self.format.final_newline = final_newline;
+            self.dirty = true;
+            self.history.forget_saved();
+        }
+    }
+
+    /// 快照已经写入文件；快照之后没有新的修改时，将缓冲区标记为已保存。
+    fn mark_saved(&mut self, revision: u64) {
+        if revision != self.revision {
+            return;
+        }
+        for line in &mut self.lines {
+            line.set_touched(false);
+        }
+        self.dirty = false;
+        self.history.mark_saved();
+    }
+
+    /// 检查自上次保存以来修改过的行，返回缩进可疑的行索引。
+    pub fn suspicious_indentation(&self) -> Vec<usize> {
+        let style = IndentStyle::detect(&self.lines);
+        self.lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| line.is_touched() && style.check(&line.to_string()).is_some())
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// 计算将整个缓冲区的缩进统一为缓冲区缩进风格时的修改，不改变缓冲区。
+    pub fn indentation_changes(&self) -> Vec<LineChange> {
+        let style = IndentStyle::detect(&self.lines);
+        self.plan_changes(0, self.height().saturating_sub(1), |text| style.normalize(text))
+    }
+
+    /// 计算删除每一行行尾空格和制表符时的修改，不改变缓冲区。
+    pub fn trailing_whitespace_changes(&self) -> Vec<LineChange> {
+        self.plan_changes(0, self.height().saturating_sub(1), |text| {
+            text.trim_end_matches([' ', '\t']).to_string()
+        })
+    }
+
+    /// 删除每一行行尾的空格和制表符，返回修改的行数。
+    ///
+    /// 只有行尾确实有空白的行才会被切分和替换，其余的行保持原样。
+    pub fn trim_trailing_whitespace(&mut self) -> usize {
+        let mut changed: usize = 0;
+        for line_index in 0..self.height() {
+            let Some(line) = self
+                .lines
+                .get(line_index)
+                .filter(|line| line.has_trailing_whitespace())
+            else {
+                continue;
+            };
+            let mut trimmed = Line::from(line.as_str());
+            if trimmed.trim_end() {
+                self.replace_line(line_index, trimmed.as_str());
+                changed = changed.saturating_add(1);
+            }
         }
+        changed
     }
 
-    /// 保存缓冲区内容到文件。
-    pub fn save(&mut self) -> Result<(), Error> {
-        if let Some(path) = &self.file_info.path {
-            let mut file = File::create(path)?;
-            for line in &self.lines {
-                writeln!(file, "{line}")?; 
+    /// 应用一组修改，返回修改的行数。
+    pub fn apply_changes(&mut self, changes: &[LineChange]) -> usize {
+        let mut applied: usize = 0;
+        for change in changes {
+            if change.line_index < self.height() {
+                self.replace_line(change.line_index, &change.text);
+                applied = applied.saturating_add(1);
             }
-            self.dirty = false;
+        }
+        applied
+    }
+
+    /// 将指定行的缩进统一为缓冲区的缩进风格。
+    pub fn fix_indentation(&mut self, line_indices: &[usize]) {
+        let style = IndentStyle::detect(&self.lines);
+        for &index in line_indices {
+            if let Some(line) = self.lines.get(index) {
+                let fixed = style.normalize(&line.to_string());
+                self.replace_line(index, &fixed);
+            }
+        }
+    }
+
+    /// 切换指定行范围的块注释：已被包裹时移除注释标记，否则添加。
+    pub fn toggle_block_comment(
+        &mut self,
+        start_line: usize,
+        end_line: usize,
+        comment: BlockComment,
+    ) -> Result<(), &'static str> {
+        if start_line > end_line || end_line >= self.height() {
+            return Err("Nothing to comment");
+        }
+        let BlockComment {
+            open,
+            close,
+            nestable,
+        } = comment;
+        let first = self.lines.get(start_line).map(Line::to_string).unwrap_or_default();
+        let last = self.lines.get(end_line).map(Line::to_string).unwrap_or_default();
+
+        let text = self.lines_text(start_line, end_line);
+        if Self::is_block_wrapped(text.trim(), comment) {
+            let last = Self::strip_block_close(&last, close);
+            if start_line == end_line {
+                self.replace_line(start_line, &Self::strip_block_open(&last, open));
+            } else {
+                self.replace_line(start_line, &Self::strip_block_open(&first, open));
+                self.replace_line(end_line, &last);
+            }
+            return Ok(());
+        }
+
+        if !nestable {
+            let contains_comment = self
+                .lines
+                .get(start_line..=end_line)
+                .unwrap_or_default()
+                .iter()
+                .map(Line::to_string)
+                .any(|text| text.contains(open) || text.contains(close));
+            if contains_comment {
+                return Err("Range already contains a block comment");
+            }
+        }
+
+        let indent_len = first.len().saturating_sub(first.trim_start().len());
+        let (indent, rest) = first.split_at(indent_len);
+        let first = format!("{indent}{open} {rest}");
+        if start_line == end_line {
+            self.replace_line(start_line, &format!("{} {close}", first.trim_end()));
+        } else {
+            self.replace_line(start_line, &first);
+            self.replace_line(end_line, &format!("{} {close}", last.trim_end()));
+        }
+        Ok(())
+    }
+
+    /// 删除一行并返回它；删除唯一的一行时留下一个空行，而不是变成没有内容的缓冲区。
+    pub fn remove_line(&mut self, index: usize) -> Option<Line> {
+        let removed = Line::from(&self.lines.get(index)?.to_string());
+        let replacement = if self.height() == 1 {
+            vec![String::new()]
+        } else {
+            Vec::new()
+        };
+        self.splice(index, 1, replacement);
+        Some(removed)
+    }
+
+    /// 统计整个缓冲区的行数、字数和词数。
+    pub fn stats(&self) -> TextStats {
+        self.lines.iter().fold(
+            TextStats {
+                lines: self.height(),
+                graphemes: 0,
+                words: 0,
+            },
+            |stats, line| TextStats {
+                graphemes: stats.graphemes.saturating_add(line.grapheme_count()),
+                words: stats
+                    .words
+                    .saturating_add(line.to_string().split_whitespace().count()),
+                ..stats
+            },
+        )
+    }
+
+    /// 在指定行之后插入它的副本；文件末尾之后的虚拟行视为空行，插入一个空行。
+    /// 行不存在时返回 `false`。
+    pub fn duplicate_line(&mut self, index: usize) -> bool {
+        if index == self.height() {
+            self.splice(index, 0, vec![String::new()]);
+            return true;
+        }
+        let Some(line) = self.lines.get(index) else {
+            return false;
+        };
+        let text = line.to_string();
+        self.splice(index.saturating_add(1), 0, vec![text]);
+        true
+    }
+
+    /// 将指定行与上一行交换；已经是第一行或行不存在时返回 `false`。
+    pub fn move_line_up(&mut self, index: usize) -> bool {
+        index > 0 && self.swap_with_next(index.saturating_sub(1))
+    }
+
+    /// 将指定行与下一行交换；已经是最后一行或行不存在时返回 `false`。
+    pub fn move_line_down(&mut self, index: usize) -> bool {
+        self.swap_with_next(index)
+    }
+
+    /// 交换指定行与它的下一行。
+    fn swap_with_next(&mut self, index: usize) -> bool {
+        let (Some(line), Some(next)) = (self.lines.get(index), self.lines.get(index.saturating_add(1)))
+        else {
+            return false;
+        };
+        let swapped = vec![next.to_string(), line.to_string()];
+        self.splice(index, 2, swapped);
+        true
+    }
+
+    /// 用给定的文本替换全部内容，作为一次修改记入撤销历史；文件的换行符等格式保持不变。
+    pub fn replace_text(&mut self, text: &str) {
+        let (_, values) = TextFormat::parse(text);
+        self.splice(0, self.height(), values.into_iter().map(String::from).collect());
+    }
+
+    /// 删除指定范围内的行（包含两端），返回删除的行数。
+    pub fn remove_lines(&mut self, start: usize, end: usize) -> usize {
+        if start > end || start >= self.height() {
+            return 0;
+        }
+        let end = end.min(self.height().saturating_sub(1));
+        let count = end.saturating_sub(start).saturating_add(1);
+        self.splice(start, count, Vec::new());
+        count
+    }
+
+    /// 获取指定范围内各行的文本，以换行符连接。
+    pub fn lines_text(&self, start: usize, end: usize) -> String {
+        self.lines
+            .get(start..=end.min(self.height().saturating_sub(1)))
+            .unwrap_or_default()
+            .iter()
+            .map(Line::to_string)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// 切换指定范围内各行的行注释：所有非空行都已注释时去掉行首的注释标记（连同其后的一个空格），
+    /// 否则在最少缩进的那一行的缩进处为每个非空行加上 `标记 `，各行保持原来的相对缩进。空行保持不变。
+    pub fn toggle_line_comment(
+        &mut self,
+        start: usize,
+        end: usize,
+        token: &str,
+    ) -> Result<(), &'static str> {
+        let indent_of = |text: &str| text.len().saturating_sub(text.trim_start().len());
+        let texts: Vec<String> = self
+            .lines
+            .get(start..=end.min(self.height().saturating_sub(1)))
+            .unwrap_or_default()
+            .iter()
+            .map(Line::to_string)
+            .filter(|text| !text.trim().is_empty())
+            .collect();
+        if texts.is_empty() {
+            return Err("Nothing to comment");
+        }
+        if texts.iter().all(|text| text.trim_start().starts_with(token)) {
+            self.map_lines(start, end, |text| {
+                let (indent, rest) = text.split_at(indent_of(text));
+                rest.strip_prefix(token).map_or_else(
+                    || text.to_string(),
+                    |rest| format!("{indent}{}", rest.strip_prefix(' ').unwrap_or(rest)),
+                )
+            });
+        } else {
+            let column = texts.iter().map(|text| indent_of(text)).min().unwrap_or(0);
+            self.map_lines(start, end, |text| {
+                if text.trim().is_empty() {
+                    return text.to_string();
+                }
+                let (indent, rest) = text.split_at_checked(column).unwrap_or(("", text));
+                format!("{indent}{token} {rest}")
+            });
         }
         Ok(())
     }
 
-}
\ No newline at end of file
+    /// 为指定范围内的非空行增加一级缩进，返回修改的行数。
+    pub fn indent_lines(&mut self, start: usize, end: usize) -> usize {
+        let unit = IndentStyle::detect(&self.lines).unit();
+        self.map_lines(start, end, |text| {
+            if text.is_empty() {
+                text.to_string()
+            } else {
+                format!("{unit}{text}")
+            }
+        })
+    }
+
+    /// 为指定范围内的行减少一级缩进，返回修改的行数。
+    pub fn dedent_lines(&mut self, start: usize, end: usize) -> usize {
+        let style = IndentStyle::detect(&self.lines);
+        self.map_lines(start, end, |text| style.dedent(text))
+    }
+
+    /// 查找文本在缓冲区中的所有出现位置，同一行内的匹配互不重叠。
+    pub fn find_all(&self, query: &str) -> Vec<Match> {
+        if query.is_empty() {
+            return Vec::new();
+        }
+        self.lines
+            .iter()
+            .enumerate()
+            .flat_map(|(line_index, line)| {
+                line.to_string()
+                    .match_indices(query)
+                    .map(|(byte_index, _)| Match {
+                        line_index,
+                        byte_index,
+                        byte_len: query.len(),
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// 与指定位置的括号配对的括号的位置，支持 `()`、`[]` 和 `{}`，可以跨行。
+    ///
+    /// 开括号向后、闭括号向前查找，途中嵌套的同类括号按层数跳过；只查找 `within` 范围内的行，
+    /// 并且直接比较行的原始文本中的字节，不会切分途经的行。
+    /// 指定位置不是括号或者括号在范围内没有配对时返回 `None`。
+    pub fn matching_bracket(&self, at: Location, within: Range<usize>) -> Option<Location> {
+        let line = self.lines.get(at.line_index)?;
+        let &[bracket] = line.graphemes().nth(at.grapheme_index)?.as_bytes() else {
+            return None;
+        };
+        let (open, close) = BRACKETS
+            .into_iter()
+            .find(|&(open, close)| bracket == open || bracket == close)?;
+        let at_byte = line.byte_index_at_grapheme(at.grapheme_index);
+        let (line_index, byte_index) = if bracket == open {
+            let end = within.end.min(self.height());
+            let after = self
+                .lines
+                .get(at.line_index..end)
+                .unwrap_or_default()
+                .iter()
+                .enumerate()
+                .flat_map(|(offset, line)| {
+                    Self::located_bytes(line, at.line_index.saturating_add(offset))
+                })
+                .skip(at_byte.saturating_add(1));
+            Self::find_partner(after, open, close)?
+        } else {
+            let start = within.start.min(at.line_index);
+            let before = self
+                .lines
+                .get(start..=at.line_index)
+                .unwrap_or_default()
+                .iter()
+                .enumerate()
+                .rev()
+                .flat_map(|(offset, line)| {
+                    Self::located_bytes(line, start.saturating_add(offset)).rev()
+                })
+                .skip_while(|&(line_index, byte_index, _)| {
+                    line_index == at.line_index && byte_index >= at_byte
+                });
+            Self::find_partner(before, close, open)?
+        };
+        let grapheme_index = self.lines.get(line_index)?.grapheme_index_at_byte(byte_index);
+        Some(Location {
+            grapheme_index,
+            line_index,
+        })
+    }
+
+    /// 行的原始文本中的各个字节，连同行索引和字节位置。
+    fn located_bytes(
+        line: &Line,
+        line_index: usize,
+    ) -> impl DoubleEndedIterator<Item = (usize, usize, u8)> {
+        line.as_str()
+            .bytes()
+            .enumerate()
+            .map(move |(byte_index, byte)| (line_index, byte_index, byte))
+    }
+
+    /// 在依次经过的字节中找到与起点括号 `same` 配对的 `other`，途中的 `same` 使层数加一。
+    ///
+    /// 括号都是 ASCII 字符，不会出现在多字节字符的编码中间，因此可以逐字节比较。
+    fn find_partner(
+        bytes: impl Iterator<Item = (usize, usize, u8)>,
+        same: u8,
+        other: u8,
+    ) -> Option<(usize, usize)> {
+        let mut depth = 0_usize;
+        for (line_index, byte_index, byte) in bytes {
+            if byte == same {
+                depth = depth.saturating_add(1);
+            } else if byte == other {
+                if depth == 0 {
+                    return Some((line_index, byte_index));
+                }
+                depth = depth.saturating_sub(1);
+            }
+        }
+        None
+    }
+
+    /// 将指定的匹配替换为新文本，返回替换的数量。
+    ///
+    /// 同一行内从后往前替换，保证前面匹配的字节位置不受影响。
+    pub fn replace_matches(&mut self, matches: &[Match], replacement: &str) -> usize {
+        let mut sorted = matches.to_vec();
+        sorted.sort_by(|a, b| {
+            (b.line_index, b.byte_index).cmp(&(a.line_index, a.byte_index))
+        });
+        let mut replaced: usize = 0;
+        let mut index = 0;
+        while let Some(first) = sorted.get(index) {
+            let line_index = first.line_index;
+            let Some(line) = self.lines.get(line_index) else {
+                index = index.saturating_add(1);
+                continue;
+            };
+            let mut text = line.to_string();
+            while let Some(found) = sorted.get(index)
+                && found.line_index == line_index
+            {
+                let range = found.byte_index..found.byte_index.saturating_add(found.byte_len);
+                if text.get(range.clone()).is_some() {
+                    text.replace_range(range, replacement);
+                    replaced = replaced.saturating_add(1);
+                }
+                index = index.saturating_add(1);
+            }
+            self.replace_line(line_index, &text);
+        }
+        replaced
+    }
+
+    /// 对指定范围内的每一行应用变换，只替换内容发生变化的行，返回修改的行数。
+    fn map_lines(&mut self, start: usize, end: usize, transform: impl Fn(&str) -> String) -> usize {
+        let changes = self.plan_changes(start, end, transform);
+        self.apply_changes(&changes)
+    }
+
+    /// 对指定范围内的每一行应用变换，收集内容会发生变化的行，不改变缓冲区。
+    ///
+    /// 试运行和实际修改都通过这里计算，保证确认提示中的行数与实际修改一致。
+    fn plan_changes(
+        &self,
+        start: usize,
+        end: usize,
+        transform: impl Fn(&str) -> String,
+    ) -> Vec<LineChange> {
+        self.lines
+            .get(start..=end.min(self.height().saturating_sub(1)))
+            .unwrap_or_default()
+            .iter()
+            .zip(start..)
+            .filter_map(|(line, line_index)| {
+                let text = line.to_string();
+                let new_text = transform(&text);
+                (new_text != text).then_some(LineChange {
+                    line_index,
+                    text: new_text,
+                })
+            })
+            .collect()
+    }
+
+    /// 文本是否整个是一段块注释：开头的起始标记与之配对的结束标记正好在末尾。
+    ///
+    /// `/* a */ x /* b */` 这样首尾各是一段注释的文本不算，去掉首尾的标记会留下不成对的标记。
+    fn is_block_wrapped(text: &str, comment: BlockComment) -> bool {
+        let BlockComment {
+            open,
+            close,
+            nestable,
+        } = comment;
+        let Some(mut rest) = text.strip_prefix(open) else {
+            return false;
+        };
+        let mut depth = 1_usize;
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix(close) {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    return after.is_empty();
+                }
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix(open).filter(|_| nestable) {
+                depth = depth.saturating_add(1);
+                rest = after;
+            } else {
+                let skip = rest.chars().next().map_or(1, char::len_utf8);
+                rest = rest.get(skip..).unwrap_or_default();
+            }
+        }
+        false
+    }
+
+    /// 移除行首的块注释起始标记及其后的一个空格。
+    fn strip_block_open(text: &str, open: &str) -> String {
+        let indent_len = text.len().saturating_sub(text.trim_start().len());
+        let (indent, rest) = text.split_at(indent_len);
+        let rest = rest.strip_prefix(open).unwrap_or(rest);
+        format!("{indent}{}", rest.strip_prefix(' ').unwrap_or(rest))
+    }
+
+    /// 移除行尾的块注释结束标记及其前的一个空格。
+    fn strip_block_close(text: &str, close: &str) -> String {
+        let trimmed = text.trim_end();
+        let rest = trimmed.strip_suffix(close).unwrap_or(trimmed);
+        rest.strip_suffix(' ').unwrap_or(rest).to_string()
+    }
+
+    /// 用新的文本替换指定行。
+    fn replace_line(&mut self, line_index: usize, text: &str) {
+        if line_index < self.height() {
+            self.splice(line_index, 1, vec![text.to_string()]);
+        }
+    }
+
+    // ==================== 语法高亮 ====================
+
+    /// 确保 `end` 之前的行的高亮都是最新的。
+    pub fn highlight_until(&mut self, end: usize) {
+        self.highlighter.highlight_until(&self.lines, end);
+    }
+
+    /// 空闲时继续高亮一部分行。
+    pub fn highlight_idle(&mut self) {
+        self.highlighter.highlight_idle(&self.lines);
+    }
+
+    /// 是否还有尚未高亮的行。
+    pub fn has_pending_highlight(&self) -> bool {
+        self.highlighter.has_pending_work(self.height())
+    }
+
+    /// 指定行的高亮。
+    pub fn highlight_spans(&self, line_index: usize) -> &[Span] {
+        self.highlighter.spans(line_index)
+    }
+
+    // ==================== 撤销和重做 ====================
+
+    /// 将从 `start` 开始的 `count` 行替换为新的行，并记录到撤销历史。
+    ///
+    /// 所有修改缓冲区内容的操作都必须经过这里或 `splice_text`，撤销历史才能保持完整。
+    fn splice(&mut self, start: usize, count: usize, inserted: Vec<String>) {
+        let end = start.saturating_add(count).min(self.height());
+        let start = start.min(end);
+        let removed = self.replace_range(start, end, &inserted);
+        self.history.record(Change::Lines(Splice {
+            start,
+            removed,
+            inserted,
+        }));
+        self.dirty = true;
+    }
+
+    /// 将第 `line_index` 行中 `graphemes` 范围内的字形替换为不含换行的 `inserted`，并记录到撤销历史。
+    ///
+    /// 直接修改保存的行，只重新切分修改处附近的字形，撤销历史中也只记录被修改的一段，
+    /// 因此在很长的行中输入不必每次复制和切分整行。
+    fn splice_text(&mut self, line_index: usize, graphemes: Range<usize>, inserted: &str) {
+        let Some(line) = self.lines.get_mut(line_index) else {
+            return;
+        };
+        let old = self
+            .swap_point
+            .get()
+            .is_some_and(|location| location.line_index == line_index)
+            .then(|| line.to_string());
+        let byte_index = line.byte_index_at_grapheme(graphemes.start);
+        let removed = line.replace(graphemes, inserted);
+        if removed.is_empty() && inserted.is_empty() {
+            return;
+        }
+        self.line_edited(line_index, old);
+        self.history.record(Change::Text(TextEdit {
+            line_index,
+            byte_index,
+            removed,
+            inserted: inserted.to_string(),
+        }));
+        self.dirty = true;
+    }
+
+    /// 重放行内的修改：把第 `line_index` 行中从 `byte_index` 开始的 `len` 个字节替换为 `text`。
+    fn replay_text(&mut self, line_index: usize, byte_index: usize, len: usize, text: &str) {
+        let Some(line) = self.lines.get_mut(line_index) else {
+            return;
+        };
+        let old = self
+            .swap_point
+            .get()
+            .is_some_and(|location| location.line_index == line_index)
+            .then(|| line.to_string());
+        line.replace_bytes(byte_index..byte_index.saturating_add(len), text);
+        self.line_edited(line_index, old);
+    }
+
+    /// 第 `line_index` 行被直接修改后，更新随内容变化的高亮、修改记录和切换位置；
+    /// `old` 是切换位置在这一行上时这一行修改前的内容。
+    fn line_edited(&mut self, line_index: usize, old: Option<String>) {
+        self.highlighter.splice(line_index, 1, 1);
+        self.revision = self.revision.wrapping_add(1);
+        self.changes.touch(line_index);
+        if let (Some(old), Some(line)) = (old, self.lines.get(line_index)) {
+            self.swap_point.splice(line_index, &[old], &[line.to_string()]);
+        }
+    }
+
+    /// 替换 `start..end` 范围内的行，返回被替换的行的内容。
+    fn replace_range(&mut self, start: usize, end: usize, inserted: &[String]) -> Vec<String> {
+        let new_lines = inserted.iter().map(|text| {
+            let mut line = Line::from(text);
+            line.set_touched(true);
+            line
+        });
+        self.highlighter
+            .splice(start, end.saturating_sub(start), inserted.len());
+        self.revision = self.revision.wrapping_add(1);
+        let removed: Vec<String> = self
+            .lines
+            .splice(start..end, new_lines)
+            .map(|line| line.to_string())
+            .collect();
+        self.marks.splice(start, &removed, inserted);
+        self.changes.splice(start, &removed, inserted);
+        self.swap_point.splice(start, &removed, inserted);
+        removed
+    }
+
+    /// 开始一个撤销步骤，之后的修改在撤销时作为一个整体。
+    pub fn begin_step(&mut self, kind: StepKind, before: CaretState) {
+        self.changes.next_generation();
+        self.history.begin(kind, before);
+    }
+
+    /// 结束当前的撤销步骤，返回新加入撤销历史的一步。
+    pub fn end_step(&mut self, after: CaretState) -> Option<&Step> {
+        self.history.end(after)
+    }
+
+    /// 撤销一步，返回被撤销的步骤。
+    pub fn undo(&mut self) -> Option<Step> {
+        let step = self.history.undo()?.clone();
+        self.changes.next_generation();
+        for change in step.changes.iter().rev() {
+            match change {
+                Change::Lines(splice) => {
+                    let end = splice.start.saturating_add(splice.inserted.len()).min(self.height());
+                    self.replace_range(splice.start.min(end), end, &splice.removed);
+                }
+                Change::Text(edit) => self.replay_text(
+                    edit.line_index,
+                    edit.byte_index,
+                    edit.inserted.len(),
+                    &edit.removed,
+                ),
+            }
+        }
+        self.dirty = !self.history.is_at_saved();
+        Some(step)
+    }
+
+    /// 重做一步，返回被重做的步骤。
+    pub fn redo(&mut self) -> Option<Step> {
+        let step = self.history.redo()?.clone();
+        self.changes.next_generation();
+        for change in &step.changes {
+            match change {
+                Change::Lines(splice) => {
+                    let end = splice.start.saturating_add(splice.removed.len()).min(self.height());
+                    self.replace_range(splice.start.min(end), end, &splice.inserted);
+                }
+                Change::Text(edit) => self.replay_text(
+                    edit.line_index,
+                    edit.byte_index,
+                    edit.removed.len(),
+                    &edit.inserted,
+                ),
+            }
+        }
+        self.dirty = !self.history.is_at_saved();
+        Some(step)
+    }
+}
+
+impl Drop for Buffer {
+    /// 正常关闭缓冲区（退出、重新读取、替换）时删除它写入的交换文件；
+    /// 程序崩溃展开栈时改为把最后的内容写入交换文件，供下次打开时恢复。
+    fn drop(&mut self) {
+        let Some(file) = self.file_info.save_path().map(Path::to_path_buf) else {
+            return;
+        };
+        if !thread::panicking() {
+            self.swap.remove(&file);
+        } else if self.dirty {
+            self.swap.write(&file, &self.contents(), self.revision);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::filetype::FileType;
+
+    const fn at(line_index: usize, grapheme_index: usize) -> Location {
+        Location {
+            grapheme_index,
+            line_index,
+        }
+    }
+
+    fn caret(location: Location) -> CaretState {
+        CaretState {
+            location,
+            anchor: None,
+        }
+    }
+
+    #[test]
+    fn typing_edits_the_stored_line_and_records_only_the_typed_text() {
+        let long = "x".repeat(10_000);
+        let mut buffer = Buffer::from_text(&long);
+        for (offset, character) in "ab好".chars().enumerate() {
+            let location = at(0, 10_000_usize.saturating_add(offset));
+            buffer.begin_step(StepKind::Typing, caret(location));
+            buffer.insert_char(character, location);
+            buffer.end_step(caret(at(0, location.grapheme_index.saturating_add(1))));
+        }
+        assert_eq!(buffer.lines_text(0, 0), format!("{long}ab好"));
+        // 行被原地修改，已经切分的字形得以保留，而不是换成一个新的未切分的行。
+        assert_eq!(buffer.segmented_lines(), 1);
+
+        let step = buffer.undo().unwrap();
+        let [Change::Text(edit)] = step.changes.as_slice() else {
+            panic!("expected a single in-line edit, got {:?}", step.changes);
+        };
+        assert_eq!((edit.byte_index, edit.removed.as_str(), edit.inserted.as_str()), (10_000, "", "ab好"));
+        assert_eq!(buffer.lines_text(0, 0), long);
+        buffer.redo().unwrap();
+        assert_eq!(buffer.lines_text(0, 0), format!("{long}ab好"));
+    }
+
+    #[test]
+    fn deleting_and_replacing_within_a_line_undo_to_the_original() {
+        let mut buffer = Buffer::from_text("cafe\u{301} ok\nnext");
+        buffer.delete(at(0, 3));
+        assert_eq!(buffer.lines_text(0, 0), "caf ok");
+        let end = buffer.replace(at(0, 4), 2, "好的");
+        assert_eq!(buffer.lines_text(0, 0), "caf 好的");
+        assert_eq!(end, at(0, 6));
+        buffer.undo().unwrap();
+        buffer.undo().unwrap();
+        assert_eq!(buffer.lines_text(0, 1), "cafe\u{301} ok\nnext");
+    }
+
+    #[test]
+    fn matching_bracket_skips_nested_pairs_across_lines() {
+        let buffer = Buffer::from_text("fn main() {\n    if (a[0]) { b(); }\n}\n");
+        let all = 0..buffer.height();
+        assert_eq!(buffer.matching_bracket(at(0, 10), all.clone()), Some(at(2, 0)));
+        assert_eq!(buffer.matching_bracket(at(2, 0), all.clone()), Some(at(0, 10)));
+        assert_eq!(buffer.matching_bracket(at(1, 7), all.clone()), Some(at(1, 12)));
+        assert_eq!(buffer.matching_bracket(at(1, 9), all.clone()), Some(at(1, 11)));
+        assert_eq!(buffer.matching_bracket(at(1, 4), all), None);
+    }
+
+    #[test]
+    fn matching_bracket_reports_grapheme_positions_after_wide_text() {
+        let buffer = Buffer::from_text("(你好, e\u{301})");
+        assert_eq!(buffer.matching_bracket(at(0, 0), 0..1), Some(at(0, 6)));
+        assert_eq!(buffer.matching_bracket(at(0, 6), 0..1), Some(at(0, 0)));
+    }
+
+    #[test]
+    fn matching_bracket_stops_at_the_window_and_leaves_lines_unsegmented() {
+        let mut text = String::from("{\n");
+        text.push_str(&"x\n".repeat(10_000));
+        text.push('}');
+        let buffer = Buffer::from_text(&text);
+
+        assert_eq!(buffer.matching_bracket(at(0, 0), 0..100), None);
+        assert_eq!(
+            buffer.matching_bracket(at(0, 0), 0..buffer.height()),
+            Some(at(10_001, 0))
+        );
+        // 只有起点和配对括号所在的行被切分。
+        assert_eq!(buffer.segmented_lines(), 2);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn saving_a_file_deleted_since_loading_restores_the_mode_recorded_at_load() {
+        use std::{fs, os::unix::fs::PermissionsExt};
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        fs::write(&path, "alpha\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o604)).unwrap();
+        let mut buffer = Buffer::load(&path, None).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        buffer.insert_char('x', at(0, 0));
+        let warnings = buffer.save(false).unwrap();
+
+        assert!(warnings.is_empty());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "xalpha\n");
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o604);
+        assert!(!buffer.dirty);
+    }
+
+    #[test]
+    fn saving_unchanged_files_without_a_final_newline_keeps_every_byte() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        for contents in ["one\ntwo", "one\r\ntwo", "x", "\n\nlast", "好"] {
+            std::fs::write(&path, contents).unwrap();
+            let mut buffer = Buffer::load(&path, None).unwrap();
+            assert!(!buffer.format.final_newline, "{contents:?}");
+
+            buffer.save(false).unwrap();
+
+            assert_eq!(std::fs::read(&path).unwrap(), contents.as_bytes(), "{contents:?}");
+        }
+    }
+
+    #[test]
+    fn invalid_utf8_is_only_opened_with_a_configured_fallback_encoding() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("latin1.txt");
+        std::fs::write(&path, b"ok\ncaf\xe9\n").unwrap();
+
+        let err = Buffer::load(&path, None).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert_eq!(
+            err.to_string(),
+            "Not valid UTF-8 (line 2), not opened (set fallback_encoding to open it)"
+        );
+
+        let buffer = Buffer::load(&path, FileEncoding::for_label("latin1")).unwrap();
+        assert_eq!(buffer.lines_text(0, 1), "ok\ncafé");
+        assert!(!buffer.file_info.encoding.is_utf8());
+    }
+
+    #[test]
+    fn editing_a_file_without_a_final_newline_does_not_add_one() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        std::fs::write(&path, "one\ntwo").unwrap();
+        let mut buffer = Buffer::load(&path, None).unwrap();
+
+        buffer.insert_char('!', at(1, 3));
+        buffer.save(false).unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "one\ntwo!");
+    }
+
+    #[test]
+    fn block_comment_wraps_and_unwraps_a_multi_line_range() {
+        let c = FileType::C.block_comment().unwrap();
+        let original = "int a;\n    int b;\nint c;";
+        let mut buffer = Buffer::from_text(original);
+
+        buffer.toggle_block_comment(0, 1, c).unwrap();
+        assert_eq!(buffer.lines_text(0, 2), "/* int a;\n    int b; */\nint c;");
+
+        buffer.toggle_block_comment(0, 1, c).unwrap();
+        assert_eq!(buffer.lines_text(0, 2), original);
+    }
+
+    #[test]
+    fn block_comment_nests_only_where_the_language_allows_it() {
+        let text = "a();\n/* b(); */\nc();";
+        let mut c = Buffer::from_text(text);
+        let mut rust = Buffer::from_text(text);
+
+        assert!(c.toggle_block_comment(0, 2, FileType::C.block_comment().unwrap()).is_err());
+        assert_eq!(c.lines_text(0, 2), text);
+        rust.toggle_block_comment(0, 2, FileType::Rust.block_comment().unwrap()).unwrap();
+        assert_eq!(rust.lines_text(0, 2), "/* a();\n/* b(); */\nc(); */");
+    }
+
+    #[test]
+    fn two_block_comments_are_not_taken_for_one() {
+        let rust = FileType::Rust.block_comment().unwrap();
+        for text in ["/* a */ x /* b */", "/* a */
+x
+/* b */"] {
+            let last = text.lines().count().saturating_sub(1);
+            let mut buffer = Buffer::from_text(text);
+            buffer.toggle_block_comment(0, last, rust).unwrap();
+            let wrapped = buffer.lines_text(0, last);
+            assert!(wrapped.starts_with("/* /* a */") && wrapped.ends_with("/* b */ */"), "{wrapped:?}");
+            buffer.toggle_block_comment(0, last, rust).unwrap();
+            assert_eq!(buffer.lines_text(0, last), text);
+        }
+
+        // 不能嵌套的语言里拒绝包裹，而不是去掉首尾的标记。
+        let mut c = Buffer::from_text("/* a */ x /* b */");
+        assert!(c.toggle_block_comment(0, 0, FileType::C.block_comment().unwrap()).is_err());
+        assert_eq!(c.lines_text(0, 0), "/* a */ x /* b */");
+    }
+}
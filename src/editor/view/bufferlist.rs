@@ -0,0 +1,193 @@
+use std::{
+    cmp::Ordering,
+    io::Error,
+    mem::take,
+    path::{Path, PathBuf},
+};
+
+use super::{Buffer, Location, Position, View, swapfile::CrashSwaps};
+
+/// 打开着但不在显示的缓冲区，连同它的光标和滚动位置。
+pub struct BufferSlot {
+    id: usize,               // 缓冲区的编号，用于把后台保存的结果对应到缓冲区。
+    buffer: Buffer,          // 缓冲区。
+    text_location: Location, // 切换走时光标的位置。
+    scroll_offset: Position, // 切换走时的滚动偏移量。
+    wrap_row: usize,         // 切换走时软换行的顶部显示行。
+}
+
+impl View {
+    /// 当前缓冲区的编号。
+    pub const fn buffer_id(&self) -> usize {
+        self.buffer_id
+    }
+
+    /// 打开的缓冲区的数量。
+    pub fn buffer_count(&self) -> usize {
+        self.other_buffers.len().saturating_add(1)
+    }
+
+    /// 当前缓冲区在所有打开的缓冲区中的序号，从 0 开始。
+    pub const fn active_buffer(&self) -> usize {
+        self.active_buffer
+    }
+
+    /// 有未保存修改的缓冲区的数量。
+    pub fn modified_buffer_count(&self) -> usize {
+        self.other_buffers
+            .iter()
+            .filter(|slot| slot.buffer.dirty)
+            .count()
+            .saturating_add(usize::from(self.buffer.dirty))
+    }
+
+    /// 把所有打开的缓冲区中未保存的修改写入暂存目录，返回各个暂存文件的路径。
+    ///
+    /// 某个缓冲区暂存失败时仍继续暂存其余的缓冲区，最后返回遇到的第一个错误。
+    pub fn stash_all_unsaved(&self) -> Result<Vec<PathBuf>, Error> {
+        let mut stashed = Vec::new();
+        let mut first_error = None;
+        for buffer in (0..self.buffer_count()).map(|index| self.buffer_at(index)) {
+            match Self::stash_buffer(buffer) {
+                Ok(path) => stashed.extend(path),
+                Err(err) => {
+                    first_error.get_or_insert(err);
+                }
+            }
+        }
+        first_error.map_or(Ok(stashed), Err)
+    }
+
+    /// 把所有缓冲区中未保存的内容交给 panic 钩子，见 `CrashSwaps`。
+    ///
+    /// 只记下会写交换文件的缓冲区：有文件名、有未保存的修改，并且没有保留之前留下的交换文件。
+    pub fn update_crash_swaps(&self, crash_swaps: &CrashSwaps) {
+        let buffers = (0..self.buffer_count())
+            .map(|index| self.buffer_at(index))
+            .filter(|buffer| buffer.dirty && !buffer.swap.is_disabled())
+            .filter_map(|buffer| {
+                let file = buffer.file_info.save_path()?;
+                Some((file, buffer.revision(), || buffer.contents()))
+            });
+        crash_swaps.update(buffers);
+    }
+
+    /// 打开文件并切换过去；该文件已经打开时直接切换到它的缓冲区。
+    pub fn open(&mut self, path: &Path) -> Result<(), Error> {
+        let buffer = Buffer::load(path, self.fallback_encoding)?;
+        let existing = (0..self.buffer_count()).find(|&index| {
+            self.buffer_at(index).file_info.save_path() == buffer.file_info.save_path()
+        });
+        match existing {
+            Some(index) => self.switch_to_buffer(index),
+            None => self.open_buffer(buffer),
+        }
+        Ok(())
+    }
+
+    /// 为还不存在的文件打开一个空缓冲区，保存时创建该文件。
+    pub fn open_new(&mut self, path: &Path) {
+        let mut buffer = Buffer::default();
+        buffer.set_path(path);
+        self.open_buffer(buffer);
+    }
+
+    /// 切换到下一个缓冲区，最后一个之后回到第一个；只有一个缓冲区时返回 `false`。
+    pub fn next_buffer(&mut self) -> bool {
+        let count = self.buffer_count();
+        if count < 2 {
+            return false;
+        }
+        self.switch_to_buffer(self.active_buffer.saturating_add(1).checked_rem(count).unwrap_or(0));
+        true
+    }
+
+    /// 切换到一个有未保存修改的缓冲区，当前缓冲区有修改时留在当前缓冲区；都没有修改时返回 `false`。
+    pub fn focus_modified_buffer(&mut self) -> bool {
+        if self.buffer.dirty {
+            return true;
+        }
+        let Some(index) = (0..self.buffer_count()).find(|&index| self.buffer_at(index).dirty) else {
+            return false;
+        };
+        self.switch_to_buffer(index);
+        true
+    }
+
+    /// 后台保存的快照已经写入文件，对对应的缓冲区做保存后的收尾（见 `Buffer::finish_save`）。
+    pub fn finish_buffer_save(&mut self, id: usize, revision: u64, replace_link: bool) {
+        if id == self.buffer_id {
+            self.buffer.finish_save(revision, replace_link);
+        } else if let Some(slot) = self.other_buffers.iter_mut().find(|slot| slot.id == id) {
+            slot.buffer.finish_save(revision, replace_link);
+        }
+    }
+
+    /// 为新的缓冲区分配编号。
+    pub(super) const fn new_buffer_id(&mut self) -> usize {
+        self.next_buffer_id = self.next_buffer_id.saturating_add(1);
+        self.next_buffer_id
+    }
+
+    /// 所有打开的缓冲区中第 `index` 个缓冲区，超出范围时返回当前缓冲区。
+    fn buffer_at(&self, index: usize) -> &Buffer {
+        let other = match index.cmp(&self.active_buffer) {
+            Ordering::Less => self.other_buffers.get(index),
+            Ordering::Equal => None,
+            Ordering::Greater => self.other_buffers.get(index.saturating_sub(1)),
+        };
+        other.map_or(&self.buffer, |slot| &slot.buffer)
+    }
+
+    /// 在当前缓冲区之后打开新的缓冲区并切换过去。
+    fn open_buffer(&mut self, buffer: Buffer) {
+        let slot = BufferSlot {
+            id: self.new_buffer_id(),
+            buffer,
+            text_location: Location::default(),
+            scroll_offset: Position::default(),
+            wrap_row: 0,
+        };
+        let current = self.park_current();
+        self.other_buffers.insert(self.active_buffer, current);
+        self.active_buffer = self.active_buffer.saturating_add(1);
+        self.activate(slot);
+    }
+
+    /// 切换到所有打开的缓冲区中的第 `index` 个。
+    fn switch_to_buffer(&mut self, index: usize) {
+        if index == self.active_buffer || index >= self.buffer_count() {
+            return;
+        }
+        let current = self.park_current();
+        self.other_buffers.insert(self.active_buffer, current);
+        let slot = self.other_buffers.remove(index);
+        self.active_buffer = index;
+        self.activate(slot);
+    }
+
+    /// 取出当前缓冲区和它的光标、滚动位置。
+    fn park_current(&mut self) -> BufferSlot {
+        BufferSlot {
+            id: self.buffer_id,
+            buffer: take(&mut self.buffer),
+            text_location: self.text_location,
+            scroll_offset: self.scroll_offset,
+            wrap_row: self.wrap_row,
+        }
+    }
+
+    /// 显示指定的缓冲区，恢复它的光标和滚动位置。
+    fn activate(&mut self, slot: BufferSlot) {
+        self.buffer_id = slot.id;
+        self.buffer = slot.buffer;
+        self.text_location = slot.text_location;
+        self.scroll_offset = slot.scroll_offset;
+        self.wrap_row = slot.wrap_row;
+        self.desired_col = None;
+        self.clear_selection();
+        self.match_highlight = None;
+        self.flash = None;
+        self.normalize_after_change();
+    }
+}
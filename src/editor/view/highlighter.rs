@@ -0,0 +1,320 @@
+use super::line::Line;
+use crate::editor::{
+    filetype::{BlockComment, FileType},
+    terminal::Style,
+};
+use std::{iter::repeat_with, ops::Range};
+
+/// 每帧在可见区域之外额外高亮的行数。
+pub const LOOKAHEAD: usize = 50;
+
+/// 空闲时每次高亮的行数。
+const IDLE_CHUNK: usize = 2000;
+
+/// 行首或行尾的扫描状态，跨行的注释和字符串靠它延续到下一行。
+#[derive(Clone, Copy, Default, Eq, PartialEq, Debug)]
+enum State {
+    #[default]
+    Code,          // 普通代码。
+    Comment(usize), // 块注释中，记录嵌套的层数。
+    String(char),  // 字符串中，记录起始的引号。
+}
+
+/// 一行中需要特殊显示的一段，以字节为单位。
+#[derive(Clone, Debug)]
+pub struct Span {
+    pub bytes: Range<usize>, // 在行内的字节范围。
+    pub style: Style,        // 显示样式。
+}
+
+/// 一行的高亮结果。
+struct Highlighted {
+    end: State,       // 行尾的扫描状态。
+    spans: Vec<Span>, // 行内需要特殊显示的部分。
+}
+
+/// 文件类型的语法：注释和字符串的标记。
+#[derive(Clone, Copy)]
+struct Syntax {
+    line_comment: Option<&'static str>,   // 行注释的起始标记。
+    block_comment: Option<BlockComment>,  // 块注释的语法。
+    quotes: &'static [char],              // 字符串的引号。
+}
+
+/// 增量的语法高亮。
+///
+/// 已经扫描过的行组成一个前缀，每行保存行尾的状态。编辑后只把被修改的行标记为
+/// 失效，重新扫描时从第一处失效的行开始，一旦某行算出的行尾状态与保存的相同，
+/// 之后的行就不需要再扫描。每帧只保证可见区域附近的行是最新的，其余的留到空闲时。
+#[derive(Default)]
+pub struct Highlighter {
+    syntax: Option<Syntax>,           // 当前文件类型的语法，`None` 表示不高亮。
+    lines: Vec<Option<Highlighted>>,  // 已扫描的行，`None` 表示被编辑后失效。
+    dirty_from: Option<usize>,        // 第一处可能过期的行，之前的行都是最新的。
+    #[cfg(test)]
+    scanned: usize,                   // 累计扫描过的行数。
+}
+
+impl Highlighter {
+    /// 构造方法，根据文件类型确定语法。
+    pub fn new(file_type: FileType) -> Self {
+        let syntax = Syntax {
+            line_comment: file_type.line_comment(),
+            block_comment: file_type.block_comment(),
+            quotes: file_type.quotes(),
+        };
+        let highlights = syntax.line_comment.is_some()
+            || syntax.block_comment.is_some()
+            || !syntax.quotes.is_empty();
+        Self {
+            syntax: highlights.then_some(syntax),
+            ..Self::default()
+        }
+    }
+
+    /// 从 `start` 开始的 `removed` 行被替换成了 `inserted` 行。
+    pub fn splice(&mut self, start: usize, removed: usize, inserted: usize) {
+        if start > self.lines.len() {
+            return;
+        }
+        let end = start.saturating_add(removed).min(self.lines.len());
+        self.lines
+            .splice(start..end, repeat_with(|| None).take(inserted));
+        self.dirty_from = Some(self.dirty_from.map_or(start, |dirty| dirty.min(start)));
+    }
+
+    /// 确保 `end` 之前的行都已经是最新的。
+    pub fn highlight_until(&mut self, lines: &[Line], end: usize) {
+        let Some(syntax) = self.syntax else {
+            return;
+        };
+        let end = end.min(lines.len());
+        let mut index = self.dirty_from.unwrap_or(self.lines.len());
+        while index < end {
+            let start_state = self.end_state(index);
+            let Some(line) = lines.get(index) else {
+                break;
+            };
+            let highlighted = syntax.scan(line.as_str(), start_state);
+            #[cfg(test)]
+            {
+                self.scanned = self.scanned.saturating_add(1);
+            }
+            let new_end = highlighted.end;
+            let old = if let Some(slot) = self.lines.get_mut(index) {
+                slot.replace(highlighted)
+            } else {
+                self.lines.push(Some(highlighted));
+                None
+            };
+            index = index.saturating_add(1);
+            // 行尾状态没有变化，之后的行直到下一处失效的行都不需要重新扫描。
+            if old.is_some_and(|old| old.end == new_end) {
+                index = self
+                    .lines
+                    .get(index..)
+                    .and_then(|rest| rest.iter().position(Option::is_none))
+                    .map_or(self.lines.len(), |offset| index.saturating_add(offset));
+            }
+        }
+        self.dirty_from = (index < self.lines.len()).then_some(index);
+    }
+
+    /// 是否还有尚未扫描或已经过期的行。
+    pub fn has_pending_work(&self, line_count: usize) -> bool {
+        self.syntax.is_some() && (self.dirty_from.is_some() || self.lines.len() < line_count)
+    }
+
+    /// 空闲时继续扫描一部分行。
+    pub fn highlight_idle(&mut self, lines: &[Line]) {
+        let start = self.dirty_from.unwrap_or(self.lines.len());
+        self.highlight_until(lines, start.saturating_add(IDLE_CHUNK));
+    }
+
+    /// 指定行需要特殊显示的部分；尚未扫描或已经过期的行没有高亮。
+    pub fn spans(&self, line_index: usize) -> &[Span] {
+        if self.dirty_from.is_some_and(|dirty| line_index >= dirty) {
+            return &[];
+        }
+        self.lines
+            .get(line_index)
+            .and_then(Option::as_ref)
+            .map_or(&[], |highlighted| highlighted.spans.as_slice())
+    }
+
+    /// 指定行行首的扫描状态，即上一行行尾的状态。
+    fn end_state(&self, line_index: usize) -> State {
+        line_index
+            .checked_sub(1)
+            .and_then(|previous| self.lines.get(previous))
+            .and_then(Option::as_ref)
+            .map_or(State::Code, |highlighted| highlighted.end)
+    }
+}
+
+impl Syntax {
+    /// 从给定的状态开始扫描一行。
+    fn scan(&self, text: &str, mut state: State) -> Highlighted {
+        let mut spans = Vec::new();
+        let mut span_start = 0;
+        let mut index = 0;
+        while index < text.len() {
+            let rest = text.get(index..).unwrap_or_default();
+            let Some(ch) = rest.chars().next() else {
+                break;
+            };
+            let mut step = ch.len_utf8();
+            match state {
+                State::Code => {
+                    if self.line_comment.is_some_and(|marker| rest.starts_with(marker)) {
+                        spans.push(Span {
+                            bytes: index..text.len(),
+                            style: Style::Dim,
+                        });
+                        return Highlighted { end: state, spans };
+                    }
+                    if let Some(comment) = self.block_comment
+                        && rest.starts_with(comment.open)
+                    {
+                        span_start = index;
+                        step = comment.open.len();
+                        state = State::Comment(1);
+                    } else if self.quotes.contains(&ch) {
+                        span_start = index;
+                        state = State::String(ch);
+                    }
+                }
+                State::Comment(depth) => {
+                    let Some(comment) = self.block_comment else {
+                        break;
+                    };
+                    if rest.starts_with(comment.close) {
+                        step = comment.close.len();
+                        state = match depth.saturating_sub(1) {
+                            0 => {
+                                spans.push(Span {
+                                    bytes: span_start..index.saturating_add(step),
+                                    style: Style::Dim,
+                                });
+                                State::Code
+                            }
+                            depth => State::Comment(depth),
+                        };
+                    } else if comment.nestable && rest.starts_with(comment.open) {
+                        step = comment.open.len();
+                        state = State::Comment(depth.saturating_add(1));
+                    }
+                }
+                State::String(quote) => {
+                    if ch == '\\' {
+                        step = rest
+                            .chars()
+                            .take(2)
+                            .map(char::len_utf8)
+                            .sum();
+                    } else if ch == quote {
+                        spans.push(Span {
+                            bytes: span_start..index.saturating_add(step),
+                            style: Style::Italic,
+                        });
+                        state = State::Code;
+                    }
+                }
+            }
+            index = index.saturating_add(step);
+        }
+        match state {
+            State::Code => {}
+            State::Comment(_) => spans.push(Span {
+                bytes: span_start..text.len(),
+                style: Style::Dim,
+            }),
+            State::String(_) => spans.push(Span {
+                bytes: span_start..text.len(),
+                style: Style::Italic,
+            }),
+        }
+        Highlighted { end: state, spans }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 一屏的行数。
+    const HEIGHT: usize = 40;
+
+    /// 每帧需要最新的行数：一屏加上预先高亮的部分。
+    const FRAME: usize = HEIGHT + LOOKAHEAD;
+
+    fn rust_lines(count: usize) -> Vec<Line> {
+        (0..count)
+            .map(|index| Line::from(&format!("let value_{index} = \"text\"; // note")))
+            .collect()
+    }
+
+    /// 扫描 `f` 执行期间新扫描的行数。
+    fn scanned_by(highlighter: &mut Highlighter, f: impl FnOnce(&mut Highlighter)) -> usize {
+        let before = highlighter.scanned;
+        f(highlighter);
+        highlighter.scanned.saturating_sub(before)
+    }
+
+    #[test]
+    fn editing_near_the_top_of_a_large_file_rescans_few_lines() {
+        let mut lines = rust_lines(200_000);
+        let mut highlighter = Highlighter::new(FileType::Rust);
+        while highlighter.has_pending_work(lines.len()) {
+            highlighter.highlight_idle(&lines);
+        }
+
+        // 不改变行尾状态的修改只重新扫描这一行和下一行。
+        lines[1] = Line::from("let changed = 1;");
+        highlighter.splice(1, 1, 1);
+        let scanned = scanned_by(&mut highlighter, |highlighter| {
+            highlighter.highlight_until(&lines, FRAME);
+        });
+        assert!(scanned <= 2, "{scanned} lines scanned");
+        assert!(!highlighter.has_pending_work(lines.len()));
+
+        // 打开块注释会影响之后所有的行，但每帧只扫描可见的部分。
+        lines[1] = Line::from("/* let changed = 1;");
+        highlighter.splice(1, 1, 1);
+        let scanned = scanned_by(&mut highlighter, |highlighter| {
+            highlighter.highlight_until(&lines, FRAME);
+        });
+        assert!(scanned <= FRAME, "{scanned} lines scanned");
+        assert_eq!(highlighter.spans(FRAME.saturating_sub(1))[0].bytes.start, 0);
+        assert!(highlighter.spans(100_000).is_empty());
+
+        // 再关闭注释：可见区域之后的行还保存着注释打开之前的状态，
+        // 空闲时扫描到与之相同的行尾状态就停止。
+        lines[1] = Line::from("/* let changed = 1; */");
+        highlighter.splice(1, 1, 1);
+        let scanned = scanned_by(&mut highlighter, |highlighter| {
+            highlighter.highlight_until(&lines, FRAME);
+        });
+        assert!(scanned <= FRAME, "{scanned} lines scanned");
+        let scanned = scanned_by(&mut highlighter, |highlighter| {
+            highlighter.highlight_idle(&lines);
+        });
+        assert!(scanned <= 2, "{scanned} lines scanned");
+        assert!(!highlighter.has_pending_work(lines.len()));
+    }
+
+    #[test]
+    fn scrolling_into_unhighlighted_lines_fills_them_in_within_one_frame() {
+        let lines = rust_lines(200_000);
+        let mut highlighter = Highlighter::new(FileType::Rust);
+        highlighter.highlight_until(&lines, FRAME);
+        assert!(highlighter.spans(150_000).is_empty());
+
+        highlighter.highlight_until(&lines, 150_000 + FRAME);
+
+        let spans = highlighter.spans(150_000);
+        assert_eq!(spans.len(), 2);
+        assert!(matches!(spans[0].style, Style::Italic));
+        assert!(matches!(spans[1].style, Style::Dim));
+    }
+}
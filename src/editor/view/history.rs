@@ -0,0 +1,297 @@
+use super::Location;
+
+/// 撤销历史最多保留的步数。
+const MAX_STEPS: usize = 1000;
+
+/// 一次行范围替换：从 `start` 开始的 `removed` 这些行被替换成了 `inserted`。
+#[derive(Clone, Debug)]
+pub struct Splice {
+    pub start: usize,          // 被替换的第一行。
+    pub removed: Vec<String>,  // 修改前这些行的内容。
+    pub inserted: Vec<String>, // 修改后这些行的内容。
+}
+
+/// 一行之内的替换：第 `line_index` 行从 `byte_index` 开始的 `removed` 被替换成了 `inserted`。
+///
+/// 在行内输入和删除时只记录被修改的一段，不保存整行的内容。
+#[derive(Clone, Debug)]
+pub struct TextEdit {
+    pub line_index: usize, // 被修改的行。
+    pub byte_index: usize, // 修改处在行中的字节位置。
+    pub removed: String,   // 修改前这一段的内容。
+    pub inserted: String,  // 修改后这一段的内容。
+}
+
+/// 对缓冲区内容的一次修改。
+///
+/// 所有对缓冲区内容的修改都表示为这两种形式之一，撤销和重做只需要反向或正向重放。
+#[derive(Clone, Debug)]
+pub enum Change {
+    Lines(Splice),  // 替换若干整行。
+    Text(TextEdit), // 替换一行之内的一段文本。
+}
+
+impl Change {
+    /// 修改涉及的第一行，以及修改前和修改后分别占据的行数。
+    const fn extent(&self) -> (usize, usize, usize) {
+        match self {
+            Self::Lines(splice) => (splice.start, splice.removed.len(), splice.inserted.len()),
+            Self::Text(edit) => (edit.line_index, 1, 1),
+        }
+    }
+}
+
+/// 修改前或修改后的光标状态。
+///
+/// 选区的锚点也记录在这里，撤销和重做会一并恢复。
+#[derive(Clone, Copy, Default, Eq, PartialEq, Debug)]
+pub struct CaretState {
+    pub location: Location,       // 光标的位置。
+    pub anchor: Option<Location>, // 选区的锚点，没有选区时为 `None`。
+}
+
+/// 一步操作的类型，用于合并连续的输入。
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum StepKind {
+    Typing, // 输入单个字符，连续的输入合并为一步。
+    Other,  // 其他修改，每次单独成为一步。
+}
+
+/// 撤销历史中的一步：一组修改以及修改前后的光标状态。
+#[derive(Clone, Debug)]
+pub struct Step {
+    pub changes: Vec<Change>, // 按发生顺序排列的修改。
+    pub before: CaretState,   // 修改前的光标状态，撤销后恢复。
+    pub after: CaretState,    // 修改后的光标状态，重做后恢复。
+    kind: StepKind,           // 操作的类型。
+}
+
+impl Step {
+    /// 这一步影响到的行范围（以修改后的行号表示）。
+    pub fn inserted_lines(&self) -> Option<(usize, usize)> {
+        let extents = self.changes.iter().map(Change::extent);
+        let start = extents.clone().map(|(start, _, _)| start).min()?;
+        let end = extents
+            .map(|(start, _, inserted)| start.saturating_add(inserted))
+            .max()?;
+        Some((start, end))
+    }
+
+    /// 这一步撤销后恢复出的行范围（以修改前的行号表示）。
+    pub fn removed_lines(&self) -> Option<(usize, usize)> {
+        let extents = self.changes.iter().map(Change::extent);
+        let start = extents.clone().map(|(start, _, _)| start).min()?;
+        let end = extents
+            .map(|(start, removed, _)| start.saturating_add(removed))
+            .max()?;
+        Some((start, end))
+    }
+
+    /// 这一步净删除的行数和字节数（每行按多一个换行符计算）。
+    pub fn deleted_size(&self) -> (usize, usize) {
+        let size = |lines: &[String]| {
+            lines
+                .iter()
+                .map(|line| line.len().saturating_add(1))
+                .fold(0_usize, usize::saturating_add)
+        };
+        self.changes.iter().fold((0, 0), |(lines, bytes), change| match change {
+            Change::Lines(splice) => (
+                lines.saturating_add(splice.removed.len().saturating_sub(splice.inserted.len())),
+                bytes.saturating_add(size(&splice.removed).saturating_sub(size(&splice.inserted))),
+            ),
+            Change::Text(edit) => (
+                lines,
+                bytes.saturating_add(edit.removed.len().saturating_sub(edit.inserted.len())),
+            ),
+        })
+    }
+
+    /// 这一步中被替换掉的所有行（行内的修改为被替换掉的那一段）及其行数，各行以换行符连接。
+    pub fn removed_text(&self) -> (String, usize) {
+        let removed: Vec<&str> = self
+            .changes
+            .iter()
+            .flat_map(|change| match change {
+                Change::Lines(splice) => splice.removed.iter().map(String::as_str).collect(),
+                Change::Text(edit) if edit.removed.is_empty() => Vec::new(),
+                Change::Text(edit) => vec![edit.removed.as_str()],
+            })
+            .collect();
+        (removed.join("\n"), removed.len())
+    }
+
+    /// 尝试把紧接着的一步输入合并进来，成功时返回 `true`。
+    fn absorb(&mut self, next: &Self) -> bool {
+        if self.kind != StepKind::Typing
+            || next.kind != StepKind::Typing
+            || self.after != next.before
+        {
+            return false;
+        }
+        let (Some(last), [change]) = (self.changes.last_mut(), next.changes.as_slice()) else {
+            return false;
+        };
+        let merged = match (last, change) {
+            // 紧接着上一次输入的末尾继续输入，把新输入的文本接到上一次后面。
+            (Change::Text(last), Change::Text(edit)) => {
+                let adjacent = last.line_index == edit.line_index
+                    && edit.removed.is_empty()
+                    && last.byte_index.saturating_add(last.inserted.len()) == edit.byte_index;
+                if adjacent {
+                    last.inserted.push_str(&edit.inserted);
+                }
+                adjacent
+            }
+            // 同一行上的整行替换直接合并成一次替换。
+            (Change::Lines(last), Change::Lines(splice)) => {
+                let same_line = last.start == splice.start
+                    && last.inserted.len() == 1
+                    && splice.removed.len() == 1
+                    && last.inserted == splice.removed;
+                if same_line {
+                    last.inserted.clone_from(&splice.inserted);
+                }
+                same_line
+            }
+            // 在刚插入的行上继续输入，把这次的修改直接写进插入的行里。
+            (Change::Lines(last), Change::Text(edit)) => {
+                let end = edit.byte_index.saturating_add(edit.removed.len());
+                match edit
+                    .line_index
+                    .checked_sub(last.start)
+                    .and_then(|offset| last.inserted.get_mut(offset))
+                {
+                    Some(line) if line.get(edit.byte_index..end) == Some(edit.removed.as_str()) => {
+                        line.replace_range(edit.byte_index..end, &edit.inserted);
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            _ => false,
+        };
+        if merged {
+            self.after = next.after;
+        }
+        merged
+    }
+}
+
+/// 撤销和重做的历史。
+pub struct History {
+    undo: Vec<Step>,       // 可以撤销的步骤，最新的在最后。
+    redo: Vec<Step>,       // 可以重做的步骤，最新撤销的在最后。
+    pending: Option<Step>, // 正在进行中的一步。
+    depth: usize,          // 嵌套开始的次数，只有最外层的一步会被记录。
+    saved: Option<usize>,  // 上次保存时撤销栈的深度，`None` 表示无法回到保存时的状态。
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            pending: None,
+            depth: 0,
+            saved: Some(0),
+        }
+    }
+}
+
+impl History {
+    /// 开始新的一步；已经在一步中时，之后的修改并入这一步。
+    pub fn begin(&mut self, kind: StepKind, before: CaretState) {
+        if self.pending.is_some() {
+            self.depth = self.depth.saturating_add(1);
+            return;
+        }
+        self.pending = Some(Step {
+            changes: Vec::new(),
+            before,
+            after: before,
+            kind,
+        });
+    }
+
+    /// 记录一次修改；不在任何一步中时单独成为一步。
+    pub fn record(&mut self, change: Change) {
+        if let Some(step) = &mut self.pending {
+            step.changes.push(change);
+            return;
+        }
+        let caret = CaretState {
+            location: Location {
+                grapheme_index: 0,
+                line_index: change.extent().0,
+            },
+            anchor: None,
+        };
+        self.begin(StepKind::Other, caret);
+        if let Some(step) = &mut self.pending {
+            step.changes.push(change);
+        }
+        self.end(caret);
+    }
+
+    /// 结束当前这一步，没有任何修改时丢弃。
+    ///
+    /// 返回新加入撤销栈的一步；嵌套的一步、被丢弃或并入上一步的输入返回 `None`。
+    pub fn end(&mut self, after: CaretState) -> Option<&Step> {
+        if self.depth > 0 {
+            self.depth = self.depth.saturating_sub(1);
+            return None;
+        }
+        let mut step = self.pending.take()?;
+        if step.changes.is_empty() {
+            return None;
+        }
+        step.after = after;
+        self.redo.clear();
+        if self.saved.is_some_and(|saved| saved > self.undo.len()) {
+            self.saved = None;
+        }
+        let at_saved = self.saved == Some(self.undo.len());
+        if !at_saved
+            && let Some(last) = self.undo.last_mut()
+            && last.absorb(&step)
+        {
+            return None;
+        }
+        self.undo.push(step);
+        if self.undo.len() > MAX_STEPS {
+            self.undo.remove(0);
+            self.saved = self.saved.and_then(|saved| saved.checked_sub(1));
+        }
+        self.undo.last()
+    }
+
+    /// 取出要撤销的一步，并移入重做栈。
+    pub fn undo(&mut self) -> Option<&Step> {
+        let step = self.undo.pop()?;
+        self.redo.push(step);
+        self.redo.last()
+    }
+
+    /// 取出要重做的一步，并移回撤销栈。
+    pub fn redo(&mut self) -> Option<&Step> {
+        let step = self.redo.pop()?;
+        self.undo.push(step);
+        self.undo.last()
+    }
+
+    /// 记录当前状态为已保存。
+    pub fn mark_saved(&mut self) {
+        self.saved = Some(self.undo.len());
+    }
+
+    /// 记录当前内容从未被保存过，撤销到最初也仍然视为已修改。
+    pub const fn forget_saved(&mut self) {
+        self.saved = None;
+    }
+
+    /// 当前内容是否与上次保存时相同。
+    pub fn is_at_saved(&self) -> bool {
+        self.saved == Some(self.undo.len())
+    }
+}
@@ -0,0 +1,148 @@
+use super::line::Line;
+
+const DEFAULT_INDENT_WIDTH: usize = 4;
+const MAX_INDENT_WIDTH: usize = 8;
+
+/// 缓冲区的缩进风格。
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum IndentStyle {
+    Tabs,          // 使用制表符缩进。
+    Spaces(usize), // 使用指定数量的空格缩进。
+}
+
+impl Default for IndentStyle {
+    fn default() -> Self {
+        Self::Spaces(DEFAULT_INDENT_WIDTH)
+    }
+}
+
+/// 可疑缩进的种类。
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum IndentIssue {
+    SpacesBeforeTab,  // 缩进中空格出现在制表符之前。
+    InconsistentStyle, // 缩进与缓冲区的缩进风格不一致。
+    TrailingTab,      // 行尾存在多余的制表符。
+}
+
+impl IndentStyle {
+    /// 根据已有的行推断缩进风格。
+    pub fn detect(lines: &[Line]) -> Self {
+        let mut tab_lines: usize = 0;
+        let mut space_lines: usize = 0;
+        let mut min_spaces: Option<usize> = None;
+
+        for line in lines {
+            let text = line.to_string();
+            match text.chars().next() {
+                Some('\t') => tab_lines = tab_lines.saturating_add(1),
+                Some(' ') => {
+                    let spaces = text.chars().take_while(|ch| *ch == ' ').count();
+                    // 只有一个空格的缩进通常是对齐，而不是缩进层级。
+                    if spaces > 1 {
+                        space_lines = space_lines.saturating_add(1);
+                        min_spaces = Some(min_spaces.map_or(spaces, |min| min.min(spaces)));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if tab_lines > space_lines {
+            Self::Tabs
+        } else {
+            Self::Spaces(min_spaces.map_or(DEFAULT_INDENT_WIDTH, |width| {
+                width.min(MAX_INDENT_WIDTH)
+            }))
+        }
+    }
+
+    /// 一个缩进层级所占的列数。
+    pub const fn width(self) -> usize {
+        match self {
+            Self::Tabs => DEFAULT_INDENT_WIDTH,
+            Self::Spaces(width) => width,
+        }
+    }
+
+    /// 一级缩进对应的文本。
+    pub fn unit(self) -> String {
+        match self {
+            Self::Tabs => String::from("\t"),
+            Self::Spaces(width) => " ".repeat(width),
+        }
+    }
+
+    /// 去掉一级缩进：一个制表符，或最多一个缩进宽度的空格。
+    pub fn dedent(self, line_text: &str) -> String {
+        if let Some(rest) = line_text.strip_prefix('\t') {
+            return rest.to_string();
+        }
+        let spaces = line_text
+            .chars()
+            .take(self.width())
+            .take_while(|ch| *ch == ' ')
+            .count();
+        line_text.get(spaces..).unwrap_or(line_text).to_string()
+    }
+
+    /// 检查一行文本的缩进是否可疑。
+    pub fn check(self, line_text: &str) -> Option<IndentIssue> {
+        let indent = leading_whitespace(line_text);
+        if indent.trim_start_matches('\t').contains('\t') {
+            return Some(IndentIssue::SpacesBeforeTab);
+        }
+        let inconsistent = match self {
+            Self::Tabs => indent.contains(' ') && indent.len() >= self.width(),
+            Self::Spaces(_) => indent.contains('\t'),
+        };
+        if inconsistent {
+            return Some(IndentIssue::InconsistentStyle);
+        }
+        if line_text.len() > indent.len() && line_text.ends_with('\t') {
+            return Some(IndentIssue::TrailingTab);
+        }
+        None
+    }
+
+    /// 将一行文本的缩进转换为当前风格，并去掉行尾的制表符。
+    pub fn normalize(self, line_text: &str) -> String {
+        let indent = leading_whitespace(line_text);
+        let width = self.width();
+        let columns = indent.chars().fold(0, |column: usize, ch| {
+            if ch == '\t' {
+                next_tab_stop(column, width)
+            } else {
+                column.saturating_add(1)
+            }
+        });
+
+        let mut result = match self {
+            Self::Tabs => {
+                #[allow(clippy::integer_division, clippy::arithmetic_side_effects)]
+                let (tabs, spaces) = (columns / width, columns % width);
+                format!("{}{}", "\t".repeat(tabs), " ".repeat(spaces))
+            }
+            Self::Spaces(_) => " ".repeat(columns),
+        };
+        let rest = line_text.get(indent.len()..).unwrap_or_default();
+        result.push_str(rest.trim_end_matches('\t'));
+        result
+    }
+}
+
+/// 获取行首的空白部分。
+fn leading_whitespace(line_text: &str) -> &str {
+    let end = line_text
+        .find(|ch: char| ch != ' ' && ch != '\t')
+        .unwrap_or(line_text.len());
+    line_text.get(..end).unwrap_or_default()
+}
+
+/// 计算下一个制表位所在的列。
+#[allow(clippy::arithmetic_side_effects)]
+const fn next_tab_stop(column: usize, width: usize) -> usize {
+    if width == 0 {
+        return column;
+    }
+    column - column % width + width
+}
@@ -1,21 +1,54 @@
 use core::fmt;
+use std::cell::OnceCell;
 use std::ops::Range;
 use unicode_segmentation::UnicodeSegmentation;
 use unicode_width::UnicodeWidthStr;
 
+use super::highlighter::Span;
+use crate::editor::terminal::StyledText;
+
+/// 制表符对齐到的列数的倍数，至少为 1；配置中没有设置 `tab_width` 时为 4。
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub struct TabWidth(usize);
+
+impl TabWidth {
+    /// 创建制表符宽度，0 按 1 处理。
+    pub fn new(width: usize) -> Self {
+        Self(width.max(1))
+    }
+
+    /// 制表符宽度的列数。
+    pub const fn get(self) -> usize {
+        self.0
+    }
+}
+
+impl Default for TabWidth {
+    fn default() -> Self {
+        Self(4)
+    }
+}
+
 /// 一个字的宽度。
 #[derive(Clone, Copy)]
 enum GraphemeWidth {
     Half,           // 半宽字符（如 ASCII 字符）。
     Full,           // 全宽字符（如中文字符）。
+    Tab,            // 制表符，延伸到下一个制表位。
 }
 
 impl GraphemeWidth {
-    // 宽度相加
-    const fn saturating_add(self, other: usize) -> usize {
+    /// 从第 `column` 列开始显示这个字后，下一个字开始的列；制表符对齐到 `tab_width` 的倍数。
+    fn saturating_add(self, column: usize, tab_width: TabWidth) -> usize {
         match self {
-            Self::Full => other.saturating_add(2),
-            Self::Half => other.saturating_add(1),
+            Self::Full => column.saturating_add(2),
+            Self::Half => column.saturating_add(1),
+            Self::Tab => {
+                let width = tab_width.get();
+                column
+                    .saturating_add(width)
+                    .saturating_sub(column.checked_rem(width).unwrap_or_default())
+            }
         }
     }
 }
@@ -28,16 +61,50 @@ struct TextFragment {
 }
 
 /// `Line` 结构体表示文本中的一行。
+///
+/// 只保存原始文本，字形切分在第一次显示或编辑这一行时才计算并缓存，
+/// 因此打开很大的文件时只有显示过的行才会占用字形的内存。
 #[derive(Default)]
 pub struct Line {
-    fragments: Vec<TextFragment>,   // 文本片段的集合。
+    text: String,                           // 行的原始文本。
+    fragments: OnceCell<Vec<TextFragment>>, // 文本片段的集合，第一次使用时切分。
+    touched: bool,                          // 自上次保存以来是否被修改过。
 }
 
 impl Line {
     /// 从字符串创建一个新的 `Line` 实例。
     pub fn from(line_str: &str) -> Self {
-        let fragments = Self::str_to_fragments(line_str);
-        Self { fragments }
+        Self::from_string(line_str.to_string())
+    }
+
+    /// 用已有的字符串创建 `Line`，不复制文本。
+    pub fn from_string(text: String) -> Self {
+        Self {
+            text,
+            fragments: OnceCell::new(),
+            touched: false,
+        }
+    }
+
+    /// 行的原始文本。
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// 这一行的字形是否已经切分过。
+    pub fn is_segmented(&self) -> bool {
+        self.fragments.get().is_some()
+    }
+
+    /// 这一行的字形片段，第一次调用时切分并缓存。
+    fn fragments(&self) -> &Vec<TextFragment> {
+        self.fragments.get_or_init(|| Self::str_to_fragments(&self.text))
+    }
+
+    /// 修改字形片段，调用者须保证原始文本与片段一致。
+    fn fragments_mut(&mut self) -> &mut Vec<TextFragment> {
+        self.fragments();
+        self.fragments.get_mut().unwrap_or_else(|| unreachable!())
     }
 
     /// 将字符串转换为文本片段的向量。
@@ -45,17 +112,21 @@ impl Line {
         line_str
             .graphemes(true)
             .map(|grapheme| {
-                let (replacement, rendered_width) = Self::replace_character(grapheme).map_or_else(
-                    || {
-                        let unicode_width = grapheme.width();
-                        let rendered_width = match unicode_width {
-                            0 | 1 => GraphemeWidth::Half,
-                            _ => GraphemeWidth::Full,
-                        };
-                        (None, rendered_width)
-                    },
-                    |replacement| (Some(replacement), GraphemeWidth::Half),
-                );
+                let (replacement, rendered_width) = if grapheme == "\t" {
+                    (Some(' '), GraphemeWidth::Tab)
+                } else {
+                    Self::replace_character(grapheme).map_or_else(
+                        || {
+                            let unicode_width = grapheme.width();
+                            let rendered_width = match unicode_width {
+                                0 | 1 => GraphemeWidth::Half,
+                                _ => GraphemeWidth::Full,
+                            };
+                            (None, rendered_width)
+                        },
+                        |replacement| (Some(replacement), GraphemeWidth::Half),
+                    )
+                };
                 TextFragment {
                     grapheme: grapheme.to_string(),
                     rendered_width,
@@ -70,14 +141,14 @@ impl Line {
         let width = for_str.width();
         match for_str {
             " " => None,
-            "\t" => Some(' '),
             _ if width > 0 && for_str.trim().is_empty() => Some('␣'),
             _ if width == 0 => {
                 let mut chars = for_str.chars();
-                if let Some(ch) = chars.next() {
-                    if ch.is_control() && chars.next().is_none() {
-                        return Some('▯');
-                    }
+                if let Some(ch) = chars.next()
+                    && ch.is_control()
+                    && chars.next().is_none()
+                {
+                    return Some('▯');
                 }
                 Some('·')
             }
@@ -86,105 +157,482 @@ impl Line {
     }
 
     /// 获取指定范围内的可见字形。
-    pub fn get_visible_graphemes(&self, range: Range<usize>) -> String {
+    pub fn get_visible_graphemes(&self, range: Range<usize>, tab_width: TabWidth) -> String {
+        self.get_visible_segments(range, &[], tab_width)
+            .into_iter()
+            .map(|segment| segment.text)
+            .collect()
+    }
+
+    /// 获取可见范围内的字形，按高亮的样式分成多段。
+    pub fn get_visible_segments(
+        &self,
+        range: Range<usize>,
+        spans: &[Span],
+        tab_width: TabWidth,
+    ) -> Vec<StyledText> {
+        let mut segments: Vec<StyledText> = Vec::new();
         if range.start >= range.end {
-            return String::new();
+            return segments;
         }
 
-        let mut result = String::new();
         let mut current_pos = 0;
+        let mut byte_index = 0;
 
-        for fragment in &self.fragments {
-            let fragment_end = fragment.rendered_width.saturating_add(current_pos);
+        for fragment in self.fragments() {
+            let fragment_end = fragment.rendered_width.saturating_add(current_pos, tab_width);
 
             if current_pos >= range.end {
                 break;
             }
 
             if fragment_end > range.start {
-                if fragment_end > range.end || current_pos < range.start {
-                    result.push('⋯'); // 超出范围时显示省略号。
+                let style = spans
+                    .iter()
+                    .find(|span| span.bytes.contains(&byte_index))
+                    .map(|span| span.style);
+                let mut text = String::new();
+                if matches!(fragment.rendered_width, GraphemeWidth::Tab) {
+                    // 制表符显示为空格，只显示落在可见范围内的列。
+                    let visible = fragment_end
+                        .min(range.end)
+                        .saturating_sub(current_pos.max(range.start));
+                    text.push_str(&" ".repeat(visible));
+                } else if fragment_end > range.end || current_pos < range.start {
+                    text.push('⋯'); // 超出范围时显示省略号。
                 } else if let Some(char) = fragment.replacement {
-                    result.push(char); // 使用替代字符。
+                    text.push(char); // 使用替代字符。
                 } else {
-                    result.push_str(&fragment.grapheme); // 添加实际字形。
+                    text.push_str(&fragment.grapheme); // 添加实际字形。
+                }
+                match segments.last_mut() {
+                    Some(last) if last.style == style => last.text.push_str(&text),
+                    _ => segments.push(StyledText { text, style }),
                 }
             }
             current_pos = fragment_end;
+            byte_index = byte_index.saturating_add(fragment.grapheme.len());
         }
-        result
+        segments
+    }
+
+    /// 在显示列范围 `range` 之外，这一行的左侧和右侧是否还有内容。
+    pub fn hidden_sides(&self, range: &Range<usize>, tab_width: TabWidth) -> (bool, bool) {
+        let width = self.width(tab_width);
+        (range.start > 0 && width > 0, width > range.end)
     }
 
     /// 获取行中字数。
     pub fn grapheme_count(&self) -> usize {
-        self.fragments.len()
+        self.fragments().len()
+    }
+
+    /// 依次取出行中的各个字形。
+    pub fn graphemes(&self) -> impl DoubleEndedIterator<Item = &str> + ExactSizeIterator {
+        self.fragments().iter().map(|fragment| fragment.grapheme.as_str())
+    }
+
+    /// 第一个不是空白的字形的索引；整行都是空白（或是空行）时返回 `None`。
+    pub fn first_non_whitespace_index(&self) -> Option<usize> {
+        self.fragments()
+            .iter()
+            .position(|fragment| !fragment.grapheme.trim().is_empty())
+    }
+
+    /// 获取整行的渲染宽度。
+    pub fn width(&self, tab_width: TabWidth) -> usize {
+        self.width_until(self.grapheme_count(), tab_width)
     }
 
     /// 计算从行首到指定字形索引的宽度。
-    pub fn width_until(&self, grapheme_index: usize) -> usize {
-        self.fragments
+    pub fn width_until(&self, grapheme_index: usize, tab_width: TabWidth) -> usize {
+        self.fragments()
             .iter()
             .take(grapheme_index)
-            .map(|fragment| match fragment.rendered_width {
-                GraphemeWidth::Half => 1,
-                GraphemeWidth::Full => 2,
-            })
-            .sum()
+            .fold(0, |column, fragment| fragment.rendered_width.saturating_add(column, tab_width))
     }
 
-    /// 在指定位置插入一个字符。
-    pub fn insert_char(&mut self, character: char, at: usize) {
-        let mut result = String::new();
-        for (index, fragment) in self.fragments.iter().enumerate() {
-            if index == at {
-                result.push(character);
+    /// 软换行时这一行拆分成的各显示行开始处的字形索引，第一个总是 0。
+    ///
+    /// 字形不会被拆开，放不下的字形移到下一显示行，宽度不足一个字形时每行仍放一个；
+    /// 最后一显示行正好占满宽度时再加一个空的显示行，留给行尾的光标。
+    pub fn wrap_starts(&self, width: usize, tab_width: TabWidth) -> Vec<usize> {
+        let mut starts = vec![0];
+        let mut row_start = 0;
+        let mut column = 0;
+        for (index, fragment) in self.fragments().iter().enumerate() {
+            let end = fragment.rendered_width.saturating_add(column, tab_width);
+            if end.saturating_sub(row_start) > width && column > row_start {
+                starts.push(index);
+                row_start = column;
             }
-            result.push_str(&fragment.grapheme);
+            column = end;
         }
-        if at >= self.fragments.len() {
-            result.push(character);
+        if width > 0 && !self.fragments().is_empty() && column.saturating_sub(row_start) >= width {
+            starts.push(self.fragments().len());
+        }
+        starts
+    }
+
+    /// 找到渲染宽度达到指定列的第一个字形索引，整行都不够宽时返回字数。
+    pub fn grapheme_index_at_width(&self, width: usize, tab_width: TabWidth) -> usize {
+        if width == 0 {
+            return 0;
+        }
+        let mut column = 0;
+        for (index, fragment) in self.fragments().iter().enumerate() {
+            column = fragment.rendered_width.saturating_add(column, tab_width);
+            if column >= width {
+                return index.saturating_add(1);
+            }
         }
-        self.fragments = Self::str_to_fragments(&result);
+        self.grapheme_count()
+    }
+
+    /// 在指定位置插入一个字符。
+    pub fn insert_char(&mut self, character: char, at: usize) {
+        self.insert_str(&character.to_string(), at);
+    }
+
+    /// 在指定位置插入一段文本。
+    pub fn insert_str(&mut self, text: &str, at: usize) {
+        self.replace(at..at, text);
     }
 
     /// 删除指定索引的字形。
     pub fn delete(&mut self, at: usize) {
-        let mut result = String::new();
-        for (index, fragment) in self.fragments.iter().enumerate() {
-            if index != at {
-                result.push_str(&fragment.grapheme);
-            }
+        if at < self.grapheme_count() {
+            self.replace(at..at.saturating_add(1), "");
         }
-        self.fragments = Self::str_to_fragments(&result);
+    }
+
+    /// 把 `graphemes` 范围内的字形替换为 `text`，返回被替换掉的文本。
+    ///
+    /// 直接修改原始文本中对应的字节，只重新切分修改处附近的字形：插入的内容可能与前后的字形合并
+    /// （如组合符号、零宽连接符），删除后前后两个字形也可能合并。
+    pub fn replace(&mut self, graphemes: Range<usize>, text: &str) -> String {
+        let count = self.grapheme_count();
+        let start = graphemes.start.min(count);
+        let end = graphemes.end.clamp(start, count);
+        let bytes = self.byte_index_at_grapheme(start)..self.byte_index_at_grapheme(end);
+        let removed = self.text.get(bytes.clone()).unwrap_or_default().to_string();
+        self.text.replace_range(bytes, text);
+        let inserted = Self::str_to_fragments(text);
+        let inserted_end = start.saturating_add(inserted.len());
+        self.fragments_mut().splice(start..end, inserted);
+        self.resegment(start.saturating_sub(1)..inserted_end.saturating_add(1));
+        self.touched = true;
+        removed
+    }
+
+    /// 把原始文本中 `bytes` 范围内的字节替换为 `text`，之后重新切分整行。
+    ///
+    /// 用于撤销和重做：记录下的字节范围不一定落在修改后的字形边界上。范围超出这一行或不在字符边界上时不做修改。
+    pub fn replace_bytes(&mut self, bytes: Range<usize>, text: &str) {
+        if self.text.get(bytes.clone()).is_none() {
+            return;
+        }
+        self.text.replace_range(bytes, text);
+        self.fragments = OnceCell::new();
+        self.touched = true;
     }
 
     /// 将一行添加到另一行
     pub fn append(&mut self, other: &Self) {
-        let mut concat = self.to_string();
-        concat.push_str(&other.to_string());
-        self.fragments = Self::str_to_fragments(&concat);
+        let at = self.grapheme_count();
+        self.replace(at..at, other.as_str());
+    }
+
+    /// 重新切分 `range` 内的字形，范围两端与相邻的字形会合并时向外扩展，直到两端都是字形边界。
+    ///
+    /// 只改变片段的划分，不改变原始文本。
+    fn resegment(&mut self, range: Range<usize>) {
+        let fragments = self.fragments_mut();
+        let len = fragments.len();
+        let mut start = range.start.min(len);
+        let mut end = range.end.clamp(start, len);
+        loop {
+            let text: String = fragments
+                .get(start..end)
+                .unwrap_or_default()
+                .iter()
+                .map(|fragment| fragment.grapheme.as_str())
+                .collect();
+            let resegmented = Self::str_to_fragments(&text);
+            let before = start
+                .checked_sub(1)
+                .and_then(|index| fragments.get(index));
+            let after = fragments.get(end);
+            let first = resegmented.first().or(after);
+            let last = resegmented.last().or(before);
+            let extend_start = before.zip(first).is_some_and(|(a, b)| Self::joins(a, b));
+            let extend_end = last.zip(after).is_some_and(|(a, b)| Self::joins(a, b));
+            if !extend_start && !extend_end {
+                fragments.splice(start..end, resegmented);
+                return;
+            }
+            if extend_start {
+                start = start.saturating_sub(1);
+            }
+            if extend_end {
+                end = end.saturating_add(1);
+            }
+        }
+    }
+
+    /// 两个相邻的字形连在一起时，它们之间是否不再是字形边界（例如合并成一个字形，
+    /// 或者区域指示符重新配对）。
+    fn joins(left: &TextFragment, right: &TextFragment) -> bool {
+        let joined = format!("{}{}", left.grapheme, right.grapheme);
+        let mut graphemes = joined.graphemes(true);
+        graphemes.next() != Some(left.grapheme.as_str())
+            || graphemes.next() != Some(right.grapheme.as_str())
+    }
+
+    /// 行尾是否有空格或制表符。
+    pub fn has_trailing_whitespace(&self) -> bool {
+        self.text.ends_with([' ', '\t'])
+    }
+
+    /// 删除行尾的空格和制表符，返回这一行是否被修改；只由空白组成的行变为空行。
+    pub fn trim_end(&mut self) -> bool {
+        if !self.has_trailing_whitespace() {
+            return false;
+        }
+        let fragments = self.fragments_mut();
+        let mut removed: usize = 0;
+        while let Some(fragment) = fragments
+            .pop_if(|fragment| matches!(fragment.grapheme.as_str(), " " | "\t"))
+        {
+            removed = removed.saturating_add(fragment.grapheme.len());
+        }
+        self.text.truncate(self.text.len().saturating_sub(removed));
+        self.touched = true;
+        true
     }
 
     /// 分割两个line 
     pub fn split(&mut self, at: usize) -> Self {
-        if at > self.fragments.len() {
+        if at > self.grapheme_count() {
             return Self::default();
         }
-        let remainder = self.fragments.split_off(at);
+        let byte_index = self.byte_index_at_grapheme(at);
+        let remainder = self.fragments_mut().split_off(at);
+        let text = self.text.split_off(byte_index);
+        self.touched = true;
         Self {
-            fragments: remainder,
+            text,
+            fragments: OnceCell::from(remainder),
+            touched: true,
+        }
+    }
+
+    /// 指定字形开头的字节位置；索引在行尾之后时返回行的字节长度。
+    pub fn byte_index_at_grapheme(&self, grapheme_index: usize) -> usize {
+        self.fragments()
+            .iter()
+            .take(grapheme_index)
+            .map(|fragment| fragment.grapheme.len())
+            .fold(0, usize::saturating_add)
+    }
+
+    /// 包含指定字节的字形的索引；字节位置在行尾之后时返回字形数。
+    pub fn grapheme_index_at_byte(&self, byte_index: usize) -> usize {
+        let mut offset: usize = 0;
+        self.fragments()
+            .iter()
+            .position(|fragment| {
+                offset = offset.saturating_add(fragment.grapheme.len());
+                offset > byte_index
+            })
+            .unwrap_or(self.fragments().len())
+    }
+
+    /// 查找文本在行内的所有出现位置（互不重叠），返回各自开头的字形索引。
+    pub fn find_all(&self, query: &str) -> Vec<usize> {
+        if query.is_empty() {
+            return Vec::new();
         }
+        let text = self.to_string();
+        let mut positions = Vec::new();
+        let mut fragments = self.fragments().iter();
+        let mut grapheme_index: usize = 0;
+        let mut byte_offset: usize = 0;
+        for (start, _) in text.match_indices(query) {
+            while byte_offset < start
+                && let Some(fragment) = fragments.next()
+            {
+                byte_offset = byte_offset.saturating_add(fragment.grapheme.len());
+                grapheme_index = grapheme_index.saturating_add(1);
+            }
+            positions.push(grapheme_index);
+        }
+        positions
+    }
+
+    /// `grapheme_index` 之前最近的一个词的开头。
+    pub fn previous_word_start(&self, grapheme_index: usize) -> Option<usize> {
+        self.word_starts()
+            .into_iter()
+            .rev()
+            .find(|start| *start < grapheme_index)
+    }
+
+    /// `grapheme_index` 之后最近的一个词的开头。
+    pub fn next_word_start(&self, grapheme_index: usize) -> Option<usize> {
+        self.word_starts()
+            .into_iter()
+            .find(|start| *start > grapheme_index)
+    }
+
+    /// 按 Unicode 词边界划分后，各个非空白片段开头的字形索引。
+    fn word_starts(&self) -> Vec<usize> {
+        let text = self.to_string();
+        let mut starts = Vec::new();
+        let mut fragments = self.fragments().iter();
+        let mut grapheme_index: usize = 0;
+        let mut byte_offset: usize = 0;
+        for (start, word) in text.split_word_bound_indices() {
+            while byte_offset < start
+                && let Some(fragment) = fragments.next()
+            {
+                byte_offset = byte_offset.saturating_add(fragment.grapheme.len());
+                grapheme_index = grapheme_index.saturating_add(1);
+            }
+            if !word.trim().is_empty() {
+                starts.push(grapheme_index);
+            }
+        }
+        starts
+    }
+
+    /// 自上次保存以来是否被修改过。
+    pub const fn is_touched(&self) -> bool {
+        self.touched
+    }
+
+    /// 设置修改标记。
+    pub const fn set_touched(&mut self, touched: bool) {
+        self.touched = touched;
     }
 }
 
 /// 实现 `Display` trait，用于格式化输出。
 impl fmt::Display for Line {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        let result: String = self
-            .fragments
-            .iter()
-            .map(|fragment| fragment.grapheme.clone())
-            .collect();
-        write!(formatter, "{result}")
+        write!(formatter, "{}", self.text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 增量修改后的切分结果必须与重新切分整行的结果一致。
+    fn assert_segmented_like_fresh(line: &Line, expected: &str) {
+        assert_eq!(line.as_str(), expected);
+        let fresh = Line::from(expected);
+        assert_eq!(
+            line.graphemes().collect::<Vec<_>>(),
+            fresh.graphemes().collect::<Vec<_>>()
+        );
+        assert_eq!(line.width(TabWidth::default()), fresh.width(TabWidth::default()));
+    }
+
+    #[test]
+    fn inserting_ascii_splices_only_the_new_fragment() {
+        let mut line = Line::from("hello");
+        line.insert_char('!', 5);
+        line.insert_str(", world", 5);
+        line.insert_char('>', 0);
+        assert_segmented_like_fresh(&line, ">hello, world!");
+        line.delete(0);
+        line.delete(5);
+        assert_segmented_like_fresh(&line, "hello world!");
+    }
+
+    #[test]
+    fn widths_count_wide_combining_and_tab_graphemes() {
+        // 制表符宽度为 4：a 占 1 列，好 占 2 列，é 占 1 列，制表符补齐到第 8 列。
+        let line = Line::from("a好e\u{301}\tb");
+        let four = TabWidth::new(4);
+        let widths: Vec<usize> = (0..=6).map(|index| line.width_until(index, four)).collect();
+        assert_eq!(widths, [0, 1, 3, 4, 8, 9, 9]);
+        assert_eq!(line.width(four), 9);
+        let indices: Vec<usize> = (0..=10).map(|width| line.grapheme_index_at_width(width, four)).collect();
+        assert_eq!(indices, [0, 1, 2, 2, 3, 4, 4, 4, 4, 5, 5]);
+    }
+
+    #[test]
+    fn each_caller_chooses_its_own_tab_width() {
+        let line = Line::from("ab\tc");
+        let (four, eight) = (TabWidth::new(4), TabWidth::new(8));
+        assert_eq!(line.width(four), 5);
+        assert_eq!(line.width(eight), 9);
+        assert_eq!(line.width(TabWidth::new(0)), 4);
+        assert_eq!(line.get_visible_graphemes(0..10, eight), "ab      c");
+        assert_eq!(line.get_visible_graphemes(0..10, four), "ab  c");
+        assert_eq!(line.wrap_starts(6, eight), [0, 2, 3]);
+    }
+
+    #[test]
+    fn combining_marks_merge_with_the_previous_grapheme() {
+        let mut line = Line::from("cafe!");
+        line.insert_char('\u{301}', 4);
+        assert_segmented_like_fresh(&line, "cafe\u{301}!");
+        assert_eq!(line.grapheme_count(), 5);
+        // 删除整个带组合符号的字形。
+        line.delete(3);
+        assert_segmented_like_fresh(&line, "caf!");
+    }
+
+    #[test]
+    fn zwj_sequences_join_and_split_at_the_edit() {
+        let mut line = Line::from("a👩b");
+        line.insert_str("\u{200d}💻", 2);
+        assert_segmented_like_fresh(&line, "a👩\u{200d}💻b");
+        assert_eq!(line.grapheme_count(), 3);
+        line.delete(1);
+        assert_segmented_like_fresh(&line, "ab");
+
+        // 删除中间的字形后，两侧的区域指示符重新配对成一面旗帜。
+        let mut flags = Line::from("🇨x🇳");
+        flags.delete(1);
+        assert_segmented_like_fresh(&flags, "🇨🇳");
+        assert_eq!(flags.grapheme_count(), 1);
+    }
+
+    #[test]
+    fn replace_returns_the_removed_text() {
+        let mut line = Line::from("你好, world");
+        assert_eq!(line.replace(1..4, "们"), "好, ");
+        assert_segmented_like_fresh(&line, "你们world");
+        assert_eq!(line.replace(2..99, ""), "world");
+        assert_segmented_like_fresh(&line, "你们");
+    }
+
+    #[test]
+    fn split_append_and_trim_keep_text_and_fragments_in_sync() {
+        let mut line = Line::from("e\u{301}t\u{e9} \t ");
+        assert!(line.trim_end());
+        assert_segmented_like_fresh(&line, "e\u{301}t\u{e9}");
+        let rest = line.split(1);
+        assert_segmented_like_fresh(&line, "e\u{301}");
+        assert_segmented_like_fresh(&rest, "t\u{e9}");
+        line.append(&rest);
+        assert_segmented_like_fresh(&line, "e\u{301}t\u{e9}");
+    }
+
+    #[test]
+    fn replace_bytes_resegments_the_whole_line() {
+        let mut line = Line::from("cafe\u{301}");
+        line.replace_bytes(4..6, "");
+        assert_segmented_like_fresh(&line, "cafe");
+        // 超出范围或不在字符边界上的范围被忽略。
+        line.replace_bytes(1..9, "x");
+        assert_segmented_like_fresh(&line, "cafe");
+        let mut wide = Line::from("你好");
+        wide.replace_bytes(1..2, "x");
+        assert_segmented_like_fresh(&wide, "你好");
     }
 }
@@ -0,0 +1,73 @@
+use std::collections::BTreeSet;
+
+/// 带有持久高亮的行，随着编辑移动。
+///
+/// 只记录行号，因此即使很多行带有高亮，代价也很小。
+#[derive(Default)]
+pub struct LineMarks {
+    lines: BTreeSet<usize>, // 带有高亮的行。
+}
+
+impl LineMarks {
+    /// 切换某一行的高亮，返回该行现在是否带有高亮。
+    pub fn toggle(&mut self, line_index: usize) -> bool {
+        if self.lines.remove(&line_index) {
+            false
+        } else {
+            self.lines.insert(line_index);
+            true
+        }
+    }
+
+    /// 某一行是否带有高亮。
+    pub fn contains(&self, line_index: usize) -> bool {
+        self.lines.contains(&line_index)
+    }
+
+    /// 按行号顺序列出带有高亮的行。
+    pub fn lines(&self) -> Vec<usize> {
+        self.lines.iter().copied().collect()
+    }
+
+    /// 清除某一行的高亮。
+    pub fn remove(&mut self, line_index: usize) {
+        self.lines.remove(&line_index);
+    }
+
+    /// 清除所有高亮。
+    pub fn clear(&mut self) {
+        self.lines.clear();
+    }
+
+    /// 从 `start` 开始的 `removed` 行被替换成了 `inserted` 行。
+    ///
+    /// 被替换的行上的高亮跟随内容相同的新行（例如在行首换行时跟随被下移的原内容），
+    /// 没有相同的行时留在对应位置的行上（新行更少时合并到最后一行）；
+    /// 所有行都被删除时，高亮随之消失。之后的行按行数的变化移动。
+    pub fn splice(&mut self, start: usize, removed: &[String], inserted: &[String]) {
+        let end = start.saturating_add(removed.len());
+        let moved: Vec<usize> = self.lines.range(start..).copied().collect();
+        if moved.is_empty() {
+            return;
+        }
+        for line_index in &moved {
+            self.lines.remove(line_index);
+        }
+        for line_index in moved {
+            if line_index >= end {
+                self.lines.insert(
+                    line_index
+                        .saturating_sub(removed.len())
+                        .saturating_add(inserted.len()),
+                );
+            } else if !inserted.is_empty() {
+                let offset = line_index.saturating_sub(start);
+                let target = removed
+                    .get(offset)
+                    .and_then(|text| inserted.iter().position(|new| new == text))
+                    .unwrap_or_else(|| offset.min(inserted.len().saturating_sub(1)));
+                self.lines.insert(start.saturating_add(target));
+            }
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use std::collections::{BTreeMap, HashMap};
+
+/// 最多记录的修改过的行数，超出时忘记最早修改的行，直到只剩 `KEEP_AFTER_EVICT` 行。
+const MAX_TRACKED: usize = 10_000;
+const KEEP_AFTER_EVICT: usize = 7_500;
+
+/// 着色的强度分档：之后又进行的修改次数少于第 `i` 个值时属于第 `i` 档，超过最后一个值后不再着色。
+const BUCKETS: [u64; 3] = [20, 200, 2000];
+
+/// 每一行最后一次被修改时的代数，用于给最近修改过的行着色以及在修改过的行之间跳转。
+///
+/// 每次修改（撤销历史中的一步）开始时代数加一；只在本次会话中有效。
+#[derive(Default)]
+pub struct RecentChanges {
+    lines: BTreeMap<usize, u64>, // 修改过的行及其最后一次修改时的代数。
+    generation: u64,             // 当前的代数。
+}
+
+impl RecentChanges {
+    /// 开始新的一次修改。
+    pub const fn next_generation(&mut self) {
+        self.generation = self.generation.saturating_add(1);
+    }
+
+    /// 从 `start` 开始的 `removed` 行被替换成了 `inserted` 行。
+    ///
+    /// 与被替换的某一行内容相同的新行（例如在行首换行时被下移的原内容）保留原来的状态，
+    /// 其余的新行记为在当前代数被修改；之后的行按行数的变化移动。
+    pub fn splice(&mut self, start: usize, removed: &[String], inserted: &[String]) {
+        let end = start.saturating_add(removed.len());
+        let mut replaced = HashMap::new();
+        for (line_index, generation) in self.lines.split_off(&start) {
+            if line_index >= end {
+                self.lines.insert(
+                    line_index
+                        .saturating_sub(removed.len())
+                        .saturating_add(inserted.len()),
+                    generation,
+                );
+            } else if let Some(text) = removed.get(line_index.saturating_sub(start)) {
+                replaced.insert(text.as_str(), generation);
+            }
+        }
+        let mut unchanged: HashMap<&str, Option<u64>> = HashMap::new();
+        for text in removed {
+            unchanged
+                .entry(text.as_str())
+                .or_insert_with(|| replaced.get(text.as_str()).copied());
+        }
+        for (offset, text) in inserted.iter().enumerate() {
+            let generation = unchanged
+                .get(text.as_str())
+                .copied()
+                .unwrap_or(Some(self.generation));
+            if let Some(generation) = generation {
+                self.lines.insert(start.saturating_add(offset), generation);
+            }
+        }
+        self.evict();
+    }
+
+    /// 指定行在行内被修改，行数不变。
+    pub fn touch(&mut self, line_index: usize) {
+        self.lines.insert(line_index, self.generation);
+        self.evict();
+    }
+
+    /// 指定行的着色强度，`0` 表示最近修改；没有修改过或已经淡出时返回 `None`。
+    pub fn bucket(&self, line_index: usize) -> Option<usize> {
+        let age = self
+            .generation
+            .saturating_sub(*self.lines.get(&line_index)?);
+        BUCKETS.iter().position(|&limit| age < limit)
+    }
+
+    /// 指定行之后的下一个修改过的行，到末尾后从头开始。
+    pub fn next_after(&self, line_index: usize) -> Option<usize> {
+        self.lines
+            .range(line_index.saturating_add(1)..)
+            .chain(self.lines.range(..=line_index))
+            .map(|(line_index, _)| *line_index)
+            .next()
+    }
+
+    /// 记录的行数超过上限时，忘记最早修改的行。
+    fn evict(&mut self) {
+        if self.lines.len() <= MAX_TRACKED {
+            return;
+        }
+        let mut generations: Vec<u64> = self.lines.values().copied().collect();
+        generations.sort_unstable();
+        let forget = self.lines.len().saturating_sub(KEEP_AFTER_EVICT);
+        let Some(&cutoff) = generations.get(forget) else {
+            return;
+        };
+        self.lines.retain(|_, generation| *generation >= cutoff);
+    }
+}
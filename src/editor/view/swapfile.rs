@@ -0,0 +1,149 @@
+use std::{
+    collections::HashMap,
+    fs::{self, OpenOptions},
+    io::{Error, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
+
+/// 两次写入交换文件之间的最小间隔。
+const SWAP_INTERVAL: Duration = Duration::from_secs(2);
+
+/// 交换文件的路径：与原文件在同一目录下的隐藏文件 `.<文件名>.swp`。
+pub fn path_for(file: &Path) -> Option<PathBuf> {
+    let name = file.file_name()?.to_string_lossy().into_owned();
+    Some(file.with_file_name(format!(".{name}.swp")))
+}
+
+/// 原文件旁边已有的交换文件的修改时间；没有交换文件时返回 `None`。
+pub fn find(file: &Path) -> Option<SystemTime> {
+    let metadata = fs::metadata(path_for(file)?).ok()?;
+    metadata.is_file().then(|| metadata.modified().ok()).flatten()
+}
+
+/// 读取原文件旁边的交换文件。
+pub fn read(file: &Path) -> Result<String, Error> {
+    let path = path_for(file).ok_or_else(|| Error::other("No swap file for this file"))?;
+    fs::read_to_string(path)
+}
+
+/// 删除原文件旁边的交换文件。
+pub fn delete(file: &Path) -> Result<(), Error> {
+    let path = path_for(file).ok_or_else(|| Error::other("No swap file for this file"))?;
+    fs::remove_file(path)
+}
+
+/// 写入交换文件；新建的交换文件只有属主能读，内容可能和原文件一样需要保密。
+fn write_private(path: &Path, contents: &str) -> Result<(), Error> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(contents.as_bytes())
+}
+
+/// 一个缓冲区的交换文件：有未保存的修改时定期写入缓冲区的内容，编辑器崩溃后可以从中恢复。
+#[derive(Default)]
+pub struct SwapFile {
+    written: Option<u64>,        // 交换文件中的内容对应的缓冲区版本，`None` 表示这个缓冲区没有写过交换文件。
+    last_write: Option<Instant>, // 上一次尝试写入的时间。
+    disabled: bool,              // 保留了之前留下的交换文件时不再写入，以免覆盖它。
+}
+
+impl SwapFile {
+    /// 版本为 `revision` 的内容应当写入交换文件的时间；已经写入或不再写入时返回 `None`。
+    pub fn deadline(&self, revision: u64) -> Option<Instant> {
+        if self.disabled || self.written == Some(revision) {
+            return None;
+        }
+        Some(self.last_write.map_or_else(Instant::now, |last| {
+            last.checked_add(SWAP_INTERVAL).unwrap_or(last)
+        }))
+    }
+
+    /// 把缓冲区的内容写入原文件旁边的交换文件；失败时等到下一个间隔再重试。
+    pub fn write(&mut self, file: &Path, contents: &str, revision: u64) {
+        if self.disabled {
+            return;
+        }
+        self.last_write = Some(Instant::now());
+        if let Some(path) = path_for(file)
+            && write_private(&path, contents).is_ok()
+        {
+            self.written = Some(revision);
+        }
+    }
+
+    /// 删除这个缓冲区写入的交换文件；没有写过时保留已有的交换文件。
+    pub fn remove(&mut self, file: &Path) {
+        if self.written.take().is_some()
+            && let Some(path) = path_for(file)
+        {
+            let _ = fs::remove_file(path);
+        }
+    }
+
+    /// 保留之前留下的交换文件，这个缓冲区不再写入交换文件。
+    pub const fn disable(&mut self) {
+        self.disabled = true;
+    }
+
+    /// 是否保留了之前留下的交换文件而不再写入。
+    pub const fn is_disabled(&self) -> bool {
+        self.disabled
+    }
+
+    /// 交换文件是否由这个缓冲区写入。
+    pub const fn is_written(&self) -> bool {
+        self.written.is_some()
+    }
+}
+
+/// 各个缓冲区中未保存的内容，与 panic 钩子共享。
+///
+/// 钩子在恢复终端之前把这里的内容写入交换文件；它不能访问编辑器本身，而且在
+/// `panic = "abort"` 时缓冲区的 `Drop` 不会运行，所以主循环每处理完一批事件就把
+/// 有新修改的缓冲区的内容复制到这里，没有修改的缓冲区不会重新复制。
+#[derive(Clone, Default)]
+pub struct CrashSwaps {
+    pending: Arc<Mutex<HashMap<PathBuf, (u64, String)>>>, // 原文件路径对应的缓冲区版本和内容。
+}
+
+impl CrashSwaps {
+    /// 用 `buffers` 中各个文件的版本和内容替换原来记下的内容；只有版本变化的文件才会调用 `contents`。
+    pub fn update<'a, F: FnOnce() -> String>(
+        &self,
+        buffers: impl IntoIterator<Item = (&'a Path, u64, F)>,
+    ) {
+        let Ok(mut pending) = self.pending.lock() else {
+            return;
+        };
+        let mut kept = HashMap::with_capacity(pending.len());
+        for (file, revision, contents) in buffers {
+            let entry = match pending.remove(file) {
+                Some(entry) if entry.0 == revision => entry,
+                _ => (revision, contents()),
+            };
+            kept.insert(file.to_path_buf(), entry);
+        }
+        *pending = kept;
+    }
+
+    /// 把记下的内容全部写入交换文件，由 panic 钩子调用。
+    ///
+    /// 崩溃发生在更新的过程中时锁被占用，这时不再等待，以免钩子卡住。
+    pub fn write_all(&self) {
+        let Ok(pending) = self.pending.try_lock() else {
+            return;
+        };
+        for (file, (_, contents)) in pending.iter() {
+            if let Some(path) = path_for(file) {
+                let _ = write_private(&path, contents);
+            }
+        }
+    }
+}
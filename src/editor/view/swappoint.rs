@@ -0,0 +1,120 @@
+use super::{Location, line::Line};
+
+/// 与光标来回切换的位置，用于在成对的两处（如开闭标签）之间反复编辑。
+///
+/// 位置随着编辑移动；所在的行被删除时随之清除。
+#[derive(Default)]
+pub struct SwapPoint {
+    location: Option<Location>, // 记下的位置，`None` 表示还没有记下。
+}
+
+impl SwapPoint {
+    /// 记下的位置。
+    pub const fn get(&self) -> Option<Location> {
+        self.location
+    }
+
+    /// 记下新的位置，返回原来记下的位置。
+    pub const fn replace(&mut self, location: Location) -> Option<Location> {
+        self.location.replace(location)
+    }
+
+    /// 清除记下的位置。
+    pub const fn clear(&mut self) {
+        self.location = None;
+    }
+
+    /// 从 `start` 开始的 `removed` 行被替换成了 `inserted` 行。
+    ///
+    /// 被替换的行拼成一段文本后比较新旧两段共同的开头和结尾：位置在共同的开头之内时不变，
+    /// 在共同的结尾之内时按长度的变化移动，在被修改的部分之内时移到修改处；所在的行被删除时清除。
+    pub fn splice(&mut self, start: usize, removed: &[String], inserted: &[String]) {
+        let Some(location) = self.location else {
+            return;
+        };
+        let end = start.saturating_add(removed.len());
+        if location.line_index < start {
+            return;
+        }
+        if location.line_index >= end {
+            self.location = Some(Location {
+                grapheme_index: location.grapheme_index,
+                line_index: location
+                    .line_index
+                    .saturating_sub(removed.len())
+                    .saturating_add(inserted.len()),
+            });
+            return;
+        }
+
+        let old = removed.join("\n");
+        let new = inserted.join("\n");
+        let prefix = common_prefix(&old, &new);
+        let suffix = common_suffix(
+            old.get(prefix..).unwrap_or_default(),
+            new.get(prefix..).unwrap_or_default(),
+        );
+        let changed_end = old.len().saturating_sub(suffix);
+
+        // 位置所在的行在拼接文本中的字节范围；行数减少且整行落在被修改的部分之内时，这一行被删除了。
+        let offset = location.line_index.saturating_sub(start);
+        let line_start = removed
+            .iter()
+            .take(offset)
+            .map(|text| text.len().saturating_add(1))
+            .fold(0, usize::saturating_add);
+        let text = removed.get(offset).map_or("", String::as_str);
+        let line_end = line_start.saturating_add(text.len());
+        if inserted.len() < removed.len() && prefix <= line_start && line_end <= changed_end {
+            self.location = None;
+            return;
+        }
+
+        let byte = line_start
+            .saturating_add(Line::from(text).byte_index_at_grapheme(location.grapheme_index));
+        let byte = if byte <= prefix {
+            byte
+        } else if byte >= changed_end {
+            byte.saturating_sub(old.len()).saturating_add(new.len())
+        } else {
+            prefix
+        };
+        self.location = Some(location_in(&new, start, byte));
+    }
+}
+
+/// 两段文本共同开头的字节长度，落在字符边界上。
+fn common_prefix(old: &str, new: &str) -> usize {
+    old.char_indices()
+        .zip(new.chars())
+        .find(|((_, a), b)| a != b)
+        .map_or_else(|| old.len().min(new.len()), |((index, _), _)| index)
+}
+
+/// 两段文本共同结尾的字节长度，落在字符边界上。
+fn common_suffix(old: &str, new: &str) -> usize {
+    old.chars()
+        .rev()
+        .zip(new.chars().rev())
+        .take_while(|(a, b)| a == b)
+        .map(|(ch, _)| ch.len_utf8())
+        .fold(0, usize::saturating_add)
+}
+
+/// 以换行符连接的多行文本中某个字节所在的位置，第一行是缓冲区中的第 `start` 行。
+fn location_in(text: &str, start: usize, byte: usize) -> Location {
+    let before = text.get(..byte).unwrap_or(text);
+    let line_offset = before.matches('\n').count();
+    let line_start = before.rfind('\n').map_or(0, |index| index.saturating_add(1));
+    let line_text = text
+        .get(line_start..)
+        .unwrap_or_default()
+        .split('\n')
+        .next()
+        .unwrap_or_default();
+    Location {
+        grapheme_index: Line::from(line_text)
+            .grapheme_index_at_byte(byte.saturating_sub(line_start)),
+        line_index: start.saturating_add(line_offset),
+    }
+}
@@ -0,0 +1,203 @@
+use std::io::{BufRead, Error, ErrorKind};
+
+/// 字节顺序标记。
+const BOM: char = '\u{feff}';
+
+/// 换行符。
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum LineEnding {
+    #[default]
+    Lf,   // `\n`
+    CrLf, // `\r\n`
+}
+
+impl LineEnding {
+    /// 换行符的文本。
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::CrLf => "\r\n",
+        }
+    }
+
+    /// 显示给用户的名称。
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Lf => "LF",
+            Self::CrLf => "CRLF",
+        }
+    }
+}
+
+/// 文件的文本格式：加载时识别，保存时按原样写回，保证未修改的文件逐字节不变。
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TextFormat {
+    pub bom: bool,                // 文件开头是否有字节顺序标记。
+    pub line_ending: LineEnding,  // 行之间的换行符。
+    pub final_newline: bool,      // 最后一行之后是否有换行符。
+    pub mixed: bool,              // 文件是否混用两种换行符；保存时统一为 `line_ending`。
+}
+
+impl Default for TextFormat {
+    /// 新建的内容使用 LF，以换行符结尾，没有字节顺序标记。
+    fn default() -> Self {
+        Self {
+            bom: false,
+            line_ending: LineEnding::Lf,
+            final_newline: true,
+            mixed: false,
+        }
+    }
+}
+
+impl TextFormat {
+    /// 识别文本的格式并按行拆分。
+    ///
+    /// 只使用一种换行符时按原样拆分，写回时与原文件逐字节相同；混用两种换行时
+    /// 取出现次数多的一种（相同时取 LF），各行末尾多出的 `\r` 被去掉，保存时统一换行符。
+    pub fn parse(contents: &str) -> (Self, Vec<&str>) {
+        let (bom, body) = contents
+            .strip_prefix(BOM)
+            .map_or((false, contents), |body| (true, body));
+        if body.is_empty() {
+            return (
+                Self {
+                    bom,
+                    ..Self::default()
+                },
+                Vec::new(),
+            );
+        }
+        let newlines = body.matches('\n').count();
+        let crlf = body.matches("\r\n").count();
+        let line_ending = if crlf > newlines.saturating_sub(crlf) {
+            LineEnding::CrLf
+        } else {
+            LineEnding::Lf
+        };
+        let mixed = crlf > 0 && crlf < newlines;
+        let separator = if mixed { "\n" } else { line_ending.as_str() };
+        let (body, final_newline) = body
+            .strip_suffix(separator)
+            .map_or((body, false), |body| (body, true));
+        let format = Self {
+            bom,
+            line_ending,
+            final_newline,
+            mixed,
+        };
+        let lines = body
+            .split(separator)
+            .map(|line| if mixed { line.strip_suffix('\r').unwrap_or(line) } else { line })
+            .collect();
+        (format, lines)
+    }
+
+    /// 逐行读取文本，识别格式并按行拆分，结果与 `parse` 相同，但不需要先把整个文件读进一个字符串。
+    ///
+    /// 内容不是有效的 UTF-8 时返回 `InvalidData` 错误，说明出错的行。
+    pub fn read(mut reader: impl BufRead) -> Result<(Self, Vec<String>), Error> {
+        let mut lines = Vec::new();
+        let mut newlines: usize = 0;
+        let mut crlf: usize = 0;
+        let mut final_newline = false;
+        loop {
+            let mut line = String::new();
+            let read = reader.read_line(&mut line).map_err(|err| {
+                if err.kind() == ErrorKind::InvalidData {
+                    Error::new(
+                        ErrorKind::InvalidData,
+                        format!("Not valid UTF-8 (line {})", lines.len().saturating_add(1)),
+                    )
+                } else {
+                    err
+                }
+            })?;
+            if read == 0 {
+                break;
+            }
+            final_newline = line.ends_with('\n');
+            if final_newline {
+                line.pop();
+                newlines = newlines.saturating_add(1);
+                if line.ends_with('\r') {
+                    line.pop();
+                    crlf = crlf.saturating_add(1);
+                }
+            }
+            lines.push(line);
+        }
+        let bom = lines.first().is_some_and(|first| first.starts_with(BOM));
+        if bom && let Some(first) = lines.first_mut() {
+            first.drain(..BOM.len_utf8());
+        }
+        if bom && lines.len() == 1 && lines.first().is_some_and(String::is_empty) && !final_newline {
+            lines.clear();
+        }
+        if lines.is_empty() {
+            return Ok((
+                Self {
+                    bom,
+                    ..Self::default()
+                },
+                lines,
+            ));
+        }
+        let line_ending = if crlf > newlines.saturating_sub(crlf) {
+            LineEnding::CrLf
+        } else {
+            LineEnding::Lf
+        };
+        let mixed = crlf > 0 && crlf < newlines;
+        // 混用换行时 `parse` 同样去掉没有换行符的最后一行末尾的 `\r`。
+        if mixed
+            && !final_newline
+            && let Some(last) = lines.last_mut()
+            && last.ends_with('\r')
+        {
+            last.pop();
+        }
+        let format = Self {
+            bom,
+            line_ending,
+            final_newline,
+            mixed,
+        };
+        Ok((format, lines))
+    }
+
+    /// 按格式将各行拼接为文件内容。
+    pub fn serialize<T: AsRef<str>>(self, lines: impl IntoIterator<Item = T>) -> String {
+        let mut contents = String::new();
+        if self.bom {
+            contents.push(BOM);
+        }
+        let mut empty = true;
+        for line in lines {
+            if !empty {
+                contents.push_str(self.line_ending.as_str());
+            }
+            contents.push_str(line.as_ref());
+            empty = false;
+        }
+        if self.final_newline && !empty {
+            contents.push_str(self.line_ending.as_str());
+        }
+        contents
+    }
+
+    /// 格式的简短描述，如 `CRLF, no final newline, BOM`。
+    pub fn describe(self) -> String {
+        let mut parts = vec![self.line_ending.name()];
+        if self.mixed {
+            parts.push("normalized from mixed line endings");
+        }
+        if !self.final_newline {
+            parts.push("no final newline");
+        }
+        if self.bom {
+            parts.push("BOM");
+        }
+        parts.join(", ")
+    }
+}
@@ -0,0 +1,264 @@
+use crate::editor::paths;
+use std::{
+    collections::VecDeque,
+    fmt::Write,
+    fs::{read_to_string, write},
+    io::Error,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// 哪些删除会被放入回收站，以及回收站的容量。
+#[derive(Clone, Copy)]
+pub struct TrashLimits {
+    pub min_lines: usize, // 一次删除至少这么多行时放入回收站。
+    pub min_bytes: usize, // 一次删除至少这么多字节时放入回收站。
+    pub capacity: usize,  // 最多保留的删除数，超出时丢弃最早的。
+    pub max_bytes: usize, // 所有删除内容的总字节数上限，超出时丢弃最早的。
+    pub max_age: Option<Duration>, // 删除保留的最长时间，超过时丢弃；`None` 表示不按时间丢弃。
+    pub persist: bool,    // 是否将回收站保存到状态目录，在会话之间保留。
+}
+
+impl Default for TrashLimits {
+    fn default() -> Self {
+        Self {
+            min_lines: 50,
+            min_bytes: 4 * 1024,
+            capacity: 10,
+            max_bytes: 8 * 1024 * 1024,
+            max_age: None,
+            persist: false,
+        }
+    }
+}
+
+/// 回收站中的一次删除。
+#[derive(Clone)]
+pub struct TrashEntry {
+    pub text: String,           // 被删除的行，以换行符连接。
+    pub lines: usize,           // 被删除的行数。
+    pub source: String,         // 删除时所在的文件。
+    pub deleted_at: SystemTime, // 删除的时间。
+}
+
+impl TrashEntry {
+    /// 距离删除过去的时间，如 `5 min ago`。
+    pub fn age(&self) -> String {
+        age_of(self.deleted_at)
+    }
+}
+
+/// 距离某个时间点过去的时间，如 `5 min ago`。
+pub fn age_of(time: SystemTime) -> String {
+    let seconds = time.elapsed().map_or(0, |elapsed| elapsed.as_secs());
+    match seconds {
+        0..60 => format!("{seconds} s ago"),
+        60..3600 => format!("{} min ago", seconds.div_euclid(60)),
+        3600..86400 => format!("{} h ago", seconds.div_euclid(3600)),
+        _ => format!("{} d ago", seconds.div_euclid(86400)),
+    }
+}
+
+/// 最近几次较大删除的内容，独立于撤销历史，撤销历史被截断后仍然可以找回。
+#[derive(Default)]
+pub struct Trash {
+    entries: VecDeque<TrashEntry>, // 最新的删除在最前面。
+    total_bytes: usize,            // 所有删除内容的总字节数。
+    limits: TrashLimits,           // 放入回收站的阈值和容量。
+}
+
+impl Trash {
+    /// 设置放入回收站的阈值和容量，超出新容量的删除被丢弃。
+    pub fn set_limits(&mut self, limits: TrashLimits) {
+        self.limits = limits;
+        self.evict();
+    }
+
+    /// 一次删除是否大到需要放入回收站。
+    pub const fn wants(&self, lines: usize, bytes: usize) -> bool {
+        lines >= self.limits.min_lines || bytes >= self.limits.min_bytes
+    }
+
+    /// 放入一次删除；超过总字节数上限的单次删除不会被保留。
+    pub fn push(&mut self, text: String, lines: usize, source: String) {
+        if text.len() > self.limits.max_bytes {
+            return;
+        }
+        self.total_bytes = self.total_bytes.saturating_add(text.len());
+        self.entries.push_front(TrashEntry {
+            text,
+            lines,
+            source,
+            deleted_at: SystemTime::now(),
+        });
+        self.evict();
+        if self.limits.persist {
+            let _ = self.save();
+        }
+    }
+
+    /// 按从新到旧的顺序排列的删除。
+    pub fn entries(&self) -> &VecDeque<TrashEntry> {
+        &self.entries
+    }
+
+    /// 移除一次删除。
+    pub fn remove(&mut self, index: usize) {
+        if let Some(entry) = self.entries.remove(index) {
+            self.total_bytes = self.total_bytes.saturating_sub(entry.text.len());
+            if self.limits.persist {
+                let _ = self.save();
+            }
+        }
+    }
+
+    /// 丢弃最早的删除，直到数量和总字节数都不超过上限，且最早的删除没有超过保留时间。
+    fn evict(&mut self) {
+        let expired = |entry: &TrashEntry, max_age: Option<Duration>| {
+            max_age.is_some_and(|max_age| {
+                entry.deleted_at.elapsed().is_ok_and(|age| age > max_age)
+            })
+        };
+        while self.entries.len() > self.limits.capacity
+            || self.total_bytes > self.limits.max_bytes
+            || self
+                .entries
+                .back()
+                .is_some_and(|entry| expired(entry, self.limits.max_age))
+        {
+            let Some(entry) = self.entries.pop_back() else {
+                break;
+            };
+            self.total_bytes = self.total_bytes.saturating_sub(entry.text.len());
+        }
+    }
+
+    // ==================== 持久化 ====================
+
+    /// 从状态目录读取上一次会话保存的回收站，放在当前内容之后。
+    pub fn load(&mut self) -> Result<(), Error> {
+        let contents = read_to_string(trash_file()?)?;
+        let mut rest = contents.as_str();
+        while let Some((header, body)) = rest.split_once('\n') {
+            let mut fields = header.splitn(4, ' ');
+            let (Some(seconds), Some(lines), Some(len), Some(source)) =
+                (fields.next(), fields.next(), fields.next(), fields.next())
+            else {
+                break;
+            };
+            let (Ok(seconds), Ok(lines), Ok(len)) =
+                (seconds.parse(), lines.parse(), len.parse::<usize>())
+            else {
+                break;
+            };
+            let Some(text) = body.get(..len) else {
+                break;
+            };
+            self.total_bytes = self.total_bytes.saturating_add(text.len());
+            self.entries.push_back(TrashEntry {
+                text: text.to_string(),
+                lines,
+                source: source.to_string(),
+                deleted_at: UNIX_EPOCH
+                    .checked_add(Duration::from_secs(seconds))
+                    .unwrap_or(UNIX_EPOCH),
+            });
+            rest = body.get(len..).unwrap_or_default();
+            rest = rest.strip_prefix('\n').unwrap_or(rest);
+        }
+        self.evict();
+        Ok(())
+    }
+
+    /// 将回收站写入状态目录：每次删除是一行头部（时间、行数、字节数、文件）加上删除的内容。
+    fn save(&self) -> Result<(), Error> {
+        let mut contents = String::new();
+        for entry in &self.entries {
+            let seconds = entry
+                .deleted_at
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs());
+            let _ = writeln!(
+                contents,
+                "{seconds} {} {} {}\n{}",
+                entry.lines,
+                entry.text.len(),
+                entry.source,
+                entry.text
+            );
+        }
+        write(trash_file()?, contents)
+    }
+}
+
+/// 保存回收站的文件：状态目录下的 `trash`。
+fn trash_file() -> Result<PathBuf, Error> {
+    paths::state_dir()
+        .map(|dir| dir.join("trash"))
+        .ok_or_else(|| Error::other("No state directory"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trash_with(limits: TrashLimits) -> Trash {
+        let mut trash = Trash::default();
+        trash.set_limits(limits);
+        trash
+    }
+
+    fn sources(trash: &Trash) -> Vec<&str> {
+        trash.entries().iter().map(|entry| entry.source.as_str()).collect()
+    }
+
+    #[test]
+    fn configured_thresholds_decide_what_goes_to_the_trash() {
+        let trash = trash_with(TrashLimits {
+            min_lines: 3,
+            min_bytes: 100,
+            ..TrashLimits::default()
+        });
+
+        assert!(!trash.wants(2, 99));
+        assert!(trash.wants(3, 10));
+        assert!(trash.wants(1, 100));
+    }
+
+    #[test]
+    fn configured_capacity_and_size_drop_the_oldest_deletions() {
+        let mut trash = trash_with(TrashLimits {
+            capacity: 2,
+            max_bytes: 10,
+            ..TrashLimits::default()
+        });
+        trash.push("aaaa".to_string(), 1, "a".to_string());
+        trash.push("bbbb".to_string(), 1, "b".to_string());
+        trash.push("cccc".to_string(), 1, "c".to_string());
+        assert_eq!(sources(&trash), vec!["c", "b"]);
+
+        trash.push("dddddd".to_string(), 1, "d".to_string());
+        assert_eq!(sources(&trash), vec!["d", "c"]);
+
+        // 单次删除超过总字节数上限时不保留。
+        trash.push("x".repeat(11), 1, "x".to_string());
+        assert_eq!(sources(&trash), vec!["d", "c"]);
+    }
+
+    #[test]
+    fn deletions_older_than_the_configured_age_are_dropped() {
+        let mut trash = Trash::default();
+        trash.push("old".to_string(), 1, "old".to_string());
+        trash.push("new".to_string(), 1, "new".to_string());
+        let hour_ago = SystemTime::now().checked_sub(Duration::from_hours(1)).unwrap();
+        trash.entries.back_mut().unwrap().deleted_at = hour_ago;
+
+        trash.set_limits(TrashLimits {
+            max_age: Some(Duration::from_mins(1)),
+            ..TrashLimits::default()
+        });
+
+        assert_eq!(sources(&trash), vec!["new"]);
+        assert_eq!(trash.total_bytes, 3);
+    }
+}
@@ -0,0 +1,13 @@
+// 启用 Clippy 的一些警告规则，用于提高代码质量。
+#![warn(
+    clippy::all,                  // 启用所有 Clippy 检查。
+    clippy::pedantic,             // 启用严格的 Clippy 检查。
+    clippy::print_stdout,         // 警告直接使用 `println!`。
+    clippy::arithmetic_side_effects, // 警告可能的算术副作用。
+    clippy::as_conversions,       // 警告使用 `as` 进行类型转换。
+    clippy::integer_division      // 警告整数除法操作。
+)]
+
+// 编辑器的全部功能都在库中，命令行程序和集成测试（如 `tests/batch.rs`）共用同一套代码。
+mod editor;
+pub use editor::Editor;
@@ -8,12 +8,25 @@
     clippy::integer_division      // 警告整数除法操作。
 )]
 
-// 导入编辑器模块。
-mod editor;
-use editor::Editor;
+use snows_edit::Editor;
 
 /// 程序的入口点。
 fn main() {
+    // 指定了 `--doctor` 时只打印终端环境的诊断报告。
+    if let Some(exit_code) = Editor::run_doctor() {
+        std::process::exit(exit_code);
+    }
+
+    // 指定了 `--check-roundtrip` 时只检查文件能否原样加载并保存。
+    if let Some(exit_code) = Editor::run_check_roundtrip() {
+        std::process::exit(exit_code);
+    }
+
+    // 指定了 `--batch` 时以批处理模式运行，不进入交互界面。
+    if let Some(exit_code) = Editor::run_batch() {
+        std::process::exit(exit_code);
+    }
+
     // 初始化编辑器并运行主循环。
     Editor::new().unwrap().run();
 }
\ No newline at end of file
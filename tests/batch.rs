@@ -0,0 +1,42 @@
+//! 以批处理脚本编写的端到端回归测试。
+//!
+//! `tests/batch` 下的每个目录是一个用例：`input.txt` 复制到临时目录后作为打开的文件，
+//! 执行 `script.txt`，之后文件的内容应当与 `expected.txt` 完全相同。
+
+use snows_edit::Editor;
+use std::{fs, path::Path};
+
+#[test]
+fn batch_scripts_produce_the_expected_files() {
+    let cases = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/batch");
+    let mut count = 0;
+    for case in fs::read_dir(&cases).unwrap() {
+        let case = case.unwrap().path();
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("input.txt");
+        fs::copy(case.join("input.txt"), &file).unwrap();
+
+        Editor::batch(&case.join("script.txt"), Some(&file))
+            .unwrap_or_else(|err| panic!("{}: {err}", case.display()));
+
+        assert_eq!(
+            fs::read(&file).unwrap(),
+            fs::read(case.join("expected.txt")).unwrap(),
+            "{}",
+            case.display()
+        );
+        count += 1;
+    }
+    assert!(count > 0, "no batch cases in {}", cases.display());
+}
+
+#[test]
+fn interactive_commands_are_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let script = dir.path().join("script.txt");
+    fs::write(&script, "palette\n").unwrap();
+
+    let err = Editor::batch(&script, None).unwrap_err();
+
+    assert!(err.contains("not available in batch mode"), "{err}");
+}